@@ -31,6 +31,7 @@ use crate::OrderType::Cancel;
 ///    The `HourglassAccount` is created and wrapped in an `Arc<Mutex>` to allow safe concurrent access.
 ///    - The account is initialized with configurations, positions, balances, and an order book.
 ///    - The `single_level_order_books` hashmap stores bid/ask data for instruments like `ETH/USDT`.
+///    - The `symbol_registry` is loaded from `symbols.toml` and maps raw market data symbols to instruments.
 ///
 /// 4. **ClickHouseClient and Market Data Source**
 ///    The `ClickHouseClient` is used to fetch historical market data from a ClickHouse database.
@@ -81,7 +82,7 @@ use crate::OrderType::Cancel;
 use dashmap::DashMap;
 use hourglass::{
     common::{
-        account_positions::{exited_positions::AccountExitedPositions, AccountPositions, PositionDirectionMode, PositionMarginMode},
+        account_positions::{exited_positions::AccountExitedPositions, AccountPositions, PositionDirectionMode, PositionMarginMode, PositionSide},
         balance::Balance,
         instrument::{kind::InstrumentKind, Instrument},
         order::{
@@ -96,13 +97,19 @@ use hourglass::{
     },
     hourglass::{
         account::{
-            account_config::{AccountConfig, CommissionLevel, HourglassMode, MarginMode},
+            account_config::{AccountConfig, CommissionLevel, HourglassMode, MarginMode, OptionConfig, OrderRiskLimits, PositionLimits, SpotMarginConfig},
+            account_handlers::risk_handler::OrderRateLimiter,
             account_latency::{AccountLatency, FluctuationMode},
+            account_margin_loan::MarginLoanBook,
             account_orders::AccountOrders,
+            account_volume::TradingVolumeTracker,
             HourglassAccount,
         },
         clickhouse_api::{datatype::clickhouse_trade_data::MarketTrade, queries_operations::ClickHouseClient},
         hourglass_client_local_mode::HourglassClient,
+        l2_order_book::L2BookBuilder,
+        risk_reserve::RiskReserve,
+        utils::config_parser::read_symbol_file,
         DataSource, HourglassExchange,
     },
     hourglass_log,
@@ -206,7 +213,8 @@ async fn main()
 
     #[allow(unused)]
     let mut hourglass_client = HourglassClient { client_event_tx: client_event_tx.clone(),
-                                                 market_event_rx };
+                                                 market_event_rx,
+                                                 bar_event_rx: None };
 
     // Creating initial positions with the updated structure
     let positions = AccountPositions::init();
@@ -221,10 +229,19 @@ async fn main()
                                                    funding_rate: 0.0,
                                                    global_leverage_rate: 1.0,
                                                    fees_book: HashMap::new(),
+                                                   fee_schedule: Vec::new(),
+                                                   instrument_fees_book: Vec::new(),
                                                    execution_mode: HourglassMode::Backtest,
                                                    max_price_deviation: 0.1,
                                                    lazy_account_positions: false,
-                                                   liquidation_threshold: 0.9 };
+                                                   liquidation_threshold: 0.9,
+                                                   liquidation_fee_rate: 0.005,
+                                                   risk_reserve_fee_share: 0.5,
+                                                   position_limits: PositionLimits::default(),
+                                                   order_risk_limits: OrderRiskLimits::default(),
+                                                   option_config: OptionConfig::default(),
+                                                   leveraged_tokens: Vec::new(),
+                                                   spot_margin: SpotMarginConfig::default() };
 
     // initialise the tokens possibly to be traded
     let mut instruments: Vec<Instrument> = vec![];
@@ -232,12 +249,18 @@ async fn main()
     // initialise 1000PEPEUSDT
     instruments.push(Instrument { base: Token::from("1000PEPE"),
                                   quote: Token::from("USDT"),
-                                  kind: InstrumentKind::Perpetual });
+                                  kind: InstrumentKind::Perpetual,
+                                  inverse: false,
+                                  expiry: None,
+                                  option_spec: None });
 
     // initialise 1000FLOKIUSDT
     instruments.push(Instrument { base: Token::from("1000FLOKI"),
                                   quote: Token::from("USDT"),
-                                  kind: InstrumentKind::Perpetual });
+                                  kind: InstrumentKind::Perpetual,
+                                  inverse: false,
+                                  expiry: None,
+                                  option_spec: None });
 
     // Instantiate HourglassAccount and wrap in Arc<Mutex> for shared access
     let account_arc = Arc::new(Mutex::new(HourglassAccount { current_session: Uuid::new_v4(),
@@ -254,7 +277,14 @@ async fn main()
                                                              positions,
                                                              exited_positions: closed_positions,
                                                              account_event_tx,
-                                                             account_margin: Arc::new(Default::default()) }));
+                                                             account_margin: Arc::new(Default::default()),
+                                                             risk_reserve: Arc::new(Mutex::new(RiskReserve::default())),
+                                                             order_rate_limiter: OrderRateLimiter::default(),
+                                                             trading_volume: TradingVolumeTracker::default(),
+                                                             leveraged_token_navs: HashMap::new(),
+                                                             margin_loans: MarginLoanBook::default(),
+                                                             symbol_registry: read_symbol_file("symbols.toml").expect("Failed to read symbols.toml"),
+                                                             l2_books: L2BookBuilder::default() }));
 
     // Sample cursor building
     let clickhouse_client = ClickHouseClient::new();
@@ -344,7 +374,8 @@ pub async fn order_parser(client: &HourglassClient, trade: &MarketTrade, order_i
                                         side: monk_order.side,                                                         // 买卖方向
                                         state: RequestOpen { reduce_only: false,
                                                              price: monk_order.price,
-                                                             size: monk_order.size } };
+                                                             size: monk_order.size,
+                                                             position_side: PositionSide::Both } };
 
                     let new_orders = client.open_orders(vec![order]).await;
                     info!("The new orders are : {:?}", &new_orders);
@@ -45,8 +45,11 @@ impl PositionExit
         // 计算退出时的总价值（不考虑费用）
         let exit_quantity = position_meta.current_size;
         let exit_value_gross = exit_quantity * position_meta.current_symbol_price;
-        // 计算实现盈亏 (realised_pnl)
-        let realised_pnl = (position_meta.current_symbol_price - position_meta.current_avg_price) * exit_quantity;
+        // 计算实现盈亏 (realised_pnl)，空头仓位在价格下跌时盈利
+        let realised_pnl = match position_meta.side {
+            | Side::Buy => (position_meta.current_symbol_price - position_meta.current_avg_price) * exit_quantity,
+            | Side::Sell => (position_meta.current_avg_price - position_meta.current_symbol_price) * exit_quantity,
+        };
 
         // 创建 `PositionExit`
         PositionExit { exchange: position_meta.exchange.clone(),       // 从 PositionMeta 获取静态数据
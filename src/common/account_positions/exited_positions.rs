@@ -10,6 +10,7 @@ pub struct AccountExitedPositions
 {
     pub margin_pos_long: Arc<RwLock<HashMap<PositionId, PositionExit>>>,
     pub margin_pos_short: Arc<RwLock<HashMap<PositionId, PositionExit>>>,
+    pub leveraged_token_pos: Arc<RwLock<HashMap<PositionId, PositionExit>>>,
     pub perpetual_pos_long: Arc<RwLock<HashMap<PositionId, PositionExit>>>,
     pub perpetual_pos_short: Arc<RwLock<HashMap<PositionId, PositionExit>>>,
     pub futures_pos_long: Arc<RwLock<HashMap<PositionId, PositionExit>>>,
@@ -27,6 +28,7 @@ impl AccountExitedPositions
     {
         Self { margin_pos_long: Arc::new(RwLock::new(HashMap::new())),
                margin_pos_short: Arc::new(RwLock::new(HashMap::new())),
+               leveraged_token_pos: Arc::new(RwLock::new(HashMap::new())),
                perpetual_pos_long: Arc::new(RwLock::new(HashMap::new())),
                perpetual_pos_short: Arc::new(RwLock::new(HashMap::new())),
                futures_pos_long: Arc::new(RwLock::new(HashMap::new())),
@@ -53,6 +55,14 @@ impl AccountExitedPositions
         pos_short.insert(position_id, position);
     }
 
+    /// 插入方法，推断 `PositionId` 并插入 `LeveragedTokenPosition` 到 `leveraged_token_pos`
+    pub async fn insert_leveraged_token_pos(&self, position: PositionExit)
+    {
+        let position_id = position.position_id.clone(); // 推断 position_id
+        let mut pos = self.leveraged_token_pos.write().await;
+        pos.insert(position_id, position);
+    }
+
     /// 插入方法，推断 `PositionId` 并插入 `PerpetualPosition` 到 `perpetual_pos_long`
     pub async fn insert_perpetual_pos_long(&self, position: PositionExit)
     {
@@ -122,6 +132,7 @@ impl AccountExitedPositions
     {
         self.margin_pos_long.write().await.clear();
         self.margin_pos_short.write().await.clear();
+        self.leveraged_token_pos.write().await.clear();
         self.perpetual_pos_long.write().await.clear();
         self.perpetual_pos_short.write().await.clear();
         self.futures_pos_long.write().await.clear();
@@ -155,6 +166,7 @@ impl Serialize for AccountExitedPositions
         )?;
         state.serialize_field("margin_pos_long", &to_map(&self.margin_pos_long))?;
         state.serialize_field("margin_pos_short", &to_map(&self.margin_pos_short))?;
+        state.serialize_field("leveraged_token_pos", &to_map(&self.leveraged_token_pos))?;
         state.serialize_field("perpetual_pos_long", &to_map(&self.perpetual_pos_long))?;
         state.serialize_field("perpetual_pos_short", &to_map(&self.perpetual_pos_short))?;
         state.serialize_field("futures_pos_long", &to_map(&self.futures_pos_long))?;
@@ -188,6 +200,7 @@ impl PartialEq for AccountExitedPositions
 
         hashmap_eq(&self.margin_pos_long, &other.margin_pos_long)
         && hashmap_eq(&self.margin_pos_short, &other.margin_pos_short)
+        && hashmap_eq(&self.leveraged_token_pos, &other.leveraged_token_pos)
         && hashmap_eq(&self.perpetual_pos_long, &other.perpetual_pos_long)
         && hashmap_eq(&self.perpetual_pos_short, &other.perpetual_pos_short)
         && hashmap_eq(&self.futures_pos_long, &other.futures_pos_long)
@@ -209,6 +222,8 @@ impl<'de> Deserialize<'de> for AccountExitedPositions
         {
             margin_pos_long: HashMap<PositionId, PositionExit>,
             margin_pos_short: HashMap<PositionId, PositionExit>,
+            #[serde(default)]
+            leveraged_token_pos: HashMap<PositionId, PositionExit>,
             perpetual_pos_long: HashMap<PositionId, PositionExit>,
             perpetual_pos_short: HashMap<PositionId, PositionExit>,
             futures_pos_long: HashMap<PositionId, PositionExit>,
@@ -223,6 +238,7 @@ impl<'de> Deserialize<'de> for AccountExitedPositions
 
         Ok(AccountExitedPositions { margin_pos_long: Arc::new(RwLock::new(data.margin_pos_long)),
                                     margin_pos_short: Arc::new(RwLock::new(data.margin_pos_short)),
+                                    leveraged_token_pos: Arc::new(RwLock::new(data.leveraged_token_pos)),
                                     perpetual_pos_long: Arc::new(RwLock::new(data.perpetual_pos_long)),
                                     perpetual_pos_short: Arc::new(RwLock::new(data.perpetual_pos_short)),
                                     futures_pos_long: Arc::new(RwLock::new(data.futures_pos_long)),
@@ -1,6 +1,11 @@
 use crate::{
-    common::account_positions::{position_meta::PositionMeta, PositionDirectionMode, PositionMarginMode},
+    common::{
+        account_positions::{liquidation::LiquidatablePosition, position_id::PositionId, position_meta::PositionMeta, Position, PositionDirectionMode, PositionMarginMode, PositionSide},
+        instrument::Instrument,
+        Side,
+    },
     hourglass::config_request::ConfigurationRequest,
+    Exchange,
 };
 use serde::{Deserialize, Serialize};
 
@@ -39,6 +44,102 @@ impl FuturePosition
     {
         self.meta = new_meta;
     }
+
+    /// 仓位在订单与成交中对应的 [`PositionSide`]，单向持仓模式下为 `Both`。
+    pub fn position_side(&self) -> PositionSide
+    {
+        match (&self.pos_config.position_direction_mode, self.meta.side) {
+            | (PositionDirectionMode::Net, _) => PositionSide::Both,
+            | (PositionDirectionMode::LongShort, Side::Buy) => PositionSide::Long,
+            | (PositionDirectionMode::LongShort, Side::Sell) => PositionSide::Short,
+        }
+    }
+
+    /// 按开仓均价计算的初始保证金，以 [`Instrument::margin_token`] 计。
+    pub fn initial_margin(&self) -> f64
+    {
+        self.meta.instrument.notional(self.meta.current_avg_price, self.meta.current_size) / self.pos_config.leverage
+    }
+
+    /// 维持保证金，与永续合约相同，为初始保证金的 `1 - liquidation_threshold`。
+    pub fn maintenance_margin(&self, liquidation_threshold: f64) -> f64
+    {
+        (1.0 - liquidation_threshold) * self.initial_margin()
+    }
+
+    /// 以给定价格计算的未实现盈亏，区分多空方向，币本位合约以 base 计。
+    pub fn unrealised_pnl_at(&self, price: f64) -> f64
+    {
+        self.meta.instrument.pnl(self.meta.side, self.meta.current_avg_price, price, self.meta.current_size)
+    }
+}
+
+impl LiquidatablePosition for FuturePosition
+{
+    fn meta(&self) -> &PositionMeta
+    {
+        &self.meta
+    }
+
+    fn meta_mut(&mut self) -> &mut PositionMeta
+    {
+        &mut self.meta
+    }
+
+    fn margin_mode(&self) -> &PositionMarginMode
+    {
+        &self.pos_config.pos_margin_mode
+    }
+
+    fn position_direction_mode(&self) -> &PositionDirectionMode
+    {
+        &self.pos_config.position_direction_mode
+    }
+
+    fn leverage(&self) -> f64
+    {
+        self.pos_config.leverage
+    }
+
+    fn position_side(&self) -> PositionSide
+    {
+        FuturePosition::position_side(self)
+    }
+
+    fn isolated_margin(&self) -> Option<f64>
+    {
+        self.isolated_margin
+    }
+
+    fn set_isolated_margin(&mut self, isolated_margin: Option<f64>)
+    {
+        self.isolated_margin = isolated_margin;
+    }
+
+    fn update_liquidation_price(&mut self, new_price: f64)
+    {
+        FuturePosition::update_liquidation_price(self, new_price)
+    }
+
+    fn initial_margin(&self) -> f64
+    {
+        FuturePosition::initial_margin(self)
+    }
+
+    fn maintenance_margin(&self, liquidation_threshold: f64) -> f64
+    {
+        FuturePosition::maintenance_margin(self, liquidation_threshold)
+    }
+
+    fn unrealised_pnl_at(&self, price: f64) -> f64
+    {
+        FuturePosition::unrealised_pnl_at(self, price)
+    }
+
+    fn into_position(self) -> Position
+    {
+        Position::Future(self)
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
@@ -49,6 +150,24 @@ pub struct FuturePositionConfig
     pub(crate) position_direction_mode: PositionDirectionMode,
 }
 
+impl FuturePositionConfig
+{
+    pub fn margin_mode(&self) -> &PositionMarginMode
+    {
+        &self.pos_margin_mode
+    }
+
+    pub fn leverage(&self) -> f64
+    {
+        self.leverage
+    }
+
+    pub fn position_direction_mode(&self) -> &PositionDirectionMode
+    {
+        &self.position_direction_mode
+    }
+}
+
 impl From<ConfigurationRequest> for FuturePositionConfig
 {
     fn from(config_request: ConfigurationRequest) -> Self
@@ -59,6 +178,22 @@ impl From<ConfigurationRequest> for FuturePositionConfig
     }
 }
 
+/// 交割合约到期后，每个被现金交割的仓位都会向客户端发送一个 [`FutureSettlement`] 事件。
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct FutureSettlement
+{
+    pub exchange: Exchange,
+    pub instrument: Instrument,
+    pub side: Side, // 被交割仓位的方向
+    pub position_id: PositionId,
+    pub margin_mode: PositionMarginMode,
+    pub timestamp: i64,        // 交割时的交易所时间
+    pub settlement_price: f64, // 交割价格
+    pub size: f64,             // 交割数量
+    pub realised_pnl: f64,     // 交割产生的已实现盈亏
+    pub released_margin: f64,  // 退回可用余额的保证金
+}
+
 #[allow(dead_code)]
 pub struct FuturePositionBuilder
 {
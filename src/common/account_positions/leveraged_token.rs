@@ -1,10 +1,17 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    common::account_positions::{position_meta::PositionMeta, PositionDirectionMode, PositionMarginMode},
+    common::{
+        account_positions::{position_meta::PositionMeta, PositionDirectionMode, PositionMarginMode},
+        instrument::Instrument,
+    },
     hourglass::config_request::ConfigurationRequest,
+    Exchange,
 };
 
+/// 一天的毫秒数，管理费按日费率连续计提。
+pub const ONE_DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct LeveragedTokenPosition
 {
@@ -29,3 +36,124 @@ impl From<ConfigurationRequest> for LeveragedTokenPositionConfig
                                        position_mode: config_request.position_direction_mode.unwrap()  /* 提供默认值或根据需求处理 None */ }
     }
 }
+
+/// 杠杆代币的净值状态。
+///
+/// 每份代币持有 `exposure` 单位的标的永续合约敞口（做空代币为负数），上次再平衡后净值随标的价格线性变化：
+/// `nav = reference_nav + exposure * (price - reference_price)`。再平衡时按目标杠杆重新设置敞口，
+/// 管理费按比例同时缩减净值与敞口，不改变实际杠杆。
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct LeveragedTokenNav
+{
+    pub exposure: f64,           // 每份代币的标的敞口
+    pub reference_price: f64,    // 上次再平衡时的标的价格
+    pub reference_nav: f64,      // 上次再平衡（或计提管理费）后的净值
+    pub last_rebalance_ts: i64,  // 上次再平衡的交易所时间
+    pub last_fee_ts: i64,        // 上次计提管理费的交易所时间
+}
+
+impl LeveragedTokenNav
+{
+    /// 以 `nav` 发行代币，并按目标杠杆建立初始敞口。
+    pub fn new(nav: f64, target_leverage: f64, price: f64, timestamp: i64) -> Self
+    {
+        Self { exposure: target_leverage * nav / price,
+               reference_price: price,
+               reference_nav: nav,
+               last_rebalance_ts: timestamp,
+               last_fee_ts: timestamp }
+    }
+
+    /// 以标的价格计算的净值，标的反向波动超过 1 / 杠杆时净值归零。
+    pub fn nav_at(&self, price: f64) -> f64
+    {
+        (self.reference_nav + self.exposure * (price - self.reference_price)).max(0.0)
+    }
+
+    /// 以标的价格计算的实际杠杆，净值归零时返回 0。
+    pub fn leverage_at(&self, price: f64) -> f64
+    {
+        let nav = self.nav_at(price);
+        if nav > 0.0 { self.exposure * price / nav } else { 0.0 }
+    }
+
+    /// 按目标杠杆重新设置敞口。
+    pub fn rebalance(&mut self, target_leverage: f64, price: f64, timestamp: i64)
+    {
+        let nav = self.nav_at(price);
+        self.exposure = target_leverage * nav / price;
+        self.reference_price = price;
+        self.reference_nav = nav;
+        self.last_rebalance_ts = timestamp;
+    }
+
+    /// 按日费率计提从上次计提至 `timestamp` 的管理费，返回每份代币被扣除的净值。
+    pub fn accrue_management_fee(&mut self, daily_fee_rate: f64, price: f64, timestamp: i64) -> f64
+    {
+        let elapsed = timestamp - self.last_fee_ts;
+        if elapsed <= 0 || daily_fee_rate <= 0.0 {
+            self.last_fee_ts = self.last_fee_ts.max(timestamp);
+            return 0.0;
+        }
+
+        let fraction = (daily_fee_rate * elapsed as f64 / ONE_DAY_MS as f64).min(1.0);
+        let fee = self.nav_at(price) * fraction;
+        self.reference_nav *= 1.0 - fraction;
+        self.exposure *= 1.0 - fraction;
+        self.last_fee_ts = timestamp;
+        fee
+    }
+}
+
+/// 触发再平衡的原因。
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Deserialize, Serialize)]
+pub enum RebalanceTrigger
+{
+    Scheduled, // 到达定期再平衡时间
+    Threshold, // 实际杠杆偏离目标杠杆超过阈值
+}
+
+/// 杠杆代币每次再平衡都会向客户端发送一个 [`LeveragedTokenRebalance`] 事件。
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct LeveragedTokenRebalance
+{
+    pub exchange: Exchange,
+    pub instrument: Instrument,
+    pub trigger: RebalanceTrigger,
+    pub timestamp: i64,        // 再平衡时的交易所时间
+    pub underlying_price: f64, // 再平衡所用的标的价格
+    pub nav: f64,              // 再平衡时的净值
+    pub leverage_before: f64,  // 再平衡前的实际杠杆
+    pub leverage_after: f64,   // 再平衡后的杠杆，即目标杠杆
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn leveraged_token_nav_should_track_leverage_and_charge_fees()
+    {
+        // 3 倍做多代币：标的上涨 10% 净值上涨 30%，杠杆下降
+        let mut long = LeveragedTokenNav::new(1.0, 3.0, 100.0, 0);
+        assert!((long.nav_at(110.0) - 1.3).abs() < 1e-12);
+        assert!((long.leverage_at(110.0) - 3.3 / 1.3).abs() < 1e-12);
+        long.rebalance(3.0, 110.0, 1_000);
+        assert!((long.leverage_at(110.0) - 3.0).abs() < 1e-12);
+        assert!((long.nav_at(121.0) - 1.69).abs() < 1e-12);
+
+        // 3 倍做空代币：标的上涨 10% 净值下跌 30%，杠杆绝对值上升
+        let short = LeveragedTokenNav::new(1.0, -3.0, 100.0, 0);
+        assert!((short.nav_at(110.0) - 0.7).abs() < 1e-12);
+        assert!((short.leverage_at(110.0) + 3.3 / 0.7).abs() < 1e-12);
+        assert_eq!(short.nav_at(140.0), 0.0);
+
+        // 日费率 0.1% 计提一天，净值与敞口同比例缩减
+        let mut charged = LeveragedTokenNav::new(1.0, 3.0, 100.0, 0);
+        let fee = charged.accrue_management_fee(0.001, 100.0, ONE_DAY_MS);
+        assert!((fee - 0.001).abs() < 1e-12);
+        assert!((charged.nav_at(100.0) - 0.999).abs() < 1e-12);
+        assert!((charged.leverage_at(100.0) - 3.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::{
+    common::{
+        account_positions::{position_id::PositionId, position_meta::PositionMeta, Position, PositionDirectionMode, PositionMarginMode, PositionSide},
+        balance::TokenBalance,
+        instrument::Instrument,
+        token::Token,
+        Side,
+    },
+    Exchange,
+};
+use serde::{Deserialize, Serialize};
+
+/// 单次强平步骤的计算结果。
+///
+/// 由 [`LiquidatablePosition::liquidation_step`] 给出，
+/// 描述为了让仓位回到维持保证金之上所需平掉的最小数量，以及这一步产生的强平费和已实现盈亏。
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
+pub struct LiquidationStep
+{
+    pub close_size: f64,    // 本次需要强平的数量
+    pub fee: f64,           // 本次强平收取的强平费
+    pub realised_pnl: f64,  // 被平部分的已实现盈亏（通常为负）
+    pub is_bankrupt: bool,  // 仓位权益不足以覆盖整体强平费，即穿仓
+}
+
+impl LiquidationStep
+{
+    /// 仓位仍满足维持保证金要求，无需强平。
+    pub fn none() -> Self
+    {
+        Self { close_size: 0.0,
+               fee: 0.0,
+               realised_pnl: 0.0,
+               is_bankrupt: false }
+    }
+
+    pub fn is_required(&self) -> bool
+    {
+        self.close_size > 0.0
+    }
+}
+
+/// 永续合约与交割合约仓位共用的强平计算。
+///
+/// 两者的保证金与盈亏都按 [`Instrument::notional`] 与 [`Instrument::pnl`] 计算，强平价格、逐步强平的数量
+/// 与自动减仓的排序因此共用同一套公式，由账户的强平流程统一处理。
+pub trait LiquidatablePosition: Clone + Send + Sync
+{
+    fn meta(&self) -> &PositionMeta;
+
+    fn meta_mut(&mut self) -> &mut PositionMeta;
+
+    fn margin_mode(&self) -> &PositionMarginMode;
+
+    fn position_direction_mode(&self) -> &PositionDirectionMode;
+
+    fn leverage(&self) -> f64;
+
+    /// 仓位在订单与成交中对应的 [`PositionSide`]，单向持仓模式下为 `Both`。
+    fn position_side(&self) -> PositionSide;
+
+    fn isolated_margin(&self) -> Option<f64>;
+
+    fn set_isolated_margin(&mut self, isolated_margin: Option<f64>);
+
+    fn update_liquidation_price(&mut self, new_price: f64);
+
+    /// 按开仓均价计算的初始保证金，以 [`Instrument::margin_token`] 计。
+    fn initial_margin(&self) -> f64;
+
+    /// 维持保证金，为初始保证金的 `1 - liquidation_threshold`。
+    fn maintenance_margin(&self, liquidation_threshold: f64) -> f64;
+
+    /// 以给定价格计算的未实现盈亏，区分多空方向，币本位合约以 base 计。
+    fn unrealised_pnl_at(&self, price: f64) -> f64;
+
+    fn into_position(self) -> Position;
+
+    /// 自动减仓的排序分数：以初始保证金计的盈利比例乘以杠杆，分数越高越先被减仓。
+    fn adl_ranking_score(&self, price: f64) -> f64
+    {
+        let initial_margin = self.initial_margin();
+        if initial_margin <= 0.0 {
+            return 0.0;
+        }
+        self.unrealised_pnl_at(price) / initial_margin * self.leverage()
+    }
+
+    /// 根据仓位可用的保证金 `margin` 重新计算强平价格。
+    ///
+    /// 新仓位（`margin` 等于初始保证金）时结果与开仓时的 [`Instrument::opening_liquidation_price`] 相同。
+    /// 币本位合约的盈亏与价格的倒数成线性关系，因此在 `1 / price` 上计算缓冲。
+    fn compute_liquidation_price(&self, margin: f64, liquidation_threshold: f64) -> f64
+    {
+        let meta = self.meta();
+        let size = meta.current_size;
+        if size <= 0.0 {
+            return 0.0;
+        }
+        let buffer = (margin - self.maintenance_margin(liquidation_threshold)) / size;
+        let avg_price = meta.current_avg_price;
+        if meta.instrument.inverse {
+            let inverse_price = match meta.side {
+                | Side::Buy => 1.0 / avg_price + buffer,
+                | Side::Sell => 1.0 / avg_price - buffer,
+            };
+            return if inverse_price > 0.0 { 1.0 / inverse_price } else { f64::MAX };
+        }
+        match meta.side {
+            | Side::Buy => (avg_price - buffer).max(0.0),
+            | Side::Sell => avg_price + buffer,
+        }
+    }
+
+    /// 计算在 `price` 处让仓位回到维持保证金之上所需的最小强平数量。
+    ///
+    /// 设仓位权益 `E = margin + upnl`，维持保证金系数 `k = maintenance_margin / size`，强平费率为 `r`，
+    /// 平掉比例 `f` 后需满足 `E - r * f * size * price >= k * (1 - f) * size`，
+    /// 由此得到 `f >= (k * size - E) / (size * (k - r * price))`。币本位合约每单位的名义价值为 `1 / price`，公式中的 `price` 相应替换。
+    /// 若权益不足以支付整体强平费，或强平费率不低于维持保证金率（部分强平无法改善保证金率），则整体平仓。
+    fn liquidation_step(&self, price: f64, margin: f64, liquidation_threshold: f64, liquidation_fee_rate: f64) -> LiquidationStep
+    {
+        let size = self.meta().current_size;
+        if size <= 0.0 {
+            return LiquidationStep::none();
+        }
+
+        let equity = margin + self.unrealised_pnl_at(price);
+        let maintenance = self.maintenance_margin(liquidation_threshold);
+        if equity >= maintenance {
+            return LiquidationStep::none();
+        }
+
+        let per_unit_maintenance = maintenance / size;
+        let per_unit_fee = liquidation_fee_rate * self.meta().instrument.notional(price, 1.0);
+        let is_bankrupt = equity - per_unit_fee * size <= 0.0;
+        let fraction = if is_bankrupt || per_unit_maintenance <= per_unit_fee {
+            1.0
+        }
+        else {
+            ((maintenance - equity) / (size * (per_unit_maintenance - per_unit_fee))).min(1.0)
+        };
+
+        let close_size = size * fraction;
+        LiquidationStep { close_size,
+                          fee: per_unit_fee * close_size,
+                          realised_pnl: self.unrealised_pnl_at(price) * fraction,
+                          is_bankrupt }
+    }
+}
+
+/// 每一步强平都会向客户端发送一个 [`PositionLiquidation`] 事件。
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct PositionLiquidation
+{
+    pub exchange: Exchange,
+    pub instrument: Instrument,
+    pub side: Side, // 被强平仓位的方向
+    pub position_id: PositionId,
+    pub margin_mode: PositionMarginMode,
+    pub timestamp: i64,
+    pub price: f64,             // 强平成交价格
+    pub closed_size: f64,       // 本次强平数量
+    pub remaining_size: f64,    // 强平后剩余仓位
+    pub fee: f64,               // 本次强平费
+    pub realised_pnl: f64,      // 本次强平的已实现盈亏
+    pub liquidation_price: f64, // 强平后重新计算的强平价格，仓位被整体平掉时为成交价格
+}
+
+/// 风险准备金不足以弥补穿仓亏损时，盈利的反向仓位被自动减仓，并收到一个 [`PositionAutoDeleverage`] 事件。
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct PositionAutoDeleverage
+{
+    pub exchange: Exchange,
+    pub instrument: Instrument,
+    pub side: Side, // 被减仓仓位的方向
+    pub position_id: PositionId,
+    pub timestamp: i64,
+    pub price: f64,           // 减仓成交价格
+    pub closed_size: f64,     // 减仓数量
+    pub realised_pnl: f64,    // 扣除分摊亏损后的已实现盈亏
+    pub socialised_loss: f64, // 该仓位分摊的穿仓亏损
+    pub ranking_score: f64,   // 减仓排序分数，盈利比例乘以杠杆
+}
+
+/// 现货杠杆账户的风险率跌破追加保证金提醒线时，发送一个 [`MarginCall`] 事件。
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct MarginCall
+{
+    pub exchange: Exchange,
+    pub timestamp: i64,
+    pub margin_level: f64,      // 当前风险率 = 总资产 / 总负债
+    pub margin_call_level: f64, // 提醒线
+}
+
+/// 现货杠杆账户被强平时，发送一个 [`MarginLiquidation`] 事件。
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct MarginLiquidation
+{
+    pub exchange: Exchange,
+    pub timestamp: i64,
+    pub margin_level: f64,           // 触发强平时的风险率
+    pub valuation_token: Token,      // 资产兑换与强平费的计价币种
+    pub fee: f64,                    // 按兑换金额收取的强平费
+    pub repaid: Vec<(Token, f64)>,   // 每个币种归还的借款
+    pub balances: Vec<TokenBalance>, // 强平后各币种的余额，仍有借款说明资产不足以还清
+}
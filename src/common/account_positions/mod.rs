@@ -7,6 +7,7 @@ use crate::{
             perpetual::{PerpetualPosition, PerpetualPositionConfig},
         },
         instrument::{kind::InstrumentKind, Instrument},
+        Side,
     },
     hourglass::config_request::ConfigurationRequest,
 };
@@ -17,14 +18,15 @@ use tokio::sync::RwLock;
 pub(crate) mod exited_position;
 pub mod exited_positions;
 pub mod future;
-pub(crate) mod leveraged_token;
-pub(crate) mod option;
+pub mod leveraged_token;
+pub mod liquidation;
+pub mod option;
 pub(crate) mod perpetual;
 mod position_delta;
 pub(crate) mod position_id;
 pub mod position_meta;
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub enum Position
 {
     Perpetual(PerpetualPosition),
@@ -33,11 +35,25 @@ pub enum Position
     Option(OptionPosition),
 }
 
+impl Position
+{
+    pub fn current_size(&self) -> f64
+    {
+        match self {
+            | Position::Perpetual(position) => position.meta.current_size,
+            | Position::LeveragedToken(position) => position.meta.current_size,
+            | Position::Future(position) => position.meta.current_size,
+            | Position::Option(position) => position.meta.current_size,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct AccountPositions
 {
     pub margin_pos_long: Arc<RwLock<HashMap<Instrument, LeveragedTokenPosition>>>,
     pub margin_pos_short: Arc<RwLock<HashMap<Instrument, LeveragedTokenPosition>>>,
+    pub leveraged_token_pos: Arc<RwLock<HashMap<Instrument, LeveragedTokenPosition>>>, // 杠杆代币的持仓，只有多头一侧
     pub perpetual_pos_long: Arc<RwLock<HashMap<Instrument, PerpetualPosition>>>,
     pub perpetual_pos_short: Arc<RwLock<HashMap<Instrument, PerpetualPosition>>>,
     pub futures_pos_long: Arc<RwLock<HashMap<Instrument, FuturePosition>>>,
@@ -96,9 +112,10 @@ impl Serialize for AccountPositions
         }
 
         // Serialize all fields
-        let mut state = serializer.serialize_struct("AccountPositions", 10)?;
+        let mut state = serializer.serialize_struct("AccountPositions", 11)?;
         state.serialize_field("margin_pos_long", &to_map(&self.margin_pos_long))?;
         state.serialize_field("margin_pos_short", &to_map(&self.margin_pos_short))?;
+        state.serialize_field("leveraged_token_pos", &to_map(&self.leveraged_token_pos))?;
         state.serialize_field("perpetual_pos_long", &to_map(&self.perpetual_pos_long))?;
         state.serialize_field("perpetual_pos_short", &to_map(&self.perpetual_pos_short))?;
         state.serialize_field("futures_pos_long", &to_map(&self.futures_pos_long))?;
@@ -131,6 +148,7 @@ impl PartialEq for AccountPositions
 
         hashmap_eq(&self.margin_pos_long, &other.margin_pos_long)
         && hashmap_eq(&self.margin_pos_short, &other.margin_pos_short)
+        && hashmap_eq(&self.leveraged_token_pos, &other.leveraged_token_pos)
         && hashmap_eq(&self.perpetual_pos_long, &other.perpetual_pos_long)
         && hashmap_eq(&self.perpetual_pos_short, &other.perpetual_pos_short)
         && hashmap_eq(&self.futures_pos_long, &other.futures_pos_long)
@@ -152,6 +170,8 @@ impl<'de> Deserialize<'de> for AccountPositions
         {
             margin_pos_long: HashMap<Instrument, LeveragedTokenPosition>,
             margin_pos_short: HashMap<Instrument, LeveragedTokenPosition>,
+            #[serde(default)]
+            leveraged_token_pos: HashMap<Instrument, LeveragedTokenPosition>,
             perpetual_pos_long: HashMap<Instrument, PerpetualPosition>,
             perpetual_pos_short: HashMap<Instrument, PerpetualPosition>,
             futures_pos_long: HashMap<Instrument, FuturePosition>,
@@ -176,6 +196,7 @@ impl<'de> Deserialize<'de> for AccountPositions
 
         Ok(AccountPositions { margin_pos_long: Arc::new(RwLock::new(data.margin_pos_long)),
                               margin_pos_short: Arc::new(RwLock::new(data.margin_pos_short)),
+                              leveraged_token_pos: Arc::new(RwLock::new(data.leveraged_token_pos)),
                               perpetual_pos_long: Arc::new(RwLock::new(data.perpetual_pos_long)),
                               perpetual_pos_short: Arc::new(RwLock::new(data.perpetual_pos_short)),
                               futures_pos_long: Arc::new(RwLock::new(data.futures_pos_long)),
@@ -204,6 +225,7 @@ impl AccountPositions
     {
         Self { margin_pos_long: Arc::new(RwLock::new(HashMap::new())),
                margin_pos_short: Arc::new(RwLock::new(HashMap::new())),
+               leveraged_token_pos: Arc::new(RwLock::new(HashMap::new())),
                perpetual_pos_long: Arc::new(RwLock::new(HashMap::new())),
                perpetual_pos_short: Arc::new(RwLock::new(HashMap::new())),
                futures_pos_long: Arc::new(RwLock::new(HashMap::new())),
@@ -232,6 +254,41 @@ pub enum PositionDirectionMode
     Net,
 }
 
+/// 订单所作用的仓位。
+///
+/// 单向持仓（`Net`）模式下只能使用 `Both`，由买卖方向自动开仓、加仓或平仓；
+/// 双向持仓（`LongShort`）模式下必须指定 `Long` 或 `Short`，买卖方向决定对该仓位是开仓还是平仓。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Hash, Deserialize, Serialize)]
+pub enum PositionSide
+{
+    Long,
+    Short,
+    #[default]
+    Both,
+}
+
+impl PositionSide
+{
+    /// 返回该订单实际作用的仓位方向，`Side::Buy` 为多头仓位，`Side::Sell` 为空头仓位。`Both` 时与订单方向相同。
+    pub fn leg(&self, order_side: Side) -> Side
+    {
+        match self {
+            | PositionSide::Long => Side::Buy,
+            | PositionSide::Short => Side::Sell,
+            | PositionSide::Both => order_side,
+        }
+    }
+
+    /// 双向持仓模式下，订单方向与所作用的仓位方向相反即为平仓单。
+    pub fn is_closing(&self, order_side: Side) -> bool
+    {
+        match self {
+            | PositionSide::Both => false,
+            | _ => self.leg(order_side) != order_side,
+        }
+    }
+}
+
 #[derive(Clone, PartialOrd, Debug, PartialEq, Deserialize, Serialize)]
 pub enum PositionMarginMode
 {
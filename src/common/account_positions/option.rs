@@ -1,14 +1,41 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    common::account_positions::{position_meta::PositionMeta, PositionDirectionMode, PositionMarginMode},
+    common::{
+        account_positions::{position_id::PositionId, position_meta::PositionMeta, PositionDirectionMode, PositionMarginMode},
+        friction::OptionFees,
+        instrument::Instrument,
+        Side,
+    },
     hourglass::config_request::ConfigurationRequest,
+    Exchange,
 };
 
+/// 欧式期权仓位。多头在开仓时支付权利金，空头收取权利金并为仓位缴纳 `seller_margin`。
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct OptionPosition
 {
     pub meta: PositionMeta,
+    #[serde(default)]
+    pub seller_margin: f64, // 空头仓位占用的保证金，多头为 0
+}
+
+impl OptionPosition
+{
+    /// 按开仓均价计算的权利金总额，多头为已支付的金额，空头为已收取的金额。
+    pub fn premium(&self) -> f64
+    {
+        self.meta.current_avg_price * self.meta.current_size
+    }
+
+    /// 以指数价格计算的到期现金交割金额。
+    pub fn payoff(&self, index_price: f64) -> f64
+    {
+        self.meta
+            .instrument
+            .option_spec
+            .map_or(0.0, |option_spec| option_spec.intrinsic_value(index_price) * self.meta.current_size)
+    }
 }
 
 #[allow(dead_code)]
@@ -29,3 +56,19 @@ impl From<ConfigurationRequest> for OptionPositionConfig
                                position_mode: config_request.position_direction_mode.unwrap()  /* 提供默认值或根据需求处理 None */ }
     }
 }
+
+/// 期权到期后，每个被自动行权或作废的仓位都会向客户端发送一个 [`OptionExercise`] 事件。
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct OptionExercise
+{
+    pub exchange: Exchange,
+    pub instrument: Instrument,
+    pub side: Side, // 被行权仓位的方向
+    pub position_id: PositionId,
+    pub timestamp: i64,   // 行权时的交易所时间
+    pub index_price: f64, // 行权所用的指数价格
+    pub size: f64,        // 行权数量
+    pub payoff: f64,      // 现金交割金额，多头收取、空头支付
+    pub fees: OptionFees, // 行权收取的费用
+    pub exercised: bool,  // 是否为实值期权并被行权，虚值期权到期作废
+}
@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    common::account_positions::{position_meta::PositionMeta, PositionDirectionMode, PositionMarginMode},
+    common::{
+        account_positions::{liquidation::LiquidatablePosition, position_meta::PositionMeta, Position, PositionDirectionMode, PositionMarginMode, PositionSide},
+        Side,
+    },
     hourglass::config_request::ConfigurationRequest,
 };
 
@@ -17,6 +20,16 @@ pub struct PerpetualPosition
 
 impl PerpetualPosition
 {
+    /// 仓位在订单与成交中对应的 [`PositionSide`]，单向持仓模式下为 `Both`。
+    pub fn position_side(&self) -> PositionSide
+    {
+        match (&self.pos_config.position_direction_mode, self.meta.side) {
+            | (PositionDirectionMode::Net, _) => PositionSide::Both,
+            | (PositionDirectionMode::LongShort, Side::Buy) => PositionSide::Long,
+            | (PositionDirectionMode::LongShort, Side::Sell) => PositionSide::Short,
+        }
+    }
+
     /// 更新平仓价格
     pub fn update_liquidation_price(&mut self, new_price: f64)
     {
@@ -34,6 +47,93 @@ impl PerpetualPosition
     {
         self.meta = new_meta;
     }
+
+    /// 按开仓均价计算的初始保证金，以 [`Instrument::margin_token`](crate::common::instrument::Instrument::margin_token) 计。
+    pub fn initial_margin(&self) -> f64
+    {
+        self.meta.instrument.notional(self.meta.current_avg_price, self.meta.current_size) / self.pos_config.leverage
+    }
+
+    /// 维持保证金。与开仓时的强平价格公式保持一致：亏损达到初始保证金的 `liquidation_threshold` 时触发强平，
+    /// 即维持保证金为初始保证金的 `1 - liquidation_threshold`。
+    pub fn maintenance_margin(&self, liquidation_threshold: f64) -> f64
+    {
+        (1.0 - liquidation_threshold) * self.initial_margin()
+    }
+
+    /// 以给定价格计算的未实现盈亏，区分多空方向，币本位合约以 base 计。
+    pub fn unrealised_pnl_at(&self, price: f64) -> f64
+    {
+        self.meta.instrument.pnl(self.meta.side, self.meta.current_avg_price, price, self.meta.current_size)
+    }
+}
+
+impl LiquidatablePosition for PerpetualPosition
+{
+    fn meta(&self) -> &PositionMeta
+    {
+        &self.meta
+    }
+
+    fn meta_mut(&mut self) -> &mut PositionMeta
+    {
+        &mut self.meta
+    }
+
+    fn margin_mode(&self) -> &PositionMarginMode
+    {
+        &self.pos_config.pos_margin_mode
+    }
+
+    fn position_direction_mode(&self) -> &PositionDirectionMode
+    {
+        &self.pos_config.position_direction_mode
+    }
+
+    fn leverage(&self) -> f64
+    {
+        self.pos_config.leverage
+    }
+
+    fn position_side(&self) -> PositionSide
+    {
+        PerpetualPosition::position_side(self)
+    }
+
+    fn isolated_margin(&self) -> Option<f64>
+    {
+        self.isolated_margin
+    }
+
+    fn set_isolated_margin(&mut self, isolated_margin: Option<f64>)
+    {
+        self.isolated_margin = isolated_margin;
+    }
+
+    fn update_liquidation_price(&mut self, new_price: f64)
+    {
+        PerpetualPosition::update_liquidation_price(self, new_price)
+    }
+
+    fn initial_margin(&self) -> f64
+    {
+        PerpetualPosition::initial_margin(self)
+    }
+
+    fn maintenance_margin(&self, liquidation_threshold: f64) -> f64
+    {
+        PerpetualPosition::maintenance_margin(self, liquidation_threshold)
+    }
+
+    fn unrealised_pnl_at(&self, price: f64) -> f64
+    {
+        PerpetualPosition::unrealised_pnl_at(self, price)
+    }
+
+    fn into_position(self) -> Position
+    {
+        Position::Perpetual(self)
+    }
 }
 
 #[allow(dead_code)]
@@ -46,6 +146,24 @@ pub struct PerpetualPositionConfig
     pub(crate) position_direction_mode: PositionDirectionMode,
 }
 
+impl PerpetualPositionConfig
+{
+    pub fn margin_mode(&self) -> &PositionMarginMode
+    {
+        &self.pos_margin_mode
+    }
+
+    pub fn leverage(&self) -> f64
+    {
+        self.leverage
+    }
+
+    pub fn position_direction_mode(&self) -> &PositionDirectionMode
+    {
+        &self.position_direction_mode
+    }
+}
+
 impl From<ConfigurationRequest> for PerpetualPositionConfig
 {
     fn from(config_request: ConfigurationRequest) -> Self
@@ -149,4 +267,90 @@ mod tests
         position.update_liquidation_price(150.0);
         assert_eq!(position.liquidation_price, 150.0);
     }
+
+    fn long_position(size: f64, avg_price: f64, leverage: f64) -> PerpetualPosition
+    {
+        PerpetualPosition { meta: PositionMeta { position_id: PositionId(1),
+                                                 enter_ts: 1625247600,
+                                                 update_ts: 1625247600,
+                                                 exchange: Exchange::Hourglass,
+                                                 instrument: Instrument::new("BTC", "USDT", InstrumentKind::Perpetual),
+                                                 side: Side::Buy,
+                                                 current_size: size,
+                                                 current_fees_total: 0.0,
+                                                 current_avg_price_gross: avg_price,
+                                                 current_symbol_price: avg_price,
+                                                 current_avg_price: avg_price,
+                                                 unrealised_pnl: 0.0,
+                                                 realised_pnl: 0.0 },
+                            pos_config: PerpetualPositionConfig { pos_margin_mode: PositionMarginMode::Isolated,
+                                                                  leverage,
+                                                                  position_direction_mode: PositionDirectionMode::Net },
+                            isolated_margin: Some(avg_price * size / leverage),
+                            liquidation_price: 0.0 }
+    }
+
+    #[test]
+    fn liquidation_price_should_match_opening_formula_for_fresh_position()
+    {
+        let position = long_position(10.0, 100.0, 10.0);
+        assert!((position.compute_liquidation_price(100.0, 0.9) - 91.0).abs() < 1e-9);
+
+        let mut short = position.clone();
+        short.meta.side = Side::Sell;
+        assert!((short.compute_liquidation_price(100.0, 0.9) - 109.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn liquidation_step_should_close_minimum_fraction_to_restore_maintenance_margin()
+    {
+        let position = long_position(10.0, 100.0, 10.0);
+
+        // 维持保证金仍然充足
+        assert!(!position.liquidation_step(91.5, 100.0, 0.9, 0.005).is_required());
+
+        let step = position.liquidation_step(90.5, 100.0, 0.9, 0.005);
+        assert!(step.is_required());
+        assert!(!step.is_bankrupt);
+        assert!(step.close_size > 0.0 && step.close_size < 10.0);
+        assert!((step.fee - step.close_size * 90.5 * 0.005).abs() < 1e-9);
+        assert!((step.realised_pnl + 9.5 * step.close_size).abs() < 1e-9);
+
+        // 强平后剩余权益恰好等于剩余仓位的维持保证金
+        let remaining_equity = 100.0 - 95.0 - step.fee;
+        let remaining_maintenance = 1.0 * (10.0 - step.close_size);
+        assert!((remaining_equity - remaining_maintenance).abs() < 1e-9);
+    }
+
+    #[test]
+    fn inverse_position_should_compute_margin_pnl_and_liquidation_in_base()
+    {
+        let mut position = long_position(10_000.0, 50_000.0, 10.0);
+        position.meta.instrument = Instrument::inverse_perpetual("BTC", "USD");
+        let margin = position.initial_margin();
+        assert!((margin - 0.02).abs() < 1e-12);
+        assert!((position.unrealised_pnl_at(40_000.0) + 0.05).abs() < 1e-12);
+
+        let opening = position.meta.instrument.opening_liquidation_price(50_000.0, Side::Buy, 10.0, 0.9);
+        assert!((position.compute_liquidation_price(margin, 0.9) - opening).abs() < 1e-6);
+        let mut short = position.clone();
+        short.meta.side = Side::Sell;
+        assert!((short.unrealised_pnl_at(40_000.0) - 0.05).abs() < 1e-12);
+        assert!((short.compute_liquidation_price(margin, 0.9) - 50_000.0 / 0.91).abs() < 1e-6);
+
+        // 强平费按以 BTC 计的名义价值收取
+        let step = position.liquidation_step(45_000.0, margin, 0.9, 0.005);
+        assert!(step.is_required());
+        assert!((step.fee - step.close_size / 45_000.0 * 0.005).abs() < 1e-12);
+    }
+
+    #[test]
+    fn liquidation_step_should_close_everything_when_bankrupt()
+    {
+        let position = long_position(10.0, 100.0, 10.0);
+        let step = position.liquidation_step(85.0, 100.0, 0.9, 0.005);
+        assert!(step.is_bankrupt);
+        assert_eq!(step.close_size, 10.0);
+        assert!((step.realised_pnl + 150.0).abs() < 1e-9);
+    }
 }
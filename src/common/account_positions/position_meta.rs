@@ -128,18 +128,18 @@ impl PositionMeta
         self.current_avg_price = self.current_avg_price_gross;
     }
 
-    /// 更新 unrealised_pnl
+    /// 更新 unrealised_pnl，按 [`Instrument::pnl`] 区分多空方向与币本位合约
     /// FIXME 在更新未实现盈亏时，现在使用 self.current_size 来计算，但是在反向仓位或部分平仓的情况下，会不会有问题，
     /// FIXME 因为仓位大小已经发生变化。建议确保每次在更新未实现盈亏时，考虑实际持仓方向和剩余仓位大小。
     pub fn update_unrealised_pnl(&mut self)
     {
-        self.unrealised_pnl = (self.current_symbol_price - self.current_avg_price) * self.current_size;
+        self.unrealised_pnl = self.instrument.pnl(self.side, self.current_avg_price, self.current_symbol_price, self.current_size);
     }
 
-    /// 更新 realised_pnl 并清空持仓
+    /// 更新 realised_pnl 并清空持仓，币本位合约的盈亏以 base 计
     pub fn update_realised_pnl(&mut self, closing_price: f64)
     {
-        self.realised_pnl = (closing_price - self.current_avg_price) * self.current_size;
+        self.realised_pnl = self.instrument.pnl(self.side, self.current_avg_price, closing_price, self.current_size);
         // 清空当前持仓
         self.current_size = 0.0;
         self.current_avg_price = 0.0;
@@ -303,6 +303,7 @@ mod tests
 {
     use super::*;
     use crate::common::{
+        account_positions::PositionSide,
         instrument::{kind::InstrumentKind, Instrument},
         order::identification::OrderId,
         trade::{ClientTrade, ClientTradeId},
@@ -321,7 +322,8 @@ mod tests
                       side: Side::Buy,
                       price: 50_000.0,
                       size: 1.0,
-                      fees: 2.0 }
+                      fees: 2.0,
+                      position_side: PositionSide::Both }
     }
 
     #[test]
@@ -371,7 +373,8 @@ mod tests
                                       side: Side::Buy,
                                       price: 60_000.0,
                                       size: 1.0,
-                                      fees: 2.0 };
+                                      fees: 2.0,
+                                      position_side: PositionSide::Both };
 
         meta.update_from_trade(&new_trade);
 
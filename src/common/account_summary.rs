@@ -0,0 +1,78 @@
+use crate::common::token::Token;
+use serde::{Deserialize, Serialize};
+
+/// 以某个结算币种计价的账户风险概览。
+///
+/// 未实现盈亏按各仓位的标记价格（单层订单簿中的最新成交价）计算，仅统计以该币种结算的永续合约与交割合约仓位。
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct AccountSummary
+{
+    pub token: Token,             // 结算币种
+    pub wallet_balance: f64,      // 钱包余额，即扣除借款后的 `Balance::net`
+    pub available_balance: f64,   // 可用余额，即 `Balance::available`
+    pub unrealised_pnl: f64,      // 所有仓位的未实现盈亏
+    pub equity: f64,              // 账户权益 = 钱包余额 + 未实现盈亏
+    pub initial_margin: f64,      // 仓位占用的初始保证金，逐仓仓位按 `isolated_margin` 计
+    pub maintenance_margin: f64,  // 仓位的维持保证金
+    pub margin_ratio: f64,        // 保证金率 = 维持保证金 / 账户权益，达到 1 即触发强平
+    pub available_margin: f64,    // 可用于开新仓的保证金 = 可用余额 + 全仓未实现盈亏
+    pub max_withdrawable: f64,    // 最大可提取金额，全仓未实现利润不可提取
+}
+
+impl AccountSummary
+{
+    /// 根据余额与仓位的汇总数据构造账户概览。`cross_unrealised_pnl` 为全仓仓位的未实现盈亏，
+    /// 逐仓仓位的盈亏只由其自身保证金承担，不影响账户的可用保证金。
+    pub fn new(token: Token, wallet_balance: f64, available_balance: f64, unrealised_pnl: f64, cross_unrealised_pnl: f64, initial_margin: f64, maintenance_margin: f64) -> Self
+    {
+        let equity = wallet_balance + unrealised_pnl;
+        let margin_ratio = if maintenance_margin <= 0.0 {
+            0.0
+        }
+        else if equity <= 0.0 {
+            f64::INFINITY
+        }
+        else {
+            maintenance_margin / equity
+        };
+
+        Self { token,
+               wallet_balance,
+               available_balance,
+               unrealised_pnl,
+               equity,
+               initial_margin,
+               maintenance_margin,
+               margin_ratio,
+               available_margin: available_balance + cross_unrealised_pnl,
+               max_withdrawable: (available_balance + cross_unrealised_pnl.min(0.0)).max(0.0) }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn account_summary_should_exclude_unrealised_profit_from_withdrawable()
+    {
+        let summary = AccountSummary::new(Token::from("USDT"), 1_000.0, 800.0, 50.0, 50.0, 200.0, 20.0);
+        assert_eq!(summary.equity, 1_050.0);
+        assert_eq!(summary.available_margin, 850.0);
+        assert_eq!(summary.max_withdrawable, 800.0);
+        assert!((summary.margin_ratio - 20.0 / 1_050.0).abs() < 1e-12);
+
+        let summary = AccountSummary::new(Token::from("USDT"), 1_000.0, 800.0, -900.0, -900.0, 200.0, 20.0);
+        assert_eq!(summary.max_withdrawable, 0.0);
+        assert_eq!(summary.available_margin, -100.0);
+    }
+
+    #[test]
+    fn account_summary_without_positions_should_have_zero_margin_ratio()
+    {
+        let summary = AccountSummary::new(Token::from("USDT"), 1_000.0, 1_000.0, 0.0, 0.0, 0.0, 0.0);
+        assert_eq!(summary.margin_ratio, 0.0);
+        assert_eq!(summary.max_withdrawable, 1_000.0);
+    }
+}
@@ -18,7 +18,9 @@ impl TokenBalance
     }
 }
 
-/// 总余额和可用余额。
+/// 总余额、可用余额与借入的数额。
+///
+/// 现货杠杆借入的资金计入 `total` 与 `available`，同时记入 `borrowed`，净资产为 `total - borrowed`。
 #[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Deserialize, Serialize)]
 pub struct Balance
 {
@@ -26,6 +28,8 @@ pub struct Balance
     // pub current_price: Option<f64>, // NOTE 当前价格 newly added on 1st Aug 2024
     pub total: f64,     // 总额
     pub available: f64, // 可用余额
+    #[serde(default)]
+    pub borrowed: f64,  // 借入未还的数额，包含已计提的利息
 }
 
 impl Balance
@@ -33,7 +37,10 @@ impl Balance
     /// 构造一个新的[`Balance`]。
     pub fn new(total: f64, available: f64) -> Self
     {
-        Self { time: Utc::now(), total, available }
+        Self { time: Utc::now(),
+               total,
+               available,
+               borrowed: 0.0 }
     }
 
     /// 计算使用过的余额（`total` - `available`）。
@@ -42,6 +49,32 @@ impl Balance
         self.total - self.available
     }
 
+    /// 扣除借款后的净资产（`total` - `borrowed`）。
+    pub fn net(&self) -> f64
+    {
+        self.total - self.borrowed
+    }
+
+    /// 借入 `amount`，借入的资金立即可用。
+    pub fn borrow(&mut self, amount: f64)
+    {
+        self.total += amount;
+        self.available += amount;
+        self.borrowed += amount;
+        self.time = Utc::now();
+    }
+
+    /// 用可用余额归还最多 `amount` 的借款，返回实际归还的数额。
+    pub fn repay(&mut self, amount: f64) -> f64
+    {
+        let repaid = amount.min(self.borrowed).min(self.available).max(0.0);
+        self.total -= repaid;
+        self.available -= repaid;
+        self.borrowed -= repaid;
+        self.time = Utc::now();
+        repaid
+    }
+
     /// 对这个[`Balance`]应用一个[`BalanceDelta`]。
     pub fn apply(&mut self, delta: BalanceDelta) -> Result<(), &'static str>
     {
@@ -114,6 +147,20 @@ mod tests
         assert_eq!(balance.available, 55.0);
     }
 
+    #[test]
+    fn balance_repay_should_be_limited_by_loan_and_available_balance()
+    {
+        let mut balance = Balance::new(100.0, 50.0);
+        balance.borrow(200.0);
+        assert_eq!((balance.total, balance.available, balance.borrowed), (300.0, 250.0, 200.0));
+        assert_eq!(balance.net(), 100.0);
+
+        balance.available = 120.0;
+        assert_eq!(balance.repay(500.0), 120.0);
+        assert_eq!((balance.total, balance.available, balance.borrowed), (180.0, 0.0, 80.0));
+        assert_eq!(balance.net(), 100.0);
+    }
+
     #[test]
     fn balance_delta_new_should_create_balance_delta()
     {
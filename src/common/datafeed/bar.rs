@@ -0,0 +1,338 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{common::datafeed::replay::ReplaySource, error::ExchangeError, hourglass::clickhouse_api::datatype::clickhouse_trade_data::MarketTrade};
+
+/// 一根 OHLCV K 线。
+///
+/// 时间 K 线的 `open_time` / `close_time` 为区间的起止时间（左闭右开），其余 K 线为第一笔与最后一笔成交的时间。
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Bar
+{
+    pub exchange: String,
+    pub symbol: String,
+    pub open_time: i64,
+    pub close_time: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64, // 成交量（基础币种）
+    #[serde(default)]
+    pub quote_volume: f64, // 成交额（计价币种）
+    #[serde(default)]
+    pub trade_count: u64, // 成交笔数
+}
+
+impl Bar
+{
+    fn from_trade(trade: &MarketTrade, open_time: i64, close_time: i64) -> Self
+    {
+        Bar { exchange: trade.exchange.clone(),
+              symbol: trade.symbol.clone(),
+              open_time,
+              close_time,
+              open: trade.price,
+              high: trade.price,
+              low: trade.price,
+              close: trade.price,
+              volume: trade.amount,
+              quote_volume: trade.price * trade.amount,
+              trade_count: 1 }
+    }
+
+    fn add(&mut self, trade: &MarketTrade)
+    {
+        self.high = self.high.max(trade.price);
+        self.low = self.low.min(trade.price);
+        self.close = trade.price;
+        self.volume += trade.amount;
+        self.quote_volume += trade.price * trade.amount;
+        self.trade_count += 1;
+    }
+
+    /// 按 `high_first` 指定的顺序把 K 线展开为开、高、低、收（或开、低、高、收）四笔合成成交。
+    ///
+    /// 成交量平均分配到四笔成交上，时间在 `open_time` 与 `close_time` 之间均匀分布。价格高于前一笔的成交记为买方成交，
+    /// 可以与不高于该价格的卖单撮合；低于前一笔的记为卖方成交，可以与不低于该价格的买单撮合。
+    pub fn to_trades(&self, high_first: bool) -> Vec<MarketTrade>
+    {
+        let prices = match high_first {
+            | true => [self.open, self.high, self.low, self.close],
+            | false => [self.open, self.low, self.high, self.close],
+        };
+        let last = self.close_time.max(self.open_time);
+        (0..prices.len()).map(|index| {
+                             let rising = match index {
+                                 | 0 => prices[0] > prices[1],
+                                 | _ => prices[index] >= prices[index - 1],
+                             };
+                             MarketTrade { exchange: self.exchange.clone(),
+                                           symbol: self.symbol.clone(),
+                                           timestamp: self.open_time + (last - self.open_time) * index as i64 / 3,
+                                           price: prices[index],
+                                           side: if rising { "buy" } else { "sell" }.to_string(),
+                                           amount: self.volume / prices.len() as f64 }
+                         })
+                         .collect()
+    }
+}
+
+/// K 线的划分方式。
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarSpec
+{
+    Time(i64),   // 固定时长（毫秒）的时间 K 线，按时间戳对齐
+    Tick(u64),   // 每 N 笔成交一根
+    Volume(f64), // 成交量达到阈值时完成
+    Dollar(f64), // 成交额达到阈值时完成
+}
+
+impl BarSpec
+{
+    /// 时长与阈值必须为正，否则时间 K 线无法对齐，其余 K 线会在每笔成交上完成。
+    pub fn validate(&self) -> Result<(), ExchangeError>
+    {
+        let valid = match *self {
+            | BarSpec::Time(interval) => interval > 0,
+            | BarSpec::Tick(count) => count > 0,
+            | BarSpec::Volume(threshold) | BarSpec::Dollar(threshold) => threshold > 0.0 && threshold.is_finite(),
+        };
+        match valid {
+            | true => Ok(()),
+            | false => Err(ExchangeError::ConfigParseError(format!("Bar spec {:?} must be positive", self))),
+        }
+    }
+}
+
+/// 把逐笔成交按 [`BarSpec`] 聚合为 K 线，每个 `(exchange, symbol)` 各自维护一根正在形成的 K 线。
+///
+/// 时间 K 线在出现下一个区间的成交时完成；成交笔数、成交量与成交额 K 线在达到阈值的那笔成交上完成，成交不会被拆分。
+#[derive(Clone, Debug)]
+pub struct BarAggregator
+{
+    spec: BarSpec,
+    building: HashMap<(String, String), Bar>, // (exchange, symbol) -> 正在形成的 K 线
+}
+
+impl BarAggregator
+{
+    pub fn new(spec: BarSpec) -> Self
+    {
+        Self { spec, building: HashMap::new() }
+    }
+
+    pub fn spec(&self) -> BarSpec
+    {
+        self.spec
+    }
+
+    /// 加入一笔成交，返回因此完成的 K 线。
+    pub fn update(&mut self, trade: &MarketTrade) -> Option<Bar>
+    {
+        let key = (trade.exchange.clone(), trade.symbol.clone());
+        match self.spec {
+            | BarSpec::Time(interval) => {
+                let open_time = trade.timestamp - trade.timestamp.rem_euclid(interval);
+                match self.building.get_mut(&key) {
+                    | Some(bar) if bar.open_time == open_time => {
+                        bar.add(trade);
+                        None
+                    }
+                    // 进入新的区间，上一根 K 线完成
+                    | _ => self.building.insert(key, Bar::from_trade(trade, open_time, open_time + interval)),
+                }
+            }
+            | _ => {
+                let bar = self.building
+                              .entry(key.clone())
+                              .and_modify(|bar| {
+                                  bar.add(trade);
+                                  bar.close_time = trade.timestamp;
+                              })
+                              .or_insert_with(|| Bar::from_trade(trade, trade.timestamp, trade.timestamp));
+                let completed = match self.spec {
+                    | BarSpec::Tick(count) => bar.trade_count >= count,
+                    | BarSpec::Volume(volume) => bar.volume >= volume,
+                    | BarSpec::Dollar(value) => bar.quote_volume >= value,
+                    | BarSpec::Time(_) => false,
+                };
+                completed.then(|| self.building.remove(&key)).flatten()
+            }
+        }
+    }
+
+    /// 数据结束时取出所有尚未完成的 K 线，按开盘时间排序。
+    pub fn flush(&mut self) -> Vec<Bar>
+    {
+        let mut bars: Vec<Bar> = self.building.drain().map(|(_, bar)| bar).collect();
+        bars.sort_by(|a, b| (a.open_time, &a.exchange, &a.symbol).cmp(&(b.open_time, &b.exchange, &b.symbol)));
+        bars
+    }
+}
+
+/// 只有 K 线数据时，K 线内部价格路径的假设，决定挂单在 K 线内的成交顺序。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntrabarFill
+{
+    OpenHighLowClose, // 先到最高价再到最低价
+    OpenLowHighClose, // 先到最低价再到最高价
+    WorstCase,        // 对当前持仓不利的极值先到达
+}
+
+impl IntrabarFill
+{
+    /// 给定金融工具当前的净持仓（多头为正），判断 K 线内是否先到达最高价。
+    ///
+    /// `WorstCase` 下持有多头时先到最低价，持有空头时先到最高价；没有持仓时走更长的路径，即阳线先到最低价、阴线先到最高价。
+    pub fn high_first(&self, bar: &Bar, net_position: f64) -> bool
+    {
+        match self {
+            | IntrabarFill::OpenHighLowClose => true,
+            | IntrabarFill::OpenLowHighClose => false,
+            | IntrabarFill::WorstCase if net_position > 0.0 => false,
+            | IntrabarFill::WorstCase if net_position < 0.0 => true,
+            | IntrabarFill::WorstCase => bar.close < bar.open,
+        }
+    }
+}
+
+/// 只有 K 线数据时的回测数据源：逐根读取 K 线，并按 [`IntrabarFill`] 展开为合成成交逐笔回放。
+pub struct BarReplay
+{
+    source: Box<dyn ReplaySource<Bar>>,
+    fill: IntrabarFill,
+    pending: VecDeque<MarketTrade>, // 当前 K 线尚未回放的合成成交
+    current: Option<Bar>,           // 正在回放的 K 线，最后一笔合成成交回放后完成
+    completed: Option<Bar>,         // 已完成、尚未取走的 K 线
+}
+
+impl BarReplay
+{
+    pub fn new(source: impl ReplaySource<Bar> + 'static, fill: IntrabarFill) -> Self
+    {
+        Self { source: Box::new(source),
+               fill,
+               pending: VecDeque::new(),
+               current: None,
+               completed: None }
+    }
+
+    pub fn fill(&self) -> IntrabarFill
+    {
+        self.fill
+    }
+
+    /// 当前 K 线的合成成交是否已全部回放。
+    pub fn is_bar_finished(&self) -> bool
+    {
+        self.pending.is_empty()
+    }
+
+    /// 读取下一根 K 线，数据源耗尽时返回 `None`。
+    pub async fn next_bar(&mut self) -> Result<Option<Bar>, ExchangeError>
+    {
+        self.source.next_event().await
+    }
+
+    /// 开始回放一根 K 线。
+    pub fn start_bar(&mut self, bar: Bar, high_first: bool)
+    {
+        self.pending = bar.to_trades(high_first).into();
+        self.current = Some(bar);
+    }
+
+    /// 取出下一笔合成成交；取出 K 线的最后一笔成交后该 K 线完成。
+    pub fn next_trade(&mut self) -> Option<MarketTrade>
+    {
+        let trade = self.pending.pop_front()?;
+        if self.pending.is_empty() {
+            self.completed = self.current.take();
+        }
+        Some(trade)
+    }
+
+    /// 取走已完成的 K 线。最后一笔合成成交被数据校验丢弃时，K 线在下一次取走时一并返回，不会丢失。
+    pub fn take_completed_bar(&mut self) -> Option<Bar>
+    {
+        self.completed.take()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_utils::create_test_market_trade;
+
+    #[test]
+    fn bar_aggregator_should_complete_time_tick_volume_and_dollar_bars()
+    {
+        let trades = [create_test_market_trade("BTCUSDT", 1_000, 100.0, "buy", 1.0),
+                      create_test_market_trade("BTCUSDT", 30_000, 105.0, "buy", 2.0),
+                      create_test_market_trade("ETHUSDT", 45_000, 10.0, "buy", 5.0),
+                      create_test_market_trade("BTCUSDT", 59_999, 98.0, "buy", 1.0),
+                      create_test_market_trade("BTCUSDT", 60_000, 101.0, "buy", 4.0)];
+
+        let mut time = BarAggregator::new(BarSpec::Time(60_000));
+        let completed: Vec<Bar> = trades.iter().filter_map(|trade| time.update(trade)).collect();
+        assert_eq!(completed.len(), 1);
+        let bar = &completed[0];
+        assert_eq!((bar.symbol.as_str(), bar.open_time, bar.close_time), ("BTCUSDT", 0, 60_000));
+        assert_eq!((bar.open, bar.high, bar.low, bar.close, bar.volume, bar.quote_volume, bar.trade_count), (100.0, 105.0, 98.0, 98.0, 4.0, 408.0, 3));
+        // 数据结束时输出未完成的 K 线
+        let flushed: Vec<(String, i64)> = time.flush().into_iter().map(|bar| (bar.symbol, bar.open_time)).collect();
+        assert_eq!(flushed, vec![("ETHUSDT".to_string(), 0), ("BTCUSDT".to_string(), 60_000)]);
+
+        let mut tick = BarAggregator::new(BarSpec::Tick(2));
+        let completed: Vec<(f64, f64, i64, i64)> = trades.iter().filter_map(|trade| tick.update(trade)).map(|bar| (bar.open, bar.close, bar.open_time, bar.close_time)).collect();
+        assert_eq!(completed, vec![(100.0, 105.0, 1_000, 30_000), (98.0, 101.0, 59_999, 60_000)]);
+
+        let mut volume = BarAggregator::new(BarSpec::Volume(3.0));
+        let completed: Vec<f64> = trades.iter().filter_map(|trade| volume.update(trade)).map(|bar| bar.volume).collect();
+        assert_eq!(completed, vec![3.0, 5.0, 5.0]);
+
+        let mut dollar = BarAggregator::new(BarSpec::Dollar(400.0));
+        let completed: Vec<(String, f64)> = trades.iter().filter_map(|trade| dollar.update(trade)).map(|bar| (bar.symbol, bar.quote_volume)).collect();
+        assert_eq!(completed, vec![("BTCUSDT".to_string(), 408.0), ("BTCUSDT".to_string(), 404.0)]);
+    }
+
+    #[tokio::test]
+    async fn bar_replay_should_expand_bars_by_intrabar_fill_assumption()
+    {
+        let bar = Bar { exchange: "binance-futures".to_string(),
+                        symbol: "BTCUSDT".to_string(),
+                        open_time: 0,
+                        close_time: 60_000,
+                        open: 100.0,
+                        high: 110.0,
+                        low: 90.0,
+                        close: 105.0,
+                        volume: 8.0,
+                        quote_volume: 0.0,
+                        trade_count: 0 };
+
+        let path = |high_first: bool| bar.to_trades(high_first).into_iter().map(|trade| (trade.timestamp, trade.price, trade.side, trade.amount)).collect::<Vec<_>>();
+        assert_eq!(path(true),
+                   vec![(0, 100.0, "sell".to_string(), 2.0), (20_000, 110.0, "buy".to_string(), 2.0), (40_000, 90.0, "sell".to_string(), 2.0), (60_000, 105.0, "buy".to_string(), 2.0)]);
+        assert_eq!(path(false).iter().map(|(_, price, side, _)| (*price, side.as_str())).collect::<Vec<_>>(), vec![(100.0, "buy"), (90.0, "sell"), (110.0, "buy"), (105.0, "sell")]);
+
+        // 持有多头时先到最低价，持有空头时先到最高价，无持仓的阳线走更长的路径
+        assert!(!IntrabarFill::WorstCase.high_first(&bar, 1.0));
+        assert!(IntrabarFill::WorstCase.high_first(&bar, -1.0));
+        assert!(!IntrabarFill::WorstCase.high_first(&bar, 0.0));
+        assert!(IntrabarFill::OpenHighLowClose.high_first(&bar, 1.0));
+
+        let mut replay = BarReplay::new(VecDeque::from(vec![bar.clone()]), IntrabarFill::OpenHighLowClose);
+        let next = replay.next_bar().await.unwrap().unwrap();
+        let high_first = replay.fill().high_first(&next, 0.0);
+        replay.start_bar(next, high_first);
+        let completed: Vec<Option<Bar>> = std::iter::from_fn(|| replay.next_trade().map(|_| replay.take_completed_bar())).collect();
+        assert_eq!(completed, vec![None, None, None, Some(bar)]);
+        assert!(replay.is_bar_finished());
+        assert!(replay.next_bar().await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,298 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use csv::StringRecord;
+use flate2::read::MultiGzDecoder;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{common::datafeed::replay::ReplaySource, error::ExchangeError};
+
+/// 本地行情文件的格式。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileFormat
+{
+    Csv,       // 首行为列名的 CSV
+    JsonLines, // 每行一个 JSON 对象
+}
+
+impl FileFormat
+{
+    /// 根据扩展名推断格式，忽略末尾的 `.gz`。
+    pub fn from_path(path: &Path) -> Option<Self>
+    {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        let name = name.strip_suffix(".gz").unwrap_or(&name);
+        if name.ends_with(".csv") {
+            Some(FileFormat::Csv)
+        }
+        else if name.ends_with(".jsonl") || name.ends_with(".ndjson") {
+            Some(FileFormat::JsonLines)
+        }
+        else {
+            None
+        }
+    }
+}
+
+/// 本地行情文件数据源的配置，可以直接写在 TOML 配置中。
+///
+/// ```toml
+/// path = "data/binance_futures_trades_2024_05_05.csv.gz"
+/// columns = { local_timestamp = "timestamp", qty = "amount" }
+/// divisors = { timestamp = 1000 }
+/// ```
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct FileSourceConfig
+{
+    pub path: PathBuf,
+    #[serde(default)]
+    pub format: Option<FileFormat>, // 为空时根据扩展名推断
+    #[serde(default)]
+    pub gzip: Option<bool>, // 为空时根据 `.gz` 扩展名判断
+    #[serde(default)]
+    pub columns: HashMap<String, String>, // 文件中的列名 -> 数据结构的字段名，未列出的列保持原名
+    #[serde(default)]
+    pub divisors: HashMap<String, i64>, // 字段名 -> 读取时向下整除的除数，例如把微秒时间戳换算为毫秒
+}
+
+impl FileSourceConfig
+{
+    pub fn new(path: impl Into<PathBuf>) -> Self
+    {
+        Self { path: path.into(),
+               format: None,
+               gzip: None,
+               columns: HashMap::new(),
+               divisors: HashMap::new() }
+    }
+
+    /// 把文件中的列 `column` 映射为字段 `field`。
+    pub fn column(mut self, column: impl Into<String>, field: impl Into<String>) -> Self
+    {
+        self.columns.insert(column.into(), field.into());
+        self
+    }
+
+    /// 读取时把整数字段 `field`（列名映射之后的名称）向下整除 `divisor`。
+    pub fn divide(mut self, field: impl Into<String>, divisor: i64) -> Self
+    {
+        self.divisors.insert(field.into(), divisor);
+        self
+    }
+}
+
+/// 从本地 CSV 或 JSON Lines 文件逐条读取行情数据，不依赖 ClickHouse。
+///
+/// 数据类型 `T` 可以是 `MarketTrade`，也可以是订单簿快照等任意可反序列化的结构。文件必须已按时间升序排列，
+/// 多个文件可以通过 [`ReplayMerger`](crate::common::datafeed::replay::ReplayMerger) 归并。
+/// gzip 压缩的文件（包括多个 gzip 成员拼接而成的文件）在读取时流式解压。
+pub struct FileSource<T>
+{
+    path: PathBuf,
+    reader: FileReader,
+    columns: HashMap<String, String>,
+    divisors: HashMap<String, i64>,
+    line: usize, // 已读取的数据行数，用于定位错误
+    _data: PhantomData<fn() -> T>,
+}
+
+enum FileReader
+{
+    Csv
+    {
+        reader: csv::Reader<Box<dyn Read + Send>>,
+        headers: StringRecord,      // 经过列名映射后的表头
+        divided: Vec<(usize, i64)>, // 需要整除的列序号与除数
+    },
+    JsonLines(Box<dyn BufRead + Send>),
+}
+
+impl<T> FileSource<T> where T: DeserializeOwned
+{
+    pub fn open(config: FileSourceConfig) -> Result<Self, ExchangeError>
+    {
+        let FileSourceConfig { path, format, gzip, columns, divisors } = config;
+        if let Some((field, divisor)) = divisors.iter().find(|(_, divisor)| **divisor <= 0) {
+            return Err(ExchangeError::ConfigParseError(format!("Divisor for {} in {} must be positive, got {}", field, path.display(), divisor)));
+        }
+        let format = format.or_else(|| FileFormat::from_path(&path))
+                           .ok_or_else(|| ExchangeError::ConfigParseError(format!("Unable to infer the format of {}", path.display())))?;
+        let gzip = gzip.unwrap_or_else(|| path.extension().is_some_and(|extension| extension == "gz"));
+
+        let file = File::open(&path).map_err(|error| Self::io_error(&path, error))?;
+        let raw: Box<dyn Read + Send> = if gzip { Box::new(MultiGzDecoder::new(file)) } else { Box::new(file) };
+
+        let reader = match format {
+            | FileFormat::Csv => {
+                let mut reader = csv::ReaderBuilder::new().has_headers(true).trim(csv::Trim::All).from_reader(raw);
+                let headers: StringRecord = reader.headers()
+                                                  .map_err(|error| ExchangeError::MarketDataError(format!("{}: {}", path.display(), error)))?
+                                                  .iter()
+                                                  .map(|column| columns.get(column).map(String::as_str).unwrap_or(column))
+                                                  .collect();
+                let divided = headers.iter().enumerate().filter_map(|(index, field)| divisors.get(field).map(|divisor| (index, *divisor))).collect();
+                FileReader::Csv { reader, headers, divided }
+            }
+            | FileFormat::JsonLines => FileReader::JsonLines(Box::new(BufReader::new(raw))),
+        };
+
+        Ok(Self { path,
+                  reader,
+                  columns,
+                  divisors,
+                  line: 0,
+                  _data: PhantomData })
+    }
+
+    /// 读取下一条数据，文件读完时返回 `None`。
+    pub fn next_record(&mut self) -> Result<Option<T>, ExchangeError>
+    {
+        match &mut self.reader {
+            | FileReader::Csv { reader, headers, divided } => {
+                let mut record = StringRecord::new();
+                let has_record = reader.read_record(&mut record).map_err(|error| ExchangeError::MarketDataError(format!("{}: {}", self.path.display(), error)))?;
+                if !has_record {
+                    return Ok(None);
+                }
+                self.line += 1;
+                if !divided.is_empty() {
+                    let mut values: Vec<String> = record.iter().map(String::from).collect();
+                    for &(index, divisor) in divided.iter() {
+                        if let Some(value) = values.get_mut(index).filter(|value| !value.is_empty()) {
+                            let parsed = value.parse::<i64>().map_err(|error| ExchangeError::MarketDataError(format!("{} line {}: {}", self.path.display(), self.line, error)))?;
+                            *value = parsed.div_euclid(divisor).to_string();
+                        }
+                    }
+                    record = StringRecord::from(values);
+                }
+                record.deserialize(Some(headers)).map(Some).map_err(|error| self.parse_error(error))
+            }
+            | FileReader::JsonLines(reader) => loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).map_err(|error| Self::io_error(&self.path, error))? == 0 {
+                    return Ok(None);
+                }
+                self.line += 1;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let value = serde_json::from_str::<Value>(&line).map_err(|error| self.parse_error(error))?;
+                let value = Self::divide_values(Self::rename_keys(value, &self.columns), &self.divisors);
+                return serde_json::from_value(value).map(Some).map_err(|error| self.parse_error(error));
+            },
+        }
+    }
+
+    /// 按列名映射重命名 JSON 对象的键。
+    fn rename_keys(value: Value, columns: &HashMap<String, String>) -> Value
+    {
+        match value {
+            | Value::Object(object) if !columns.is_empty() => Value::Object(object.into_iter().map(|(key, value)| (columns.get(&key).cloned().unwrap_or(key), value)).collect()),
+            | value => value,
+        }
+    }
+
+    /// 按除数整除 JSON 对象中的整数字段。
+    fn divide_values(mut value: Value, divisors: &HashMap<String, i64>) -> Value
+    {
+        if let Value::Object(object) = &mut value {
+            for (field, divisor) in divisors {
+                if let Some(number) = object.get(field).and_then(Value::as_i64) {
+                    object.insert(field.clone(), Value::from(number.div_euclid(*divisor)));
+                }
+            }
+        }
+        value
+    }
+
+    fn parse_error(&self, error: impl std::fmt::Display) -> ExchangeError
+    {
+        ExchangeError::MarketDataError(format!("{} line {}: {}", self.path.display(), self.line, error))
+    }
+
+    fn io_error(path: &Path, error: io::Error) -> ExchangeError
+    {
+        ExchangeError::MarketDataError(format!("{}: {}", path.display(), error))
+    }
+}
+
+#[async_trait]
+impl<T> ReplaySource<T> for FileSource<T> where T: DeserializeOwned + Send
+{
+    /// 直接在当前任务中同步读取：读取经过缓冲，绝大多数调用只从内存中解析一行，
+    /// 且回放循环在拿到下一条数据之前本来就无事可做。为此把每条记录都交给 `spawn_blocking` 的开销反而更大。
+    async fn next_event(&mut self) -> Result<Option<T>, ExchangeError>
+    {
+        self.next_record()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::hourglass::clickhouse_api::datatype::clickhouse_trade_data::MarketTrade;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    fn read_all(config: FileSourceConfig) -> Vec<(String, i64, f64)>
+    {
+        let mut source = FileSource::<MarketTrade>::open(config).unwrap();
+        let mut trades = Vec::new();
+        while let Some(trade) = source.next_record().unwrap() {
+            trades.push((trade.symbol, trade.timestamp, trade.amount));
+        }
+        trades
+    }
+
+    #[test]
+    fn file_source_should_read_mapped_csv_and_jsonl_including_gzip()
+    {
+        let dir = tempfile::tempdir().unwrap();
+
+        let csv_path = dir.path().join("trades.csv");
+        std::fs::write(&csv_path,
+                       "exchange,symbol,side,price,ts,qty\n\
+                        binance-futures,BTCUSDT,buy,60000.5,1000,0.1\n\
+                        binance-futures,BTCUSDT,sell,60001.0,2000,0.2\n").unwrap();
+        let csv_config = FileSourceConfig::new(&csv_path).column("ts", "timestamp").column("qty", "amount");
+        assert_eq!(read_all(csv_config.clone()), vec![("BTCUSDT".to_string(), 1000, 0.1), ("BTCUSDT".to_string(), 2000, 0.2)]);
+
+        let jsonl_path = dir.path().join("trades.jsonl");
+        let mut file = File::create(&jsonl_path).unwrap();
+        writeln!(file, r#"{{"exchange":"binance-futures","symbol":"ETHUSDT","side":"buy","price":3000.0,"ts":1500,"amount":1.5}}"#).unwrap();
+        writeln!(file).unwrap();
+        writeln!(file, r#"{{"exchange":"binance-futures","symbol":"ETHUSDT","side":"sell","price":3001.0,"ts":2500,"amount":2.5}}"#).unwrap();
+        drop(file);
+        assert_eq!(read_all(FileSourceConfig::new(&jsonl_path).column("ts", "timestamp")), vec![("ETHUSDT".to_string(), 1500, 1.5), ("ETHUSDT".to_string(), 2500, 2.5)]);
+        assert_eq!(read_all(FileSourceConfig::new(&jsonl_path).column("ts", "timestamp").divide("timestamp", 1000)), vec![("ETHUSDT".to_string(), 1, 1.5), ("ETHUSDT".to_string(), 2, 2.5)]);
+
+        // gzip 压缩的 CSV 根据扩展名自动识别，按行拼接的多个 gzip 成员也能连续读取
+        let mut file = File::create(dir.path().join("trades.csv.gz")).unwrap();
+        for line in std::fs::read_to_string(&csv_path).unwrap().split_inclusive('\n') {
+            let mut encoder = GzEncoder::new(&mut file, Compression::default());
+            encoder.write_all(line.as_bytes()).unwrap();
+            encoder.finish().unwrap();
+        }
+        drop(file);
+        let gzip_config = FileSourceConfig { path: dir.path().join("trades.csv.gz"),
+                                             ..csv_config };
+        assert_eq!(read_all(gzip_config), vec![("BTCUSDT".to_string(), 1000, 0.1), ("BTCUSDT".to_string(), 2000, 0.2)]);
+
+        // 缺少必需的列时报告出错的行
+        let mut source = FileSource::<MarketTrade>::open(FileSourceConfig::new(&csv_path)).unwrap();
+        assert!(matches!(source.next_record(), Err(ExchangeError::MarketDataError(message)) if message.contains("line 1")));
+        assert!(FileSource::<MarketTrade>::open(FileSourceConfig::new(dir.path().join("trades.txt"))).is_err());
+        // 除数必须为正数
+        let result = FileSource::<MarketTrade>::open(FileSourceConfig::new(&csv_path).divide("timestamp", 0));
+        assert!(matches!(result, Err(ExchangeError::ConfigParseError(message)) if message.contains("timestamp")));
+    }
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use crate::hourglass::{clickhouse_api::datatype::clickhouse_trade_data::MarketTrade, l2_order_book::L2BookMessage};
+
+/// 回放中的一条行情：逐笔成交或 L2 订单簿消息，两者可以按时间归并到同一条数据流中。
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum MarketData
+{
+    Trade(MarketTrade),
+    Book(L2BookMessage),
+}
+
+impl From<MarketTrade> for MarketData
+{
+    fn from(trade: MarketTrade) -> Self
+    {
+        MarketData::Trade(trade)
+    }
+}
+
+impl From<L2BookMessage> for MarketData
+{
+    fn from(message: L2BookMessage) -> Self
+    {
+        MarketData::Book(message)
+    }
+}
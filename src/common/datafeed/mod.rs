@@ -1 +1,6 @@
+pub mod bar; // K 线聚合与只有 K 线数据时的回测
+pub mod file_source; // 本地 CSV / JSON Lines 行情文件
+pub mod market_data; // 成交与订单簿消息的统一回放类型
 pub mod market_event;
+pub mod replay; // 多数据源按时间归并回放
+pub mod validation; // 回放数据的质量校验与报告
@@ -0,0 +1,323 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+    marker::PhantomData,
+};
+
+use async_trait::async_trait;
+use clickhouse::query::RowCursor;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    common::datafeed::{bar::Bar, market_data::MarketData, market_event::MarketEvent},
+    error::ExchangeError,
+    hourglass::{
+        clickhouse_api::datatype::{
+            clickhouse_trade_data::MarketTrade, derivative_ticker::DerivativeTicker, incremental_book_l2::IncrementalBookL2, order_book_25::OrderBook25, tardis_trade::TardisTrade,
+        },
+        l2_order_book::L2BookMessage,
+    },
+};
+
+/// 可按时间回放的数据，提供用于归并排序的时间戳（毫秒）。
+pub trait ReplayTimestamp
+{
+    fn replay_timestamp(&self) -> i64;
+}
+
+impl ReplayTimestamp for MarketTrade
+{
+    fn replay_timestamp(&self) -> i64
+    {
+        self.timestamp
+    }
+}
+
+impl ReplayTimestamp for TardisTrade
+{
+    fn replay_timestamp(&self) -> i64
+    {
+        self.timestamp
+    }
+}
+
+impl ReplayTimestamp for IncrementalBookL2
+{
+    fn replay_timestamp(&self) -> i64
+    {
+        self.timestamp
+    }
+}
+
+impl ReplayTimestamp for OrderBook25
+{
+    fn replay_timestamp(&self) -> i64
+    {
+        self.timestamp
+    }
+}
+
+impl ReplayTimestamp for DerivativeTicker
+{
+    fn replay_timestamp(&self) -> i64
+    {
+        self.timestamp
+    }
+}
+
+impl ReplayTimestamp for L2BookMessage
+{
+    fn replay_timestamp(&self) -> i64
+    {
+        self.timestamp
+    }
+}
+
+impl ReplayTimestamp for MarketData
+{
+    fn replay_timestamp(&self) -> i64
+    {
+        match self {
+            | MarketData::Trade(trade) => trade.replay_timestamp(),
+            | MarketData::Book(message) => message.replay_timestamp(),
+        }
+    }
+}
+
+impl ReplayTimestamp for Bar
+{
+    fn replay_timestamp(&self) -> i64
+    {
+        self.open_time
+    }
+}
+
+impl<Data> ReplayTimestamp for MarketEvent<Data>
+{
+    fn replay_timestamp(&self) -> i64
+    {
+        self.exchange_ts
+    }
+}
+
+/// 单个回放数据源，按时间升序逐条产出数据。
+#[async_trait]
+pub trait ReplaySource<T>: Send
+{
+    /// 读取下一条数据，数据源耗尽时返回 `None`。
+    async fn next_event(&mut self) -> Result<Option<T>, ExchangeError>;
+}
+
+#[async_trait]
+impl<T> ReplaySource<T> for RowCursor<T> where T: DeserializeOwned + Send
+{
+    async fn next_event(&mut self) -> Result<Option<T>, ExchangeError>
+    {
+        self.next().await.map_err(|error| ExchangeError::MarketDataError(error.to_string()))
+    }
+}
+
+/// 内存中已排好序的数据，例如测试数据或预先加载的小文件。
+#[async_trait]
+impl<T> ReplaySource<T> for VecDeque<T> where T: Send
+{
+    async fn next_event(&mut self) -> Result<Option<T>, ExchangeError>
+    {
+        Ok(self.pop_front())
+    }
+}
+
+/// 把数据源产出的数据转换为另一种类型后输出，例如把成交与订单簿消息统一为 [`MarketData`]。
+struct ConvertedSource<S, U>
+{
+    source: S,
+    _data: PhantomData<fn() -> U>,
+}
+
+#[async_trait]
+impl<S, U, T> ReplaySource<T> for ConvertedSource<S, U>
+    where S: ReplaySource<U>,
+          U: Into<T> + Send,
+          T: Send
+{
+    async fn next_event(&mut self) -> Result<Option<T>, ExchangeError>
+    {
+        Ok(self.source.next_event().await?.map(Into::into))
+    }
+}
+
+/// 对数据源产出的每条数据做可能失败的类型转换，转换失败视为数据源出错。
+struct TryConvertedSource<S, U>
+{
+    source: S,
+    _data: PhantomData<fn() -> U>,
+}
+
+#[async_trait]
+impl<S, U, T> ReplaySource<T> for TryConvertedSource<S, U>
+    where S: ReplaySource<U>,
+          U: TryInto<T, Error = ExchangeError> + Send,
+          T: Send
+{
+    async fn next_event(&mut self) -> Result<Option<T>, ExchangeError>
+    {
+        self.source.next_event().await?.map(TryInto::try_into).transpose()
+    }
+}
+
+/// 把任意数量的回放数据源按时间戳 k 路归并为一条时间有序的数据流。
+///
+/// 每个数据源只在内存中保留一条待输出的数据，不需要预先合并成一张表或在内存中整体排序。
+/// 时间戳相同时按数据源加入的先后顺序输出，同一数据源内部保持原有顺序，因此回放结果是确定的。
+pub struct ReplayMerger<T>
+{
+    sources: Vec<Box<dyn ReplaySource<T>>>,
+    heads: Vec<Option<T>>,                    // 每个数据源当前待输出的数据
+    queue: BinaryHeap<Reverse<(i64, usize)>>, // (时间戳, 数据源序号) 的小顶堆
+    primed: bool,                             // 是否已从每个数据源读取第一条数据
+}
+
+impl<T> Default for ReplayMerger<T>
+{
+    fn default() -> Self
+    {
+        Self { sources: Vec::new(),
+               heads: Vec::new(),
+               queue: BinaryHeap::new(),
+               primed: false }
+    }
+}
+
+impl<T> ReplayMerger<T> where T: ReplayTimestamp
+{
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// 加入一个数据源，时间戳相同时先加入的数据源先输出。必须在开始回放前加入。
+    pub fn with_source(mut self, source: impl ReplaySource<T> + 'static) -> Self
+    {
+        self.sources.push(Box::new(source));
+        self.heads.push(None);
+        self
+    }
+
+    /// 加入一个产出其他类型数据的数据源，数据在输出前转换为 `T`。
+    pub fn with_source_into<U>(self, source: impl ReplaySource<U> + 'static) -> Self
+        where U: Into<T> + Send + 'static,
+              T: Send + 'static
+    {
+        self.with_source(ConvertedSource { source, _data: PhantomData })
+    }
+
+    /// 加入一个产出其他类型数据的数据源，转换失败时回放以该错误结束。
+    pub fn with_source_try_into<U>(self, source: impl ReplaySource<U> + 'static) -> Self
+        where U: TryInto<T, Error = ExchangeError> + Send + 'static,
+              T: Send + 'static
+    {
+        self.with_source(TryConvertedSource { source, _data: PhantomData })
+    }
+
+    /// 数据源数量。
+    pub fn source_count(&self) -> usize
+    {
+        self.sources.len()
+    }
+
+    /// 按时间顺序读取下一条数据，所有数据源都耗尽时返回 `None`。
+    pub async fn next_event(&mut self) -> Result<Option<T>, ExchangeError>
+    {
+        if !self.primed {
+            for index in 0..self.sources.len() {
+                self.advance(index).await?;
+            }
+            self.primed = true;
+        }
+
+        let Some(Reverse((_, index))) = self.queue.pop()
+        else {
+            return Ok(None);
+        };
+        let event = self.heads[index].take();
+        self.advance(index).await?;
+        Ok(event)
+    }
+
+    /// 从第 `index` 个数据源读取下一条数据放入堆中。
+    async fn advance(&mut self, index: usize) -> Result<(), ExchangeError>
+    {
+        if let Some(event) = self.sources[index].next_event().await? {
+            self.queue.push(Reverse((event.replay_timestamp(), index)));
+            self.heads[index] = Some(event);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T> ReplaySource<T> for ReplayMerger<T> where T: ReplayTimestamp + Send
+{
+    async fn next_event(&mut self) -> Result<Option<T>, ExchangeError>
+    {
+        ReplayMerger::next_event(self).await
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{hourglass::clickhouse_api::datatype::incremental_book_l2::IncrementalBookL2, test_utils::create_test_market_trade};
+
+    #[tokio::test]
+    async fn replay_merger_should_merge_sources_by_timestamp_with_stable_ties()
+    {
+        let btc = VecDeque::from(vec![create_test_market_trade("BTCUSDT", 1, 100.0, "buy", 1.0),
+                                      create_test_market_trade("BTCUSDT", 3, 100.0, "buy", 1.0),
+                                      create_test_market_trade("BTCUSDT", 3, 100.0, "buy", 1.0),
+                                      create_test_market_trade("BTCUSDT", 7, 100.0, "buy", 1.0)]);
+        let eth = VecDeque::from(vec![create_test_market_trade("ETHUSDT", 2, 100.0, "buy", 1.0),
+                                      create_test_market_trade("ETHUSDT", 3, 100.0, "buy", 1.0),
+                                      create_test_market_trade("ETHUSDT", 8, 100.0, "buy", 1.0)]);
+        let empty = VecDeque::new();
+        let mut merger = ReplayMerger::new().with_source(eth).with_source(empty).with_source(btc);
+        assert_eq!(merger.source_count(), 3);
+
+        let mut replayed = Vec::new();
+        while let Some(trade) = merger.next_event().await.unwrap() {
+            replayed.push((trade.symbol, trade.timestamp));
+        }
+
+        // 时间戳相同时先加入的 ETHUSDT 在前，同一数据源内部保持原有顺序
+        assert_eq!(replayed,
+                   vec![("BTCUSDT".to_string(), 1),
+                        ("ETHUSDT".to_string(), 2),
+                        ("ETHUSDT".to_string(), 3),
+                        ("BTCUSDT".to_string(), 3),
+                        ("BTCUSDT".to_string(), 3),
+                        ("BTCUSDT".to_string(), 7),
+                        ("ETHUSDT".to_string(), 8)]);
+        assert!(merger.next_event().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn replay_merger_should_stop_on_failed_conversion()
+    {
+        let row = IncrementalBookL2 { exchange: "binance-futures".to_string(),
+                                      symbol: "BTCUSDT".to_string(),
+                                      timestamp: 1,
+                                      local_timestamp: 1,
+                                      is_snapshot: true,
+                                      side: "bid".to_string(),
+                                      price: 100.0,
+                                      amount: 1.0 };
+        let mut merger: ReplayMerger<L2BookMessage> = ReplayMerger::new().with_source_try_into(VecDeque::from(vec![row.clone()]));
+        assert_eq!(merger.next_event().await.unwrap().unwrap().bids, vec![(100.0, 1.0)]);
+
+        let unknown_side = IncrementalBookL2 { side: "mid".to_string(),
+                                               ..row };
+        let mut merger: ReplayMerger<L2BookMessage> = ReplayMerger::new().with_source_try_into(VecDeque::from(vec![unknown_side]));
+        assert!(matches!(merger.next_event().await, Err(ExchangeError::MarketDataError(_))));
+    }
+}
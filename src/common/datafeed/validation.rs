@@ -0,0 +1,341 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::ExchangeError, hourglass::clickhouse_api::datatype::clickhouse_trade_data::MarketTrade};
+
+/// 行情数据的质量问题。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataIssue
+{
+    OutOfOrder,    // 时间戳早于同一金融工具的上一笔成交
+    Duplicate,     // 与同一时间戳的某笔成交的价格、数量与方向完全相同
+    InvalidPrice,  // 价格为零、负数或非有限值
+    InvalidAmount, // 数量为负数或非有限值
+    InvalidSide,   // 方向既不是 "buy" 也不是 "sell"
+    Gap,           // 与同一金融工具上一笔成交的间隔超过阈值
+}
+
+/// 发现数据问题时的处理方式。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationPolicy
+{
+    Drop,   // 丢弃该条数据
+    Repair, // 尽量修复后保留，无法修复时丢弃
+    Abort,  // 中止回放
+}
+
+/// 行情数据校验配置，可以直接写在 TOML 配置中，未写出的字段取默认值。
+///
+/// 各类问题的修复方式：乱序的时间戳改为上一笔成交的时间戳；无效价格改为同一金融工具上一笔有效成交的价格；
+/// 无效数量改为 0，只用于更新价格而不参与撮合；无效方向先按大小写与 `bid` / `ask` 等别名归一，仍无法识别时按与上一笔成交的价格比较推断。
+/// 重复成交无法修复，`Repair` 与 `Drop` 相同。
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ValidationConfig
+{
+    pub out_of_order: ValidationPolicy,
+    pub duplicate: ValidationPolicy,
+    pub invalid_price: ValidationPolicy,
+    pub invalid_amount: ValidationPolicy,
+    pub invalid_side: ValidationPolicy,
+    pub max_gap_ms: Option<i64>, // 同一金融工具相邻两笔成交的最大间隔，为空时不检查缺口
+    pub abort_on_gap: bool,      // 出现缺口时中止回放，否则只记录在报告中
+    pub max_samples: usize,      // 报告中保留的问题样本数量
+}
+
+impl Default for ValidationConfig
+{
+    fn default() -> Self
+    {
+        Self { out_of_order: ValidationPolicy::Repair,
+               duplicate: ValidationPolicy::Drop,
+               invalid_price: ValidationPolicy::Repair,
+               invalid_amount: ValidationPolicy::Drop,
+               invalid_side: ValidationPolicy::Repair,
+               max_gap_ms: None,
+               abort_on_gap: false,
+               max_samples: 20 }
+    }
+}
+
+/// 同一金融工具两笔相邻成交之间的数据缺口。
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DataGap
+{
+    pub exchange: String,
+    pub symbol: String,
+    pub from: i64, // 缺口前最后一笔成交的时间戳
+    pub to: i64,   // 缺口后第一笔成交的时间戳
+}
+
+/// 一条数据问题的样本，用于定位损坏的数据。
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct DataIssueSample
+{
+    pub issue: DataIssue,
+    pub exchange: String,
+    pub symbol: String,
+    pub timestamp: i64,
+    pub detail: String,
+}
+
+/// 一次回放的数据质量报告。
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct DataQualityReport
+{
+    pub total: u64,                       // 校验的成交笔数
+    pub passed: u64,                      // 原样通过的笔数
+    pub repaired: u64,                    // 修复后通过的笔数
+    pub dropped: u64,                     // 被丢弃的笔数
+    pub issues: BTreeMap<DataIssue, u64>, // 各类问题出现的次数
+    pub gaps: Vec<DataGap>,               // 检测到的数据缺口
+    pub samples: Vec<DataIssueSample>,    // 最先出现的若干条问题
+    pub aborted: Option<String>,          // 回放被中止时的原因
+}
+
+impl DataQualityReport
+{
+    /// 没有发现任何问题。
+    pub fn is_clean(&self) -> bool
+    {
+        self.issues.is_empty()
+    }
+}
+
+/// 单个金融工具的校验状态。
+#[derive(Debug, Default)]
+struct SymbolState
+{
+    last_timestamp: Option<i64>,
+    last_price: Option<f64>,
+    same_timestamp: Vec<(u64, u64, String)>, // 上一个时间戳上已通过的成交的 (价格, 数量, 方向)，用于识别重复成交
+}
+
+/// 回放数据路径中的成交校验器，按 [`ValidationConfig`] 丢弃、修复数据或中止回放，并汇总 [`DataQualityReport`]。
+///
+/// 成交数据不带成交编号，重复成交按同一时间戳上价格、数量与方向完全相同判断。
+#[derive(Debug, Default)]
+pub struct TradeValidator
+{
+    config: ValidationConfig,
+    symbols: HashMap<(String, String), SymbolState>,
+    report: DataQualityReport,
+}
+
+impl TradeValidator
+{
+    pub fn new(config: ValidationConfig) -> Self
+    {
+        Self { config,
+               symbols: HashMap::new(),
+               report: DataQualityReport::default() }
+    }
+
+    pub fn report(&self) -> &DataQualityReport
+    {
+        &self.report
+    }
+
+    /// 校验一笔成交，返回通过（或修复后）的成交；被丢弃时返回 `None`，按策略中止时返回错误。
+    pub fn validate(&mut self, mut trade: MarketTrade) -> Result<Option<MarketTrade>, ExchangeError>
+    {
+        self.report.total += 1;
+        let key = (trade.exchange.clone(), trade.symbol.clone());
+        let state = self.symbols.entry(key.clone()).or_default();
+        let (last_timestamp, last_price) = (state.last_timestamp, state.last_price);
+        let mut repaired = false;
+
+        if trade.side != "buy" && trade.side != "sell" {
+            let side = normalize_side(&trade.side).or_else(|| match last_price {
+                                                      | Some(last) if trade.price.is_finite() && trade.price > last => Some("buy"),
+                                                      | Some(last) if trade.price > 0.0 && trade.price < last => Some("sell"),
+                                                      | _ => None,
+                                                  });
+            if !self.resolve(DataIssue::InvalidSide, &trade, format!("side {:?}", trade.side), side.is_some())? {
+                return Ok(None);
+            }
+            trade.side = side.unwrap_or_default().to_string();
+            repaired = true;
+        }
+
+        if !trade.price.is_finite() || trade.price <= 0.0 {
+            if !self.resolve(DataIssue::InvalidPrice, &trade, format!("price {}", trade.price), last_price.is_some())? {
+                return Ok(None);
+            }
+            trade.price = last_price.unwrap_or_default();
+            repaired = true;
+        }
+
+        if !trade.amount.is_finite() || trade.amount < 0.0 {
+            if !self.resolve(DataIssue::InvalidAmount, &trade, format!("amount {}", trade.amount), true)? {
+                return Ok(None);
+            }
+            trade.amount = 0.0;
+            repaired = true;
+        }
+
+        if let Some(last_timestamp) = last_timestamp.filter(|last| trade.timestamp < *last) {
+            if !self.resolve(DataIssue::OutOfOrder, &trade, format!("timestamp {} after {}", trade.timestamp, last_timestamp), true)? {
+                return Ok(None);
+            }
+            trade.timestamp = last_timestamp;
+            repaired = true;
+        }
+
+        let fingerprint = (trade.price.to_bits(), trade.amount.to_bits(), trade.side.clone());
+        if last_timestamp == Some(trade.timestamp) && self.symbols[&key].same_timestamp.contains(&fingerprint) {
+            self.resolve(DataIssue::Duplicate, &trade, format!("price {} amount {} side {}", trade.price, trade.amount, trade.side), false)?;
+            return Ok(None);
+        }
+
+        if let (Some(max_gap), Some(last_timestamp)) = (self.config.max_gap_ms, last_timestamp) {
+            if trade.timestamp - last_timestamp > max_gap {
+                self.record(DataIssue::Gap, &trade, format!("no trades for {} ms", trade.timestamp - last_timestamp));
+                self.report.gaps.push(DataGap { exchange: trade.exchange.clone(),
+                                                symbol: trade.symbol.clone(),
+                                                from: last_timestamp,
+                                                to: trade.timestamp });
+                if self.config.abort_on_gap {
+                    return Err(self.abort(DataIssue::Gap, &trade));
+                }
+            }
+        }
+
+        let state = self.symbols.entry(key).or_default();
+        if state.last_timestamp != Some(trade.timestamp) {
+            state.same_timestamp.clear();
+        }
+        state.same_timestamp.push(fingerprint);
+        state.last_timestamp = Some(trade.timestamp);
+        state.last_price = Some(trade.price);
+
+        if repaired {
+            self.report.repaired += 1;
+        }
+        else {
+            self.report.passed += 1;
+        }
+        Ok(Some(trade))
+    }
+
+    /// 记录问题并按策略处理，返回是否保留修复后的数据。`repairable` 表示该条数据能否修复。
+    fn resolve(&mut self, issue: DataIssue, trade: &MarketTrade, detail: String, repairable: bool) -> Result<bool, ExchangeError>
+    {
+        self.record(issue, trade, detail);
+        let policy = match issue {
+            | DataIssue::OutOfOrder => self.config.out_of_order,
+            | DataIssue::Duplicate => self.config.duplicate,
+            | DataIssue::InvalidPrice => self.config.invalid_price,
+            | DataIssue::InvalidAmount => self.config.invalid_amount,
+            | DataIssue::InvalidSide => self.config.invalid_side,
+            | DataIssue::Gap => ValidationPolicy::Repair,
+        };
+        match policy {
+            | ValidationPolicy::Abort => Err(self.abort(issue, trade)),
+            | ValidationPolicy::Repair if repairable => Ok(true),
+            | ValidationPolicy::Repair | ValidationPolicy::Drop => {
+                self.report.dropped += 1;
+                Ok(false)
+            }
+        }
+    }
+
+    fn record(&mut self, issue: DataIssue, trade: &MarketTrade, detail: String)
+    {
+        *self.report.issues.entry(issue).or_default() += 1;
+        if self.report.samples.len() < self.config.max_samples {
+            self.report.samples.push(DataIssueSample { issue,
+                                                       exchange: trade.exchange.clone(),
+                                                       symbol: trade.symbol.clone(),
+                                                       timestamp: trade.timestamp,
+                                                       detail });
+        }
+    }
+
+    fn abort(&mut self, issue: DataIssue, trade: &MarketTrade) -> ExchangeError
+    {
+        let reason = format!("Data quality check failed: {:?} in {} {} at {}", issue, trade.exchange, trade.symbol, trade.timestamp);
+        self.report.aborted = Some(reason.clone());
+        ExchangeError::MarketDataError(reason)
+    }
+}
+
+/// 把常见的方向写法归一为 "buy" / "sell"。
+fn normalize_side(side: &str) -> Option<&'static str>
+{
+    match side.trim().to_lowercase().as_str() {
+        | "buy" | "b" | "bid" | "long" => Some("buy"),
+        | "sell" | "s" | "ask" | "short" => Some("sell"),
+        | _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_utils::create_test_market_trade;
+
+    fn run(validator: &mut TradeValidator, trades: Vec<MarketTrade>) -> Vec<(i64, f64, String, f64)>
+    {
+        trades.into_iter()
+              .filter_map(|trade| validator.validate(trade).unwrap())
+              .map(|trade| (trade.timestamp, trade.price, trade.side, trade.amount))
+              .collect()
+    }
+
+    #[test]
+    fn trade_validator_should_drop_repair_or_abort_and_report_issues()
+    {
+        let corrupt_day = || {
+            vec![create_test_market_trade("BTCUSDT", 1_000, 100.0, "buy", 1.0),
+                 create_test_market_trade("BTCUSDT", 1_000, 100.0, "buy", 1.0),  // 重复
+                 create_test_market_trade("BTCUSDT", 2_000, 0.0, "sell", 1.0),   // 价格为 0
+                 create_test_market_trade("BTCUSDT", 1_500, 101.0, "sell", 1.0), // 乱序
+                 create_test_market_trade("BTCUSDT", 3_000, 102.0, "BUY", 1.0),  // 大小写
+                 create_test_market_trade("BTCUSDT", 4_000, 101.0, "?", 1.0),    // 按价格下跌推断为卖方
+                 create_test_market_trade("BTCUSDT", 5_000, 101.0, "buy", -1.0), // 数量为负
+                 create_test_market_trade("BTCUSDT", 65_000, 103.0, "sell", 2.0)]
+        };
+
+        let mut validator = TradeValidator::new(ValidationConfig { max_gap_ms: Some(30_000),
+                                                                   ..ValidationConfig::default() });
+        assert_eq!(run(&mut validator, corrupt_day()),
+                   vec![(1_000, 100.0, "buy".to_string(), 1.0),
+                        (2_000, 100.0, "sell".to_string(), 1.0),
+                        (2_000, 101.0, "sell".to_string(), 1.0),
+                        (3_000, 102.0, "buy".to_string(), 1.0),
+                        (4_000, 101.0, "sell".to_string(), 1.0),
+                        (65_000, 103.0, "sell".to_string(), 2.0)]);
+        let report = validator.report();
+        assert_eq!((report.total, report.passed, report.repaired, report.dropped), (8, 2, 4, 2));
+        assert_eq!(report.issues.values().sum::<u64>(), 7);
+        assert_eq!(report.issues[&DataIssue::Gap], 1);
+        assert_eq!(report.gaps,
+                   vec![DataGap { exchange: "binance-futures".to_string(),
+                                  symbol: "BTCUSDT".to_string(),
+                                  from: 4_000,
+                                  to: 65_000 }]);
+        assert_eq!(report.samples[0].issue, DataIssue::Duplicate);
+
+        // 全部丢弃
+        let mut validator = TradeValidator::new(ValidationConfig { out_of_order: ValidationPolicy::Drop,
+                                                                   invalid_price: ValidationPolicy::Drop,
+                                                                   invalid_side: ValidationPolicy::Drop,
+                                                                   ..ValidationConfig::default() });
+        // 被丢弃的成交不更新时间戳，之后较早的成交不再视为乱序
+        assert_eq!(run(&mut validator, corrupt_day()).iter().map(|(timestamp, ..)| *timestamp).collect::<Vec<_>>(), vec![1_000, 1_500, 65_000]);
+        assert_eq!(validator.report().dropped, 5);
+
+        // 中止时返回错误并记录原因
+        let mut validator = TradeValidator::new(ValidationConfig { out_of_order: ValidationPolicy::Abort,
+                                                                   ..ValidationConfig::default() });
+        let result: Result<Vec<_>, _> = corrupt_day().into_iter().map(|trade| validator.validate(trade)).collect();
+        assert!(matches!(result, Err(ExchangeError::MarketDataError(message)) if message.contains("OutOfOrder")));
+        assert!(validator.report().aborted.is_some());
+        assert!(!validator.report().is_clean());
+    }
+}
@@ -2,7 +2,13 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     common::{
-        account_positions::AccountPositions,
+        account_positions::{
+            future::FutureSettlement,
+            leveraged_token::LeveragedTokenRebalance,
+            liquidation::{MarginCall, MarginLiquidation, PositionAutoDeleverage, PositionLiquidation},
+            option::OptionExercise,
+            AccountPositions, Position,
+        },
         balance::TokenBalance,
         order::{
             states::{
@@ -40,7 +46,15 @@ pub enum AccountEventKind
     Trade(ClientTrade),
     Balances(Vec<TokenBalance>),
     Positions(AccountPositions),
-    AccountConfig(AccountConfig),
+    Position(Position),
+    AccountConfig(Box<AccountConfig>),
+    Liquidation(PositionLiquidation),
+    AutoDeleveraged(PositionAutoDeleverage),
+    FutureSettlement(FutureSettlement),
+    OptionExercise(OptionExercise),
+    LeveragedTokenRebalance(LeveragedTokenRebalance),
+    MarginCall(MarginCall),
+    MarginLiquidation(MarginLiquidation),
     // OrderBookUpdate(OrderBookUpdate),
     // MarketStatus(MarketStatus),
     // MarginUpdate(MarginUpdate),
@@ -94,7 +108,7 @@ mod tests
                          // AccountEventKind::Trade(ClientTrade::default()),
                          AccountEventKind::Balances(vec![]),
                          /* AccountEventKind::Positions(AccountPositions::default()),
-                          * AccountEventKind::AccountConfig(AccountConfig::default()), */];
+                          * AccountEventKind::AccountConfig(Box::default()), */];
         for kind in kinds {
             let serialized = serde_json::to_string(&kind).unwrap();
             let deserialized: AccountEventKind = serde_json::from_str(&serialized).unwrap();
@@ -45,9 +45,9 @@ pub struct FutureFees
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, PartialOrd)]
 pub struct OptionFees
 {
-    pub trade_fee: f64,
-    // 交易费率
-    // 期权可能还有其他费用，如行权费等，根据需要添加
+    pub trade_fee: f64,    // 交易费率
+    #[serde(default)]
+    pub exercise_fee: f64, // 行权费，仅在到期行权时收取
 }
 
 #[derive(Debug, Copy, Clone, PartialOrd, Serialize, Deserialize, PartialEq)]
@@ -118,7 +118,7 @@ mod tests
     #[test]
     fn option_fees_should_serialize_and_deserialize_correctly()
     {
-        let fees = OptionFees { trade_fee: 0.1 };
+        let fees = OptionFees { trade_fee: 0.1, exercise_fee: 0.0 };
         let serialized = serde_json::to_string(&fees).unwrap();
         let deserialized: OptionFees = serde_json::from_str(&serialized).unwrap();
         assert_eq!(fees, deserialized);
@@ -134,7 +134,7 @@ mod tests
                                  Fees::Future(FutureFees { maker_fee: 0.1,
                                                            taker_fee: 0.2,
                                                            funding_fee: 0.01 }),
-                                 Fees::Option(OptionFees { trade_fee: 0.1 }),];
+                                 Fees::Option(OptionFees { trade_fee: 0.1, exercise_fee: 0.0 }),];
         for fees in fees_variants {
             let serialized = serde_json::to_string(&fees).unwrap();
             let deserialized: Fees = serde_json::from_str(&serialized).unwrap();
@@ -2,9 +2,18 @@ use std::fmt::{Display, Formatter};
 
 use serde::{Deserialize, Serialize};
 
-use crate::common::{instrument::kind::InstrumentKind, token::Token};
+use crate::common::{
+    instrument::{
+        kind::InstrumentKind,
+        option_spec::{OptionKind, OptionSpec},
+    },
+    token::Token,
+    Side,
+};
 
 pub mod kind;
+pub mod option_spec;
+pub mod symbol_registry;
 
 // 定义Instrument结构体，用于表示金融工具。
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Deserialize, Serialize)]
@@ -15,6 +24,12 @@ pub struct Instrument
     pub quote: Token, // 报价货币
     #[serde(rename = "instrument_kind")]
     pub kind: InstrumentKind, // 金融工具的类型
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub inverse: bool, // 币本位（反向）合约：数量为 quote 计价的合约张数，保证金与盈亏以 base 结算
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiry: Option<i64>, // 交割合约与期权的到期时间（毫秒），永续合约与现货为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub option_spec: Option<OptionSpec>, // 期权的类型与行权价，其他金融工具为 None
 }
 
 // 为Instrument实现Display trait，方便打印显示。
@@ -22,7 +37,11 @@ impl Display for Instrument
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
     {
-        write!(f, "({}_{}, {})", self.base, self.quote, self.kind)
+        match (self.expiry, self.option_spec) {
+            | (Some(expiry), Some(option_spec)) => write!(f, "({}_{}, {}, {}, {})", self.base, self.quote, self.kind, expiry, option_spec),
+            | (Some(expiry), None) => write!(f, "({}_{}, {}, {})", self.base, self.quote, self.kind, expiry),
+            | _ => write!(f, "({}_{}, {})", self.base, self.quote, self.kind),
+        }
     }
 }
 
@@ -33,7 +52,10 @@ impl<S> From<(S, S, InstrumentKind)> for Instrument where S: Into<Token>
     {
         Self { base: base.into(),
                quote: quote.into(),
-               kind }
+               kind,
+               inverse: false,
+               expiry: None,
+               option_spec: None }
     }
 }
 
@@ -45,7 +67,122 @@ impl Instrument
     {
         Self { base: base.into(),
                quote: quote.into(),
-               kind }
+               kind,
+               inverse: false,
+               expiry: None,
+               option_spec: None }
+    }
+
+    /// 创建一个在 `expiry`（毫秒）到期的交割合约。
+    pub fn future<S>(base: S, quote: S, expiry: i64) -> Self
+        where S: Into<Token>
+    {
+        Self { base: base.into(),
+               quote: quote.into(),
+               kind: InstrumentKind::Future,
+               inverse: false,
+               expiry: Some(expiry),
+               option_spec: None }
+    }
+
+    /// 创建一个在 `expiry`（毫秒）到期、以 `strike` 为行权价的欧式期权。
+    pub fn crypto_option<S>(base: S, quote: S, expiry: i64, option_kind: OptionKind, strike: f64) -> Self
+        where S: Into<Token>
+    {
+        Self { base: base.into(),
+               quote: quote.into(),
+               kind: InstrumentKind::CryptoOption,
+               inverse: false,
+               expiry: Some(expiry),
+               option_spec: Some(OptionSpec::new(option_kind, strike)) }
+    }
+
+    /// 创建一个币本位（反向）永续合约，例如 `BTC/USD`：每张合约价值 1 单位 quote，保证金与盈亏以 base 结算。
+    pub fn inverse_perpetual<S>(base: S, quote: S) -> Self
+        where S: Into<Token>
+    {
+        Self { base: base.into(),
+               quote: quote.into(),
+               kind: InstrumentKind::Perpetual,
+               inverse: true,
+               expiry: None,
+               option_spec: None }
+    }
+
+    /// 缴纳保证金与结算盈亏的币种：币本位合约为 base，其余为 quote。
+    pub fn margin_token(&self) -> &Token
+    {
+        if self.inverse { &self.base } else { &self.quote }
+    }
+
+    /// 以 [`Self::margin_token`] 计的名义价值。U 本位为 `price * size`，币本位为 `size / price`。
+    pub fn notional(&self, price: f64, size: f64) -> f64
+    {
+        match self.inverse {
+            | true if price > 0.0 => size / price,
+            | true => 0.0,
+            | false => price * size,
+        }
+    }
+
+    /// 以 quote 计的名义价值，用于累计不同结算币种的成交额：币本位合约按成交价把 [`Self::notional`] 换算回 quote。
+    pub fn quote_notional(&self, price: f64, size: f64) -> f64
+    {
+        match self.inverse {
+            | true => self.notional(price, size) * price,
+            | false => self.notional(price, size),
+        }
+    }
+
+    /// 以 [`Self::margin_token`] 计的盈亏：在 `entry_price` 开仓、`exit_price` 平仓 `size` 数量的 `side` 方向仓位。
+    /// 币本位合约的盈亏为 `size * (1 / entry_price - 1 / exit_price)`。
+    pub fn pnl(&self, side: Side, entry_price: f64, exit_price: f64, size: f64) -> f64
+    {
+        let long_pnl = match self.inverse {
+            | true => self.notional(entry_price, size) - self.notional(exit_price, size),
+            | false => self.notional(exit_price, size) - self.notional(entry_price, size),
+        };
+        match side {
+            | Side::Buy => long_pnl,
+            | Side::Sell => -long_pnl,
+        }
+    }
+
+    /// 新开仓位的强平价格：亏损达到初始保证金的 `liquidation_threshold` 时强平。
+    ///
+    /// U 本位为 `price * (1 ∓ liquidation_threshold / leverage)`，币本位为 `price / (1 ± liquidation_threshold / leverage)`。
+    /// 币本位空仓的亏损没有上限，当 `liquidation_threshold >= leverage` 时返回 `f64::MAX`。
+    pub fn opening_liquidation_price(&self, price: f64, side: Side, leverage: f64, liquidation_threshold: f64) -> f64
+    {
+        let ratio = liquidation_threshold / leverage;
+        match (self.inverse, side) {
+            | (false, Side::Buy) => price * (1.0 - ratio),
+            | (false, Side::Sell) => price * (1.0 + ratio),
+            | (true, Side::Buy) => price / (1.0 + ratio),
+            | (true, Side::Sell) if ratio < 1.0 => price / (1.0 - ratio),
+            | (true, Side::Sell) => f64::MAX,
+        }
+    }
+
+    /// 去掉到期时间后的金融工具。回测行情中的交割合约不带到期时间，用于查找对应的单层订单簿。
+    pub fn undated(&self) -> Self
+    {
+        Self { expiry: None,
+               ..self.clone() }
+    }
+
+    /// 行情中对应的金融工具。回测行情可能不带到期时间与币本位标记，用于查找对应的单层订单簿。
+    pub fn market_instrument(&self) -> Self
+    {
+        Self { inverse: false,
+               expiry: None,
+               ..self.clone() }
+    }
+
+    /// 交易所时间 `timestamp` 是否已经到达该合约的到期时间，没有到期时间的金融工具永不过期。
+    pub fn is_expired(&self, timestamp: i64) -> bool
+    {
+        self.expiry.is_some_and(|expiry| timestamp >= expiry)
     }
 }
 
@@ -55,6 +192,9 @@ pub struct InstrumentBuilder
     base: Option<Token>,
     quote: Option<Token>,
     kind: Option<InstrumentKind>,
+    inverse: bool,
+    expiry: Option<i64>,
+    option_spec: Option<OptionSpec>,
 }
 
 impl Default for InstrumentBuilder
@@ -70,7 +210,12 @@ impl InstrumentBuilder
     // 初始化构建器，所有字段均为None。
     pub fn new() -> Self
     {
-        InstrumentBuilder { base: None, quote: None, kind: None }
+        InstrumentBuilder { base: None,
+                            quote: None,
+                            kind: None,
+                            inverse: false,
+                            expiry: None,
+                            option_spec: None }
     }
 
     // 设置基础货币。
@@ -96,11 +241,35 @@ impl InstrumentBuilder
         self
     }
 
+    // 设置为币本位（反向）合约。
+    pub fn inverse(mut self) -> Self
+    {
+        self.inverse = true;
+        self
+    }
+
+    // 设置交割合约的到期时间（毫秒）。
+    pub fn expiry(mut self, expiry: i64) -> Self
+    {
+        self.expiry = Some(expiry);
+        self
+    }
+
+    // 设置期权的类型与行权价。
+    pub fn option_spec(mut self, option_spec: OptionSpec) -> Self
+    {
+        self.option_spec = Some(option_spec);
+        self
+    }
+
     // 结束构建，并尝试生成Instrument。如果任何字段未设置，将返回错误。
     pub fn initiate(self) -> Result<Instrument, &'static str>
     {
         Ok(Instrument { base: self.base.ok_or("Base is missing")?,
                         quote: self.quote.ok_or("Quote is missing")?,
-                        kind: self.kind.ok_or("Instrument kind is missing")? })
+                        kind: self.kind.ok_or("Instrument kind is missing")?,
+                        inverse: self.inverse,
+                        expiry: self.expiry,
+                        option_spec: self.option_spec })
     }
 }
@@ -0,0 +1,113 @@
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// 期权的类型：看涨或看跌。
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OptionKind
+{
+    Call,
+    Put,
+}
+
+/// 期权的行权价。
+///
+/// `f64` 不满足 `Eq` 与 `Hash`，这里按位比较，以便作为 [`Instrument`](super::Instrument) 的一部分用作 HashMap 的键。
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Strike(pub f64);
+
+impl PartialEq for Strike
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for Strike {}
+
+impl Hash for Strike
+{
+    fn hash<H: Hasher>(&self, state: &mut H)
+    {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl PartialOrd for Strike
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering>
+    {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Strike
+{
+    fn cmp(&self, other: &Self) -> Ordering
+    {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// 欧式期权的合约条款，到期时间使用 [`Instrument::expiry`](super::Instrument)。
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Deserialize, Serialize)]
+pub struct OptionSpec
+{
+    pub option_kind: OptionKind, // 看涨或看跌
+    pub strike: Strike,          // 行权价
+}
+
+impl OptionSpec
+{
+    pub fn new(option_kind: OptionKind, strike: f64) -> Self
+    {
+        Self { option_kind,
+               strike: Strike(strike) }
+    }
+
+    /// 以指数价格计算的每单位内在价值，即到期时多头获得的现金交割金额。
+    pub fn intrinsic_value(&self, index_price: f64) -> f64
+    {
+        match self.option_kind {
+            | OptionKind::Call => (index_price - self.strike.0).max(0.0),
+            | OptionKind::Put => (self.strike.0 - index_price).max(0.0),
+        }
+    }
+}
+
+impl Display for OptionSpec
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        match self.option_kind {
+            | OptionKind::Call => write!(f, "C{}", self.strike.0),
+            | OptionKind::Put => write!(f, "P{}", self.strike.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn option_spec_should_compute_intrinsic_value()
+    {
+        let call = OptionSpec::new(OptionKind::Call, 3_000.0);
+        let put = OptionSpec::new(OptionKind::Put, 3_000.0);
+        assert_eq!(call.intrinsic_value(3_200.0), 200.0);
+        assert_eq!(call.intrinsic_value(2_800.0), 0.0);
+        assert_eq!(put.intrinsic_value(2_800.0), 200.0);
+        assert_eq!(put.intrinsic_value(3_200.0), 0.0);
+        assert_eq!(format!("{}", call), "C3000");
+        assert!(OptionSpec::new(OptionKind::Call, 2_900.0) < call);
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{common::instrument::Instrument, error::ExchangeError};
+
+/// 一个交易所原始符号与标准 [`Instrument`] 的对应关系。
+///
+/// 在符号文件中以 `[[symbols]]` 表的形式出现，`Instrument` 的字段直接展开在同一层：
+///
+/// ```toml
+/// [[symbols]]
+/// exchange = "binance-futures"
+/// symbol = "BTCUSDT"
+/// base = "BTC"
+/// quote = "USDT"
+/// instrument_kind = "perpetual"
+/// ```
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SymbolSpec
+{
+    pub exchange: String, // 行情数据中的交易所名称，例如 `binance-futures`
+    pub symbol: String,   // 行情数据中的原始符号，例如 `BTCUSDT`
+    #[serde(flatten)]
+    pub instrument: Instrument, // 对应的标准金融工具
+}
+
+/// 符号文件的内容。
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct SymbolFile
+{
+    #[serde(default)]
+    pub symbols: Vec<SymbolSpec>,
+}
+
+/// 把 `(交易所, 原始符号)` 映射为标准 [`Instrument`] 的符号表。
+///
+/// 所有行情数据都经由符号表解析金融工具，未登记的符号返回 [`ExchangeError::UnknownSymbol`]，不再按符号后缀猜测。
+#[derive(Clone, Debug, Default)]
+pub struct SymbolRegistry
+{
+    symbols: HashMap<(String, String), Instrument>,
+}
+
+impl SymbolRegistry
+{
+    /// 登记一个符号，同一交易所的同一符号只能对应一个金融工具。
+    pub fn register(&mut self, exchange: impl Into<String>, symbol: impl Into<String>, instrument: Instrument) -> Result<(), ExchangeError>
+    {
+        let key = (exchange.into(), symbol.into());
+        match self.symbols.get(&key) {
+            | Some(existing) if existing != &instrument => {
+                Err(ExchangeError::ConfigParseError(format!("Symbol {} on {} is registered as both {} and {}", key.1, key.0, existing, instrument)))
+            }
+            | _ => {
+                self.symbols.insert(key, instrument);
+                Ok(())
+            }
+        }
+    }
+
+    /// 解析交易所 `exchange` 上的原始符号 `symbol`。
+    pub fn resolve(&self, exchange: &str, symbol: &str) -> Result<&Instrument, ExchangeError>
+    {
+        self.symbols
+            .get(&(exchange.to_string(), symbol.to_string()))
+            .ok_or_else(|| ExchangeError::UnknownSymbol { exchange: exchange.to_string(),
+                                                          symbol: symbol.to_string() })
+    }
+
+    /// 已登记的符号数量。
+    pub fn len(&self) -> usize
+    {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.symbols.is_empty()
+    }
+}
+
+impl TryFrom<Vec<SymbolSpec>> for SymbolRegistry
+{
+    type Error = ExchangeError;
+
+    fn try_from(specs: Vec<SymbolSpec>) -> Result<Self, Self::Error>
+    {
+        let mut registry = SymbolRegistry::default();
+        for spec in specs {
+            registry.register(spec.exchange, spec.symbol, spec.instrument)?;
+        }
+        Ok(registry)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::common::instrument::kind::InstrumentKind;
+
+    #[test]
+    fn symbol_registry_should_resolve_registered_symbols_only()
+    {
+        let content = r#"
+            [[symbols]]
+            exchange = "binance-futures"
+            symbol = "BTCUSDT"
+            base = "BTC"
+            quote = "USDT"
+            instrument_kind = "perpetual"
+
+            [[symbols]]
+            exchange = "binance-coin-futures"
+            symbol = "BTCUSD_PERP"
+            base = "BTC"
+            quote = "USD"
+            instrument_kind = "perpetual"
+            inverse = true
+
+            [[symbols]]
+            exchange = "binance-futures"
+            symbol = "BTCUSDT_240927"
+            base = "BTC"
+            quote = "USDT"
+            instrument_kind = "future"
+            expiry = 1727424000000
+        "#;
+        let file: SymbolFile = toml::from_str(content).unwrap();
+        let registry = SymbolRegistry::try_from(file.symbols).unwrap();
+        assert_eq!(registry.len(), 3);
+
+        assert_eq!(registry.resolve("binance-futures", "BTCUSDT").unwrap(), &Instrument::new("BTC", "USDT", InstrumentKind::Perpetual));
+        assert_eq!(registry.resolve("binance-coin-futures", "BTCUSD_PERP").unwrap(), &Instrument::inverse_perpetual("BTC", "USD"));
+        assert_eq!(registry.resolve("binance-futures", "BTCUSDT_240927").unwrap(), &Instrument::future("BTC", "USDT", 1727424000000));
+
+        // 同一符号在其他交易所或未登记的符号都不会被猜测为现货
+        assert_eq!(registry.resolve("binance", "BTCUSDT").unwrap_err(),
+                   ExchangeError::UnknownSymbol { exchange: "binance".to_string(),
+                                                  symbol: "BTCUSDT".to_string() });
+        assert!(registry.resolve("binance-futures", "XRP").is_err());
+
+        // 重复登记为不同的金融工具视为配置错误
+        let mut registry = registry;
+        assert!(registry.register("binance-futures", "BTCUSDT", Instrument::new("BTC", "USDT", InstrumentKind::Perpetual)).is_ok());
+        assert!(registry.register("binance-futures", "BTCUSDT", Instrument::new("BTC", "USDT", InstrumentKind::Spot)).is_err());
+    }
+}
@@ -6,11 +6,13 @@ use std::{
 use serde::{Deserialize, Serialize};
 
 pub mod account_positions;
+pub mod account_summary; // 账户权益与保证金概览
 pub mod balance; // 通用balance模块
 pub mod datafeed;
 pub mod event; // 定义通用事件和状态
 pub mod friction;
 pub mod instrument;
+pub mod option_pricing; // 期权定价模型与希腊字母
 pub mod order;
 pub mod stable_token;
 pub mod status;
@@ -0,0 +1,378 @@
+use std::{
+    f64::consts::{PI, SQRT_2},
+    ops::{Add, AddAssign, Mul},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::{
+    instrument::{option_spec::OptionKind, Instrument},
+    token::Token,
+    Side,
+};
+
+/// 一年的毫秒数，到期时间按自然日的年化比例计算。
+pub const MILLIS_PER_YEAR: f64 = 365.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+
+/// 欧式期权的定价模型。
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PricingModel
+{
+    /// 以现货价格为标的的 Black-Scholes 模型。
+    #[default]
+    BlackScholes,
+    /// 以远期（期货）价格为标的的 Black-76 模型，加密期权通常以期货价格定价。
+    Black76,
+}
+
+/// 期权定价所需的市场参数。`underlying_price` 在 Black-Scholes 下为现货价格，在 Black-76 下为远期价格。
+#[derive(Copy, Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct PricingInput
+{
+    pub model: PricingModel,
+    pub option_kind: OptionKind,
+    pub underlying_price: f64, // 标的价格
+    pub strike: f64,           // 行权价
+    pub time_to_expiry: f64,   // 剩余期限（年）
+    pub volatility: f64,       // 年化波动率
+    pub risk_free_rate: f64,   // 无风险利率（连续复利）
+}
+
+/// 单位期权的希腊字母。`vega` 为波动率变动 1 个百分点的价值变化，`theta` 为每个自然日的时间价值衰减。
+#[derive(Copy, Clone, PartialEq, Debug, Default, Deserialize, Serialize)]
+pub struct OptionGreeks
+{
+    pub delta: f64,
+    pub gamma: f64,
+    pub vega: f64,
+    pub theta: f64,
+}
+
+impl Add for OptionGreeks
+{
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self
+    {
+        Self { delta: self.delta + other.delta,
+               gamma: self.gamma + other.gamma,
+               vega: self.vega + other.vega,
+               theta: self.theta + other.theta }
+    }
+}
+
+impl AddAssign for OptionGreeks
+{
+    fn add_assign(&mut self, other: Self)
+    {
+        *self = *self + other;
+    }
+}
+
+impl Mul<f64> for OptionGreeks
+{
+    type Output = Self;
+
+    fn mul(self, quantity: f64) -> Self
+    {
+        Self { delta: self.delta * quantity,
+               gamma: self.gamma * quantity,
+               vega: self.vega * quantity,
+               theta: self.theta * quantity }
+    }
+}
+
+/// 单个期权仓位的估值与希腊字母，空头仓位的数值取反。
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct PositionGreeks
+{
+    pub instrument: Instrument,
+    pub side: Side,
+    pub size: f64,
+    pub underlying_price: f64,   // 计算所用的标的价格
+    pub option_price: f64,       // 用于反推隐含波动率的期权价格
+    pub implied_volatility: f64, // 隐含波动率
+    pub fair_value: f64,         // 仓位的理论价值
+    pub greeks: OptionGreeks,    // 按仓位数量与方向加总的希腊字母
+}
+
+/// 无法定价的期权仓位，例如缺少指数价格，或期权价格超出无套利区间而无法反推隐含波动率。
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct UnpricedPosition
+{
+    pub instrument: Instrument,
+    pub side: Side,
+    pub size: f64,
+    pub reason: String, // 无法定价的原因
+}
+
+/// 以某个结算币种计价的期权仓位希腊字母汇总。
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct AccountGreeks
+{
+    pub token: Token,
+    pub positions: Vec<PositionGreeks>,
+    pub unpriced: Vec<UnpricedPosition>, // 未计入汇总的仓位
+    pub total: OptionGreeks,
+}
+
+impl AccountGreeks
+{
+    pub fn new(token: Token, positions: Vec<PositionGreeks>, unpriced: Vec<UnpricedPosition>) -> Self
+    {
+        let total = positions.iter().fold(OptionGreeks::default(), |total, position| total + position.greeks);
+        Self { token,
+               positions,
+               unpriced,
+               total }
+    }
+}
+
+impl PricingInput
+{
+    fn discount_factor(&self) -> f64
+    {
+        (-self.risk_free_rate * self.time_to_expiry).exp()
+    }
+
+    /// 到期或波动率为零时期权只剩内在价值。
+    fn is_degenerate(&self) -> bool
+    {
+        self.time_to_expiry <= 0.0 || self.volatility <= 0.0
+    }
+
+    fn d1_d2(&self) -> (f64, f64)
+    {
+        let sigma_sqrt_t = self.volatility * self.time_to_expiry.sqrt();
+        let drift = match self.model {
+            | PricingModel::BlackScholes => self.risk_free_rate + 0.5 * self.volatility * self.volatility,
+            | PricingModel::Black76 => 0.5 * self.volatility * self.volatility,
+        };
+        let d1 = ((self.underlying_price / self.strike).ln() + drift * self.time_to_expiry) / sigma_sqrt_t;
+        (d1, d1 - sigma_sqrt_t)
+    }
+
+    /// 期权的理论价值。
+    pub fn fair_value(&self) -> f64
+    {
+        let discount = self.discount_factor();
+        if self.is_degenerate() {
+            let forward_intrinsic = match self.option_kind {
+                | OptionKind::Call => (self.forward_price() - self.strike).max(0.0),
+                | OptionKind::Put => (self.strike - self.forward_price()).max(0.0),
+            };
+            return forward_intrinsic * discount;
+        }
+
+        let (d1, d2) = self.d1_d2();
+        let (underlying, strike) = match self.model {
+            | PricingModel::BlackScholes => (self.underlying_price, self.strike * discount),
+            | PricingModel::Black76 => (self.underlying_price * discount, self.strike * discount),
+        };
+        match self.option_kind {
+            | OptionKind::Call => underlying * norm_cdf(d1) - strike * norm_cdf(d2),
+            | OptionKind::Put => strike * norm_cdf(-d2) - underlying * norm_cdf(-d1),
+        }
+    }
+
+    /// 单位期权的希腊字母，delta 与 gamma 相对于 `underlying_price`。
+    pub fn greeks(&self) -> OptionGreeks
+    {
+        let discount = self.discount_factor();
+        if self.is_degenerate() {
+            let in_the_money = match self.option_kind {
+                | OptionKind::Call => self.forward_price() > self.strike,
+                | OptionKind::Put => self.forward_price() < self.strike,
+            };
+            let delta = match (self.option_kind, in_the_money) {
+                | (_, false) => 0.0,
+                | (OptionKind::Call, true) => 1.0,
+                | (OptionKind::Put, true) => -1.0,
+            };
+            let delta = match self.model {
+                | PricingModel::BlackScholes => delta,
+                | PricingModel::Black76 => delta * discount,
+            };
+            return OptionGreeks { delta, ..Default::default() };
+        }
+
+        let (d1, d2) = self.d1_d2();
+        let sqrt_t = self.time_to_expiry.sqrt();
+        let (underlying_discount, strike_discount) = match self.model {
+            | PricingModel::BlackScholes => (1.0, discount),
+            | PricingModel::Black76 => (discount, discount),
+        };
+
+        let delta = match self.option_kind {
+            | OptionKind::Call => underlying_discount * norm_cdf(d1),
+            | OptionKind::Put => underlying_discount * (norm_cdf(d1) - 1.0),
+        };
+        let gamma = underlying_discount * norm_pdf(d1) / (self.underlying_price * self.volatility * sqrt_t);
+        let vega = underlying_discount * self.underlying_price * norm_pdf(d1) * sqrt_t;
+
+        // 年化的 theta：时间价值衰减项，加上折现带来的利息项
+        let decay = -underlying_discount * self.underlying_price * norm_pdf(d1) * self.volatility / (2.0 * sqrt_t);
+        let theta = match self.model {
+            | PricingModel::BlackScholes => match self.option_kind {
+                | OptionKind::Call => decay - self.risk_free_rate * self.strike * strike_discount * norm_cdf(d2),
+                | OptionKind::Put => decay + self.risk_free_rate * self.strike * strike_discount * norm_cdf(-d2),
+            },
+            | PricingModel::Black76 => decay + self.risk_free_rate * self.fair_value(),
+        };
+
+        OptionGreeks { delta,
+                       gamma,
+                       vega: vega / 100.0,
+                       theta: theta / 365.0 }
+    }
+
+    /// 由期权价格反推隐含波动率。价格超出无套利区间或无法收敛时返回 `None`。
+    ///
+    /// 先用 Newton 法迭代，vega 过小或迭代越界时退回二分法。
+    pub fn implied_volatility(&self, option_price: f64) -> Option<f64>
+    {
+        const MIN_VOLATILITY: f64 = 1e-6;
+        const MAX_VOLATILITY: f64 = 10.0;
+        const TOLERANCE: f64 = 1e-10;
+        const MAX_ITERATIONS: usize = 100;
+
+        if self.time_to_expiry <= 0.0 || !option_price.is_finite() {
+            return None;
+        }
+        let price_at = |volatility: f64| PricingInput { volatility, ..*self }.fair_value();
+        let (lower_bound, upper_bound) = (price_at(MIN_VOLATILITY), price_at(MAX_VOLATILITY));
+        if option_price < lower_bound - TOLERANCE || option_price > upper_bound + TOLERANCE {
+            return None;
+        }
+
+        let (mut low, mut high) = (MIN_VOLATILITY, MAX_VOLATILITY);
+        let mut volatility = 0.5;
+        for _ in 0..MAX_ITERATIONS {
+            let input = PricingInput { volatility, ..*self };
+            let diff = input.fair_value() - option_price;
+            if diff.abs() < TOLERANCE {
+                return Some(volatility);
+            }
+            if diff > 0.0 {
+                high = volatility;
+            }
+            else {
+                low = volatility;
+            }
+
+            // greeks().vega 按 1 个百分点计，换算回每单位波动率
+            let vega = input.greeks().vega * 100.0;
+            let newton = volatility - diff / vega;
+            volatility = if vega > 1e-12 && newton > low && newton < high { newton } else { 0.5 * (low + high) };
+        }
+        Some(volatility)
+    }
+
+    /// Black-Scholes 下由现货价格推出的远期价格，Black-76 下即为 `underlying_price`。
+    fn forward_price(&self) -> f64
+    {
+        match self.model {
+            | PricingModel::BlackScholes => self.underlying_price / self.discount_factor(),
+            | PricingModel::Black76 => self.underlying_price,
+        }
+    }
+}
+
+/// 标准正态分布的概率密度函数。
+pub fn norm_pdf(x: f64) -> f64
+{
+    (-0.5 * x * x).exp() / (2.0 * PI).sqrt()
+}
+
+/// 标准正态分布的累积分布函数。
+pub fn norm_cdf(x: f64) -> f64
+{
+    0.5 * erfc(-x / SQRT_2)
+}
+
+/// 互补误差函数，采用 Numerical Recipes 中的 Chebyshev 近似，相对误差小于 1.2e-7。
+fn erfc(x: f64) -> f64
+{
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.26551223
+               + t * (1.00002368
+                      + t * (0.37409196 + t * (0.09678418 + t * (-0.18628806 + t * (0.27886807 + t * (-1.13520398 + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    let result = t * poly.exp();
+    if x >= 0.0 {
+        result
+    }
+    else {
+        2.0 - result
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn input(model: PricingModel, option_kind: OptionKind) -> PricingInput
+    {
+        PricingInput { model,
+                       option_kind,
+                       underlying_price: 100.0,
+                       strike: 100.0,
+                       time_to_expiry: 1.0,
+                       volatility: 0.2,
+                       risk_free_rate: 0.05 }
+    }
+
+    #[test]
+    fn black_scholes_should_match_reference_values()
+    {
+        let call = input(PricingModel::BlackScholes, OptionKind::Call);
+        let put = input(PricingModel::BlackScholes, OptionKind::Put);
+        assert!((call.fair_value() - 10.4506).abs() < 1e-4);
+        assert!((put.fair_value() - 5.5735).abs() < 1e-4);
+
+        let greeks = call.greeks();
+        assert!((greeks.delta - 0.6368).abs() < 1e-4);
+        assert!((greeks.gamma - 0.018762).abs() < 1e-5);
+        assert!((greeks.vega - 0.37524).abs() < 1e-4);
+        assert!((greeks.theta - (-6.4140 / 365.0)).abs() < 1e-5);
+        assert!((put.greeks().delta - (0.6368 - 1.0)).abs() < 1e-4);
+
+        // Black-76 在远期价格为 S * e^{rT} 时与 Black-Scholes 的价格一致
+        let forward = PricingInput { underlying_price: 100.0 * 0.05_f64.exp(),
+                                     ..input(PricingModel::Black76, OptionKind::Call) };
+        assert!((forward.fair_value() - call.fair_value()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn implied_volatility_should_recover_input_volatility()
+    {
+        for model in [PricingModel::BlackScholes, PricingModel::Black76] {
+            for option_kind in [OptionKind::Call, OptionKind::Put] {
+                for volatility in [0.05, 0.6, 2.5] {
+                    let priced = PricingInput { volatility,
+                                                strike: 120.0,
+                                                ..input(model, option_kind) };
+                    let implied = priced.implied_volatility(priced.fair_value()).unwrap();
+                    assert!((implied - volatility).abs() < 1e-6, "{:?} {:?} {} -> {}", model, option_kind, volatility, implied);
+                }
+            }
+        }
+
+        // 低于内在价值的价格无法反推波动率
+        let deep_call = PricingInput { strike: 50.0,
+                                       ..input(PricingModel::BlackScholes, OptionKind::Call) };
+        assert!(deep_call.implied_volatility(40.0).is_none());
+    }
+
+    #[test]
+    fn expired_option_should_only_keep_intrinsic_value()
+    {
+        let expired = PricingInput { time_to_expiry: 0.0,
+                                     underlying_price: 110.0,
+                                     ..input(PricingModel::Black76, OptionKind::Call) };
+        assert_eq!(expired.fair_value(), 10.0);
+        assert_eq!(expired.greeks(), OptionGreeks { delta: 1.0, ..Default::default() });
+    }
+}
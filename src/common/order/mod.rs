@@ -35,7 +35,10 @@ pub enum OrderRole
 mod tests
 {
     use super::*;
-    use crate::common::order::states::{request_cancel::RequestCancel, request_open::RequestOpen};
+    use crate::common::{
+        account_positions::PositionSide,
+        order::states::{request_cancel::RequestCancel, request_open::RequestOpen},
+    };
     use identification::OrderId;
 
     #[test]
@@ -54,10 +57,12 @@ mod tests
     {
         let req1 = RequestOpen { reduce_only: true,
                                  price: 50.0,
-                                 size: 1.0 };
+                                 size: 1.0,
+                                 position_side: PositionSide::Both };
         let req2 = RequestOpen { reduce_only: false,
                                  price: 60.0,
-                                 size: 2.0 };
+                                 size: 2.0,
+                                 position_side: PositionSide::Both };
         assert!(req1 < req2);
     }
 
@@ -1,4 +1,7 @@
-use crate::common::order::{identification::OrderId, Order, OrderRole};
+use crate::common::{
+    account_positions::PositionSide,
+    order::{identification::OrderId, Order, OrderRole},
+};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
@@ -11,6 +14,8 @@ pub struct Open
     pub size: f64,
     pub filled_quantity: f64,
     pub order_role: OrderRole,
+    #[serde(default)]
+    pub position_side: PositionSide,
 }
 
 impl Open
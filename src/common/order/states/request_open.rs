@@ -1,4 +1,4 @@
-use crate::common::order::Order;
+use crate::common::{account_positions::PositionSide, order::Order};
 use fmt::Display;
 use serde::{Deserialize, Serialize};
 use std::{cmp::Ordering, fmt};
@@ -6,13 +6,16 @@ use std::{cmp::Ordering, fmt};
 
 /// 订单初始状态。发送到client进行操作
 ///
-/// `RequestOpen` 用于表示一个初始订单状态。这个状态包含了订单的价格、大小，以及是否为 `reduce_only` 订单。
+/// `RequestOpen` 用于表示一个初始订单状态。这个状态包含了订单的价格、大小、是否为 `reduce_only` 订单，
+/// 以及订单作用的仓位 `position_side`。
 #[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
 pub struct RequestOpen
 {
     pub reduce_only: bool,
     pub price: f64,
     pub size: f64,
+    #[serde(default)]
+    pub position_side: PositionSide,
     // pub leverage: Option<f64>,
     // pub margin_mode: Option<PositionMarginMode>,
     // pub position_direction_mode: Option<PositionDirectionMode>
@@ -46,7 +49,9 @@ impl Display for RequestOpen
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
     {
-        write!(f, "RequestOpen {{ reduce_only: {}, price: {}, size: {} }}", self.reduce_only, self.price, self.size)
+        write!(f,
+               "RequestOpen {{ reduce_only: {}, price: {}, size: {}, position_side: {:?} }}",
+               self.reduce_only, self.price, self.size, self.position_side)
     }
 }
 
@@ -1,6 +1,7 @@
 // 引入相关模块和结构体。
 use crate::{
     common::{
+        account_positions::PositionSide,
         instrument::Instrument,
         order::identification::{client_order_id::ClientOrderId, OrderId},
         Side,
@@ -22,6 +23,8 @@ pub struct ClientTrade
     pub price: f64,
     pub size: f64,
     pub fees: f64,
+    #[serde(default)]
+    pub position_side: PositionSide, // 成交所作用的仓位，继承自订单
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Deserialize, Serialize)]
@@ -187,4 +187,28 @@ pub enum ExchangeError
 
     #[error("PasswordHashError.")]
     PasswordHashError,
+
+    /// 保证金不足，例如提取逐仓保证金后低于初始保证金。
+    #[error("Insufficient margin: {0}")]
+    InsufficientMargin(String),
+
+    /// 订单成交后的潜在仓位会超过账户配置的仓位限额。
+    #[error("Position limit exceeded: {0}")]
+    PositionLimitExceeded(String),
+
+    /// 交易所规则不允许切换保证金模式，例如该金融工具仍有挂单。
+    #[error("Margin mode switch forbidden: {0}")]
+    MarginModeSwitchForbidden(String),
+
+    /// 行情中的符号没有在符号表中登记。
+    #[error("Unknown symbol {symbol} on {exchange}")]
+    UnknownSymbol
+    {
+        exchange: String,
+        symbol: String,
+    },
+
+    /// 读取行情数据源失败，例如数据库游标出错。
+    #[error("Market data error: {0}")]
+    MarketDataError(String),
 }
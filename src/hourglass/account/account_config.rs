@@ -1,7 +1,9 @@
 use crate::{
     common::{
         account_positions::{PositionDirectionMode, PositionMarginMode},
-        instrument::kind::InstrumentKind,
+        instrument::{kind::InstrumentKind, Instrument},
+        option_pricing::PricingModel,
+        token::Token,
     },
     error::ExchangeError,
     hourglass::utils::config_parser::read_config_file,
@@ -19,10 +21,234 @@ pub struct AccountConfig
     pub funding_rate: f64,                                     // 资金费率，用于合约交易中计算资金费用
     pub global_leverage_rate: f64,                             // 账户杠杆率，决定账户在杠杆交易中的放大倍数
     pub fees_book: HashMap<InstrumentKind, CommissionRates>,   // 手续费表，存储每种合约类型的手续费率
+    #[serde(default)]
+    pub fee_schedule: Vec<FeeTier>,                            // 按手续费等级划分的费率表，为空时只使用 `fees_book`
+    #[serde(default)]
+    pub instrument_fees_book: Vec<InstrumentCommissionRates>,  // 按金融工具单独设置的手续费率，优先于其他费率表
     pub execution_mode: HourglassMode,                         // 执行模式，定义账户是在沙盒模式（模拟交易）还是在真实环境中运行
     pub max_price_deviation: f64,                              // 最大价格偏差，用于限制订单价格与市场价格的偏离范围
     pub lazy_account_positions: bool,                          // 是否惰性更新以节约性能
     pub liquidation_threshold: f64,                            // 平仓的门槛，通常为一个0.9~1的系数
+    pub liquidation_fee_rate: f64,                             // 强平费率，按强平成交名义价值收取
+    pub risk_reserve_fee_share: f64,                           // 手续费与强平费中划入风险准备金的比例
+    #[serde(default)]
+    pub position_limits: PositionLimits,                       // 下单前检查的硬性仓位限额
+    #[serde(default)]
+    pub order_risk_limits: OrderRiskLimits,                    // 下单与撤单前风控网关的限制
+    #[serde(default)]
+    pub option_config: OptionConfig,                           // 期权卖方保证金与行权费的设置
+    #[serde(default)]
+    pub leveraged_tokens: Vec<LeveragedTokenSpec>,             // 可申购与赎回的杠杆代币
+    #[serde(default)]
+    pub spot_margin: SpotMarginConfig,                         // 现货杠杆借币的利率与风险等级
+}
+
+/// 手续费等级费率表中的一档。
+///
+/// 滚动 30 天成交额达到 `min_volume` 即可升到该等级；maker 费率可以为负数，表示返佣。
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct FeeTier
+{
+    pub level: CommissionLevel,                              // 手续费等级
+    pub min_volume: f64,                                     // 升到该等级所需的滚动 30 天成交额
+    pub fees_book: HashMap<InstrumentKind, CommissionRates>, // 该等级下每种合约类型的手续费率，缺失的类型退回 `AccountConfig::fees_book`
+}
+
+/// 针对单个金融工具的手续费率，例如交易所对部分交易对推出的零手续费活动。
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct InstrumentCommissionRates
+{
+    pub instrument: Instrument,
+    #[serde(flatten)]
+    pub rates: CommissionRates,
+}
+
+impl AccountConfig
+{
+    /// 按滚动成交额确定手续费等级。配置中的 `commission_level` 是保底等级，成交额只能在其之上升级，
+    /// 成交额回落时也只会降回该等级。
+    pub fn commission_level_for_volume(&self, volume: f64) -> CommissionLevel
+    {
+        self.fee_schedule
+            .iter()
+            .filter(|tier| volume >= tier.min_volume)
+            .map(|tier| tier.level)
+            .fold(self.commission_level, CommissionLevel::max)
+    }
+
+    /// 返回指定等级下某个金融工具的手续费率。
+    ///
+    /// 依次查找 `instrument_fees_book` 中该金融工具的费率、`fee_schedule` 中该等级对应合约类型的费率，最后退回 `fees_book`。
+    pub fn commission_rates(&self, level: CommissionLevel, instrument: &Instrument) -> Option<&CommissionRates>
+    {
+        if let Some(rates) = self.instrument_commission_rates(instrument) {
+            return Some(rates);
+        }
+
+        self.fee_schedule
+            .iter()
+            .find(|tier| tier.level == level)
+            .and_then(|tier| tier.fees_book.get(&instrument.kind))
+            .or_else(|| self.fees_book.get(&instrument.kind))
+    }
+
+    /// 返回单独为该金融工具设置的手续费率
+    pub fn instrument_commission_rates(&self, instrument: &Instrument) -> Option<&CommissionRates>
+    {
+        self.instrument_fees_book
+            .iter()
+            .find(|entry| &entry.instrument == instrument)
+            .map(|entry| &entry.rates)
+    }
+
+    /// 设置或移除（`rates` 为 `None`）某个金融工具的手续费率，返回之前的设置。
+    pub fn set_instrument_commission_rates(&mut self, instrument: &Instrument, rates: Option<CommissionRates>) -> Option<CommissionRates>
+    {
+        let previous = self.instrument_fees_book
+                           .iter()
+                           .position(|entry| &entry.instrument == instrument)
+                           .map(|index| self.instrument_fees_book.remove(index).rates);
+        if let Some(rates) = rates {
+            self.instrument_fees_book.push(InstrumentCommissionRates { instrument: instrument.clone(),
+                                                                       rates });
+        }
+        previous
+    }
+
+    /// 返回杠杆代币的产品参数
+    pub fn leveraged_token_spec(&self, instrument: &Instrument) -> Option<&LeveragedTokenSpec>
+    {
+        self.leveraged_tokens.iter().find(|spec| &spec.instrument == instrument)
+    }
+}
+
+/// 下单与撤单前风控网关的限制，`None` 表示不做限制。
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct OrderRiskLimits
+{
+    pub max_orders_per_second: Option<usize>,          // 按交易所时间计，每秒最多可提交的下单与撤单请求数
+    pub max_open_orders_per_instrument: Option<usize>, // 单个金融工具的最大挂单数
+    pub max_order_notional: Option<f64>,               // 单笔订单的最大名义价值
+    pub max_mark_price_deviation: Option<f64>,         // 胖手指检查：订单价格偏离标记价格的最大比例
+}
+
+/// 欧式期权的保证金与行权设置。
+///
+/// 买方开仓时支付权利金，卖方收取权利金并按行权价的 `seller_margin_rate` 倍缴纳保证金，
+/// 到期自动行权时对实值期权的多头按行权收益的 `exercise_fee_rate` 收取行权费。
+/// 计算希腊字母时使用 `pricing_model` 与 `risk_free_rate`。
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct OptionConfig
+{
+    pub seller_margin_rate: f64,     // 卖方保证金率，保证金 = 行权价 * 数量 * 保证金率
+    pub exercise_fee_rate: f64,      // 行权费率，按行权收益收取
+    #[serde(default)]
+    pub pricing_model: PricingModel, // 计算理论价值与希腊字母的定价模型
+    #[serde(default)]
+    pub risk_free_rate: f64,         // 定价使用的无风险利率（连续复利）
+}
+
+impl Default for OptionConfig
+{
+    fn default() -> Self
+    {
+        Self { seller_margin_rate: 0.15,
+               exercise_fee_rate: 0.0,
+               pricing_model: PricingModel::default(),
+               risk_free_rate: 0.0 }
+    }
+}
+
+impl OptionConfig
+{
+    /// 卖出 `size` 张期权所需缴纳的保证金。
+    pub fn seller_margin(&self, instrument: &Instrument, size: f64) -> f64
+    {
+        instrument.option_spec.map_or(0.0, |option_spec| option_spec.strike.0 * size * self.seller_margin_rate)
+    }
+}
+
+/// 杠杆代币的产品参数。
+///
+/// 代币净值跟踪标的永续合约 `target_leverage` 倍的收益（做空代币为负数），每隔 `rebalance_interval_ms`
+/// 定期再平衡一次，实际杠杆偏离目标杠杆超过 `rebalance_threshold` 时提前再平衡，并按日费率计提管理费。
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct LeveragedTokenSpec
+{
+    pub instrument: Instrument,     // 杠杆代币本身，例如 ETH3L/USDT
+    pub underlying: Instrument,     // 跟踪的标的永续合约
+    pub target_leverage: f64,       // 目标杠杆，例如 3.0 或 -3.0
+    pub rebalance_threshold: f64,   // 实际杠杆与目标杠杆的最大偏离比例，例如 0.2 表示 3 倍杠杆在 2.4 ~ 3.6 之外触发再平衡
+    pub rebalance_interval_ms: i64, // 定期再平衡的间隔（毫秒）
+    pub management_fee_rate: f64,   // 日管理费率
+    pub initial_nav: f64,           // 首次有标的价格时的发行净值
+}
+
+/// 现货杠杆（借币）的设置。
+///
+/// 借入的币种按小时计息，利息计入借款。风险率 = 总资产 / 总负债，均以 `valuation_token` 计价：
+/// 借币后的风险率不能低于 `initial_margin_level`，跌破 `margin_call_level` 时发出追加保证金提醒，
+/// 跌破 `liquidation_margin_level` 时按市价卖出资产偿还借款。
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SpotMarginConfig
+{
+    #[serde(default)]
+    pub hourly_interest_rates: HashMap<Token, f64>, // 按币种设置的小时利率
+    pub default_hourly_interest_rate: f64,          // 未单独设置的币种使用的小时利率
+    pub initial_margin_level: f64,                  // 借币后允许的最低风险率
+    pub margin_call_level: f64,                     // 追加保证金提醒的风险率
+    pub liquidation_margin_level: f64,              // 强平的风险率
+    pub auto_repay: bool,                           // 成交收到借入的币种时自动还款
+    pub valuation_token: Token,                     // 计算风险率所用的计价币种
+}
+
+impl Default for SpotMarginConfig
+{
+    fn default() -> Self
+    {
+        Self { hourly_interest_rates: HashMap::new(),
+               default_hourly_interest_rate: 0.00001,
+               initial_margin_level: 1.5,
+               margin_call_level: 1.3,
+               liquidation_margin_level: 1.1,
+               auto_repay: true,
+               valuation_token: Token::from("USDT") }
+    }
+}
+
+impl SpotMarginConfig
+{
+    /// 返回某个币种的小时利率
+    pub fn hourly_interest_rate(&self, token: &Token) -> f64
+    {
+        self.hourly_interest_rates.get(token).copied().unwrap_or(self.default_hourly_interest_rate)
+    }
+}
+
+/// 账户的硬性仓位限额，`None` 表示不做限制。名义价值均以结算币种计价。
+///
+/// 检查时假设新订单与所有挂单全部成交，即按潜在仓位计算敞口。
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct PositionLimits
+{
+    pub max_position_notional: Option<f64>,                     // 单个金融工具单一方向的最大仓位名义价值
+    #[serde(default)]
+    pub instrument_max_position_notional: HashMap<String, f64>, // 按交易对（如 "BTCUSDT"）单独设置的最大仓位名义价值，优先于 `max_position_notional`
+    pub max_gross_exposure: Option<f64>,                        // 账户总敞口上限 = 所有多头与空头名义价值之和
+    pub max_net_exposure: Option<f64>,                          // 账户净敞口上限 = |多头名义价值 - 空头名义价值|
+    pub max_leveraged_exposure: Option<f64>,                    // 杠杆调整后的敞口上限 = 总敞口 / 账户权益，即账户的实际杠杆倍数
+}
+
+impl PositionLimits
+{
+    /// 返回指定金融工具适用的最大仓位名义价值
+    pub fn max_position_notional_for(&self, instrument: &Instrument) -> Option<f64>
+    {
+        self.instrument_max_position_notional
+            .get(&format!("{}{}", instrument.base, instrument.quote))
+            .copied()
+            .or(self.max_position_notional)
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
@@ -89,9 +315,9 @@ impl CommissionRatesBuilder
 
 pub trait FeesQuerier
 {
-    fn get_maker_fee_rate(&self, instrument_kind: &InstrumentKind) -> Result<f64, ExchangeError>;
+    fn get_maker_fee_rate(&self, instrument: &Instrument, level: CommissionLevel) -> Result<f64, ExchangeError>;
 
-    fn get_taker_fee_rate(&self, instrument_kind: &InstrumentKind) -> Result<f64, ExchangeError>;
+    fn get_taker_fee_rate(&self, instrument: &Instrument, level: CommissionLevel) -> Result<f64, ExchangeError>;
 }
 
 pub trait ConfigLoader
@@ -109,20 +335,18 @@ impl ConfigLoader for AccountConfig
 
 impl FeesQuerier for AccountConfig
 {
-    fn get_maker_fee_rate(&self, instrument_kind: &InstrumentKind) -> Result<f64, ExchangeError>
+    fn get_maker_fee_rate(&self, instrument: &Instrument, level: CommissionLevel) -> Result<f64, ExchangeError>
     {
-        self.fees_book
-            .get(instrument_kind)
+        self.commission_rates(level, instrument)
             .map(|rates| rates.maker_fees)
-            .ok_or_else(|| ExchangeError::Hourglass(format!("Open fee rate for {:?} not found", instrument_kind)))
+            .ok_or_else(|| ExchangeError::Hourglass(format!("Open fee rate for {} not found", instrument)))
     }
 
-    fn get_taker_fee_rate(&self, instrument_kind: &InstrumentKind) -> Result<f64, ExchangeError>
+    fn get_taker_fee_rate(&self, instrument: &Instrument, level: CommissionLevel) -> Result<f64, ExchangeError>
     {
-        self.fees_book
-            .get(instrument_kind)
+        self.commission_rates(level, instrument)
             .map(|rates| rates.taker_fees)
-            .ok_or_else(|| ExchangeError::Hourglass(format!("Close fee rate for {:?} not found", instrument_kind)))
+            .ok_or_else(|| ExchangeError::Hourglass(format!("Close fee rate for {} not found", instrument)))
     }
 }
 
@@ -135,7 +359,8 @@ pub enum MarginMode
     PortfolioMargin,
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+/// 手续费等级，等级越高费率越低。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
 pub enum CommissionLevel
 {
     Lv1,
@@ -153,10 +378,19 @@ pub struct AccountConfigBuilder
     fund_fee_rate: Option<f64>,
     global_leverage_rate: Option<f64>,
     fees_book: Option<HashMap<InstrumentKind, CommissionRates>>,
+    fee_schedule: Option<Vec<FeeTier>>,
+    instrument_fees_book: Option<Vec<InstrumentCommissionRates>>,
     execution_mode: Option<HourglassMode>,
     max_price_deviation: Option<f64>,
     lazy_account_positions: Option<bool>,
     liquidation_threshold: Option<f64>,
+    liquidation_fee_rate: Option<f64>,
+    risk_reserve_fee_share: Option<f64>,
+    position_limits: Option<PositionLimits>,
+    order_risk_limits: Option<OrderRiskLimits>,
+    option_config: Option<OptionConfig>,
+    leveraged_tokens: Option<Vec<LeveragedTokenSpec>>,
+    spot_margin: Option<SpotMarginConfig>,
 }
 
 impl Default for AccountConfigBuilder
@@ -177,10 +411,19 @@ impl AccountConfigBuilder
                fund_fee_rate: None,
                global_leverage_rate: None,
                fees_book: None,
+               fee_schedule: None,
+               instrument_fees_book: None,
                execution_mode: None,
                max_price_deviation: None,
                lazy_account_positions: None,
-               liquidation_threshold: None }
+               liquidation_threshold: None,
+               liquidation_fee_rate: None,
+               risk_reserve_fee_share: None,
+               position_limits: None,
+               order_risk_limits: None,
+               option_config: None,
+               leveraged_tokens: None,
+               spot_margin: None }
     }
 
     pub fn margin_mode(mut self, margin_mode: MarginMode) -> Self
@@ -237,6 +480,18 @@ impl AccountConfigBuilder
         self
     }
 
+    pub fn fee_schedule(mut self, fee_schedule: Vec<FeeTier>) -> Self
+    {
+        self.fee_schedule = Some(fee_schedule);
+        self
+    }
+
+    pub fn instrument_fees_book(mut self, instrument_fees_book: Vec<InstrumentCommissionRates>) -> Self
+    {
+        self.instrument_fees_book = Some(instrument_fees_book);
+        self
+    }
+
     pub fn execution_mode(mut self, execution_mode: HourglassMode) -> Self
     {
         self.execution_mode = Some(execution_mode);
@@ -260,6 +515,59 @@ impl AccountConfigBuilder
         }
     }
 
+    pub fn liquidation_fee_rate(mut self, liquidation_fee_rate: f64) -> Result<Self, ExchangeError>
+    {
+        // 假设强平费率的合理范围是 0 到 0.05
+        if (0.0..=0.05).contains(&liquidation_fee_rate) {
+            self.liquidation_fee_rate = Some(liquidation_fee_rate);
+            Ok(self)
+        }
+        else {
+            Err(ExchangeError::Hourglass("input liquidation fee rate invalid.".into()))
+        }
+    }
+
+    pub fn risk_reserve_fee_share(mut self, risk_reserve_fee_share: f64) -> Result<Self, ExchangeError>
+    {
+        if (0.0..=1.0).contains(&risk_reserve_fee_share) {
+            self.risk_reserve_fee_share = Some(risk_reserve_fee_share);
+            Ok(self)
+        }
+        else {
+            Err(ExchangeError::Hourglass("input risk reserve fee share invalid.".into()))
+        }
+    }
+
+    pub fn position_limits(mut self, position_limits: PositionLimits) -> Self
+    {
+        self.position_limits = Some(position_limits);
+        self
+    }
+
+    pub fn order_risk_limits(mut self, order_risk_limits: OrderRiskLimits) -> Self
+    {
+        self.order_risk_limits = Some(order_risk_limits);
+        self
+    }
+
+    pub fn option_config(mut self, option_config: OptionConfig) -> Self
+    {
+        self.option_config = Some(option_config);
+        self
+    }
+
+    pub fn leveraged_tokens(mut self, leveraged_tokens: Vec<LeveragedTokenSpec>) -> Self
+    {
+        self.leveraged_tokens = Some(leveraged_tokens);
+        self
+    }
+
+    pub fn spot_margin(mut self, spot_margin: SpotMarginConfig) -> Self
+    {
+        self.spot_margin = Some(spot_margin);
+        self
+    }
+
     pub fn initiate(self) -> Result<AccountConfig, &'static str>
     {
         Ok(AccountConfig { margin_mode: self.margin_mode.ok_or("margin_mode is required")?,
@@ -269,9 +577,18 @@ impl AccountConfigBuilder
                            funding_rate: self.fund_fee_rate.ok_or("fund_fee_rate is required")?,
                            global_leverage_rate: Default::default(),
                            fees_book: Default::default(),
+                           fee_schedule: self.fee_schedule.unwrap_or_default(),
+                           instrument_fees_book: self.instrument_fees_book.unwrap_or_default(),
                            execution_mode: HourglassMode::Backtest,
                            max_price_deviation: self.max_price_deviation.ok_or("max price deviation is required")?,
                            lazy_account_positions: self.lazy_account_positions.ok_or("lazy_account_positions switch is required")?,
-                           liquidation_threshold: self.liquidation_threshold.ok_or("liquidation threshold is required")? })
+                           liquidation_threshold: self.liquidation_threshold.ok_or("liquidation threshold is required")?,
+                           liquidation_fee_rate: self.liquidation_fee_rate.ok_or("liquidation fee rate is required")?,
+                           risk_reserve_fee_share: self.risk_reserve_fee_share.ok_or("risk reserve fee share is required")?,
+                           position_limits: self.position_limits.unwrap_or_default(),
+                           order_risk_limits: self.order_risk_limits.unwrap_or_default(),
+                           option_config: self.option_config.unwrap_or_default(),
+                           leveraged_tokens: self.leveraged_tokens.unwrap_or_default(),
+                           spot_margin: self.spot_margin.unwrap_or_default() })
     }
 }
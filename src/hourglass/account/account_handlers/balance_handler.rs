@@ -1,5 +1,7 @@
 use crate::{
     common::{
+        account_positions::{future::FuturePosition, perpetual::PerpetualPosition, PositionMarginMode},
+        account_summary::AccountSummary,
         balance::{Balance, BalanceDelta, TokenBalance},
         event::{AccountEvent, AccountEventKind},
         instrument::{kind::InstrumentKind, Instrument},
@@ -12,14 +14,14 @@ use crate::{
         Side,
     },
     error::ExchangeError,
-    hourglass::account::{respond, DashMapRefMut, HourglassAccount},
+    hourglass::account::{account_handlers::leveraged_token_handler::LeveragedTokenHandler, respond, DashMapRefMut, HourglassAccount},
     hourglass_log::info,
     Exchange,
 };
 use async_trait::async_trait;
 use dashmap::mapref::one::Ref;
-use std::sync::atomic::Ordering;
-use tokio::sync::oneshot::Sender;
+use std::{collections::HashMap, sync::atomic::Ordering};
+use tokio::sync::{oneshot::Sender, RwLock};
 
 #[async_trait]
 pub trait BalanceHandler
@@ -44,6 +46,13 @@ pub trait BalanceHandler
     async fn required_available_balance<'a>(&'a self, order: &'a Order<RequestOpen>, order_role: OrderRole) -> Result<(&'a Token, f64), ExchangeError>;
     /// 判断client是否有足够的可用[`Balance`]来执行[`Order<RequestOpen>`]。
     fn has_sufficient_available_balance(&self, token: &Token, required_balance: f64) -> Result<(), ExchangeError>;
+    /// 判断计入全仓未实现盈亏后的可用保证金是否足够，只适用于全仓模式下的永续与交割合约订单。
+    async fn has_sufficient_available_margin(&self, token: &Token, required_balance: f64) -> Result<(), ExchangeError>;
+    /// 订单是否以全仓模式开仓：已有同向仓位配置时按配置判断，否则按账户的全局仓位保证金模式判断。
+    async fn uses_cross_margin(&self, order: &Order<RequestOpen>) -> bool;
+    /// 以标记价格汇总指定结算币种的账户权益、保证金和保证金率。
+    async fn compute_account_summary(&self, token: &Token) -> Result<AccountSummary, ExchangeError>;
+    async fn fetch_account_summary_and_respond(&self, token: &Token, response_tx: Sender<Result<AccountSummary, ExchangeError>>);
 }
 
 #[async_trait]
@@ -91,10 +100,19 @@ impl BalanceHandler for HourglassAccount
 
         // 根据 PositionMarginMode 处理余额更新 注意 : 暂时不支持spot的仓位逻辑
         match open.instrument.kind {
-            | InstrumentKind::Perpetual | InstrumentKind::Future | InstrumentKind::CryptoLeveragedToken => {
+            // 现货与杠杆代币的买单冻结 quote，卖单冻结要卖出的 base
+            | InstrumentKind::Spot | InstrumentKind::CryptoLeveragedToken => {
+                let token = match open.side {
+                    | Side::Buy => &open.instrument.quote,
+                    | Side::Sell => &open.instrument.base,
+                };
+                self.apply_balance_delta(token, BalanceDelta { total: 0.0,
+                                                               available: -required_balance })
+            }
+            | InstrumentKind::Perpetual | InstrumentKind::Future | InstrumentKind::CryptoOption => {
                 let delta = BalanceDelta { total: 0.0,
                                            available: -required_balance };
-                self.apply_balance_delta(&open.instrument.quote, delta)
+                self.apply_balance_delta(open.instrument.margin_token(), delta)
             }
             | _ => {
                 return Err(ExchangeError::Hourglass(format!("Unsupported InstrumentKind or PositionMarginMode for open order: {:?}", open.instrument.kind)));
@@ -102,42 +120,42 @@ impl BalanceHandler for HourglassAccount
         };
 
         // 更新后的余额
-        let updated_balance = match open.side {
-            | Side::Buy => *self.get_balance(&open.instrument.quote)?,
-            | Side::Sell => *self.get_balance(&open.instrument.base)?,
+        let token = match (open.instrument.kind, open.side) {
+            | (InstrumentKind::Spot | InstrumentKind::CryptoLeveragedToken, Side::Sell) => &open.instrument.base,
+            | _ => open.instrument.margin_token(),
         };
+        let updated_balance = *self.get_balance(token)?;
 
         Ok(AccountEvent { exchange_timestamp: self.exchange_timestamp.load(Ordering::SeqCst),
                           exchange: Exchange::Hourglass,
-                          kind: AccountEventKind::Balance(TokenBalance::new(open.instrument.quote.clone(), updated_balance)) })
+                          kind: AccountEventKind::Balance(TokenBalance::new(token.clone(), updated_balance)) })
     }
 
     /// 当client取消[`Order<Open>`]时，更新相关的[`Token`] [`Balance`]。
     /// [`Balance`]的变化取决于[`Order<Open>`]是[`Side::Buy`]还是[`Side::Sell`]。
     fn apply_cancel_order_changes(&mut self, cancelled: &Order<Open>) -> Result<AccountEvent, ExchangeError>
     {
-        let updated_balance = match cancelled.side {
-            | Side::Buy => {
-                info!("[apply_cancel_order_changes] : applying cancelled balance");
-                let mut balance = self.get_balance_mut(&cancelled.instrument.quote).expect("Balance existence checked when opening Order");
-                info!("[apply_cancel_order_changes] : balance before application of change: {:?}", *balance);
-                info!("[apply_cancel_order_changes] : cancelled order's price is : {:?}", cancelled.state.price);
-                balance.available += cancelled.state.price * cancelled.state.remaining_quantity();
-                info!("[apply_cancel_order_changes] : balance after application of change: {:?}", *balance);
-                *balance
-            }
-            | Side::Sell => {
-                let mut balance = self.get_balance_mut(&cancelled.instrument.base).expect("Balance existence checked when opening Order");
-                balance.available += cancelled.state.price * cancelled.state.remaining_quantity();
-                *balance
+        let remaining = cancelled.state.remaining_quantity();
+        // 退回的金额与挂单时 `required_available_balance` 预留的一致
+        let (token, refund) = match (cancelled.instrument.kind, cancelled.side) {
+            // 永续与交割合约按名义价值除以杠杆预留保证金，币本位合约以 base 缴纳
+            | (InstrumentKind::Perpetual | InstrumentKind::Future, _) => {
+                (cancelled.instrument.margin_token(), cancelled.instrument.notional(cancelled.state.price, remaining) / self.config.global_leverage_rate)
             }
+            // 期权卖单挂单时以 quote 预留了卖方保证金
+            | (InstrumentKind::CryptoOption, Side::Sell) => (&cancelled.instrument.quote, self.config.option_config.seller_margin(&cancelled.instrument, remaining)),
+            // 现货卖单挂单时冻结的是要卖出的 base 数量
+            | (_, Side::Sell) => (&cancelled.instrument.base, remaining),
+            | (_, Side::Buy) => (&cancelled.instrument.quote, cancelled.state.price * remaining),
         };
 
-        // 根据 `Side` 确定使用 `base` 或 `quote` 作为 `Token`
-        let token = match cancelled.side {
-            | Side::Buy => cancelled.instrument.quote.clone(),
-            | Side::Sell => cancelled.instrument.base.clone(),
+        info!("[apply_cancel_order_changes] : releasing {:?} {:?} for cancelled order", refund, token);
+        let updated_balance = {
+            let mut balance = self.get_balance_mut(token).expect("Balance existence checked when opening Order");
+            balance.available += refund;
+            *balance
         };
+        let token = token.clone();
 
         Ok(AccountEvent { exchange_timestamp: self.exchange_timestamp.load(Ordering::SeqCst),
                           exchange: Exchange::Hourglass,
@@ -155,7 +173,8 @@ impl BalanceHandler for HourglassAccount
                                // let trade_quantity = trade.quantity;
 
         match kind {
-            | InstrumentKind::Spot => {
+            // 杠杆代币按净值申购与赎回，余额变化与现货相同
+            | InstrumentKind::Spot | InstrumentKind::CryptoLeveragedToken => {
                 let base = &trade.instrument.base;
                 let (base_delta, quote_delta) = match side {
                     | Side::Buy => {
@@ -185,7 +204,21 @@ impl BalanceHandler for HourglassAccount
                                   kind: AccountEventKind::Balances(vec![TokenBalance::new(base.clone(), base_balance), TokenBalance::new(quote.clone(), quote_balance),]) })
             }
             | InstrumentKind::CryptoOption => {
-                todo!("Option handling is not implemented yet");
+                let premium = trade.size * trade.price;
+                let quote_delta = match side {
+                    // Note: available was already decreased by the premium when opening the Side::Buy order
+                    | Side::Buy => BalanceDelta { total: -premium - fee,
+                                                  available: -fee },
+                    // Note: seller margin stays reserved from the opening of the Side::Sell order, the premium is received immediately
+                    | Side::Sell => BalanceDelta { total: premium - fee,
+                                                   available: premium - fee },
+                };
+
+                let quote_balance = self.apply_balance_delta(quote, quote_delta);
+
+                Ok(AccountEvent { exchange_timestamp: self.get_exchange_ts().expect("Failed to get exchange timestamp"),
+                                  exchange: Exchange::Hourglass,
+                                  kind: AccountEventKind::Balances(vec![TokenBalance::new(quote.clone(), quote_balance),]) })
             }
             | InstrumentKind::CommodityOption => {
                 todo!("CommodityOption handling is not implemented yet")
@@ -193,7 +226,9 @@ impl BalanceHandler for HourglassAccount
             | InstrumentKind::CommodityFuture => {
                 todo!("CommodityFuture handling is not implemented yet")
             }
-            | InstrumentKind::Perpetual | InstrumentKind::Future | InstrumentKind::CryptoLeveragedToken => {
+            // 币本位合约的手续费以 base 计，从 base 余额中扣除
+            | InstrumentKind::Perpetual | InstrumentKind::Future => {
+                let margin_token = trade.instrument.margin_token();
                 let leverage_rate = self.config.global_leverage_rate;
                 let quote_delta = match side {
                     | Side::Buy => {
@@ -209,13 +244,13 @@ impl BalanceHandler for HourglassAccount
                 };
 
                 info!("[apply_trade_changes] : quote_delta: {:?}", quote_delta);
-                // 应用 quote 的余额变动
-                let quote_balance = self.apply_balance_delta(quote, quote_delta);
+                // 应用保证金币种的余额变动
+                let quote_balance = self.apply_balance_delta(margin_token, quote_delta);
 
-                // 生成账户事件，只涉及 quote
+                // 生成账户事件，只涉及保证金币种
                 Ok(AccountEvent { exchange_timestamp: self.get_exchange_ts().expect("Failed to get exchange timestamp"),
                                   exchange: Exchange::Hourglass,
-                                  kind: AccountEventKind::Balances(vec![TokenBalance::new(quote.clone(), quote_balance),]) })
+                                  kind: AccountEventKind::Balances(vec![TokenBalance::new(margin_token.clone(), quote_balance),]) })
             }
         }
     }
@@ -237,9 +272,18 @@ impl BalanceHandler for HourglassAccount
         let max_price_deviation = self.config.max_price_deviation;
         info!("[required_available_balance] : The Maximum of price deviation is {:?}", max_price_deviation);
 
+        // 杠杆代币没有订单簿，按当前净值成交：买单冻结买入金额，卖单冻结要卖出的代币
+        if order.instrument.kind == InstrumentKind::CryptoLeveragedToken {
+            return match order.side {
+                | Side::Buy => Ok((&order.instrument.quote, self.leveraged_token_nav(&order.instrument).await? * order.state.size)),
+                | Side::Sell => Ok((&order.instrument.base, order.state.size)),
+            };
+        }
+
         // 将锁定的 order_book 引用存储在一个变量中，确保其生命周期足够长
         let mut order_books_lock = self.single_level_order_book.lock().await;
-        let order_book = order_books_lock.get_mut(&order.instrument).unwrap();
+        let book_key = if order_books_lock.contains_key(&order.instrument) { order.instrument.clone() } else { order.instrument.market_instrument() };
+        let order_book = order_books_lock.get_mut(&book_key).unwrap();
 
         match order.instrument.kind {
             // Spot 交易
@@ -276,19 +320,15 @@ impl BalanceHandler for HourglassAccount
                         if order.state.price < latest_ask * (1.0 - max_price_deviation) {
                             return Err(ExchangeError::OrderRejected("Sell order price is too low compared to the market".into()));
                         }
-                        // 计算所需的余额 (挂单价格 * 数量)
-                        let required_balance = order.state.price * order.state.size;
-                        Ok((&order.instrument.base, required_balance))
-                    }
-                    | (Side::Sell, OrderRole::Taker) => {
-                        // taker 必须以最新的买单价成交
-                        let required_balance = latest_bid * order.state.size;
-                        Ok((&order.instrument.base, required_balance))
+                        // 卖单冻结要卖出的 base 数量
+                        Ok((&order.instrument.base, order.state.size))
                     }
+                    | (Side::Sell, OrderRole::Taker) => Ok((&order.instrument.base, order.state.size)),
                 }
             }
-            // Perpetual 和 Future 合约类型
+            // Perpetual 和 Future 合约类型，保证金按名义价值计算，币本位合约以 base 缴纳
             | InstrumentKind::Perpetual | InstrumentKind::Future => {
+                let margin_token = order.instrument.margin_token();
                 let latest_ask = order_book.latest_ask;
                 let latest_bid = order_book.latest_bid;
                 info!("[required_available_balance] : latest_ask is {:?}", latest_ask);
@@ -305,13 +345,13 @@ impl BalanceHandler for HourglassAccount
                             return Err(ExchangeError::OrderRejected("Buy order price is too high compared to the market".into()));
                         }
                         // maker 挂单时需要按照 order.state.price 计算保证金
-                        let required_balance = order.state.price * order.state.size / self.config.global_leverage_rate;
-                        Ok((&order.instrument.quote, required_balance))
+                        let required_balance = order.instrument.notional(order.state.price, order.state.size) / self.config.global_leverage_rate;
+                        Ok((margin_token, required_balance))
                     }
                     | (Side::Buy, OrderRole::Taker) => {
                         // taker 买单，以市场卖价成交
-                        let required_balance = latest_ask * order.state.size / self.config.global_leverage_rate;
-                        Ok((&order.instrument.quote, required_balance))
+                        let required_balance = order.instrument.notional(latest_ask, order.state.size) / self.config.global_leverage_rate;
+                        Ok((margin_token, required_balance))
                     }
                     // Sell 订单处理
                     | (Side::Sell, OrderRole::Maker) => {
@@ -323,23 +363,55 @@ impl BalanceHandler for HourglassAccount
                             return Err(ExchangeError::OrderRejected("Sell order price is too low compared to the market".into()));
                         }
                         // maker 卖单按照 order.state.price 计算
-                        let required_balance = order.state.price * order.state.size / self.config.global_leverage_rate;
-                        Ok((&order.instrument.quote, required_balance))
+                        let required_balance = order.instrument.notional(order.state.price, order.state.size) / self.config.global_leverage_rate;
+                        Ok((margin_token, required_balance))
                     }
                     | (Side::Sell, OrderRole::Taker) => {
                         // taker 卖单，以市场买价成交
-                        let required_balance = latest_bid * order.state.size / self.config.global_leverage_rate;
-                        Ok((&order.instrument.quote, required_balance))
+                        let required_balance = order.instrument.notional(latest_bid, order.state.size) / self.config.global_leverage_rate;
+                        Ok((margin_token, required_balance))
                     }
                 }
             }
-            // 其他类型待实现
+            // 期权：买方按价格支付权利金，卖方按行权价缴纳保证金
             | InstrumentKind::CryptoOption => {
-                todo!("CryptoOption is not supported yet")
-            }
-            | InstrumentKind::CryptoLeveragedToken => {
-                todo!("CryptoLeveragedToken is not supported yet")
+                let latest_ask = order_book.latest_ask;
+                let latest_bid = order_book.latest_bid;
+
+                match (order.side, order_role) {
+                    | (Side::Buy, OrderRole::Maker) => {
+                        if order.state.price < latest_ask * (1.0 - max_price_deviation) {
+                            return Err(ExchangeError::OrderRejected("Buy order price is too low compared to the market".into()));
+                        }
+                        if order.state.price > latest_bid * (1.0 + max_price_deviation) {
+                            return Err(ExchangeError::OrderRejected("Buy order price is too high compared to the market".into()));
+                        }
+                        // maker 买单按挂单价格预留权利金
+                        let required_balance = order.state.price * order.state.size;
+                        Ok((&order.instrument.quote, required_balance))
+                    }
+                    | (Side::Buy, OrderRole::Taker) => {
+                        // taker 买单以最新的卖单价支付权利金
+                        let required_balance = latest_ask * order.state.size;
+                        Ok((&order.instrument.quote, required_balance))
+                    }
+                    | (Side::Sell, order_role) => {
+                        if order_role == OrderRole::Maker {
+                            if order.state.price > latest_bid * (1.0 + max_price_deviation) {
+                                return Err(ExchangeError::OrderRejected("Sell order price is too high compared to the market".into()));
+                            }
+                            if order.state.price < latest_ask * (1.0 - max_price_deviation) {
+                                return Err(ExchangeError::OrderRejected("Sell order price is too low compared to the market".into()));
+                            }
+                        }
+                        // 卖方的保证金只取决于行权价与数量
+                        let required_balance = self.config.option_config.seller_margin(&order.instrument, order.state.size);
+                        Ok((&order.instrument.quote, required_balance))
+                    }
+                }
             }
+            | InstrumentKind::CryptoLeveragedToken => unreachable!("Leveraged tokens are priced at NAV before looking up the order book"),
+            // 其他类型待实现
             | InstrumentKind::CommodityOption => {
                 todo!("CommodityOption is not supported yet")
             }
@@ -361,6 +433,190 @@ impl BalanceHandler for HourglassAccount
             Err(ExchangeError::InsufficientBalance(token.clone()))
         }
     }
+
+    async fn has_sufficient_available_margin(&self, token: &Token, required_balance: f64) -> Result<(), ExchangeError>
+    {
+        let available = self.compute_account_summary(token).await?.available_margin;
+        if available >= required_balance {
+            info!("Currently the account has sufficient cross margin");
+            Ok(())
+        }
+        else {
+            Err(ExchangeError::InsufficientBalance(token.clone()))
+        }
+    }
+
+    async fn uses_cross_margin(&self, order: &Order<RequestOpen>) -> bool
+    {
+        let leg = order.state.position_side.leg(order.side);
+        let margin_mode = match order.instrument.kind {
+            | InstrumentKind::Perpetual => {
+                let config = match leg {
+                    | Side::Buy => self.positions.perpetual_pos_long_config.read().await.get(&order.instrument).cloned(),
+                    | Side::Sell => self.positions.perpetual_pos_short_config.read().await.get(&order.instrument).cloned(),
+                };
+                config.map(|config| config.pos_margin_mode).unwrap_or_else(|| self.config.global_position_margin_mode.clone())
+            }
+            | InstrumentKind::Future => {
+                let config = match leg {
+                    | Side::Buy => self.positions.futures_pos_long_config.read().await.get(&order.instrument).cloned(),
+                    | Side::Sell => self.positions.futures_pos_short_config.read().await.get(&order.instrument).cloned(),
+                };
+                config.map(|config| config.pos_margin_mode).unwrap_or_else(|| self.config.global_position_margin_mode.clone())
+            }
+            | _ => return false,
+        };
+        margin_mode == PositionMarginMode::Cross
+    }
+
+    async fn compute_account_summary(&self, token: &Token) -> Result<AccountSummary, ExchangeError>
+    {
+        let balance = *self.get_balance(token)?;
+        let liquidation_threshold = self.config.liquidation_threshold;
+
+        // 标记价格取单层订单簿中的最新成交价，尚无行情时退回仓位记录的最新价格
+        let mark_prices: HashMap<Instrument, f64> = self.single_level_order_book
+                                                        .lock()
+                                                        .await
+                                                        .iter()
+                                                        .filter(|(_, order_book)| order_book.latest_price > 0.0)
+                                                        .map(|(instrument, order_book)| (instrument.clone(), order_book.latest_price))
+                                                        .collect();
+
+        let mut totals = MarginTotals::default();
+        totals.add_positions(&self.positions.perpetual_pos_long, token, &mark_prices, liquidation_threshold).await;
+        totals.add_positions(&self.positions.perpetual_pos_short, token, &mark_prices, liquidation_threshold).await;
+        totals.add_positions(&self.positions.futures_pos_long, token, &mark_prices, liquidation_threshold).await;
+        totals.add_positions(&self.positions.futures_pos_short, token, &mark_prices, liquidation_threshold).await;
+
+        // 期权空头的保证金计入初始保证金
+        for positions in [&self.positions.option_pos_short_call, &self.positions.option_pos_short_put] {
+            totals.initial_margin += positions.read().await.iter().filter(|(instrument, _)| instrument.margin_token() == token).map(|(_, position)| position.seller_margin).sum::<f64>();
+        }
+
+        Ok(AccountSummary::new(token.clone(),
+                               balance.net(),
+                               balance.available,
+                               totals.unrealised_pnl,
+                               totals.cross_unrealised_pnl,
+                               totals.initial_margin,
+                               totals.maintenance_margin))
+    }
+
+    async fn fetch_account_summary_and_respond(&self, token: &Token, response_tx: Sender<Result<AccountSummary, ExchangeError>>)
+    {
+        let summary = self.compute_account_summary(token).await;
+        respond(response_tx, summary);
+    }
+}
+
+/// 永续合约与交割合约仓位在保证金汇总中共用的计算。
+trait MarginPosition
+{
+    fn margin_mode(&self) -> &PositionMarginMode;
+    fn isolated_margin(&self) -> Option<f64>;
+    fn last_price(&self) -> f64;
+    fn initial_margin(&self) -> f64;
+    fn maintenance_margin(&self, liquidation_threshold: f64) -> f64;
+    fn unrealised_pnl_at(&self, price: f64) -> f64;
+}
+
+impl MarginPosition for PerpetualPosition
+{
+    fn margin_mode(&self) -> &PositionMarginMode
+    {
+        &self.pos_config.pos_margin_mode
+    }
+
+    fn isolated_margin(&self) -> Option<f64>
+    {
+        self.isolated_margin
+    }
+
+    fn last_price(&self) -> f64
+    {
+        self.meta.current_symbol_price
+    }
+
+    fn initial_margin(&self) -> f64
+    {
+        PerpetualPosition::initial_margin(self)
+    }
+
+    fn maintenance_margin(&self, liquidation_threshold: f64) -> f64
+    {
+        PerpetualPosition::maintenance_margin(self, liquidation_threshold)
+    }
+
+    fn unrealised_pnl_at(&self, price: f64) -> f64
+    {
+        PerpetualPosition::unrealised_pnl_at(self, price)
+    }
+}
+
+impl MarginPosition for FuturePosition
+{
+    fn margin_mode(&self) -> &PositionMarginMode
+    {
+        &self.pos_config.pos_margin_mode
+    }
+
+    fn isolated_margin(&self) -> Option<f64>
+    {
+        self.isolated_margin
+    }
+
+    fn last_price(&self) -> f64
+    {
+        self.meta.current_symbol_price
+    }
+
+    fn initial_margin(&self) -> f64
+    {
+        FuturePosition::initial_margin(self)
+    }
+
+    fn maintenance_margin(&self, liquidation_threshold: f64) -> f64
+    {
+        FuturePosition::maintenance_margin(self, liquidation_threshold)
+    }
+
+    fn unrealised_pnl_at(&self, price: f64) -> f64
+    {
+        FuturePosition::unrealised_pnl_at(self, price)
+    }
+}
+
+/// 以某个保证金币种汇总的盈亏与保证金。
+#[derive(Default)]
+struct MarginTotals
+{
+    unrealised_pnl: f64,
+    cross_unrealised_pnl: f64, // 全仓仓位的未实现盈亏
+    initial_margin: f64,
+    maintenance_margin: f64,
+}
+
+impl MarginTotals
+{
+    /// 按标记价格计入以 `margin_token` 结算的仓位，行情中的交割合约不带到期时间，找不到该合约自身的价格时使用不带到期时间的价格。
+    async fn add_positions<P>(&mut self, positions: &RwLock<HashMap<Instrument, P>>, margin_token: &Token, mark_prices: &HashMap<Instrument, f64>, liquidation_threshold: f64)
+        where P: MarginPosition
+    {
+        for (instrument, position) in positions.read().await.iter().filter(|(instrument, _)| instrument.margin_token() == margin_token) {
+            let mark_price = mark_prices.get(instrument).or_else(|| mark_prices.get(&instrument.market_instrument())).copied().unwrap_or(position.last_price());
+            let pnl = position.unrealised_pnl_at(mark_price);
+            self.unrealised_pnl += pnl;
+            self.maintenance_margin += position.maintenance_margin(liquidation_threshold);
+            match position.margin_mode() {
+                | PositionMarginMode::Cross => {
+                    self.cross_unrealised_pnl += pnl;
+                    self.initial_margin += position.initial_margin();
+                }
+                | PositionMarginMode::Isolated => self.initial_margin += position.isolated_margin().unwrap_or(0.0),
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -368,13 +624,23 @@ mod tests
 {
     use super::*;
     use crate::{
-        common::order::{
-            identification::{client_order_id::ClientOrderId, OrderId},
-            order_instructions::OrderInstruction,
-            states::request_open::RequestOpen,
-            OrderRole,
+        common::{
+            account_positions::{perpetual::PerpetualPositionConfig, PositionDirectionMode, PositionSide},
+            order::{
+                identification::{client_order_id::ClientOrderId, OrderId},
+                order_instructions::OrderInstruction,
+                states::request_open::RequestOpen,
+                OrderRole,
+            },
+            trade::ClientTradeId,
+        },
+        hourglass::{
+            account::account_handlers::{
+                position_handler::{PositionHandler, PositionHandling},
+                trade_handler::TradeHandler,
+            },
+            clickhouse_api::datatype::single_level_order_book::SingleLevelOrderBook,
         },
-        hourglass::account::account_handlers::{position_handler::PositionHandler, trade_handler::TradeHandler},
         test_utils::create_test_account,
     };
 
@@ -404,7 +670,7 @@ mod tests
     async fn test_get_fee()
     {
         let account = create_test_account();
-        let fee = account.await.fees_percent(&InstrumentKind::Perpetual, OrderRole::Maker).await.unwrap();
+        let fee = account.await.fees_percent(&Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual)), OrderRole::Maker).await.unwrap();
         assert_eq!(fee, 0.001);
     }
 
@@ -423,7 +689,8 @@ mod tests
                                           price: 100.0,
                                           size: 2.0,
                                           filled_quantity: 0.0,
-                                          order_role: OrderRole::Maker } };
+                                          order_role: OrderRole::Maker,
+                                          position_side: PositionSide::Both } };
 
         let balance_before = account.get_balance(&Token::from("USDT")).unwrap().available;
         let account_event = account.apply_cancel_order_changes(&order).unwrap();
@@ -473,7 +740,8 @@ mod tests
                             side: Side::Buy,
                             state: RequestOpen { price: 100.0, // 设置一个低于市场价格的买单
                                                  size: 2.0,
-                                                 reduce_only: false } };
+                                                 reduce_only: false,
+                                                 position_side: PositionSide::Both } };
 
         match account.required_available_balance(&order, OrderRole::Maker).await {
             | Ok((_token, _required_balance)) => {
@@ -500,7 +768,8 @@ mod tests
                             side: Side::Buy,
                             state: RequestOpen { price: 16499.0,
                                                  size: 2.0,
-                                                 reduce_only: false } };
+                                                 reduce_only: false,
+                                                 position_side: PositionSide::Both } };
 
         match account.required_available_balance(&order, OrderRole::Maker).await {
             | Ok((token, required_balance)) => {
@@ -555,7 +824,8 @@ mod tests
                                          side: Side::Buy,
                                          state: RequestOpen { price: 1.0,
                                                               size: 2.0,
-                                                              reduce_only: false } };
+                                                              reduce_only: false,
+                                                              position_side: PositionSide::Both } };
 
         // 将订单状态从 RequestOpen 转换为 Open
         let open_order = Order { instruction: open_order_request.instruction,
@@ -569,7 +839,8 @@ mod tests
                                                price: open_order_request.state.price,
                                                size: open_order_request.state.size,
                                                filled_quantity: 0.0,
-                                               order_role: OrderRole::Maker } };
+                                               order_role: OrderRole::Maker,
+                                               position_side: PositionSide::Both } };
 
         let required_balance = 2.0; // 模拟需要的余额
 
@@ -594,7 +865,8 @@ mod tests
                                          side: Side::Sell,
                                          state: RequestOpen { price: 1.0,
                                                               size: 2.0,
-                                                              reduce_only: false } };
+                                                              reduce_only: false,
+                                                              position_side: PositionSide::Both } };
 
         // 将订单状态从 RequestOpen 转换为 Open
         let open_order = Order { instruction: open_order_request.instruction,
@@ -608,7 +880,8 @@ mod tests
                                                price: open_order_request.state.price,
                                                size: open_order_request.state.size,
                                                filled_quantity: 0.0,
-                                               order_role: OrderRole::Maker } };
+                                               order_role: OrderRole::Maker,
+                                               position_side: PositionSide::Both } };
 
         let required_balance = 2.0; // 模拟需要的余额
 
@@ -618,4 +891,114 @@ mod tests
         let balance = account.get_balance(&Token::from("USDT")).unwrap();
         assert_eq!(balance.available, 9998.0); // 原始余额是 10000.0，减去 2.0 后应该是 9998.0
     }
+
+    #[tokio::test]
+    async fn test_account_summary_and_cross_margin_admission()
+    {
+        let mut account = create_test_account().await;
+        let instrument = Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual));
+        let usdt = Token::from("USDT");
+
+        account.positions.perpetual_pos_long_config.write().await.insert(instrument.clone(),
+                                                                          PerpetualPositionConfig { pos_margin_mode: PositionMarginMode::Cross,
+                                                                                                    leverage: 10.0,
+                                                                                                    position_direction_mode: PositionDirectionMode::LongShort });
+        account.positions.perpetual_pos_short_config.write().await.insert(instrument.clone(),
+                                                                           PerpetualPositionConfig { pos_margin_mode: PositionMarginMode::Isolated,
+                                                                                                     leverage: 5.0,
+                                                                                                     position_direction_mode: PositionDirectionMode::LongShort });
+        let long_trade = ClientTrade { exchange: Exchange::Hourglass,
+                                       timestamp: 1690000000,
+                                       trade_id: ClientTradeId(1),
+                                       order_id: None,
+                                       cid: None,
+                                       instrument: instrument.clone(),
+                                       side: Side::Buy,
+                                       price: 100.0,
+                                       size: 10.0,
+                                       fees: 0.0,
+                                       position_side: PositionSide::Both };
+        let short_trade = ClientTrade { side: Side::Sell,
+                                        size: 5.0,
+                                        trade_id: ClientTradeId(2),
+                                        ..long_trade.clone() };
+        account.create_perpetual_position(long_trade, PositionHandling::OpenBrandNewPosition).await.unwrap();
+        account.create_perpetual_position(short_trade, PositionHandling::OpenBrandNewPosition).await.unwrap();
+        account.single_level_order_book.lock().await.get_mut(&instrument).unwrap().latest_price = 90.0;
+
+        // 全仓多头亏损 100，逐仓空头盈利 50
+        let summary = account.compute_account_summary(&usdt).await.unwrap();
+        assert_eq!(summary.unrealised_pnl, -50.0);
+        assert_eq!(summary.equity, 9_950.0);
+        assert_eq!(summary.initial_margin, 200.0);
+        assert!((summary.maintenance_margin - 20.0).abs() < 1e-9);
+        assert!((summary.margin_ratio - 20.0 / 9_950.0).abs() < 1e-12);
+        assert_eq!(summary.available_margin, 9_900.0);
+        assert_eq!(summary.max_withdrawable, 9_900.0);
+
+        // 全仓亏损会减少可用于开仓的保证金
+        assert!(account.has_sufficient_available_margin(&usdt, 9_900.0).await.is_ok());
+        assert!(account.has_sufficient_available_margin(&usdt, 9_950.0).await.is_err());
+
+        // 全仓浮盈只能用于合约开仓，现货与期权仍按可用余额判断
+        account.single_level_order_book.lock().await.get_mut(&instrument).unwrap().latest_price = 110.0;
+        let available = account.get_balance(&usdt).unwrap().available;
+        assert!(account.has_sufficient_available_margin(&usdt, available + 100.0).await.is_ok());
+        assert!(account.has_sufficient_available_balance(&usdt, available + 100.0).is_err());
+        assert!(account.has_sufficient_available_balance(&usdt, available).is_ok());
+    }
+
+    #[tokio::test]
+    async fn inverse_perpetual_should_margin_and_settle_in_base()
+    {
+        let mut account = create_test_account().await;
+        let instrument = Instrument::inverse_perpetual("BTC", "USD");
+        let btc = Token::from("BTC");
+        account.deposit_bitcoin(1.0).unwrap();
+        account.single_level_order_book.lock().await.insert(instrument.market_instrument(),
+                                                            SingleLevelOrderBook { latest_bid: 49_990.0,
+                                                                                   latest_ask: 50_000.0,
+                                                                                   latest_price: 0.0 });
+
+        // 数量为 10000 张 1 USD 合约，taker 买单按卖一价以 BTC 预留保证金
+        let order = Order { instruction: OrderInstruction::Market,
+                            exchange: Exchange::Hourglass,
+                            instrument: instrument.clone(),
+                            timestamp: 1690000000,
+                            cid: Some(ClientOrderId("inverse".into())),
+                            side: Side::Buy,
+                            state: RequestOpen { price: 50_000.0,
+                                                 size: 10_000.0,
+                                                 reduce_only: false,
+                                                 position_side: PositionSide::Both } };
+        let (token, required_balance) = account.required_available_balance(&order, OrderRole::Taker).await.unwrap();
+        assert_eq!(token, &btc);
+        assert!((required_balance - 0.2).abs() < 1e-12);
+
+        account.positions.perpetual_pos_long_config.write().await.insert(instrument.clone(),
+                                                                          PerpetualPositionConfig { pos_margin_mode: PositionMarginMode::Cross,
+                                                                                                    leverage: 10.0,
+                                                                                                    position_direction_mode: PositionDirectionMode::Net });
+        let trade = ClientTrade { exchange: Exchange::Hourglass,
+                                  timestamp: 1690000000,
+                                  trade_id: ClientTradeId(1),
+                                  order_id: None,
+                                  cid: None,
+                                  instrument: instrument.clone(),
+                                  side: Side::Buy,
+                                  price: 50_000.0,
+                                  size: 10_000.0,
+                                  fees: 0.0,
+                                  position_side: PositionSide::Both };
+        let position = account.create_perpetual_position(trade, PositionHandling::OpenBrandNewPosition).await.unwrap();
+        assert!((position.initial_margin() - 0.02).abs() < 1e-12);
+        assert!((position.liquidation_price - 50_000.0 / 1.09).abs() < 1e-6);
+
+        // 价格跌到 40000，盈亏为 10000 * (1 / 50000 - 1 / 40000) = -0.05 BTC，且只计入 BTC 账户
+        account.single_level_order_book.lock().await.get_mut(&instrument.market_instrument()).unwrap().latest_price = 40_000.0;
+        let summary = account.compute_account_summary(&btc).await.unwrap();
+        assert!((summary.unrealised_pnl + 0.05).abs() < 1e-12);
+        assert!((summary.initial_margin - 0.02).abs() < 1e-12);
+        assert_eq!(account.compute_account_summary(&Token::from("USDT")).await.unwrap().initial_margin, 0.0);
+    }
 }
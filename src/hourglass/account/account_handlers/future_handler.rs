@@ -0,0 +1,459 @@
+use crate::{
+    common::{
+        account_positions::{
+            future::{FuturePosition, FuturePositionConfig, FutureSettlement},
+            liquidation::LiquidatablePosition,
+            PositionDirectionMode, PositionMarginMode, PositionSide,
+        },
+        balance::{BalanceDelta, TokenBalance},
+        event::{AccountEvent, AccountEventKind},
+        instrument::Instrument,
+        trade::ClientTrade,
+        Side,
+    },
+    error::ExchangeError,
+    hourglass::account::{
+        account_handlers::{balance_handler::BalanceHandler, position_handler::PositionHandler},
+        HourglassAccount,
+    },
+    hourglass_log::warn,
+    Exchange,
+};
+use async_trait::async_trait;
+use std::sync::atomic::Ordering;
+
+/// 交割合约的仓位生命周期：按成交开平仓，并在到期时按交割价格现金交割。
+///
+/// 保证金的处理与永续合约一致：开仓所需的保证金在挂单时已从可用余额中预留，全仓仓位计入 `account_margin`，
+/// 逐仓仓位计入 `isolated_margin`；减仓时按比例释放保证金并结算盈亏。
+#[async_trait]
+pub trait FutureHandler
+{
+    /// 返回交割合约的仓位配置：优先使用预设的配置，没有预设时使用账户的全局设置。
+    async fn future_config_or_default(&self, instrument: &Instrument, side: Side) -> FuturePositionConfig;
+
+    async fn get_future_position(&self, instrument: &Instrument, side: Side) -> Option<FuturePosition>;
+
+    async fn store_future_position(&self, position: FuturePosition);
+
+    /// 按成交更新交割合约仓位。单向持仓模式下先减少反向仓位，剩余数量再开仓或加仓；
+    /// 双向持仓模式下按成交的 `position_side` 开平对应的仓位，平仓数量不能超过仓位大小。
+    async fn update_future_position_from_client_trade(&mut self, trade: ClientTrade) -> Result<(), ExchangeError>;
+
+    /// 在已有仓位上加仓，并追加相应的保证金。
+    async fn increase_future_position(&mut self, position: FuturePosition, trade: &ClientTrade) -> Result<FuturePosition, ExchangeError>;
+
+    /// 以 `price` 平掉仓位中 `size` 的部分，结算盈亏并按比例释放保证金，返回 `(已实现盈亏, 释放的保证金)`。
+    /// 仓位被全部平掉时移入 [`AccountExitedPositions`](crate::common::account_positions::exited_positions::AccountExitedPositions)。
+    async fn reduce_future_position(&mut self, position: FuturePosition, price: f64, size: f64, timestamp: i64) -> Result<(f64, f64), ExchangeError>;
+
+    /// 交割价格取该合约的标记价格，尚无行情时退回仓位记录的最新价格。
+    async fn settlement_price(&self, position: &FuturePosition) -> f64;
+
+    /// 交易所时间到达到期时间后，撤销已到期合约上的全部挂单，并按交割价格现金交割全部仓位。
+    /// 每个被交割的仓位都会发送一个 [`AccountEventKind::FutureSettlement`] 事件。
+    async fn settle_expired_futures(&mut self) -> Result<Vec<FutureSettlement>, ExchangeError>;
+}
+
+#[async_trait]
+impl FutureHandler for HourglassAccount
+{
+    async fn future_config_or_default(&self, instrument: &Instrument, side: Side) -> FuturePositionConfig
+    {
+        let config = match side {
+            | Side::Buy => self.positions.futures_pos_long_config.read().await.get(instrument).cloned(),
+            | Side::Sell => self.positions.futures_pos_short_config.read().await.get(instrument).cloned(),
+        };
+        config.unwrap_or_else(|| FuturePositionConfig { pos_margin_mode: self.config.global_position_margin_mode.clone(),
+                                                        leverage: self.config.global_leverage_rate,
+                                                        position_direction_mode: self.config.global_position_direction_mode.clone() })
+    }
+
+    async fn get_future_position(&self, instrument: &Instrument, side: Side) -> Option<FuturePosition>
+    {
+        match side {
+            | Side::Buy => self.positions.futures_pos_long.read().await.get(instrument).cloned(),
+            | Side::Sell => self.positions.futures_pos_short.read().await.get(instrument).cloned(),
+        }
+    }
+
+    async fn store_future_position(&self, position: FuturePosition)
+    {
+        let instrument = position.meta.instrument.clone();
+        match position.meta.side {
+            | Side::Buy => self.positions.futures_pos_long.write().await.insert(instrument, position),
+            | Side::Sell => self.positions.futures_pos_short.write().await.insert(instrument, position),
+        };
+    }
+
+    async fn update_future_position_from_client_trade(&mut self, trade: ClientTrade) -> Result<(), ExchangeError>
+    {
+        if trade.instrument.is_expired(trade.timestamp) {
+            return Err(ExchangeError::OrderRejected(format!("{} has expired", trade.instrument)));
+        }
+
+        let leg = trade.position_side.leg(trade.side);
+        let config = self.future_config_or_default(&trade.instrument, leg).await;
+
+        // 双向持仓模式下按成交指定的仓位独立开平
+        if config.position_direction_mode == PositionDirectionMode::LongShort && trade.position_side != PositionSide::Both {
+            let position = self.get_future_position(&trade.instrument, leg).await;
+            if !trade.position_side.is_closing(trade.side) {
+                match position {
+                    | Some(position) => self.increase_future_position(position, &trade).await?,
+                    | None => self.create_future_position(trade).await?,
+                };
+                return Ok(());
+            }
+
+            let position = position.ok_or(ExchangeError::AttemptToRemoveNonExistingPosition)?;
+            if trade.size > position.meta.current_size {
+                return Err(ExchangeError::InvalidTradeSize);
+            }
+            self.reduce_future_position(position, trade.price, trade.size, trade.timestamp).await?;
+            return Ok(());
+        }
+
+        // 单向持仓模式下先减少反向仓位
+        let mut remaining = trade.size;
+        if let Some(opposite) = self.get_future_position(&trade.instrument, trade.side.toggle()).await {
+            let close_size = remaining.min(opposite.meta.current_size);
+            self.reduce_future_position(opposite, trade.price, close_size, trade.timestamp).await?;
+            remaining -= close_size;
+        }
+
+        if remaining > 0.0 {
+            let trade = ClientTrade { size: remaining, ..trade };
+            match self.get_future_position(&trade.instrument, trade.side).await {
+                | Some(position) => self.increase_future_position(position, &trade).await?,
+                | None => self.create_future_position(trade).await?,
+            };
+        }
+
+        Ok(())
+    }
+
+    async fn increase_future_position(&mut self, mut position: FuturePosition, trade: &ClientTrade) -> Result<FuturePosition, ExchangeError>
+    {
+        position.meta.update_from_trade(trade);
+
+        let margin_to_add = trade.instrument.notional(trade.price, trade.size) / position.pos_config.leverage;
+        match position.pos_config.pos_margin_mode {
+            | PositionMarginMode::Cross => {
+                self.account_margin.fetch_add(margin_to_add, Ordering::SeqCst);
+            }
+            | PositionMarginMode::Isolated => {
+                position.isolated_margin = Some(position.isolated_margin.unwrap_or(0.0) + margin_to_add);
+            }
+        }
+
+        // 强平价格按加仓后的开仓均价与仓位的保证金重新计算，与永续合约共用同一公式
+        let margin = match position.pos_config.pos_margin_mode {
+            | PositionMarginMode::Cross => position.initial_margin(),
+            | PositionMarginMode::Isolated => position.isolated_margin.unwrap_or(0.0),
+        };
+        position.update_liquidation_price(position.compute_liquidation_price(margin, self.config.liquidation_threshold));
+
+        self.store_future_position(position.clone()).await;
+        Ok(position)
+    }
+
+    async fn reduce_future_position(&mut self, mut position: FuturePosition, price: f64, size: f64, timestamp: i64) -> Result<(f64, f64), ExchangeError>
+    {
+        let current_size = position.meta.current_size;
+        if size <= 0.0 || current_size <= 0.0 {
+            return Ok((0.0, 0.0));
+        }
+
+        let fraction = (size / current_size).min(1.0);
+        let is_full_close = fraction >= 1.0;
+        let realised_pnl = match position.meta.side {
+            | Side::Buy => (price - position.meta.current_avg_price) * size,
+            | Side::Sell => (position.meta.current_avg_price - price) * size,
+        };
+
+        // 结算被平部分的盈亏，并把对应的保证金退回可用余额
+        let (released_margin, quote_delta) = match position.pos_config.pos_margin_mode {
+            | PositionMarginMode::Cross => {
+                let released_margin = position.initial_margin() * fraction;
+                self.account_margin.fetch_sub(released_margin, Ordering::SeqCst);
+                (released_margin,
+                 BalanceDelta { total: realised_pnl,
+                                available: released_margin + realised_pnl })
+            }
+            | PositionMarginMode::Isolated => {
+                // 逐仓的损失以被平部分的仓位保证金为上限
+                let isolated_margin = position.isolated_margin.unwrap_or(0.0);
+                let released_margin = isolated_margin * fraction;
+                let settled = (released_margin + realised_pnl).max(0.0);
+                position.isolated_margin = Some(isolated_margin - released_margin);
+                (released_margin,
+                 BalanceDelta { total: settled - released_margin,
+                                available: settled })
+            }
+        };
+        let quote = position.meta.instrument.quote.clone();
+        let balance = self.apply_balance_delta(&quote, quote_delta);
+
+        position.meta.update_ts = timestamp;
+        position.meta.current_symbol_price = price;
+        position.meta.realised_pnl += realised_pnl;
+        if is_full_close {
+            let exit_margin = match position.pos_config.pos_margin_mode {
+                | PositionMarginMode::Cross => None,
+                | PositionMarginMode::Isolated => Some(released_margin),
+            };
+            self.register_exit_position(&position.meta, position.meta.side, exit_margin).await?;
+            self.remove_future_position(position.meta.instrument.clone(), position.meta.side).await;
+        }
+        else {
+            position.meta.current_size -= size;
+            position.meta.update_unrealised_pnl();
+            self.store_future_position(position).await;
+        }
+
+        if let Err(err) = self.account_event_tx.send(AccountEvent { exchange_timestamp: self.exchange_timestamp.load(Ordering::SeqCst),
+                                                                    exchange: Exchange::Hourglass,
+                                                                    kind: AccountEventKind::Balance(TokenBalance::new(quote, balance)) })
+        {
+            warn!("Client offline - Failed to send AccountEvent::Balance: {:?}", err);
+        }
+
+        Ok((realised_pnl, released_margin))
+    }
+
+    async fn settlement_price(&self, position: &FuturePosition) -> f64
+    {
+        self.mark_price(&position.meta.instrument).await.unwrap_or(position.meta.current_symbol_price)
+    }
+
+    async fn settle_expired_futures(&mut self) -> Result<Vec<FutureSettlement>, ExchangeError>
+    {
+        let exchange_timestamp = self.exchange_timestamp.load(Ordering::SeqCst);
+
+        // 撤销已到期合约上的全部挂单，释放预留的保证金
+        self.cancel_expired_orders().await;
+
+        let mut expired_positions = Vec::new();
+        for positions in [&self.positions.futures_pos_long, &self.positions.futures_pos_short] {
+            expired_positions.extend(positions.read().await.values().filter(|position| position.meta.instrument.is_expired(exchange_timestamp)).cloned());
+        }
+
+        let mut settlements = Vec::with_capacity(expired_positions.len());
+        for position in expired_positions {
+            let settlement_price = self.settlement_price(&position).await;
+            let settlement_ts = position.meta.instrument.expiry.unwrap_or(exchange_timestamp);
+            let (instrument, side, size) = (position.meta.instrument.clone(), position.meta.side, position.meta.current_size);
+            let (position_id, margin_mode) = (position.meta.position_id.clone(), position.pos_config.pos_margin_mode.clone());
+
+            let (realised_pnl, released_margin) = self.reduce_future_position(position, settlement_price, size, settlement_ts).await?;
+            let settlement = FutureSettlement { exchange: Exchange::Hourglass,
+                                                instrument,
+                                                side,
+                                                position_id,
+                                                margin_mode,
+                                                timestamp: settlement_ts,
+                                                settlement_price,
+                                                size,
+                                                realised_pnl,
+                                                released_margin };
+            if let Err(err) = self.account_event_tx.send(AccountEvent { exchange_timestamp,
+                                                                        exchange: Exchange::Hourglass,
+                                                                        kind: AccountEventKind::FutureSettlement(settlement.clone()) })
+            {
+                warn!("Client offline - Failed to send AccountEvent::FutureSettlement: {:?}", err);
+            }
+            settlements.push(settlement);
+        }
+
+        Ok(settlements)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{
+        common::{
+            instrument::kind::InstrumentKind,
+            order::{identification::client_order_id::ClientOrderId, order_instructions::OrderInstruction, states::request_open::RequestOpen, Order},
+            token::Token,
+        },
+        hourglass::{
+            account::account_handlers::trade_handler::TradeHandler,
+            clickhouse_api::datatype::{clickhouse_trade_data::MarketTrade, single_level_order_book::SingleLevelOrderBook},
+        },
+        test_utils::{create_test_account, create_test_client_trade, TEST_EXPIRY},
+    };
+
+    #[tokio::test]
+    async fn future_position_should_net_reduce_and_reverse()
+    {
+        let mut account = create_test_account().await;
+        let instrument = Instrument::future("ETH", "USDT", TEST_EXPIRY);
+
+        // 开仓前与挂单一样先预留 1000 的保证金
+        account.apply_balance_delta(&Token::from("USDT"), BalanceDelta { total: 0.0, available: -1_000.0 });
+        account.update_future_position_from_client_trade(create_test_client_trade(&instrument, Side::Buy, 100.0, 10.0, 0.0)).await.unwrap();
+        assert_eq!(account.account_margin.load(Ordering::SeqCst), 1_000.0);
+
+        // 平仓 4 张释放 400 的保证金并实现 40 的盈利
+        account.update_future_position_from_client_trade(create_test_client_trade(&instrument, Side::Sell, 110.0, 4.0, 0.0)).await.unwrap();
+        let long = account.get_future_position(&instrument, Side::Buy).await.unwrap();
+        assert_eq!(long.meta.current_size, 6.0);
+        assert_eq!(long.meta.realised_pnl, 40.0);
+        assert_eq!(account.account_margin.load(Ordering::SeqCst), 600.0);
+        let balance = *account.get_balance(&Token::from("USDT")).unwrap();
+        assert_eq!((balance.total, balance.available), (10_040.0, 9_440.0));
+
+        // 平掉剩余的 6 张多头后反向开 4 张空头
+        account.update_future_position_from_client_trade(create_test_client_trade(&instrument, Side::Sell, 110.0, 10.0, 0.0)).await.unwrap();
+        assert!(account.get_future_position(&instrument, Side::Buy).await.is_none());
+        assert_eq!(account.get_future_position(&instrument, Side::Sell).await.unwrap().meta.current_size, 4.0);
+        assert_eq!(account.account_margin.load(Ordering::SeqCst), 440.0);
+
+        let exited = account.exited_positions.futures_pos_long.read().await;
+        assert_eq!(exited.len(), 1);
+        assert_eq!(exited.values().next().unwrap().realised_pnl, 60.0);
+    }
+
+    #[tokio::test]
+    async fn expired_future_should_cancel_orders_and_cash_settle()
+    {
+        let mut account = create_test_account().await;
+        let instrument = Instrument::future("ETH", "USDT", TEST_EXPIRY);
+        let quote = Token::from("USDT");
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+        account.account_event_tx = event_tx;
+        account.config.fees_book.insert(InstrumentKind::Future, account.config.fees_book[&InstrumentKind::Perpetual].clone());
+        account.account_open_book.read().await.instrument_orders_map.insert(instrument.clone(), Default::default());
+        account.single_level_order_book.lock().await.insert(instrument.undated(),
+                                                            SingleLevelOrderBook { latest_bid: 99.0,
+                                                                                   latest_ask: 101.0,
+                                                                                   latest_price: 0.0 });
+        account.config.global_leverage_rate = 2.0;
+        account.update_exchange_ts(TEST_EXPIRY - 60_000);
+
+        // 以 2 倍杠杆挂一张买单和一张卖单，并持有一个 2 张的全仓空头仓位，开仓时按挂单流程预留保证金
+        let request = Order { instruction: OrderInstruction::Limit,
+                              exchange: Exchange::Hourglass,
+                              instrument: instrument.clone(),
+                              timestamp: TEST_EXPIRY - 60_000,
+                              cid: Some(ClientOrderId("future-bid".into())),
+                              side: Side::Buy,
+                              state: RequestOpen { price: 100.0,
+                                                   size: 1.0,
+                                                   reduce_only: false,
+                                                   position_side: PositionSide::Both } };
+        account.atomic_open(request.clone()).await.unwrap();
+        account.atomic_open(Order { cid: Some(ClientOrderId("future-ask".into())),
+                                    side: Side::Sell,
+                                    state: RequestOpen { price: 103.0, ..request.state.clone() },
+                                    ..request.clone() })
+               .await
+               .unwrap();
+        assert_eq!(account.get_balance(&quote).unwrap().available, 10_000.0 - 50.0 - 51.5);
+        assert_eq!(account.get_balance(&Token::from("ETH")).unwrap().available, 10.0);
+        account.update_future_position_from_client_trade(create_test_client_trade(&instrument, Side::Sell, 100.0, 2.0, 0.0)).await.unwrap();
+        account.apply_balance_delta(&quote, BalanceDelta { total: 0.0, available: -100.0 });
+
+        // 到期前的行情只更新标记价格，买方主动成交不会撮合买单
+        let market_trade = MarketTrade { exchange: "binance-coin-futures".to_string(),
+                                         symbol: "ETHUSDT".to_string(),
+                                         side: Side::Buy.to_string(),
+                                         price: 90.0,
+                                         timestamp: TEST_EXPIRY - 1,
+                                         amount: 1.0 };
+        account.handle_trade_data(&market_trade).await.unwrap();
+        assert_eq!(account.get_future_position(&instrument, Side::Sell).await.unwrap().meta.current_size, 2.0);
+
+        account.update_exchange_ts(TEST_EXPIRY);
+        let settlements = account.settle_expired_futures().await.unwrap();
+        assert_eq!(settlements.len(), 1);
+        assert_eq!(settlements[0].settlement_price, 90.0);
+        assert_eq!(settlements[0].realised_pnl, 20.0);
+        assert_eq!(settlements[0].released_margin, 100.0);
+        let mut settlement_events = 0;
+        while let Ok(event) = event_rx.try_recv() {
+            if let AccountEventKind::FutureSettlement(settlement) = event.kind {
+                assert_eq!(settlement, settlements[0]);
+                settlement_events += 1;
+            }
+        }
+        assert_eq!(settlement_events, 1);
+
+        // 挂单被撤销，按挂单时预留的保证金退回 quote，保证金与盈亏退回余额
+        assert!(account.account_open_book.read().await.fetch_all().is_empty());
+        let balance = *account.get_balance(&quote).unwrap();
+        assert_eq!((balance.total, balance.available), (10_020.0, 10_020.0));
+        assert_eq!(account.get_balance(&Token::from("ETH")).unwrap().available, 10.0);
+        assert_eq!(account.account_margin.load(Ordering::SeqCst), 0.0);
+        assert!(account.positions.futures_pos_short.read().await.is_empty());
+        assert_eq!(account.exited_positions.futures_pos_short.read().await.values().next().unwrap().realised_pnl, 20.0);
+
+        // 到期后的合约不再接受新订单
+        assert!(matches!(account.atomic_open(request).await, Err(ExchangeError::OrderRejected(_))));
+        assert!(account.settle_expired_futures().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn future_position_should_be_liquidated_incrementally()
+    {
+        let mut account = create_test_account().await;
+        let instrument = Instrument::future("ETH", "USDT", TEST_EXPIRY);
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+        account.account_event_tx = event_tx;
+        account.positions.futures_pos_long_config.write().await.insert(instrument.clone(),
+                                                                        FuturePositionConfig { pos_margin_mode: PositionMarginMode::Isolated,
+                                                                                               leverage: 10.0,
+                                                                                               position_direction_mode: PositionDirectionMode::Net });
+        account.update_exchange_ts(TEST_EXPIRY - 60_000);
+
+        // 10 倍杠杆的逐仓多头，先在 100 开 10 张，再在 110 加 10 张，开仓前与挂单一样先预留保证金
+        account.apply_balance_delta(&Token::from("USDT"), BalanceDelta { total: 0.0, available: -210.0 });
+        account.update_future_position_from_client_trade(create_test_client_trade(&instrument, Side::Buy, 100.0, 10.0, 0.0)).await.unwrap();
+        assert_eq!(account.get_future_position(&instrument, Side::Buy).await.unwrap().liquidation_price, 91.0);
+        account.update_future_position_from_client_trade(create_test_client_trade(&instrument, Side::Buy, 110.0, 10.0, 0.0)).await.unwrap();
+
+        // 加仓后的强平价格按开仓均价 105 与 210 的逐仓保证金计算，而不是按最新成交价
+        let position = account.get_future_position(&instrument, Side::Buy).await.unwrap();
+        assert_eq!(position.isolated_margin, Some(210.0));
+        assert!((position.liquidation_price - 95.55).abs() < 1e-9);
+
+        // 不带到期时间的行情跌破强平价格，只平掉恢复维持保证金所需的部分
+        let market_trade = MarketTrade { exchange: "binance-coin-futures".to_string(),
+                                         symbol: "ETHUSDT".to_string(),
+                                         side: "Sell".to_string(),
+                                         price: 95.0,
+                                         timestamp: TEST_EXPIRY - 30_000,
+                                         amount: 1.0 };
+        account.handle_trade_data(&market_trade).await.unwrap();
+
+        let position = account.get_future_position(&instrument, Side::Buy).await.unwrap();
+        let closed_size = 20.0 * 11.0 / 11.5;
+        let fee = closed_size * 95.0 * 0.005;
+        assert!((position.meta.current_size - (20.0 - closed_size)).abs() < 1e-9);
+        assert!((position.isolated_margin.unwrap() - (210.0 - 10.0 * closed_size - fee)).abs() < 1e-9);
+        assert!((position.liquidation_price - 95.0).abs() < 1e-9);
+
+        // 继续下跌后穿仓，仓位被整体平掉
+        account.handle_trade_data(&MarketTrade { price: 80.0,
+                                                 timestamp: TEST_EXPIRY - 20_000,
+                                                 ..market_trade })
+               .await
+               .unwrap();
+        assert!(account.positions.futures_pos_long.read().await.is_empty());
+
+        let mut liquidations = vec![];
+        while let Ok(event) = event_rx.try_recv() {
+            if let AccountEventKind::Liquidation(liquidation) = event.kind {
+                liquidations.push(liquidation);
+            }
+        }
+        assert_eq!(liquidations.len(), 2);
+        assert!(liquidations.iter().all(|liquidation| liquidation.instrument == instrument && liquidation.side == Side::Buy));
+        assert!((liquidations[0].closed_size - closed_size).abs() < 1e-9);
+        assert_eq!(liquidations[1].remaining_size, 0.0);
+    }
+}
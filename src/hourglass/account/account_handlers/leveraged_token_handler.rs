@@ -0,0 +1,389 @@
+use crate::{
+    common::{
+        account_positions::{
+            leveraged_token::{LeveragedTokenNav, LeveragedTokenRebalance, RebalanceTrigger},
+            PositionSide,
+        },
+        event::{AccountEvent, AccountEventKind},
+        instrument::Instrument,
+        order::{
+            order_instructions::OrderInstruction,
+            states::{open::Open, request_open::RequestOpen},
+            Order, OrderRole,
+        },
+        trade::{ClientTrade, ClientTradeId},
+        Side,
+    },
+    error::ExchangeError,
+    hourglass::account::{
+        account_config::LeveragedTokenSpec,
+        account_handlers::{balance_handler::BalanceHandler, position_handler::PositionHandler, trade_handler::TradeHandler},
+        respond, HourglassAccount,
+    },
+    hourglass_log::warn,
+    Exchange,
+};
+use async_trait::async_trait;
+use std::sync::atomic::Ordering;
+use tokio::sync::oneshot::Sender;
+
+/// 杠杆代币的申购、赎回与净值维护。
+///
+/// 代币的产品参数来自 [`AccountConfig::leveraged_tokens`](crate::hourglass::account::account_config::AccountConfig::leveraged_tokens)，
+/// 标的永续合约首次有价格时按 `initial_nav` 发行。代币没有订单簿，买卖订单按当前净值以 taker 费率即时全部成交，
+/// 余额的检查、冻结与成交都走现货的逻辑：买入扣除 quote 并增加代币余额，卖出扣除代币余额并退回 quote。
+/// 申购与赎回是以市价单买入与卖出代币的简写。
+#[async_trait]
+pub trait LeveragedTokenHandler
+{
+    /// 返回杠杆代币当前的净值。尚未发行的代币返回发行净值，标的没有价格时返回错误。
+    async fn leveraged_token_nav(&self, instrument: &Instrument) -> Result<f64, ExchangeError>;
+
+    /// 按最新的标的价格计提管理费，并对到达再平衡时间或杠杆偏离超过阈值的代币再平衡，同时刷新代币仓位的最新价格。
+    /// 每次再平衡都会发送一个 [`AccountEventKind::LeveragedTokenRebalance`] 事件。
+    async fn update_leveraged_tokens(&mut self) -> Result<Vec<LeveragedTokenRebalance>, ExchangeError>;
+
+    /// 按当前净值立即成交一笔杠杆代币订单，返回全部成交的订单与生成的成交。
+    /// 限价买单的价格低于净值、限价卖单的价格高于净值时无法成交，订单被拒绝。
+    async fn execute_leveraged_token_order(&mut self, order: Order<RequestOpen>) -> Result<(Order<Open>, ClientTrade), ExchangeError>;
+
+    /// 以当前净值申购 `size` 份杠杆代币，返回生成的成交。
+    async fn subscribe_leveraged_token(&mut self, instrument: &Instrument, size: f64) -> Result<ClientTrade, ExchangeError>;
+
+    /// 以当前净值赎回 `size` 份杠杆代币，返回生成的成交。
+    async fn redeem_leveraged_token(&mut self, instrument: &Instrument, size: f64) -> Result<ClientTrade, ExchangeError>;
+
+    /// 按申购或赎回的成交更新代币的持仓记录，赎回按持仓均价结算已实现盈亏。
+    async fn update_leveraged_token_position_from_client_trade(&mut self, trade: ClientTrade) -> Result<(), ExchangeError>;
+
+    async fn subscribe_leveraged_token_and_respond(&mut self, instrument: &Instrument, size: f64, response_tx: Sender<Result<ClientTrade, ExchangeError>>);
+
+    async fn redeem_leveraged_token_and_respond(&mut self, instrument: &Instrument, size: f64, response_tx: Sender<Result<ClientTrade, ExchangeError>>);
+}
+
+impl HourglassAccount
+{
+    fn leveraged_token_spec_of(&self, instrument: &Instrument) -> Result<LeveragedTokenSpec, ExchangeError>
+    {
+        self.config
+            .leveraged_token_spec(instrument)
+            .cloned()
+            .ok_or_else(|| ExchangeError::InvalidInstrument(format!("{} is not a configured leveraged token", instrument)))
+    }
+
+    /// 申购与赎回对应的市价单
+    fn leveraged_token_market_order(&self, instrument: &Instrument, side: Side, size: f64) -> Order<RequestOpen>
+    {
+        Order { instruction: OrderInstruction::Market,
+                exchange: Exchange::Hourglass,
+                instrument: instrument.clone(),
+                timestamp: self.exchange_timestamp.load(Ordering::SeqCst),
+                cid: None,
+                side,
+                state: RequestOpen { reduce_only: false,
+                                     price: 0.0,
+                                     size,
+                                     position_side: PositionSide::Both } }
+    }
+}
+
+#[async_trait]
+impl LeveragedTokenHandler for HourglassAccount
+{
+    async fn leveraged_token_nav(&self, instrument: &Instrument) -> Result<f64, ExchangeError>
+    {
+        let spec = self.leveraged_token_spec_of(instrument)?;
+        let price = self.mark_price(&spec.underlying)
+                        .await
+                        .ok_or_else(|| ExchangeError::Hourglass(format!("No price available for {}", spec.underlying)))?;
+
+        Ok(self.leveraged_token_navs.get(instrument).map_or(spec.initial_nav, |state| state.nav_at(price)))
+    }
+
+    async fn update_leveraged_tokens(&mut self) -> Result<Vec<LeveragedTokenRebalance>, ExchangeError>
+    {
+        let timestamp = self.exchange_timestamp.load(Ordering::SeqCst);
+        let mut rebalances = Vec::new();
+
+        for spec in self.config.leveraged_tokens.clone() {
+            let Some(price) = self.mark_price(&spec.underlying).await
+            else {
+                continue;
+            };
+
+            let state = self.leveraged_token_navs
+                            .entry(spec.instrument.clone())
+                            .or_insert_with(|| LeveragedTokenNav::new(spec.initial_nav, spec.target_leverage, price, timestamp));
+            state.accrue_management_fee(spec.management_fee_rate, price, timestamp);
+
+            let leverage = state.leverage_at(price);
+            let trigger = if spec.rebalance_interval_ms > 0 && timestamp - state.last_rebalance_ts >= spec.rebalance_interval_ms {
+                Some(RebalanceTrigger::Scheduled)
+            }
+            else if (leverage - spec.target_leverage).abs() > spec.rebalance_threshold * spec.target_leverage.abs() {
+                Some(RebalanceTrigger::Threshold)
+            }
+            else {
+                None
+            };
+
+            if let Some(trigger) = trigger {
+                state.rebalance(spec.target_leverage, price, timestamp);
+                rebalances.push(LeveragedTokenRebalance { exchange: Exchange::Hourglass,
+                                                          instrument: spec.instrument.clone(),
+                                                          trigger,
+                                                          timestamp,
+                                                          underlying_price: price,
+                                                          nav: state.reference_nav,
+                                                          leverage_before: leverage,
+                                                          leverage_after: spec.target_leverage });
+            }
+
+            // 代币仓位以净值作为最新价格
+            let nav = state.nav_at(price);
+            if let Some(position) = self.positions.leveraged_token_pos.write().await.get_mut(&spec.instrument) {
+                position.meta.current_symbol_price = nav;
+                position.meta.update_unrealised_pnl();
+            }
+        }
+
+        for rebalance in &rebalances {
+            if let Err(err) = self.account_event_tx.send(AccountEvent { exchange_timestamp: timestamp,
+                                                                        exchange: Exchange::Hourglass,
+                                                                        kind: AccountEventKind::LeveragedTokenRebalance(rebalance.clone()) })
+            {
+                warn!("Client offline - Failed to send AccountEvent::LeveragedTokenRebalance: {:?}", err);
+            }
+        }
+
+        Ok(rebalances)
+    }
+
+    async fn execute_leveraged_token_order(&mut self, order: Order<RequestOpen>) -> Result<(Order<Open>, ClientTrade), ExchangeError>
+    {
+        if order.state.size <= 0.0 {
+            return Err(ExchangeError::OrderRejected(format!("Invalid leveraged token size: {}", order.state.size)));
+        }
+
+        self.update_leveraged_tokens().await?;
+        let nav = self.leveraged_token_nav(&order.instrument).await?;
+        if nav <= 0.0 {
+            return Err(ExchangeError::OrderRejected(format!("{} has no remaining net asset value", order.instrument)));
+        }
+        if order.instruction != OrderInstruction::Market {
+            let fillable = match order.side {
+                | Side::Buy => nav <= order.state.price,
+                | Side::Sell => nav >= order.state.price,
+            };
+            if !fillable {
+                return Err(ExchangeError::OrderRejected(format!("{} NAV {} does not reach the {:?} limit price {}", order.instrument, nav, order.side, order.state.price)));
+            }
+        }
+
+        // 与现货订单一样先冻结买入金额或卖出的代币，成交时再扣除总额与手续费
+        self.initialize_tokens(vec![order.instrument.base.to_string()])?;
+        let (token, required_balance) = self.required_available_balance(&order, OrderRole::Taker).await?;
+        let token = token.clone();
+        self.has_sufficient_available_balance(&token, required_balance)?;
+        let mut open = self.account_open_book.write().await.build_order_open(order, OrderRole::Taker).await;
+        let balance_event = self.apply_open_order_changes(&open, required_balance).await?;
+        if let Err(err) = self.account_event_tx.send(balance_event) {
+            warn!("Client offline - Failed to send AccountEvent::Balance: {:?}", err);
+        }
+
+        let fees = nav * open.state.size * self.fees_percent(&open.instrument, OrderRole::Taker).await?;
+        let trade = ClientTrade { exchange: Exchange::Hourglass,
+                                  timestamp: self.exchange_timestamp.load(Ordering::SeqCst),
+                                  trade_id: ClientTradeId(self.client_trade_counter.fetch_add(1, Ordering::SeqCst)),
+                                  order_id: Some(open.state.id.clone()),
+                                  cid: open.cid.clone(),
+                                  instrument: open.instrument.clone(),
+                                  side: open.side,
+                                  price: nav,
+                                  size: open.state.size,
+                                  fees,
+                                  position_side: PositionSide::Both };
+        self.process_trade(trade.clone()).await?;
+        self.update_leveraged_token_position_from_client_trade(trade.clone()).await?;
+
+        open.state.filled_quantity = open.state.size;
+        Ok((open, trade))
+    }
+
+    async fn subscribe_leveraged_token(&mut self, instrument: &Instrument, size: f64) -> Result<ClientTrade, ExchangeError>
+    {
+        let order = self.leveraged_token_market_order(instrument, Side::Buy, size);
+        Ok(self.execute_leveraged_token_order(order).await?.1)
+    }
+
+    async fn redeem_leveraged_token(&mut self, instrument: &Instrument, size: f64) -> Result<ClientTrade, ExchangeError>
+    {
+        let order = self.leveraged_token_market_order(instrument, Side::Sell, size);
+        Ok(self.execute_leveraged_token_order(order).await?.1)
+    }
+
+    async fn update_leveraged_token_position_from_client_trade(&mut self, trade: ClientTrade) -> Result<(), ExchangeError>
+    {
+        let existing = self.positions.leveraged_token_pos.read().await.get(&trade.instrument).cloned();
+        match (trade.side, existing) {
+            | (Side::Buy, Some(mut position)) => {
+                position.meta.update_from_trade(&trade);
+                self.positions.leveraged_token_pos.write().await.insert(trade.instrument.clone(), position);
+            }
+            | (Side::Buy, None) => {
+                self.create_leveraged_token_position(trade).await?;
+            }
+            | (Side::Sell, Some(mut position)) => {
+                let size = trade.size.min(position.meta.current_size);
+                position.meta.realised_pnl += (trade.price - position.meta.current_avg_price) * size;
+                position.meta.current_fees_total += trade.fees;
+                position.meta.current_symbol_price = trade.price;
+                position.meta.update_ts = trade.timestamp;
+                if size >= position.meta.current_size {
+                    self.register_exit_position(&position.meta, Side::Buy, None).await?;
+                    self.remove_leveraged_token_position(trade.instrument, Side::Buy).await;
+                }
+                else {
+                    position.meta.current_size -= size;
+                    position.meta.update_unrealised_pnl();
+                    self.positions.leveraged_token_pos.write().await.insert(trade.instrument.clone(), position);
+                }
+            }
+            // 赎回的代币没有对应的申购记录（例如直接充值的代币），无需更新持仓
+            | (Side::Sell, None) => {}
+        }
+
+        Ok(())
+    }
+
+    async fn subscribe_leveraged_token_and_respond(&mut self, instrument: &Instrument, size: f64, response_tx: Sender<Result<ClientTrade, ExchangeError>>)
+    {
+        let result = self.subscribe_leveraged_token(instrument, size).await;
+        respond(response_tx, result);
+    }
+
+    async fn redeem_leveraged_token_and_respond(&mut self, instrument: &Instrument, size: f64, response_tx: Sender<Result<ClientTrade, ExchangeError>>)
+    {
+        let result = self.redeem_leveraged_token(instrument, size).await;
+        respond(response_tx, result);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{
+        common::{account_positions::leveraged_token::ONE_DAY_MS, instrument::kind::InstrumentKind, token::Token},
+        hourglass::account::account_config::CommissionRates,
+        test_utils::{create_test_account, create_test_request_open},
+    };
+
+    async fn set_price(account: &HourglassAccount, instrument: &Instrument, price: f64)
+    {
+        account.single_level_order_book.lock().await.get_mut(instrument).unwrap().latest_price = price;
+    }
+
+    /// 以 ETH 永续合约为标的、3 倍杠杆的 ETH3L，taker 费率 0.1%。
+    async fn leveraged_token_account() -> (HourglassAccount, Instrument, Instrument)
+    {
+        let mut account = create_test_account().await;
+        let token = Instrument::new("ETH3L", "USDT", InstrumentKind::CryptoLeveragedToken);
+        let underlying = Instrument::new("ETH", "USDT", InstrumentKind::Perpetual);
+        account.config.fees_book.insert(InstrumentKind::CryptoLeveragedToken, CommissionRates { maker_fees: 0.001, taker_fees: 0.001 });
+        account.config.leveraged_tokens.push(LeveragedTokenSpec { instrument: token.clone(),
+                                                                  underlying: underlying.clone(),
+                                                                  target_leverage: 3.0,
+                                                                  rebalance_threshold: 0.2,
+                                                                  rebalance_interval_ms: ONE_DAY_MS,
+                                                                  management_fee_rate: 0.0,
+                                                                  initial_nav: 1.0 });
+        (account, token, underlying)
+    }
+
+    #[tokio::test]
+    async fn leveraged_token_orders_should_fill_at_nav_like_spot()
+    {
+        let (mut account, token, underlying) = leveraged_token_account().await;
+        set_price(&account, &underlying, 100.0).await;
+        account.update_exchange_ts(1_000);
+        let order = |side: Side, instruction: OrderInstruction, price: f64, size: f64| {
+            let mut request = create_test_request_open("ETH3L", "USDT");
+            request.instrument = token.clone();
+            request.side = side;
+            request.instruction = instruction;
+            request.state.price = price;
+            request.state.size = size;
+            request
+        };
+
+        // 限价买单低于净值 1.0 时无法成交
+        assert!(matches!(account.atomic_open(order(Side::Buy, OrderInstruction::Limit, 0.9, 500.0)).await, Err(ExchangeError::OrderRejected(_))));
+
+        // 限价买单按净值立即全部成交，与现货一样扣除 quote 与手续费
+        let bought = account.atomic_open(order(Side::Buy, OrderInstruction::Limit, 1.0, 500.0)).await.unwrap();
+        assert_eq!(bought.state.filled_quantity, 500.0);
+        assert_eq!(account.get_balance(&Token::from("USDT")).unwrap().total, 9_499.5);
+        assert_eq!(account.get_balance(&Token::from("USDT")).unwrap().available, 9_499.5);
+        assert_eq!(account.get_balance(&Token::from("ETH3L")).unwrap().total, 500.0);
+        assert_eq!(account.positions.leveraged_token_pos.read().await.get(&token).unwrap().meta.current_size, 500.0);
+        // 杠杆代币的持仓与现货杠杆仓位分开记录
+        assert!(account.positions.margin_pos_long.read().await.is_empty());
+        assert!(account.account_open_book.read().await.fetch_all().is_empty());
+
+        // 市价卖单冻结并扣除代币，退回 quote
+        account.atomic_open(order(Side::Sell, OrderInstruction::Market, 0.0, 200.0)).await.unwrap();
+        assert_eq!(account.get_balance(&Token::from("ETH3L")).unwrap().total, 300.0);
+        assert_eq!(account.get_balance(&Token::from("ETH3L")).unwrap().available, 300.0);
+        assert!((account.get_balance(&Token::from("USDT")).unwrap().total - 9_699.3).abs() < 1e-9);
+        assert!(matches!(account.atomic_open(order(Side::Sell, OrderInstruction::Market, 0.0, 301.0)).await, Err(ExchangeError::InsufficientBalance(_))));
+    }
+
+    #[tokio::test]
+    async fn leveraged_token_should_subscribe_rebalance_and_redeem_at_nav()
+    {
+        let (mut account, token, underlying) = leveraged_token_account().await;
+
+        // 以发行净值 1.0 申购 1000 份，手续费 1
+        set_price(&account, &underlying, 100.0).await;
+        account.update_exchange_ts(1_000);
+        let subscription = account.subscribe_leveraged_token(&token, 1_000.0).await.unwrap();
+        assert_eq!(subscription.price, 1.0);
+        assert_eq!(account.get_balance(&Token::from("USDT")).unwrap().total, 8_999.0);
+        assert_eq!(account.get_balance(&Token::from("USDT")).unwrap().available, 8_999.0);
+        assert_eq!(account.get_balance(&Token::from("ETH3L")).unwrap().total, 1_000.0);
+
+        // 标的上涨 10%，杠杆降到 2.54，仍在阈值内
+        set_price(&account, &underlying, 110.0).await;
+        account.update_exchange_ts(2_000);
+        assert!(account.update_leveraged_tokens().await.unwrap().is_empty());
+        assert!((account.leveraged_token_nav(&token).await.unwrap() - 1.3).abs() < 1e-9);
+
+        // 标的上涨 20%，杠杆降到 2.25，超过阈值触发再平衡
+        set_price(&account, &underlying, 120.0).await;
+        account.update_exchange_ts(3_000);
+        let rebalances = account.update_leveraged_tokens().await.unwrap();
+        assert_eq!(rebalances.len(), 1);
+        assert_eq!(rebalances[0].trigger, RebalanceTrigger::Threshold);
+        assert!((rebalances[0].nav - 1.6).abs() < 1e-9);
+        assert!((rebalances[0].leverage_before - 2.25).abs() < 1e-9);
+        let position = account.positions.leveraged_token_pos.read().await.get(&token).cloned().unwrap();
+        assert!((position.meta.unrealised_pnl - 600.0).abs() < 1e-6);
+
+        // 按净值 1.6 赎回 400 份，手续费 0.64
+        let redemption = account.redeem_leveraged_token(&token, 400.0).await.unwrap();
+        assert!((redemption.price - 1.6).abs() < 1e-9);
+        assert!((account.get_balance(&Token::from("USDT")).unwrap().total - 9_638.36).abs() < 1e-6);
+        assert_eq!(account.get_balance(&Token::from("ETH3L")).unwrap().total, 600.0);
+        let position = account.positions.leveraged_token_pos.read().await.get(&token).cloned().unwrap();
+        assert_eq!(position.meta.current_size, 600.0);
+        assert!((position.meta.realised_pnl - 240.0).abs() < 1e-6);
+        assert!(matches!(account.redeem_leveraged_token(&token, 1_000.0).await, Err(ExchangeError::InsufficientBalance(_))));
+
+        // 距上次再平衡满一天时定期再平衡
+        account.update_exchange_ts(3_000 + ONE_DAY_MS);
+        let rebalances = account.update_leveraged_tokens().await.unwrap();
+        assert_eq!(rebalances.len(), 1);
+        assert_eq!(rebalances[0].trigger, RebalanceTrigger::Scheduled);
+    }
+}
@@ -0,0 +1,509 @@
+use crate::{
+    common::{
+        account_positions::liquidation::{MarginCall, MarginLiquidation},
+        balance::{Balance, TokenBalance},
+        event::{AccountEvent, AccountEventKind},
+        instrument::{kind::InstrumentKind, Instrument},
+        token::Token,
+    },
+    error::ExchangeError,
+    hourglass::account::{
+        account_handlers::{balance_handler::BalanceHandler, position_handler::PositionHandler},
+        respond, HourglassAccount,
+    },
+    hourglass_log::warn,
+    Exchange,
+};
+use async_trait::async_trait;
+use std::sync::atomic::Ordering;
+use tokio::sync::oneshot::Sender;
+
+/// 现货杠杆：借入 quote 买入，或借入 base 卖空。
+///
+/// 借款直接计入 [`Balance`] 的 `total`、`available` 与 `borrowed`，之后的现货交易与普通余额无异。
+/// 利息按整小时计入借款，风险率 = 总资产 / 总负债，均按 [`SpotMarginConfig::valuation_token`](crate::hourglass::account::account_config::SpotMarginConfig::valuation_token) 计价。
+#[async_trait]
+pub trait MarginHandler
+{
+    /// 以计价币种表示的币种价格，依次取现货与永续合约的标记价格。
+    async fn valuation_price(&self, token: &Token) -> Option<f64>;
+
+    /// 返回以计价币种计的 `(总资产, 总负债)`。有借款的币种没有价格时返回 `None`，没有价格的资产不计入总资产。
+    async fn margin_totals(&self) -> Option<(f64, f64)>;
+
+    /// 当前的风险率，没有借款或无法估值时返回 `None`。
+    async fn margin_level(&self) -> Option<f64>;
+
+    /// 借入 `amount` 的 `token`，借款后的风险率不能低于 `initial_margin_level`。
+    async fn borrow(&mut self, token: &Token, amount: f64) -> Result<TokenBalance, ExchangeError>;
+
+    /// 用可用余额归还最多 `amount` 的借款。
+    async fn repay(&mut self, token: &Token, amount: f64) -> Result<TokenBalance, ExchangeError>;
+
+    /// 开启自动还款时，用成交收到的 `token` 归还该币种的借款，有还款时返回更新后的余额。
+    async fn auto_repay(&mut self, token: &Token) -> Option<TokenBalance>;
+
+    /// 对所有借款按经过的整小时数计息，利息计入借款，返回有变化的余额。
+    async fn accrue_margin_interest(&mut self) -> Vec<TokenBalance>;
+
+    /// 计息后检查风险率：跌破提醒线时发送 [`AccountEventKind::MarginCall`]，跌破强平线时强平现货杠杆账户。
+    async fn check_margin_level(&mut self) -> Result<Option<MarginLiquidation>, ExchangeError>;
+
+    /// 撤销全部现货挂单后，按标记价格卖出还清借款所需的资产、买回卖空的币种并归还全部借款，按兑换金额收取强平费。
+    /// 只卖出补足借款所需的数量，其余资产（例如币本位合约的保证金）不受影响；资产不足以还清的借款保留在余额中。
+    async fn liquidate_margin_account(&mut self, margin_level: f64) -> Result<MarginLiquidation, ExchangeError>;
+
+    async fn borrow_and_respond(&mut self, token: &Token, amount: f64, response_tx: Sender<Result<TokenBalance, ExchangeError>>);
+
+    async fn repay_and_respond(&mut self, token: &Token, amount: f64, response_tx: Sender<Result<TokenBalance, ExchangeError>>);
+}
+
+impl HourglassAccount
+{
+    fn send_balance_event(&self, kind: AccountEventKind)
+    {
+        if let Err(err) = self.account_event_tx.send(AccountEvent { exchange_timestamp: self.exchange_timestamp.load(Ordering::SeqCst),
+                                                                    exchange: Exchange::Hourglass,
+                                                                    kind })
+        {
+            warn!("Client offline - Failed to send AccountEvent::Balance: {:?}", err);
+        }
+    }
+}
+
+#[async_trait]
+impl MarginHandler for HourglassAccount
+{
+    async fn valuation_price(&self, token: &Token) -> Option<f64>
+    {
+        let valuation_token = &self.config.spot_margin.valuation_token;
+        if token == valuation_token {
+            return Some(1.0);
+        }
+
+        let spot = Instrument::new(token.clone(), valuation_token.clone(), InstrumentKind::Spot);
+        let perpetual = Instrument::new(token.clone(), valuation_token.clone(), InstrumentKind::Perpetual);
+        match self.mark_price(&spot).await {
+            | Some(price) => Some(price),
+            | None => self.mark_price(&perpetual).await,
+        }
+    }
+
+    async fn margin_totals(&self) -> Option<(f64, f64)>
+    {
+        let balances: Vec<(Token, Balance)> = self.balances.iter().map(|entry| (entry.key().clone(), *entry.value())).collect();
+
+        let (mut assets, mut debt) = (0.0, 0.0);
+        for (token, balance) in balances {
+            match self.valuation_price(&token).await {
+                | Some(price) => {
+                    assets += balance.total * price;
+                    debt += balance.borrowed * price;
+                }
+                | None if balance.borrowed > 0.0 => return None,
+                | None => {}
+            }
+        }
+        Some((assets, debt))
+    }
+
+    async fn margin_level(&self) -> Option<f64>
+    {
+        let (assets, debt) = self.margin_totals().await?;
+        if debt > 0.0 { Some(assets / debt) } else { None }
+    }
+
+    async fn borrow(&mut self, token: &Token, amount: f64) -> Result<TokenBalance, ExchangeError>
+    {
+        if !amount.is_finite() || amount <= 0.0 {
+            return Err(ExchangeError::OrderRejected(format!("Invalid borrow amount: {}", amount)));
+        }
+        let price = self.valuation_price(token)
+                        .await
+                        .ok_or_else(|| ExchangeError::Hourglass(format!("No price available to value {}", token)))?;
+        let (assets, debt) = self.margin_totals()
+                                 .await
+                                 .ok_or_else(|| ExchangeError::Hourglass("Existing loans cannot be valued".into()))?;
+
+        let margin_level = (assets + amount * price) / (debt + amount * price);
+        let initial_margin_level = self.config.spot_margin.initial_margin_level;
+        if margin_level < initial_margin_level {
+            return Err(ExchangeError::OrderRejected(format!("Borrowing {} {} would bring margin level to {:.4}, below {}", amount, token, margin_level, initial_margin_level)));
+        }
+
+        self.initialize_tokens(vec![token.to_string()])?;
+        let balance = {
+            let mut balance = self.get_balance_mut(token)?;
+            balance.borrow(amount);
+            *balance
+        };
+        let token_balance = TokenBalance::new(token.clone(), balance);
+        self.send_balance_event(AccountEventKind::Balance(token_balance.clone()));
+
+        Ok(token_balance)
+    }
+
+    async fn repay(&mut self, token: &Token, amount: f64) -> Result<TokenBalance, ExchangeError>
+    {
+        let balance = {
+            let mut balance = self.get_balance_mut(token)?;
+            if balance.borrowed <= 0.0 {
+                return Err(ExchangeError::Hourglass(format!("No outstanding {} loan", token)));
+            }
+            if balance.repay(amount) <= 0.0 {
+                return Err(ExchangeError::InsufficientBalance(token.clone()));
+            }
+            *balance
+        };
+        let token_balance = TokenBalance::new(token.clone(), balance);
+        self.send_balance_event(AccountEventKind::Balance(token_balance.clone()));
+
+        Ok(token_balance)
+    }
+
+    async fn auto_repay(&mut self, token: &Token) -> Option<TokenBalance>
+    {
+        if !self.config.spot_margin.auto_repay {
+            return None;
+        }
+
+        let mut balance = self.get_balance_mut(token).ok()?;
+        if balance.borrowed <= 0.0 || balance.available <= 0.0 {
+            return None;
+        }
+        let borrowed = balance.borrowed;
+        balance.repay(borrowed);
+        Some(TokenBalance::new(token.clone(), *balance))
+    }
+
+    async fn accrue_margin_interest(&mut self) -> Vec<TokenBalance>
+    {
+        let hours = self.margin_loans.hours_due(self.exchange_timestamp.load(Ordering::SeqCst));
+        if hours == 0 {
+            return Vec::new();
+        }
+
+        let mut updated = Vec::new();
+        for mut entry in self.balances.iter_mut() {
+            if entry.borrowed <= 0.0 {
+                continue;
+            }
+            let interest = entry.borrowed * self.config.spot_margin.hourly_interest_rate(entry.key()) * hours as f64;
+            entry.borrowed += interest;
+            self.margin_loans.record_interest(entry.key(), interest);
+            updated.push(TokenBalance::new(entry.key().clone(), *entry.value()));
+        }
+
+        if !updated.is_empty() {
+            self.send_balance_event(AccountEventKind::Balances(updated.clone()));
+        }
+        updated
+    }
+
+    async fn check_margin_level(&mut self) -> Result<Option<MarginLiquidation>, ExchangeError>
+    {
+        self.accrue_margin_interest().await;
+
+        let Some(margin_level) = self.margin_level().await
+        else {
+            self.margin_loans.margin_call_active = false;
+            return Ok(None);
+        };
+
+        let margin_call_level = self.config.spot_margin.margin_call_level;
+        if margin_level < self.config.spot_margin.liquidation_margin_level {
+            self.margin_loans.margin_call_active = false;
+            return self.liquidate_margin_account(margin_level).await.map(Some);
+        }
+
+        if margin_level >= margin_call_level {
+            self.margin_loans.margin_call_active = false;
+        }
+        else if !self.margin_loans.margin_call_active {
+            // 每次跌破提醒线只提醒一次
+            self.margin_loans.margin_call_active = true;
+            let margin_call = MarginCall { exchange: Exchange::Hourglass,
+                                           timestamp: self.exchange_timestamp.load(Ordering::SeqCst),
+                                           margin_level,
+                                           margin_call_level };
+            if let Err(err) = self.account_event_tx.send(AccountEvent { exchange_timestamp: margin_call.timestamp,
+                                                                        exchange: Exchange::Hourglass,
+                                                                        kind: AccountEventKind::MarginCall(margin_call) })
+            {
+                warn!("Client offline - Failed to send AccountEvent::MarginCall: {:?}", err);
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn liquidate_margin_account(&mut self, margin_level: f64) -> Result<MarginLiquidation, ExchangeError>
+    {
+        let valuation_token = self.config.spot_margin.valuation_token.clone();
+        let fee_rate = self.config.liquidation_fee_rate;
+
+        // 撤销现货挂单，释放冻结的余额
+        self.cancel_orders_where(|instrument| instrument.kind == InstrumentKind::Spot).await;
+        self.initialize_tokens(vec![valuation_token.to_string()])?;
+
+        // 卖出时先动用有借款币种的多余部分，再动用其他资产；按币种排序保证强平结果可复现
+        let mut tokens: Vec<(bool, Token)> = self.balances
+                                                 .iter()
+                                                 .filter(|entry| entry.key() != &valuation_token)
+                                                 .map(|entry| (entry.borrowed <= 0.0, entry.key().clone()))
+                                                 .collect();
+        tokens.sort();
+        let mut prices = Vec::with_capacity(tokens.len());
+        for (_, token) in tokens {
+            if let Some(price) = self.valuation_price(&token).await {
+                prices.push((token, price));
+            }
+        }
+
+        // 还清借款所需的计价币种：计价币种自身的欠款，加上买回其他借款币种缺口的花费
+        let mut required = {
+            let balance = self.get_balance(&valuation_token)?;
+            (balance.borrowed - balance.available).max(0.0)
+        };
+        for (token, price) in &prices {
+            let balance = self.get_balance(token)?;
+            required += (balance.borrowed - balance.available).max(0.0) * price * (1.0 + fee_rate);
+        }
+
+        let mut fee = 0.0;
+        // 只卖出补足所需计价币种的资产，与借款无关的余额保持不动
+        for (token, price) in &prices {
+            if required <= 0.0 {
+                break;
+            }
+            let sold = {
+                let mut balance = self.get_balance_mut(token)?;
+                let excess = (balance.available - balance.borrowed).max(0.0);
+                let sold = excess.min(required / (price * (1.0 - fee_rate)));
+                balance.total -= sold;
+                balance.available -= sold;
+                sold
+            };
+            let proceeds = sold * price;
+            required -= proceeds * (1.0 - fee_rate);
+            fee += proceeds * fee_rate;
+            let mut valuation_balance = self.get_balance_mut(&valuation_token)?;
+            valuation_balance.total += proceeds * (1.0 - fee_rate);
+            valuation_balance.available += proceeds * (1.0 - fee_rate);
+        }
+
+        // 用计价币种买回卖空的币种，计价币种不足时尽量买回
+        for (token, price) in &prices {
+            let shortfall = {
+                let balance = self.get_balance(token)?;
+                (balance.borrowed - balance.available).max(0.0)
+            };
+            if shortfall <= 0.0 {
+                continue;
+            }
+            let bought = {
+                let mut valuation_balance = self.get_balance_mut(&valuation_token)?;
+                let bought = shortfall.min(valuation_balance.available.max(0.0) / (price * (1.0 + fee_rate)));
+                valuation_balance.total -= bought * price * (1.0 + fee_rate);
+                valuation_balance.available -= bought * price * (1.0 + fee_rate);
+                bought
+            };
+            fee += bought * price * fee_rate;
+            let mut balance = self.get_balance_mut(token)?;
+            balance.total += bought;
+            balance.available += bought;
+        }
+
+        // 归还全部借款
+        let mut repaid = Vec::new();
+        let mut balances = Vec::new();
+        for mut entry in self.balances.iter_mut() {
+            if entry.borrowed <= 0.0 {
+                continue;
+            }
+            let borrowed = entry.borrowed;
+            let amount = entry.repay(borrowed);
+            repaid.push((entry.key().clone(), amount));
+            balances.push(TokenBalance::new(entry.key().clone(), *entry.value()));
+        }
+        self.risk_reserve.lock().await.contribute(fee * self.config.risk_reserve_fee_share);
+
+        let liquidation = MarginLiquidation { exchange: Exchange::Hourglass,
+                                              timestamp: self.exchange_timestamp.load(Ordering::SeqCst),
+                                              margin_level,
+                                              valuation_token,
+                                              fee,
+                                              repaid,
+                                              balances };
+        if let Err(err) = self.account_event_tx.send(AccountEvent { exchange_timestamp: liquidation.timestamp,
+                                                                    exchange: Exchange::Hourglass,
+                                                                    kind: AccountEventKind::MarginLiquidation(liquidation.clone()) })
+        {
+            warn!("Client offline - Failed to send AccountEvent::MarginLiquidation: {:?}", err);
+        }
+
+        Ok(liquidation)
+    }
+
+    async fn borrow_and_respond(&mut self, token: &Token, amount: f64, response_tx: Sender<Result<TokenBalance, ExchangeError>>)
+    {
+        let result = self.borrow(token, amount).await;
+        respond(response_tx, result);
+    }
+
+    async fn repay_and_respond(&mut self, token: &Token, amount: f64, response_tx: Sender<Result<TokenBalance, ExchangeError>>)
+    {
+        let result = self.repay(token, amount).await;
+        respond(response_tx, result);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{
+        common::{
+            account_positions::PositionSide,
+            trade::{ClientTrade, ClientTradeId},
+            Side,
+        },
+        hourglass::account::{account_handlers::trade_handler::TradeHandler, account_margin_loan::ONE_HOUR_MS},
+        test_utils::create_test_account,
+    };
+
+    async fn set_eth_price(account: &HourglassAccount, price: f64)
+    {
+        let perpetual = Instrument::new("ETH", "USDT", InstrumentKind::Perpetual);
+        account.single_level_order_book.lock().await.get_mut(&perpetual).unwrap().latest_price = price;
+    }
+
+    #[tokio::test]
+    async fn margin_loans_should_respect_margin_level_accrue_interest_and_auto_repay()
+    {
+        let mut account = create_test_account().await;
+        let (event_tx, _event_rx) = tokio::sync::mpsc::unbounded_channel();
+        account.account_event_tx = event_tx;
+        let usdt = Token::from("USDT");
+        set_eth_price(&account, 1_000.0).await;
+
+        // 总资产 10000 USDT + 10 ETH = 20000，借 30000 后风险率 1.67，再借 20000 会降到 1.4
+        account.update_exchange_ts(1_000);
+        let balance = account.borrow(&usdt, 30_000.0).await.unwrap();
+        assert_eq!((balance.balance.total, balance.balance.available, balance.balance.borrowed), (40_000.0, 40_000.0, 30_000.0));
+        assert!(matches!(account.borrow(&usdt, 20_000.0).await, Err(ExchangeError::OrderRejected(_))));
+        assert!((account.margin_level().await.unwrap() - 50_000.0 / 30_000.0).abs() < 1e-9);
+
+        // 两个整小时计息两次
+        account.accrue_margin_interest().await;
+        account.update_exchange_ts(1_000 + 2 * ONE_HOUR_MS + 10);
+        account.accrue_margin_interest().await;
+        assert!((account.get_balance(&usdt).unwrap().borrowed - 30_000.6).abs() < 1e-9);
+        assert!((account.margin_loans.accrued_interest(&usdt) - 0.6).abs() < 1e-9);
+
+        // 手动还款 10000
+        let balance = account.repay(&usdt, 10_000.0).await.unwrap();
+        assert_eq!(balance.balance.total, 30_000.0);
+        assert!((balance.balance.borrowed - 20_000.6).abs() < 1e-9);
+
+        // 卖出现货收到 USDT 时自动还款，可用余额足以还清全部借款
+        account.get_balance_mut(&Token::from("ETH")).unwrap().available = 0.0; // 挂卖单时已冻结
+        let trade = ClientTrade { exchange: Exchange::Hourglass,
+                                  timestamp: 1_000 + 2 * ONE_HOUR_MS + 10,
+                                  trade_id: ClientTradeId(1),
+                                  order_id: None,
+                                  cid: None,
+                                  instrument: Instrument::new("ETH", "USDT", InstrumentKind::Spot),
+                                  side: Side::Sell,
+                                  price: 1_000.0,
+                                  size: 10.0,
+                                  fees: 0.0,
+                                  position_side: PositionSide::Both };
+        account.process_trade(trade).await.unwrap();
+        let balance = *account.get_balance(&usdt).unwrap();
+        assert_eq!(balance.borrowed, 0.0);
+        assert!((balance.total - 19_999.4).abs() < 1e-9);
+        assert_eq!(balance.available, balance.total);
+    }
+
+    #[tokio::test]
+    async fn short_margin_account_should_receive_margin_call_then_be_liquidated()
+    {
+        let mut account = create_test_account().await;
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+        account.account_event_tx = event_tx;
+        let (usdt, eth) = (Token::from("USDT"), Token::from("ETH"));
+        set_eth_price(&account, 1_000.0).await;
+
+        // 借入 15 ETH 后连同持有的 10 ETH 一起卖出，换得 25000 USDT
+        account.borrow(&eth, 15.0).await.unwrap();
+        {
+            let mut eth_balance = account.get_balance_mut(&eth).unwrap();
+            eth_balance.total = 0.0;
+            eth_balance.available = 0.0;
+        }
+        {
+            let mut usdt_balance = account.get_balance_mut(&usdt).unwrap();
+            usdt_balance.total = 35_000.0;
+            usdt_balance.available = 35_000.0;
+        }
+
+        // ETH 涨到 1900，风险率 1.23 跌破提醒线
+        set_eth_price(&account, 1_900.0).await;
+        assert!(account.check_margin_level().await.unwrap().is_none());
+        assert!(account.margin_loans.margin_call_active);
+        let mut margin_calls = 0;
+        while let Ok(event) = event_rx.try_recv() {
+            if let AccountEventKind::MarginCall(margin_call) = event.kind {
+                assert!((margin_call.margin_level - 35_000.0 / 28_500.0).abs() < 1e-9);
+                margin_calls += 1;
+            }
+        }
+        assert_eq!(margin_calls, 1);
+
+        // ETH 涨到 2200，风险率 1.06 跌破强平线，买回 15 ETH 还款
+        set_eth_price(&account, 2_200.0).await;
+        let liquidation = account.check_margin_level().await.unwrap().unwrap();
+        let fee_rate = account.config.liquidation_fee_rate;
+        assert!((liquidation.fee - 15.0 * 2_200.0 * fee_rate).abs() < 1e-9);
+        assert_eq!(liquidation.repaid, vec![(eth.clone(), 15.0)]);
+        assert_eq!(account.get_balance(&eth).unwrap().borrowed, 0.0);
+        assert!((account.get_balance(&usdt).unwrap().total - (35_000.0 - 33_000.0 * (1.0 + fee_rate))).abs() < 1e-6);
+        assert!(account.margin_level().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn long_margin_liquidation_should_only_sell_what_covers_the_loan()
+    {
+        let mut account = create_test_account().await;
+        let (event_tx, _event_rx) = tokio::sync::mpsc::unbounded_channel();
+        account.account_event_tx = event_tx;
+        let (usdt, eth) = (Token::from("USDT"), Token::from("ETH"));
+        set_eth_price(&account, 1_000.0).await;
+
+        // 借入 30000 USDT 后连同持有的 10000 USDT 全部买入 ETH，共持有 50 ETH
+        account.borrow(&usdt, 30_000.0).await.unwrap();
+        {
+            let mut usdt_balance = account.get_balance_mut(&usdt).unwrap();
+            usdt_balance.total = 0.0;
+            usdt_balance.available = 0.0;
+        }
+        {
+            let mut eth_balance = account.get_balance_mut(&eth).unwrap();
+            eth_balance.total = 50.0;
+            eth_balance.available = 50.0;
+        }
+
+        // ETH 跌到 650，风险率 1.08 跌破强平线，只卖出还清 30000 USDT 所需的 ETH
+        set_eth_price(&account, 650.0).await;
+        let liquidation = account.check_margin_level().await.unwrap().unwrap();
+        let fee_rate = account.config.liquidation_fee_rate;
+        let sold = 30_000.0 / (650.0 * (1.0 - fee_rate));
+        assert_eq!(liquidation.repaid, vec![(usdt.clone(), 30_000.0)]);
+        assert!((liquidation.fee - sold * 650.0 * fee_rate).abs() < 1e-6);
+        assert!((account.get_balance(&eth).unwrap().total - (50.0 - sold)).abs() < 1e-9);
+        let usdt_balance = *account.get_balance(&usdt).unwrap();
+        assert_eq!(usdt_balance.borrowed, 0.0);
+        assert!(usdt_balance.total.abs() < 1e-6);
+    }
+}
@@ -1,3 +1,8 @@
 pub mod balance_handler;
+pub mod future_handler;
+pub mod leveraged_token_handler;
+pub mod margin_handler;
+pub mod option_handler;
 pub mod position_handler;
+pub mod risk_handler;
 pub mod trade_handler;
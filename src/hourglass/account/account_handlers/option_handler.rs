@@ -0,0 +1,532 @@
+use crate::{
+    common::{
+        account_positions::{
+            option::{OptionExercise, OptionPosition},
+            AccountPositions,
+        },
+        balance::{BalanceDelta, TokenBalance},
+        event::{AccountEvent, AccountEventKind},
+        friction::OptionFees,
+        instrument::{
+            kind::InstrumentKind,
+            option_spec::{OptionKind, OptionSpec},
+            Instrument,
+        },
+        option_pricing::{AccountGreeks, PositionGreeks, PricingInput, UnpricedPosition, MILLIS_PER_YEAR},
+        token::Token,
+        trade::ClientTrade,
+        Side,
+    },
+    error::ExchangeError,
+    hourglass::account::{
+        account_handlers::{balance_handler::BalanceHandler, position_handler::PositionHandler},
+        respond, HourglassAccount,
+    },
+    hourglass_log::warn,
+    Exchange,
+};
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    sync::{atomic::Ordering, Arc},
+};
+use tokio::sync::{oneshot::Sender, RwLock};
+
+/// 欧式期权的仓位生命周期：买方支付权利金，卖方收取权利金并缴纳保证金，到期时按指数价格自动行权并现金交割。
+///
+/// 权利金与手续费在成交时由 [`BalanceHandler::apply_trade_changes`] 结算，本 trait 只维护仓位与卖方保证金。
+/// 期权仓位总是按单向持仓处理：反向成交先减少已有仓位，剩余数量再开仓或加仓。
+#[async_trait]
+pub trait OptionHandler
+{
+    async fn get_option_position(&self, instrument: &Instrument, side: Side) -> Option<OptionPosition>;
+
+    async fn store_option_position(&self, position: OptionPosition);
+
+    /// 按成交更新期权仓位。
+    async fn update_option_position_from_client_trade(&mut self, trade: ClientTrade) -> Result<(), ExchangeError>;
+
+    /// 以 `price` 平掉仓位中 `size` 的部分，释放对应的卖方保证金，返回已实现盈亏。
+    /// 仓位被全部平掉时移入 [`AccountExitedPositions`](crate::common::account_positions::exited_positions::AccountExitedPositions)。
+    async fn reduce_option_position(&mut self, position: OptionPosition, price: f64, size: f64, timestamp: i64) -> Result<f64, ExchangeError>;
+
+    /// 行权所用的指数价格，依次取标的永续合约与现货的标记价格。
+    async fn index_price(&self, instrument: &Instrument) -> Option<f64>;
+
+    /// 按期权的标记价格反推隐含波动率，计算仓位的理论价值与希腊字母。
+    /// 尚无期权行情时使用仓位记录的最新成交价，标的价格取 [`OptionHandler::index_price`]。
+    async fn compute_position_greeks(&self, position: &OptionPosition) -> Result<PositionGreeks, ExchangeError>;
+
+    /// 汇总以 `token` 结算的全部期权仓位的希腊字母。无法定价的仓位不计入汇总，列在 [`AccountGreeks::unpriced`] 中。
+    async fn compute_account_greeks(&self, token: &Token) -> Result<AccountGreeks, ExchangeError>;
+
+    async fn fetch_account_greeks_and_respond(&self, token: &Token, response_tx: Sender<Result<AccountGreeks, ExchangeError>>);
+
+    /// 交易所时间到达到期时间后，撤销已到期期权上的全部挂单，并按指数价格对全部仓位自动行权。
+    /// 实值期权的多头收取现金交割金额并支付行权费，空头支付交割金额后取回保证金；虚值期权到期作废。
+    /// 每个被处理的仓位都会发送一个 [`AccountEventKind::OptionExercise`] 事件，尚无指数价格的仓位保留到下一次检查。
+    async fn settle_expired_options(&mut self) -> Result<Vec<OptionExercise>, ExchangeError>;
+}
+
+/// 根据方向与期权类型选择对应的仓位表
+fn option_positions(positions: &AccountPositions, side: Side, option_kind: OptionKind) -> &Arc<RwLock<HashMap<Instrument, OptionPosition>>>
+{
+    match (side, option_kind) {
+        | (Side::Buy, OptionKind::Call) => &positions.option_pos_long_call,
+        | (Side::Buy, OptionKind::Put) => &positions.option_pos_long_put,
+        | (Side::Sell, OptionKind::Call) => &positions.option_pos_short_call,
+        | (Side::Sell, OptionKind::Put) => &positions.option_pos_short_put,
+    }
+}
+
+fn option_spec_of(instrument: &Instrument) -> Result<OptionSpec, ExchangeError>
+{
+    instrument.option_spec.ok_or_else(|| ExchangeError::InvalidInstrument(format!("{} has no option spec", instrument)))
+}
+
+#[async_trait]
+impl OptionHandler for HourglassAccount
+{
+    async fn get_option_position(&self, instrument: &Instrument, side: Side) -> Option<OptionPosition>
+    {
+        let option_spec = instrument.option_spec?;
+        option_positions(&self.positions, side, option_spec.option_kind).read().await.get(instrument).cloned()
+    }
+
+    async fn store_option_position(&self, position: OptionPosition)
+    {
+        let Some(option_spec) = position.meta.instrument.option_spec
+        else {
+            warn!("Refusing to store option position without option spec: {:?}", position.meta.instrument);
+            return;
+        };
+        option_positions(&self.positions, position.meta.side, option_spec.option_kind).write().await.insert(position.meta.instrument.clone(), position);
+    }
+
+    async fn update_option_position_from_client_trade(&mut self, trade: ClientTrade) -> Result<(), ExchangeError>
+    {
+        option_spec_of(&trade.instrument)?;
+        if trade.instrument.is_expired(trade.timestamp) {
+            return Err(ExchangeError::OrderRejected(format!("{} has expired", trade.instrument)));
+        }
+
+        // 先减少反向仓位
+        let mut remaining = trade.size;
+        if let Some(opposite) = self.get_option_position(&trade.instrument, trade.side.toggle()).await {
+            let close_size = remaining.min(opposite.meta.current_size);
+            self.reduce_option_position(opposite, trade.price, close_size, trade.timestamp).await?;
+            remaining -= close_size;
+
+            // 平掉多头的卖单在挂单时按开空预留了卖方保证金，平仓部分不需要保证金
+            if trade.side == Side::Sell {
+                let released_margin = self.config.option_config.seller_margin(&trade.instrument, close_size);
+                self.apply_balance_delta(&trade.instrument.quote, BalanceDelta { total: 0.0,
+                                                                                 available: released_margin });
+            }
+        }
+
+        if remaining > 0.0 {
+            let trade = ClientTrade { size: remaining, ..trade };
+            match self.get_option_position(&trade.instrument, trade.side).await {
+                | Some(mut position) => {
+                    position.meta.update_from_trade(&trade);
+                    if trade.side == Side::Sell {
+                        position.seller_margin += self.config.option_config.seller_margin(&trade.instrument, trade.size);
+                    }
+                    self.store_option_position(position).await;
+                }
+                | None => {
+                    self.create_option_position(trade).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn reduce_option_position(&mut self, mut position: OptionPosition, price: f64, size: f64, timestamp: i64) -> Result<f64, ExchangeError>
+    {
+        let current_size = position.meta.current_size;
+        if size <= 0.0 || current_size <= 0.0 {
+            return Ok(0.0);
+        }
+
+        let fraction = (size / current_size).min(1.0);
+        let realised_pnl = match position.meta.side {
+            | Side::Buy => (price - position.meta.current_avg_price) * size,
+            | Side::Sell => (position.meta.current_avg_price - price) * size,
+        };
+
+        // 权利金已在成交时结算，这里只退回空头被平部分的保证金
+        let released_margin = position.seller_margin * fraction;
+        position.seller_margin -= released_margin;
+        let quote = position.meta.instrument.quote.clone();
+        let balance = self.apply_balance_delta(&quote, BalanceDelta { total: 0.0,
+                                                                      available: released_margin });
+
+        position.meta.update_ts = timestamp;
+        position.meta.current_symbol_price = price;
+        position.meta.realised_pnl += realised_pnl;
+        if fraction >= 1.0 {
+            self.register_exit_position(&position.meta, position.meta.side, None).await?;
+            self.remove_option_position(position.meta.instrument.clone(), position.meta.side).await;
+        }
+        else {
+            position.meta.current_size -= size;
+            position.meta.update_unrealised_pnl();
+            self.store_option_position(position).await;
+        }
+
+        if let Err(err) = self.account_event_tx.send(AccountEvent { exchange_timestamp: self.exchange_timestamp.load(Ordering::SeqCst),
+                                                                    exchange: Exchange::Hourglass,
+                                                                    kind: AccountEventKind::Balance(TokenBalance::new(quote, balance)) })
+        {
+            warn!("Client offline - Failed to send AccountEvent::Balance: {:?}", err);
+        }
+
+        Ok(realised_pnl)
+    }
+
+    async fn index_price(&self, instrument: &Instrument) -> Option<f64>
+    {
+        let perpetual = Instrument::new(instrument.base.clone(), instrument.quote.clone(), InstrumentKind::Perpetual);
+        let spot = Instrument::new(instrument.base.clone(), instrument.quote.clone(), InstrumentKind::Spot);
+        match self.mark_price(&perpetual).await {
+            | Some(price) => Some(price),
+            | None => self.mark_price(&spot).await,
+        }
+    }
+
+    async fn compute_position_greeks(&self, position: &OptionPosition) -> Result<PositionGreeks, ExchangeError>
+    {
+        let instrument = &position.meta.instrument;
+        let option_spec = option_spec_of(instrument)?;
+        let underlying_price = self.index_price(instrument)
+                                   .await
+                                   .ok_or_else(|| ExchangeError::Hourglass(format!("No index price to price option {}", instrument)))?;
+        let option_price = self.mark_price(instrument).await.unwrap_or(position.meta.current_symbol_price);
+
+        let exchange_timestamp = self.exchange_timestamp.load(Ordering::SeqCst);
+        let time_to_expiry = instrument.expiry.map_or(0.0, |expiry| (expiry - exchange_timestamp).max(0) as f64 / MILLIS_PER_YEAR);
+        let mut input = PricingInput { model: self.config.option_config.pricing_model,
+                                       option_kind: option_spec.option_kind,
+                                       underlying_price,
+                                       strike: option_spec.strike.0,
+                                       time_to_expiry,
+                                       volatility: 0.0,
+                                       risk_free_rate: self.config.option_config.risk_free_rate };
+
+        // 到期后期权只剩内在价值，不再反推波动率
+        if time_to_expiry > 0.0 {
+            input.volatility = input.implied_volatility(option_price)
+                                    .ok_or_else(|| ExchangeError::Hourglass(format!("Cannot solve implied volatility for {} at price {}", instrument, option_price)))?;
+        }
+
+        let signed_size = match position.meta.side {
+            | Side::Buy => position.meta.current_size,
+            | Side::Sell => -position.meta.current_size,
+        };
+        Ok(PositionGreeks { instrument: instrument.clone(),
+                            side: position.meta.side,
+                            size: position.meta.current_size,
+                            underlying_price,
+                            option_price,
+                            implied_volatility: input.volatility,
+                            fair_value: input.fair_value() * signed_size,
+                            greeks: input.greeks() * signed_size })
+    }
+
+    async fn compute_account_greeks(&self, token: &Token) -> Result<AccountGreeks, ExchangeError>
+    {
+        let mut positions = Vec::new();
+        for option_positions in [&self.positions.option_pos_long_call,
+                                 &self.positions.option_pos_long_put,
+                                 &self.positions.option_pos_short_call,
+                                 &self.positions.option_pos_short_put]
+        {
+            positions.extend(option_positions.read().await.values().filter(|position| &position.meta.instrument.quote == token).cloned());
+        }
+
+        // 单个仓位无法定价时不影响其余仓位的汇总，只在结果中标记出来
+        let mut position_greeks = Vec::with_capacity(positions.len());
+        let mut unpriced = Vec::new();
+        for position in &positions {
+            match self.compute_position_greeks(position).await {
+                | Ok(greeks) => position_greeks.push(greeks),
+                | Err(error) => {
+                    warn!("Excluding {} from account greeks: {}", position.meta.instrument, error);
+                    unpriced.push(UnpricedPosition { instrument: position.meta.instrument.clone(),
+                                                     side: position.meta.side,
+                                                     size: position.meta.current_size,
+                                                     reason: error.to_string() });
+                }
+            }
+        }
+        Ok(AccountGreeks::new(token.clone(), position_greeks, unpriced))
+    }
+
+    async fn fetch_account_greeks_and_respond(&self, token: &Token, response_tx: Sender<Result<AccountGreeks, ExchangeError>>)
+    {
+        let greeks = self.compute_account_greeks(token).await;
+        respond(response_tx, greeks);
+    }
+
+    async fn settle_expired_options(&mut self) -> Result<Vec<OptionExercise>, ExchangeError>
+    {
+        let exchange_timestamp = self.exchange_timestamp.load(Ordering::SeqCst);
+
+        // 撤销已到期期权上的全部挂单，释放预留的权利金与保证金
+        self.cancel_expired_orders().await;
+
+        let mut expired_positions = Vec::new();
+        for positions in [&self.positions.option_pos_long_call,
+                          &self.positions.option_pos_long_put,
+                          &self.positions.option_pos_short_call,
+                          &self.positions.option_pos_short_put]
+        {
+            expired_positions.extend(positions.read().await.values().filter(|position| position.meta.instrument.is_expired(exchange_timestamp)).cloned());
+        }
+
+        let mut exercises = Vec::with_capacity(expired_positions.len());
+        for mut position in expired_positions {
+            let Some(index_price) = self.index_price(&position.meta.instrument).await
+            else {
+                warn!("No index price to exercise expired option {}, keeping the position", position.meta.instrument);
+                continue;
+            };
+
+            let option_spec = option_spec_of(&position.meta.instrument)?;
+            let exercise_ts = position.meta.instrument.expiry.unwrap_or(exchange_timestamp);
+            let size = position.meta.current_size;
+            let payoff = position.payoff(index_price);
+            let exercise_fee = match position.meta.side {
+                | Side::Buy => payoff * self.config.option_config.exercise_fee_rate,
+                | Side::Sell => 0.0,
+            };
+
+            // 多头收取交割金额并支付行权费，空头支付交割金额并取回保证金
+            let quote_delta = match position.meta.side {
+                | Side::Buy => BalanceDelta { total: payoff - exercise_fee,
+                                              available: payoff - exercise_fee },
+                | Side::Sell => BalanceDelta { total: -payoff,
+                                               available: position.seller_margin - payoff },
+            };
+            let quote = position.meta.instrument.quote.clone();
+            let balance = self.apply_balance_delta(&quote, quote_delta);
+
+            // 以每单位的交割金额作为退出价格记录仓位
+            position.meta.update_ts = exercise_ts;
+            position.meta.current_symbol_price = option_spec.intrinsic_value(index_price);
+            position.meta.current_fees_total += exercise_fee;
+            self.register_exit_position(&position.meta, position.meta.side, None).await?;
+            self.remove_option_position(position.meta.instrument.clone(), position.meta.side).await;
+
+            let exercise = OptionExercise { exchange: Exchange::Hourglass,
+                                            instrument: position.meta.instrument.clone(),
+                                            side: position.meta.side,
+                                            position_id: position.meta.position_id.clone(),
+                                            timestamp: exercise_ts,
+                                            index_price,
+                                            size,
+                                            payoff,
+                                            fees: OptionFees { trade_fee: 0.0,
+                                                               exercise_fee },
+                                            exercised: payoff > 0.0 };
+            for kind in [AccountEventKind::Balance(TokenBalance::new(quote, balance)), AccountEventKind::OptionExercise(exercise.clone())] {
+                if let Err(err) = self.account_event_tx.send(AccountEvent { exchange_timestamp,
+                                                                            exchange: Exchange::Hourglass,
+                                                                            kind })
+                {
+                    warn!("Client offline - Failed to send AccountEvent: {:?}", err);
+                }
+            }
+            exercises.push(exercise);
+        }
+
+        Ok(exercises)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{
+        common::{
+            account_positions::PositionSide,
+            option_pricing::PricingModel,
+            order::{
+                identification::client_order_id::ClientOrderId,
+                order_instructions::OrderInstruction,
+                states::{request_cancel::RequestCancel, request_open::RequestOpen},
+                Order,
+            },
+        },
+        hourglass::{
+            account::{account_config::CommissionRates, account_handlers::trade_handler::TradeHandler},
+            clickhouse_api::datatype::single_level_order_book::SingleLevelOrderBook,
+        },
+        test_utils::{create_test_account, create_test_client_trade, TEST_EXPIRY},
+    };
+
+    #[tokio::test]
+    async fn option_orders_should_reserve_premium_and_seller_margin()
+    {
+        let mut account = create_test_account().await;
+        let call = Instrument::crypto_option("ETH", "USDT", TEST_EXPIRY, OptionKind::Call, 3_000.0);
+        let quote = Token::from("USDT");
+        let (event_tx, _event_rx) = tokio::sync::mpsc::unbounded_channel();
+        account.account_event_tx = event_tx;
+        account.config.fees_book.insert(InstrumentKind::CryptoOption, CommissionRates { maker_fees: 0.01, taker_fees: 0.02 });
+        account.account_open_book.read().await.instrument_orders_map.insert(call.clone(), Default::default());
+        account.single_level_order_book.lock().await.insert(call.clone(),
+                                                            SingleLevelOrderBook { latest_bid: 99.0,
+                                                                                   latest_ask: 101.0,
+                                                                                   latest_price: 100.0 });
+        account.update_exchange_ts(TEST_EXPIRY - 60_000);
+
+        let request = |side: Side, cid: &str| Order { instruction: OrderInstruction::Limit,
+                                                      exchange: Exchange::Hourglass,
+                                                      instrument: call.clone(),
+                                                      timestamp: TEST_EXPIRY - 60_000,
+                                                      cid: Some(ClientOrderId(cid.into())),
+                                                      side,
+                                                      state: RequestOpen { price: 100.0,
+                                                                           size: 2.0,
+                                                                           reduce_only: false,
+                                                                           position_side: PositionSide::Both } };
+
+        // 买单预留权利金，卖单按行权价预留 15% 的保证金
+        account.atomic_open(request(Side::Buy, "option-bid")).await.unwrap();
+        assert_eq!(account.get_balance(&quote).unwrap().available, 9_800.0);
+        let ask = account.atomic_open(request(Side::Sell, "option-ask")).await.unwrap();
+        assert_eq!(account.get_balance(&quote).unwrap().available, 8_900.0);
+
+        // 撤销卖单退回保证金
+        let cancel = Order { state: RequestCancel { id: Some(ask.state.id) },
+                             instrument: ask.instrument,
+                             side: ask.side,
+                             instruction: ask.instruction,
+                             cid: ask.cid,
+                             exchange: Exchange::Hourglass,
+                             timestamp: TEST_EXPIRY - 60_000 };
+        account.atomic_cancel(cancel).await.unwrap();
+        assert_eq!(account.get_balance(&quote).unwrap().available, 9_800.0);
+
+        // 买单成交时扣除权利金与手续费
+        account.apply_trade_changes(&create_test_client_trade(&call, Side::Buy, 100.0, 2.0, 2.0)).await.unwrap();
+        let balance = *account.get_balance(&quote).unwrap();
+        assert_eq!((balance.total, balance.available), (9_798.0, 9_798.0));
+
+        // 卖出成交立即收取权利金，保证金仍被占用
+        let put = Instrument::crypto_option("ETH", "USDT", TEST_EXPIRY, OptionKind::Put, 3_000.0);
+        account.apply_balance_delta(&quote, BalanceDelta { total: 0.0, available: -450.0 });
+        account.apply_trade_changes(&create_test_client_trade(&put, Side::Sell, 50.0, 1.0, 0.5)).await.unwrap();
+        account.update_option_position_from_client_trade(create_test_client_trade(&put, Side::Sell, 50.0, 1.0, 0.5)).await.unwrap();
+        let balance = *account.get_balance(&quote).unwrap();
+        assert_eq!((balance.total, balance.available), (9_847.5, 9_397.5));
+        assert_eq!(account.get_option_position(&put, Side::Sell).await.unwrap().seller_margin, 450.0);
+        assert_eq!(account.compute_account_summary(&quote).await.unwrap().initial_margin, 450.0);
+    }
+
+    #[tokio::test]
+    async fn expired_options_should_exercise_against_index_price()
+    {
+        let mut account = create_test_account().await;
+        let call = Instrument::crypto_option("ETH", "USDT", TEST_EXPIRY, OptionKind::Call, 3_000.0);
+        let put = Instrument::crypto_option("ETH", "USDT", TEST_EXPIRY, OptionKind::Put, 3_000.0);
+        let quote = Token::from("USDT");
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+        account.account_event_tx = event_tx;
+        account.config.option_config.exercise_fee_rate = 0.01;
+
+        // 持有 2 张看涨期权多头，并卖出 1 张看跌期权（保证金在挂单时已预留）
+        account.update_option_position_from_client_trade(create_test_client_trade(&call, Side::Buy, 100.0, 2.0, 0.0)).await.unwrap();
+        account.apply_balance_delta(&quote, BalanceDelta { total: 0.0, available: -450.0 });
+        account.update_option_position_from_client_trade(create_test_client_trade(&put, Side::Sell, 80.0, 1.0, 0.0)).await.unwrap();
+
+        // 尚无指数价格时保留到期仓位
+        account.update_exchange_ts(TEST_EXPIRY);
+        assert!(account.settle_expired_options().await.unwrap().is_empty());
+        assert!(account.get_option_position(&call, Side::Buy).await.is_some());
+
+        let perpetual = Instrument::new("ETH", "USDT", InstrumentKind::Perpetual);
+        account.single_level_order_book.lock().await.get_mut(&perpetual).unwrap().latest_price = 3_200.0;
+        let exercises = account.settle_expired_options().await.unwrap();
+        assert_eq!(exercises.len(), 2);
+
+        let call_exercise = exercises.iter().find(|exercise| exercise.instrument == call).unwrap();
+        assert!(call_exercise.exercised);
+        assert_eq!(call_exercise.payoff, 400.0);
+        assert_eq!(call_exercise.fees.exercise_fee, 4.0);
+        let put_exercise = exercises.iter().find(|exercise| exercise.instrument == put).unwrap();
+        assert!(!put_exercise.exercised);
+        assert_eq!(put_exercise.payoff, 0.0);
+
+        // 看涨多头收取 400 - 4，看跌空头取回 450 的保证金
+        let balance = *account.get_balance(&quote).unwrap();
+        assert_eq!((balance.total, balance.available), (10_396.0, 10_396.0));
+        assert!(account.get_option_position(&call, Side::Buy).await.is_none());
+        assert!(account.get_option_position(&put, Side::Sell).await.is_none());
+        assert_eq!(account.exited_positions.option_pos_long_call.read().await.values().next().unwrap().realised_pnl, 200.0);
+        assert_eq!(account.exited_positions.option_pos_short_put.read().await.values().next().unwrap().realised_pnl, 80.0);
+
+        let mut exercise_events = 0;
+        while let Ok(event) = event_rx.try_recv() {
+            if let AccountEventKind::OptionExercise(_) = event.kind {
+                exercise_events += 1;
+            }
+        }
+        assert_eq!(exercise_events, 2);
+    }
+
+    #[tokio::test]
+    async fn account_greeks_should_aggregate_signed_option_positions()
+    {
+        let mut account = create_test_account().await;
+        let call = Instrument::crypto_option("ETH", "USDT", TEST_EXPIRY, OptionKind::Call, 3_000.0);
+        let put = Instrument::crypto_option("ETH", "USDT", TEST_EXPIRY, OptionKind::Put, 2_800.0);
+        let perpetual = Instrument::new("ETH", "USDT", InstrumentKind::Perpetual);
+        account.single_level_order_book.lock().await.get_mut(&perpetual).unwrap().latest_price = 3_000.0;
+        account.update_exchange_ts(TEST_EXPIRY - 30 * 24 * 60 * 60 * 1000);
+
+        // 以 60% 与 80% 的波动率给两张期权定价，作为成交价格
+        let priced = |option_kind: OptionKind, strike: f64, volatility: f64| PricingInput { model: PricingModel::BlackScholes,
+                                                                                            option_kind,
+                                                                                            underlying_price: 3_000.0,
+                                                                                            strike,
+                                                                                            time_to_expiry: 30.0 / 365.0,
+                                                                                            volatility,
+                                                                                            risk_free_rate: 0.0 };
+        let (call_input, put_input) = (priced(OptionKind::Call, 3_000.0, 0.6), priced(OptionKind::Put, 2_800.0, 0.8));
+        account.update_option_position_from_client_trade(create_test_client_trade(&call, Side::Buy, call_input.fair_value(), 2.0, 0.0)).await.unwrap();
+        account.update_option_position_from_client_trade(create_test_client_trade(&put, Side::Sell, put_input.fair_value(), 1.0, 0.0)).await.unwrap();
+
+        let greeks = account.compute_account_greeks(&Token::from("USDT")).await.unwrap();
+        assert_eq!(greeks.positions.len(), 2);
+        let call_greeks = greeks.positions.iter().find(|position| position.instrument == call).unwrap();
+        let put_greeks = greeks.positions.iter().find(|position| position.instrument == put).unwrap();
+        assert!((call_greeks.implied_volatility - 0.6).abs() < 1e-6);
+        assert!((put_greeks.implied_volatility - 0.8).abs() < 1e-6);
+        assert!((call_greeks.greeks.delta - 2.0 * call_input.greeks().delta).abs() < 1e-6);
+        assert!((put_greeks.greeks.delta + put_input.greeks().delta).abs() < 1e-6);
+        assert!(put_greeks.fair_value < 0.0);
+
+        // 多头看涨与空头看跌的 delta 同为正，vega 方向相反
+        let expected_delta = 2.0 * call_input.greeks().delta - put_input.greeks().delta;
+        assert!((greeks.total.delta - expected_delta).abs() < 1e-6);
+        assert!(greeks.total.delta > 0.0);
+        assert!((greeks.total.vega - (call_greeks.greeks.vega + put_greeks.greeks.vega)).abs() < 1e-9);
+        assert!(account.compute_account_greeks(&Token::from("BTC")).await.unwrap().positions.is_empty());
+        assert!(greeks.unpriced.is_empty());
+
+        // 低于内在价值的期权价格无法反推隐含波动率，只标记该仓位，不影响其余仓位的汇总
+        let deep_call = Instrument::crypto_option("ETH", "USDT", TEST_EXPIRY, OptionKind::Call, 2_000.0);
+        account.update_option_position_from_client_trade(create_test_client_trade(&deep_call, Side::Buy, 500.0, 1.0, 0.0)).await.unwrap();
+        let with_unpriced = account.compute_account_greeks(&Token::from("USDT")).await.unwrap();
+        assert_eq!(with_unpriced.positions.len(), 2);
+        assert_eq!(with_unpriced.unpriced.len(), 1);
+        assert_eq!((&with_unpriced.unpriced[0].instrument, with_unpriced.unpriced[0].size), (&deep_call, 1.0));
+        assert!((with_unpriced.total.delta - greeks.total.delta).abs() < 1e-9);
+    }
+}
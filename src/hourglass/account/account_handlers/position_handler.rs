@@ -17,18 +17,28 @@ use crate::{
             exited_position::PositionExit,
             future::{FuturePosition, FuturePositionConfig},
             leveraged_token::{LeveragedTokenPosition, LeveragedTokenPositionConfig},
+            liquidation::{LiquidatablePosition, PositionAutoDeleverage, PositionLiquidation},
             option::OptionPosition,
             perpetual::{PerpetualPosition, PerpetualPositionConfig},
             position_meta::PositionMeta,
-            AccountPositions, PositionDirectionMode, PositionMarginMode,
+            AccountPositions, PositionDirectionMode, PositionMarginMode, PositionSide,
         },
-        instrument::kind::InstrumentKind,
+        balance::{Balance, BalanceDelta, TokenBalance},
+        event::{AccountEvent, AccountEventKind},
+        instrument::{kind::InstrumentKind, option_spec::OptionKind},
         trade::{ClientTrade, ClientTradeId},
         Side,
     },
     hourglass::{
         account::{
-            account_handlers::{position_handler::PositionHandling::CloseCompleteAndReverse, trade_handler::TradeHandler},
+            account_handlers::{
+                balance_handler::BalanceHandler,
+                future_handler::FutureHandler,
+                leveraged_token_handler::LeveragedTokenHandler,
+                option_handler::OptionHandler,
+                position_handler::PositionHandling::CloseCompleteAndReverse,
+                trade_handler::TradeHandler,
+            },
             respond, HourglassAccount,
         },
         clickhouse_api::datatype::clickhouse_trade_data::MarketTrade,
@@ -86,6 +96,9 @@ pub trait PositionHandler
 
     async fn remove_position(&self, instrument: Instrument, side: Side) -> Option<Position>;
 
+    /// 按仓位的类型与方向写回对应的仓位表，目前用于强平流程中的永续合约与交割合约仓位。
+    async fn store_position(&self, position: Position);
+
     async fn remove_perpetual_position(&self, instrument: Instrument, side: Side) -> Option<PerpetualPosition>;
 
     async fn remove_future_position(&self, instrument: Instrument, side: Side) -> Option<FuturePosition>;
@@ -107,6 +120,14 @@ pub trait PositionHandler
 
     async fn check_and_handle_liquidation(&mut self, trade: &MarketTrade) -> Result<(), ExchangeError>;
 
+    async fn liquidate_position_incrementally<P>(&mut self, position: P, trade: &MarketTrade) -> Result<(), ExchangeError>
+        where P: LiquidatablePosition + 'static;
+
+    async fn auto_deleverage(&mut self, instrument: &Instrument, bankrupt_side: Side, trade: &MarketTrade, shortfall: f64) -> Result<f64, ExchangeError>;
+
+    async fn deleverage_positions<P>(&mut self, candidates: Vec<P>, bankrupt_side: Side, trade: &MarketTrade, shortfall: f64) -> Result<f64, ExchangeError>
+        where P: LiquidatablePosition + 'static;
+
     async fn close_and_reverse_position(&mut self, trade: ClientTrade, remaining: f64) -> Result<(), ExchangeError>;
     // 爆仓提醒 / Margin Call, return a Option<f64>
     async fn margin_call(&mut self, instrument: Instrument) -> Result<Option<f64>, ExchangeError>;
@@ -116,6 +137,30 @@ pub trait PositionHandler
     async fn partial_close_position(&mut self, trade: ClientTrade) -> Result<(), ExchangeError>;
     // 更新隔离保证金 /// NOTE this is currently problematic and should be checked very carefully.
     async fn update_isolated_margin(&mut self, position: &mut PerpetualPosition, trade: &ClientTrade);
+
+    async fn adjust_isolated_margin(&mut self, instrument: &Instrument, side: Side, delta: f64) -> Result<PerpetualPosition, ExchangeError>;
+
+    async fn adjust_isolated_margin_and_respond(&mut self, instrument: &Instrument, side: Side, delta: f64, response_tx: Sender<Result<Position, ExchangeError>>);
+
+    async fn set_leverage(&mut self, instrument: &Instrument, side: Side, leverage: f64) -> Result<PositionConfig, ExchangeError>;
+
+    async fn set_leverage_and_respond(&mut self, instrument: &Instrument, side: Side, leverage: f64, response_tx: Sender<Result<PositionConfig, ExchangeError>>);
+
+    async fn set_margin_mode(&mut self, instrument: &Instrument, side: Side, margin_mode: PositionMarginMode) -> Result<PositionConfig, ExchangeError>;
+
+    async fn set_margin_mode_and_respond(&mut self, instrument: &Instrument, side: Side, margin_mode: PositionMarginMode, response_tx: Sender<Result<PositionConfig, ExchangeError>>);
+
+    async fn get_perpetual_position(&self, instrument: &Instrument, side: Side) -> Option<PerpetualPosition>;
+
+    async fn store_perpetual_position(&self, position: PerpetualPosition);
+
+    fn send_margin_update_events(&self, position: &PerpetualPosition, balance: Balance);
+
+    async fn perpetual_config_or_default(&self, instrument: &Instrument, side: Side) -> PerpetualPositionConfig;
+
+    async fn store_perpetual_config(&self, instrument: &Instrument, side: Side, config: PerpetualPositionConfig);
+
+    async fn mark_price(&self, instrument: &Instrument) -> Option<f64>;
 }
 
 #[async_trait]
@@ -265,13 +310,19 @@ impl PositionHandler for HourglassAccount
                 }
             }
             | InstrumentKind::Future => {
-                todo!()
+                if let Some(position) = positions.futures_pos_long.read().await.get(instrument) {
+                    return Ok(Some(Position::Future(position.clone())));
+                }
             }
             | InstrumentKind::CryptoOption => {
-                todo!()
+                if let Some(position) = self.get_option_position(instrument, Side::Buy).await {
+                    return Ok(Some(Position::Option(position)));
+                }
             }
             | InstrumentKind::CryptoLeveragedToken => {
-                todo!()
+                if let Some(position) = positions.leveraged_token_pos.read().await.get(instrument) {
+                    return Ok(Some(Position::LeveragedToken(position.clone())));
+                }
             }
             | InstrumentKind::CommodityOption | InstrumentKind::CommodityFuture => {
                 todo!("Commodity positions are not yet implemented");
@@ -302,10 +353,14 @@ impl PositionHandler for HourglassAccount
                 }
             }
             | InstrumentKind::Future => {
-                return Err(ExchangeError::UnsupportedInstrumentKind);
+                if let Some(position) = positions.futures_pos_short.read().await.get(instrument) {
+                    return Ok(Some(Position::Future(position.clone())));
+                }
             }
             | InstrumentKind::CryptoOption => {
-                return Err(ExchangeError::UnsupportedInstrumentKind);
+                if let Some(position) = self.get_option_position(instrument, Side::Sell).await {
+                    return Ok(Some(Position::Option(position)));
+                }
             }
             | InstrumentKind::CryptoLeveragedToken => {
                 return Err(ExchangeError::UnsupportedInstrumentKind);
@@ -336,13 +391,21 @@ impl PositionHandler for HourglassAccount
                 Ok((long_pos, short_pos))
             }
             | InstrumentKind::Future => {
-                todo!()
+                let long_pos = positions.futures_pos_long.read().await.get(instrument).map(|pos| Position::Future(pos.clone()));
+                let short_pos = positions.futures_pos_short.read().await.get(instrument).map(|pos| Position::Future(pos.clone()));
+
+                Ok((long_pos, short_pos))
             }
             | InstrumentKind::CryptoOption => {
-                todo!()
+                let long_pos = self.get_option_position(instrument, Side::Buy).await.map(Position::Option);
+                let short_pos = self.get_option_position(instrument, Side::Sell).await.map(Position::Option);
+
+                Ok((long_pos, short_pos))
             }
             | InstrumentKind::CryptoLeveragedToken => {
-                todo!()
+                let long_pos = positions.leveraged_token_pos.read().await.get(instrument).map(|pos| Position::LeveragedToken(pos.clone()));
+
+                Ok((long_pos, None))
             }
             | InstrumentKind::CommodityOption | InstrumentKind::CommodityFuture => {
                 todo!("Commodity positions are not yet implemented");
@@ -473,11 +536,11 @@ impl PositionHandler for HourglassAccount
             // Cross Mode: Use account-wide margin, no isolated margin.
             | PositionMarginMode::Cross => {
                 // Calculate margin to add to the global margin (account_margin).
-                let margin_to_add = trade.size * trade.price / perpetual_config.leverage;
+                let margin_to_add = trade.instrument.notional(trade.price, trade.size) / perpetual_config.leverage;
                 self.account_margin.fetch_add(margin_to_add, Ordering::SeqCst);
 
                 // Calculate liquidation price in Cross Mode (it depends on account-wide margin and liquidation threshold).
-                let liquidation_price = trade.price * (1.0 - liquidation_threshold / perpetual_config.leverage);
+                let liquidation_price = trade.instrument.opening_liquidation_price(trade.price, trade.side, perpetual_config.leverage, liquidation_threshold);
 
                 // No isolated margin in Cross mode.
                 (None, liquidation_price)
@@ -486,10 +549,10 @@ impl PositionHandler for HourglassAccount
             // Isolated Mode: Calculate isolated margin and liquidation price separately.
             | PositionMarginMode::Isolated => {
                 // Calculate isolated margin.
-                let isolated_margin = Some(trade.price / perpetual_config.leverage * trade.size);
+                let isolated_margin = Some(trade.instrument.notional(trade.price, trade.size) / perpetual_config.leverage);
 
                 // Calculate liquidation price for isolated positions.
-                let liquidation_price = trade.price * (1.0 - liquidation_threshold / perpetual_config.leverage);
+                let liquidation_price = trade.instrument.opening_liquidation_price(trade.price, trade.side, perpetual_config.leverage, liquidation_threshold);
 
                 (isolated_margin, liquidation_price)
             }
@@ -510,33 +573,65 @@ impl PositionHandler for HourglassAccount
         Ok(new_position)
     }
 
-    #[allow(dead_code)]
-    /// 更新 FuturePosition 的方法（占位符）
-    async fn create_future_position(&mut self, _trade: ClientTrade) -> Result<FuturePosition, ExchangeError>
+    /// 按成交开立新的交割合约仓位，保证金与强平价格的计算方式与永续合约相同。
+    async fn create_future_position(&mut self, trade: ClientTrade) -> Result<FuturePosition, ExchangeError>
     {
-        todo!()
+        let liquidation_threshold = self.config.liquidation_threshold;
+        let future_config = self.future_config_or_default(&trade.instrument, trade.side).await;
+
+        let initial_margin = trade.instrument.notional(trade.price, trade.size) / future_config.leverage;
+        let isolated_margin = match future_config.pos_margin_mode {
+            | PositionMarginMode::Cross => {
+                self.account_margin.fetch_add(initial_margin, Ordering::SeqCst);
+                None
+            }
+            | PositionMarginMode::Isolated => Some(initial_margin),
+        };
+        let liquidation_price = trade.instrument.opening_liquidation_price(trade.price, trade.side, future_config.leverage, liquidation_threshold);
+
+        let new_position = FuturePosition { meta: PositionMeta::create_from_trade(&trade),
+                                            pos_config: future_config,
+                                            liquidation_price,
+                                            isolated_margin,
+                                            funding_fee: 0.0 };
+        self.store_future_position(new_position.clone()).await;
+
+        Ok(new_position)
     }
 
-    #[allow(dead_code)]
-    /// 更新 OptionPosition 的方法（占位符）
-    async fn create_option_position(&mut self, _trade: ClientTrade) -> Result<OptionPosition, ExchangeError>
+    /// 按成交开立新的期权仓位。权利金已在成交时结算，空头仓位记录挂单时预留的卖方保证金。
+    async fn create_option_position(&mut self, trade: ClientTrade) -> Result<OptionPosition, ExchangeError>
     {
-        todo!("Updating Option positions is not yet implemented")
+        let seller_margin = match trade.side {
+            | Side::Buy => 0.0,
+            | Side::Sell => self.config.option_config.seller_margin(&trade.instrument, trade.size),
+        };
+
+        let new_position = OptionPosition { meta: PositionMeta::create_from_trade(&trade),
+                                            seller_margin };
+        self.store_option_position(new_position.clone()).await;
+
+        Ok(new_position)
     }
 
-    #[allow(dead_code)]
-    /// 更新 LeveragedTokenPosition 的方法（占位符）
-    async fn create_leveraged_token_position(&mut self, _trade: ClientTrade) -> Result<LeveragedTokenPosition, ExchangeError>
+    /// 按申购成交开立杠杆代币的持仓记录。代币的持仓只有多头一侧，申购金额已在成交时按现货逻辑结算。
+    async fn create_leveraged_token_position(&mut self, trade: ClientTrade) -> Result<LeveragedTokenPosition, ExchangeError>
     {
-        todo!("Updating Leveraged Token positions is not yet implemented")
+        let new_position = LeveragedTokenPosition { meta: PositionMeta::create_from_trade(&trade) };
+        self.positions.leveraged_token_pos.write().await.insert(trade.instrument, new_position.clone());
+
+        Ok(new_position)
     }
 
     /// FIXME 查看是否仅在 `Net` 的时候 才会继承
     /// 当且仅当 `PositionDirectionMode` 是 `Net` 的时候, 允许在处理新的trade的时候继承反向仓位的configuration.并且返回.
+    /// 同向指的是成交所作用的仓位方向，双向持仓模式下的平仓成交使用被平仓位的配置。
     async fn handle_config_inheritance(&self, trade: &ClientTrade) -> Result<PerpetualPositionConfig, ExchangeError>
     {
+        let leg = trade.position_side.leg(trade.side);
+
         // 尝试获取同向仓位配置
-        let same_side_config = match trade.side {
+        let same_side_config = match leg {
             | Side::Buy => self.get_position_long_config(&trade.instrument).await?,
             | Side::Sell => self.get_position_short_config(&trade.instrument).await?,
         };
@@ -547,7 +642,7 @@ impl PositionHandler for HourglassAccount
         }
 
         // 如果没有找到同向配置，尝试获取反向仓位配置
-        let opposite_side_config = match trade.side {
+        let opposite_side_config = match leg {
             | Side::Buy => self.get_position_short_config(&trade.instrument).await?,
             | Side::Sell => self.get_position_long_config(&trade.instrument).await?,
         };
@@ -572,6 +667,32 @@ impl PositionHandler for HourglassAccount
         // 获取仓位配置
         let config = self.handle_config_inheritance(&trade).await?;
 
+        // 双向持仓模式下按成交指定的仓位独立开平，平仓数量超过仓位大小时不会反向开仓
+        if config.position_direction_mode == PositionDirectionMode::LongShort && trade.position_side != PositionSide::Both {
+            let position = match trade.position_side.leg(trade.side) {
+                | Side::Buy => self.get_position_long(&trade.instrument).await?,
+                | Side::Sell => self.get_position_short(&trade.instrument).await?,
+            };
+
+            if !trade.position_side.is_closing(trade.side) {
+                return match position {
+                    | Some(_) => Ok(PositionHandling::UpdateExisting),
+                    | None => Ok(PositionHandling::OpenBrandNewPosition),
+                };
+            }
+
+            let current_size = position.ok_or(ExchangeError::AttemptToRemoveNonExistingPosition)?.current_size();
+            return if trade.size == current_size {
+                Ok(PositionHandling::CloseComplete)
+            }
+            else if trade.size < current_size {
+                Ok(PositionHandling::ClosePartial)
+            }
+            else {
+                Err(ExchangeError::InvalidTradeSize)
+            };
+        }
+
         // 检查是否存在既有同向仓位
         let has_existing_long_position = self.get_position_long(&trade.instrument).await?.is_some();
         let has_existing_short_position = self.get_position_short(&trade.instrument).await?.is_some();
@@ -650,6 +771,14 @@ impl PositionHandler for HourglassAccount
 
     async fn update_position_from_client_trade(&mut self, trade: ClientTrade) -> Result<(), ExchangeError>
     {
+        // 交割合约、期权与杠杆代币的开平仓分别由 FutureHandler、OptionHandler 与 LeveragedTokenHandler 处理
+        match trade.instrument.kind {
+            | InstrumentKind::Future => return self.update_future_position_from_client_trade(trade).await,
+            | InstrumentKind::CryptoOption => return self.update_option_position_from_client_trade(trade).await,
+            | InstrumentKind::CryptoLeveragedToken => return self.update_leveraged_token_position_from_client_trade(trade).await,
+            | _ => {}
+        }
+
         // 通过调用 determine_handling_type 确定该交易的处理方式
         let handling_type = self.determine_handling_type(trade.clone()).await?;
 
@@ -691,6 +820,18 @@ impl PositionHandler for HourglassAccount
         }
     }
 
+    async fn store_position(&self, position: Position)
+    {
+        match position {
+            | Position::Perpetual(position) => self.store_perpetual_position(position).await,
+            | Position::Future(position) => self.store_future_position(position).await,
+            | Position::LeveragedToken(position) => {
+                self.positions.leveraged_token_pos.write().await.insert(position.meta.instrument.clone(), position);
+            }
+            | Position::Option(position) => self.store_option_position(position).await,
+        }
+    }
+
     async fn remove_perpetual_position(&self, instrument: Instrument, side: Side) -> Option<PerpetualPosition>
     {
         match side {
@@ -722,14 +863,9 @@ impl PositionHandler for HourglassAccount
     async fn remove_leveraged_token_position(&self, instrument: Instrument, side: Side) -> Option<LeveragedTokenPosition>
     {
         match side {
-            | Side::Buy => {
-                let mut long_positions = self.positions.margin_pos_long.write().await;
-                long_positions.remove(&instrument)
-            }
-            | Side::Sell => {
-                let mut short_positions = self.positions.margin_pos_short.write().await;
-                short_positions.remove(&instrument)
-            }
+            | Side::Buy => self.positions.leveraged_token_pos.write().await.remove(&instrument),
+            // 杠杆代币的持仓只有多头一侧
+            | Side::Sell => None,
         }
     }
 
@@ -764,6 +900,26 @@ impl PositionHandler for HourglassAccount
             | (InstrumentKind::Perpetual, Side::Sell) => {
                 self.exited_positions.insert_perpetual_pos_short(exited).await;
             }
+            | (InstrumentKind::Future, Side::Buy) => {
+                self.exited_positions.insert_futures_pos_long(exited).await;
+            }
+            | (InstrumentKind::Future, Side::Sell) => {
+                self.exited_positions.insert_futures_pos_short(exited).await;
+            }
+            | (InstrumentKind::CryptoOption, side) => {
+                let option_spec = meta.instrument
+                                      .option_spec
+                                      .ok_or_else(|| ExchangeError::InvalidInstrument(format!("{} has no option spec", meta.instrument)))?;
+                match (side, option_spec.option_kind) {
+                    | (Side::Buy, OptionKind::Call) => self.exited_positions.insert_option_pos_long_call(exited).await,
+                    | (Side::Buy, OptionKind::Put) => self.exited_positions.insert_option_pos_long_put(exited).await,
+                    | (Side::Sell, OptionKind::Call) => self.exited_positions.insert_option_pos_short_call(exited).await,
+                    | (Side::Sell, OptionKind::Put) => self.exited_positions.insert_option_pos_short_put(exited).await,
+                }
+            }
+            | (InstrumentKind::CryptoLeveragedToken, _) => {
+                self.exited_positions.insert_leveraged_token_pos(exited).await;
+            }
             // You can add handling for other position types here
             | _ => return Err(ExchangeError::UnsupportedInstrumentKind),
         }
@@ -806,18 +962,18 @@ impl PositionHandler for HourglassAccount
                     match position.pos_config.pos_margin_mode {
                         | PositionMarginMode::Cross => {
                             // 更新 Cross 模式下的保证金
-                            let margin_to_add = trade.size * trade.price / position.pos_config.leverage;
+                            let margin_to_add = trade.instrument.notional(trade.price, trade.size) / position.pos_config.leverage;
                             self.account_margin.fetch_add(margin_to_add, Ordering::SeqCst);
 
                             // 更新清算价格
-                            position.liquidation_price = trade.price * (1.0 - self.config.liquidation_threshold / position.pos_config.leverage);
+                            position.liquidation_price = trade.instrument.opening_liquidation_price(trade.price, Side::Buy, position.pos_config.leverage, self.config.liquidation_threshold);
                         }
                         | PositionMarginMode::Isolated => {
                             // 更新 Isolated 模式下的保证金
                             self.update_isolated_margin(&mut position, &trade).await;
 
                             // 更新清算价格
-                            position.liquidation_price = trade.price * (1.0 - self.config.liquidation_threshold / position.pos_config.leverage);
+                            position.liquidation_price = trade.instrument.opening_liquidation_price(trade.price, Side::Buy, position.pos_config.leverage, self.config.liquidation_threshold);
                         }
                     }
 
@@ -838,17 +994,17 @@ impl PositionHandler for HourglassAccount
                     // 根据仓位模式更新保证金和清算价格
                     match position.pos_config.pos_margin_mode {
                         | PositionMarginMode::Cross => {
-                            let margin_to_add = trade.size * trade.price / position.pos_config.leverage;
+                            let margin_to_add = trade.instrument.notional(trade.price, trade.size) / position.pos_config.leverage;
                             self.account_margin.fetch_add(margin_to_add, Ordering::SeqCst);
 
                             // 更新清算价格
-                            position.liquidation_price = trade.price * (1.0 + self.config.liquidation_threshold / position.pos_config.leverage);
+                            position.liquidation_price = trade.instrument.opening_liquidation_price(trade.price, Side::Sell, position.pos_config.leverage, self.config.liquidation_threshold);
                         }
                         | PositionMarginMode::Isolated => {
                             self.update_isolated_margin(&mut position, &trade).await;
 
                             // 更新清算价格
-                            position.liquidation_price = trade.price * (1.0 + self.config.liquidation_threshold / position.pos_config.leverage);
+                            position.liquidation_price = trade.instrument.opening_liquidation_price(trade.price, Side::Sell, position.pos_config.leverage, self.config.liquidation_threshold);
                         }
                     }
 
@@ -873,7 +1029,7 @@ impl PositionHandler for HourglassAccount
                     match position.pos_config.pos_margin_mode {
                         | PositionMarginMode::Cross => {
                             // 减去对应的保证金
-                            let margin_to_subtract = position.meta.current_size / position.pos_config.leverage;
+                            let margin_to_subtract = position.initial_margin();
                             self.account_margin.fetch_sub(margin_to_subtract, Ordering::SeqCst);
                             self.register_exit_position(&position.meta, side, None).await?;
                         }
@@ -898,7 +1054,7 @@ impl PositionHandler for HourglassAccount
                     match position.pos_config.pos_margin_mode {
                         | PositionMarginMode::Cross => {
                             // 减去对应的保证金
-                            let margin_to_subtract = position.meta.current_size / position.pos_config.leverage;
+                            let margin_to_subtract = position.initial_margin();
                             self.account_margin.fetch_sub(margin_to_subtract, Ordering::SeqCst);
                         }
                         | PositionMarginMode::Isolated => {
@@ -921,63 +1077,273 @@ impl PositionHandler for HourglassAccount
     async fn check_and_handle_liquidation(&mut self, trade: &MarketTrade) -> Result<(), ExchangeError>
     {
         // 解析金融工具
-        let instrument = trade.parse_instrument().ok_or_else(|| ExchangeError::InvalidInstrument("Instrument parsing failed".to_string()))?;
+        let instrument = trade.resolve_instrument(&self.symbol_registry)?;
+
+        if instrument.kind == InstrumentKind::Future {
+            // 行情中的交割合约不带到期时间时，同一标的所有到期日的仓位都按这笔成交检查
+            let matches = |position: &FuturePosition| position.meta.instrument == instrument || (instrument.expiry.is_none() && position.meta.instrument.undated() == instrument);
+            let long_positions: Vec<FuturePosition> = self.positions.futures_pos_long.read().await.values().filter(|position| matches(position)).cloned().collect();
+            let short_positions: Vec<FuturePosition> = self.positions.futures_pos_short.read().await.values().filter(|position| matches(position)).cloned().collect();
+
+            for long_pos in long_positions {
+                if trade.price <= long_pos.liquidation_price && trade.parse_side() == Side::Sell {
+                    self.liquidate_position_incrementally(long_pos, trade).await?;
+                }
+            }
+            for short_pos in short_positions {
+                if trade.price >= short_pos.liquidation_price && trade.parse_side() == Side::Buy {
+                    self.liquidate_position_incrementally(short_pos, trade).await?;
+                }
+            }
+            return Ok(());
+        }
 
         // 获取多头和空头仓位
         let (long_position, short_position) = self.get_position_both_ways(&instrument).await?;
-        // info!("Long Position: {:?}", long_position);
-        // info!("Short Position: {:?}", short_position);
 
-        // 生成新的交易 ID
-        let trade_id_value = self.client_trade_counter.fetch_add(1, Ordering::SeqCst);
-        let trade_id = ClientTradeId(trade_id_value);
         // 检查并处理多头仓位
         if let Some(Position::Perpetual(long_pos)) = long_position {
             if trade.price <= long_pos.liquidation_price && trade.parse_side() == Side::Sell {
-                // 生成平仓的 `ClientTrade`
-                let liquidation_trade = ClientTrade { exchange: Exchange::Hourglass,
-                                                      timestamp: trade.timestamp,
-                                                      trade_id,
-                                                      order_id: None,
-                                                      cid: None,
-                                                      instrument: instrument.clone(),
-                                                      side: Side::Sell,
-                                                      price: trade.price,
-                                                      size: long_pos.meta.current_size,
-                                                      fees: 0.0 };
-
-                // 处理平仓
-                self.liquidate_position_by_trade(&mut Position::Perpetual(long_pos), Side::Buy).await?;
-                self.process_trade(liquidation_trade).await?;
-                return Ok(());
+                return self.liquidate_position_incrementally(long_pos, trade).await;
             }
         }
 
         // 检查并处理空头仓位
         if let Some(Position::Perpetual(short_pos)) = short_position {
             if trade.price >= short_pos.liquidation_price && trade.parse_side() == Side::Buy {
-                // 生成平仓的 `ClientTrade`
-                let liquidation_trade = ClientTrade { exchange: Exchange::Hourglass,
-                                                      timestamp: trade.timestamp,
-                                                      trade_id,
-                                                      order_id: None,
-                                                      cid: None,
-                                                      instrument: instrument.clone(),
-                                                      side: Side::Buy,
-                                                      price: trade.price,
-                                                      size: short_pos.meta.current_size,
-                                                      fees: 0.0 };
+                return self.liquidate_position_incrementally(short_pos, trade).await;
+            }
+        }
+
+        Ok(())
+    }
 
-                // 处理平仓
-                self.liquidate_position_by_trade(&mut Position::Perpetual(short_pos), Side::Sell).await?;
-                self.process_trade(liquidation_trade).await?;
-                return Ok(());
+    /// 强平价格被触发后，按 [`LiquidatablePosition::liquidation_step`] 算出的最小比例减仓，永续合约与交割合约仓位共用这一流程。
+    ///
+    /// 逐仓模式下仓位只能动用自身的 `isolated_margin`，全仓模式下还可以动用账户中可用的保证金币种余额。
+    /// 每一步都会收取强平费、重新计算强平价格，并发送 [`AccountEventKind::Liquidation`] 事件；
+    /// 若保证金仍然充足则只刷新强平价格。穿仓或需要整体平仓时，仓位会被移除。
+    async fn liquidate_position_incrementally<P>(&mut self, mut position: P, trade: &MarketTrade) -> Result<(), ExchangeError>
+        where P: LiquidatablePosition + 'static
+    {
+        let liquidation_threshold = self.config.liquidation_threshold;
+        let instrument = position.meta().instrument.clone();
+        let position_side = position.meta().side;
+        let margin_mode = position.margin_mode().clone();
+
+        let margin = match margin_mode {
+            | PositionMarginMode::Cross => position.initial_margin() + self.get_balance(instrument.margin_token())?.available,
+            | PositionMarginMode::Isolated => position.isolated_margin().unwrap_or(0.0),
+        };
+
+        let step = position.liquidation_step(trade.price, margin, liquidation_threshold, self.config.liquidation_fee_rate);
+        if !step.is_required() {
+            // 价格越过了旧的强平价格，但保证金仍然充足，只需要刷新强平价格
+            position.update_liquidation_price(position.compute_liquidation_price(margin, liquidation_threshold));
+            self.store_position(position.into_position()).await;
+            return Ok(());
+        }
+
+        let close_side = match position_side {
+            | Side::Buy => Side::Sell,
+            | Side::Sell => Side::Buy,
+        };
+        let trade_id = ClientTradeId(self.client_trade_counter.fetch_add(1, Ordering::SeqCst));
+        let liquidation_trade = ClientTrade { exchange: Exchange::Hourglass,
+                                              timestamp: trade.timestamp,
+                                              trade_id,
+                                              order_id: None,
+                                              cid: None,
+                                              instrument: instrument.clone(),
+                                              side: close_side,
+                                              price: trade.price,
+                                              size: step.close_size,
+                                              fees: 0.0,
+                                              position_side: position.position_side() };
+
+        let is_full_close = step.is_bankrupt || step.close_size >= position.meta().current_size;
+        let released_margin = position.initial_margin() * step.close_size / position.meta().current_size;
+
+        // 结算被平部分的盈亏与强平费。整体平仓时亏损以仓位可动用的保证金为上限，
+        // 权益不足以支付的强平费不再收取，超出保证金的亏损即穿仓缺口。
+        let (paid_fee, shortfall) = if is_full_close {
+            let equity = margin + step.realised_pnl;
+            (step.fee.min(equity.max(0.0)), (-equity).max(0.0))
+        }
+        else {
+            (step.fee, 0.0)
+        };
+        let account_loss = (-step.realised_pnl).min(margin) + paid_fee;
+        let quote_delta = match margin_mode {
+            | PositionMarginMode::Cross => BalanceDelta { total: -account_loss,
+                                                          available: released_margin - account_loss },
+            // 逐仓剩余的保证金退回可用余额
+            | PositionMarginMode::Isolated if is_full_close => BalanceDelta { total: -account_loss,
+                                                                              available: margin - account_loss },
+            | PositionMarginMode::Isolated => {
+                position.set_isolated_margin(Some(margin - account_loss));
+                BalanceDelta { total: -account_loss, available: 0.0 }
+            }
+        };
+        self.apply_balance_delta(instrument.margin_token(), quote_delta);
+        self.risk_reserve.lock().await.contribute(paid_fee * self.config.risk_reserve_fee_share);
+
+        let (remaining_size, liquidation_price) = if is_full_close {
+            self.liquidate_position_by_trade(&mut position.clone().into_position(), position_side).await?;
+            (0.0, trade.price)
+        }
+        else {
+            if margin_mode == PositionMarginMode::Cross {
+                self.account_margin.fetch_sub(released_margin, Ordering::SeqCst);
+            }
+            let meta = position.meta_mut();
+            meta.update_from_trade(&liquidation_trade);
+            meta.current_fees_total += paid_fee;
+            meta.realised_pnl += step.realised_pnl;
+
+            let remaining_margin = match margin_mode {
+                | PositionMarginMode::Cross => position.initial_margin() + self.get_balance(instrument.margin_token())?.available,
+                | PositionMarginMode::Isolated => position.isolated_margin().unwrap_or(0.0),
+            };
+
+            let liquidation_price = position.compute_liquidation_price(remaining_margin, liquidation_threshold);
+            position.update_liquidation_price(liquidation_price);
+            self.store_position(position.clone().into_position()).await;
+            (position.meta().current_size, liquidation_price)
+        };
+
+        self.process_forced_trade(liquidation_trade).await?;
+
+        // 穿仓缺口先由风险准备金弥补，不足部分通过自动减仓分摊给盈利的反向仓位
+        if shortfall > 0.0 {
+            let covered = self.risk_reserve.lock().await.deduct(shortfall);
+            if shortfall > covered {
+                match position.position_direction_mode() {
+                    | PositionDirectionMode::LongShort => {
+                        self.auto_deleverage(&instrument, position_side, trade, shortfall - covered).await?;
+                    }
+                    // 单向持仓模式下账户内不存在同一合约的反向仓位，也没有对手方可以分摊
+                    | PositionDirectionMode::Net => warn!("Risk reserve could not absorb the remaining bankruptcy loss of {} on {:?}", shortfall - covered, instrument),
+                }
             }
         }
 
+        let liquidation = PositionLiquidation { exchange: Exchange::Hourglass,
+                                                instrument,
+                                                side: position_side,
+                                                position_id: position.meta().position_id.clone(),
+                                                margin_mode,
+                                                timestamp: trade.timestamp,
+                                                price: trade.price,
+                                                closed_size: step.close_size,
+                                                remaining_size,
+                                                fee: paid_fee,
+                                                realised_pnl: step.realised_pnl,
+                                                liquidation_price };
+        if let Err(err) = self.account_event_tx.send(AccountEvent { exchange_timestamp: self.exchange_timestamp.load(Ordering::SeqCst),
+                                                                    exchange: Exchange::Hourglass,
+                                                                    kind: AccountEventKind::Liquidation(liquidation) })
+        {
+            warn!("Client offline - Failed to send AccountEvent::Liquidation: {:?}", err);
+        }
+
         Ok(())
     }
 
+    /// 风险准备金耗尽后，按盈利比例与杠杆的乘积从高到低平掉盈利的反向仓位，由其利润分摊剩余的穿仓亏损。
+    ///
+    /// 每个被减仓的仓位都会收到一个 [`AccountEventKind::AutoDeleveraged`] 事件。返回仍未被分摊的亏损。
+    ///
+    /// 回测只模拟本账户，不模拟其他交易者，因此只有双向持仓模式下同一账户的反向仓位可以作为减仓对象。
+    async fn auto_deleverage(&mut self, instrument: &Instrument, bankrupt_side: Side, trade: &MarketTrade, shortfall: f64) -> Result<f64, ExchangeError>
+    {
+        let remaining = match instrument.kind {
+            | InstrumentKind::Future => {
+                let candidates: Vec<FuturePosition> = match bankrupt_side {
+                    | Side::Buy => self.positions.futures_pos_short.read().await.get(instrument).cloned().into_iter().collect(),
+                    | Side::Sell => self.positions.futures_pos_long.read().await.get(instrument).cloned().into_iter().collect(),
+                };
+                self.deleverage_positions(candidates, bankrupt_side, trade, shortfall).await?
+            }
+            | _ => {
+                let candidates: Vec<PerpetualPosition> = match bankrupt_side {
+                    | Side::Buy => self.positions.perpetual_pos_short.read().await.get(instrument).cloned().into_iter().collect(),
+                    | Side::Sell => self.positions.perpetual_pos_long.read().await.get(instrument).cloned().into_iter().collect(),
+                };
+                self.deleverage_positions(candidates, bankrupt_side, trade, shortfall).await?
+            }
+        };
+
+        if remaining > 0.0 {
+            warn!("Auto-deleveraging could not absorb the remaining bankruptcy loss of {} on {:?}", remaining, instrument);
+        }
+        Ok(remaining)
+    }
+
+    /// 按自动减仓的排序分数依次整体平掉盈利的候选仓位，返回仍未被分摊的亏损。
+    async fn deleverage_positions<P>(&mut self, mut candidates: Vec<P>, bankrupt_side: Side, trade: &MarketTrade, shortfall: f64) -> Result<f64, ExchangeError>
+        where P: LiquidatablePosition + 'static
+    {
+        candidates.retain(|position| position.unrealised_pnl_at(trade.price) > 0.0);
+        candidates.sort_by(|a, b| b.adl_ranking_score(trade.price).total_cmp(&a.adl_ranking_score(trade.price)));
+
+        let mut remaining = shortfall;
+        for position in candidates {
+            if remaining <= 0.0 {
+                break;
+            }
+
+            let profit = position.unrealised_pnl_at(trade.price);
+            let socialised_loss = profit.min(remaining);
+            remaining -= socialised_loss;
+
+            // 被减仓的仓位按市价整体平掉，利润扣除分摊的亏损后连同保证金退回可用余额
+            let instrument = position.meta().instrument.clone();
+            let released_margin = match position.margin_mode() {
+                | PositionMarginMode::Cross => position.initial_margin(),
+                | PositionMarginMode::Isolated => position.isolated_margin().unwrap_or(0.0),
+            };
+            self.apply_balance_delta(instrument.margin_token(),
+                                     BalanceDelta { total: profit - socialised_loss,
+                                                    available: released_margin + profit - socialised_loss });
+
+            let side = position.meta().side;
+            let adl_trade = ClientTrade { exchange: Exchange::Hourglass,
+                                          timestamp: trade.timestamp,
+                                          trade_id: ClientTradeId(self.client_trade_counter.fetch_add(1, Ordering::SeqCst)),
+                                          order_id: None,
+                                          cid: None,
+                                          instrument: instrument.clone(),
+                                          side: bankrupt_side,
+                                          price: trade.price,
+                                          size: position.meta().current_size,
+                                          fees: 0.0,
+                                          position_side: position.position_side() };
+            let deleverage = PositionAutoDeleverage { exchange: Exchange::Hourglass,
+                                                      instrument: instrument.clone(),
+                                                      side,
+                                                      position_id: position.meta().position_id.clone(),
+                                                      timestamp: trade.timestamp,
+                                                      price: trade.price,
+                                                      closed_size: position.meta().current_size,
+                                                      realised_pnl: profit - socialised_loss,
+                                                      socialised_loss,
+                                                      ranking_score: position.adl_ranking_score(trade.price) };
+
+            self.liquidate_position_by_trade(&mut position.into_position(), side).await?;
+            self.process_forced_trade(adl_trade).await?;
+
+            if let Err(err) = self.account_event_tx.send(AccountEvent { exchange_timestamp: self.exchange_timestamp.load(Ordering::SeqCst),
+                                                                        exchange: Exchange::Hourglass,
+                                                                        kind: AccountEventKind::AutoDeleveraged(deleverage) })
+            {
+                warn!("Client offline - Failed to send AccountEvent::AutoDeleveraged: {:?}", err);
+            }
+        }
+
+        Ok(remaining)
+    }
+
     // 关闭并反向开仓
     async fn close_and_reverse_position(&mut self, trade: ClientTrade, remaining: f64) -> Result<(), ExchangeError>
     {
@@ -997,40 +1363,28 @@ impl PositionHandler for HourglassAccount
     /// 根据收到的爆仓MarketTrade来处理爆仓。
     async fn liquidate_position_by_trade(&mut self, pos: &mut Position, side: Side) -> Result<(), ExchangeError>
     {
-        match pos {
-            | Position::Perpetual(perpetual_pos) => {
-                // 获取当前仓位的大小
-                let position_size = perpetual_pos.meta.current_size;
-                if position_size > 0.0 {
-                    match perpetual_pos.pos_config.pos_margin_mode {
-                        | PositionMarginMode::Cross => {
-                            // 减去对应的保证金
-                            let margin_to_subtract = position_size / perpetual_pos.pos_config.leverage;
-                            self.account_margin.fetch_sub(margin_to_subtract, Ordering::SeqCst);
-                        }
-                        | PositionMarginMode::Isolated => {
-                            // 清空 isolated 保证金
-                            perpetual_pos.isolated_margin = Some(0.0);
-                        }
-                    }
+        // 永续合约与交割合约的保证金处理相同
+        let (meta, margin_mode, initial_margin, isolated_margin) = match pos {
+            | Position::Perpetual(position) => (position.meta.clone(), position.pos_config.pos_margin_mode.clone(), position.initial_margin(), &mut position.isolated_margin),
+            | Position::Future(position) => (position.meta.clone(), position.pos_config.pos_margin_mode.clone(), position.initial_margin(), &mut position.isolated_margin),
+            // 你可以为其他类型的 Position 添加类似的处理逻辑，例如 Option 等
+            | _ => return Err(ExchangeError::UnsupportedInstrumentKind),
+        };
 
-                    // 根据仓位的方向移除仓位
-                    match side {
-                        | Side::Buy => {
-                            self.remove_position(perpetual_pos.meta.instrument.clone(), Side::Buy)
-                                .await
-                                .ok_or(ExchangeError::AttemptToRemoveNonExistingPosition)?;
-                        }
-                        | Side::Sell => {
-                            self.remove_position(perpetual_pos.meta.instrument.clone(), Side::Sell)
-                                .await
-                                .ok_or(ExchangeError::AttemptToRemoveNonExistingPosition)?;
-                        }
-                    }
+        if meta.current_size > 0.0 {
+            match margin_mode {
+                | PositionMarginMode::Cross => {
+                    // 减去对应的保证金
+                    self.account_margin.fetch_sub(initial_margin, Ordering::SeqCst);
+                }
+                | PositionMarginMode::Isolated => {
+                    // 清空 isolated 保证金
+                    *isolated_margin = Some(0.0);
                 }
             }
-            // 你可以为其他类型的 Position 添加类似的处理逻辑，例如 Future、Option 等
-            | _ => return Err(ExchangeError::UnsupportedInstrumentKind),
+
+            // 根据仓位的方向移除仓位
+            self.remove_position(meta.instrument, side).await.ok_or(ExchangeError::AttemptToRemoveNonExistingPosition)?;
         }
 
         Ok(())
@@ -1053,14 +1407,14 @@ impl PositionHandler for HourglassAccount
                     match position.pos_config.pos_margin_mode {
                         | PositionMarginMode::Cross => {
                             // 减去对应的 Cross 保证金
-                            let margin_to_subtract = trade.size * trade.price / position.pos_config.leverage;
+                            let margin_to_subtract = trade.instrument.notional(trade.price, trade.size) / position.pos_config.leverage;
                             self.account_margin.fetch_sub(margin_to_subtract, Ordering::SeqCst);
                         }
                         | PositionMarginMode::Isolated => {
                             // 根据平仓比例减少 Isolated 保证金
                             if let Some(isolated_margin) = position.isolated_margin {
                                 info!("isolated_margin: {}", isolated_margin);
-                                let margin_to_subtract = trade.price * trade.size / position.pos_config.leverage;
+                                let margin_to_subtract = trade.instrument.notional(trade.price, trade.size) / position.pos_config.leverage;
                                 info!("margin to subtract: {}", margin_to_subtract);
                                 position.isolated_margin = Some(isolated_margin - margin_to_subtract);
                             }
@@ -1086,7 +1440,7 @@ impl PositionHandler for HourglassAccount
                     match position.pos_config.pos_margin_mode {
                         | PositionMarginMode::Cross => {
                             // 减去对应的 Cross 保证金
-                            let margin_to_subtract = trade.size * trade.price / position.pos_config.leverage;
+                            let margin_to_subtract = trade.instrument.notional(trade.price, trade.size) / position.pos_config.leverage;
                             self.account_margin.fetch_sub(margin_to_subtract, Ordering::SeqCst);
                         }
                         | PositionMarginMode::Isolated => {
@@ -1111,10 +1465,233 @@ impl PositionHandler for HourglassAccount
     {
         if let PositionMarginMode::Isolated = position.pos_config.pos_margin_mode {
             if let Some(ref mut margin) = position.isolated_margin {
-                *margin += trade.price * trade.size / position.pos_config.leverage;
+                *margin += trade.instrument.notional(trade.price, trade.size) / position.pos_config.leverage;
             }
             else {
-                position.isolated_margin = Some(trade.price * trade.size / position.pos_config.leverage);
+                position.isolated_margin = Some(trade.instrument.notional(trade.price, trade.size) / position.pos_config.leverage);
+            }
+        }
+    }
+
+    /// 在保证金币种的 [`Balance`] 与逐仓仓位的 `isolated_margin` 之间划转保证金。
+    ///
+    /// `side` 为仓位方向，`delta` 为正时追加保证金，为负时提取保证金，提取后不得低于仓位的初始保证金。
+    /// 划转完成后重新计算强平价格，并发送余额与仓位事件。
+    async fn adjust_isolated_margin(&mut self, instrument: &Instrument, side: Side, delta: f64) -> Result<PerpetualPosition, ExchangeError>
+    {
+        let mut position = self.get_perpetual_position(instrument, side).await.ok_or(ExchangeError::AttemptToUpdateNonExistingPosition)?;
+
+        if position.pos_config.pos_margin_mode != PositionMarginMode::Isolated {
+            return Err(ExchangeError::Hourglass(format!("Margin of {:?} position on {} is not isolated", side, instrument)));
+        }
+
+        let new_margin = position.isolated_margin.unwrap_or(0.0) + delta;
+        if delta > 0.0 && self.get_balance(instrument.margin_token())?.available < delta {
+            return Err(ExchangeError::InsufficientBalance(instrument.margin_token().clone()));
+        }
+        if delta < 0.0 && new_margin < position.initial_margin() {
+            return Err(ExchangeError::InsufficientMargin(format!("isolated margin {} would fall below initial margin {}", new_margin, position.initial_margin())));
+        }
+
+        let balance = self.apply_balance_delta(instrument.margin_token(), BalanceDelta { total: 0.0, available: -delta });
+        position.isolated_margin = Some(new_margin);
+        position.update_liquidation_price(position.compute_liquidation_price(new_margin, self.config.liquidation_threshold));
+        self.store_perpetual_position(position.clone()).await;
+        self.send_margin_update_events(&position, balance);
+
+        Ok(position)
+    }
+
+    async fn adjust_isolated_margin_and_respond(&mut self, instrument: &Instrument, side: Side, delta: f64, response_tx: Sender<Result<Position, ExchangeError>>)
+    {
+        let result = self.adjust_isolated_margin(instrument, side, delta).await.map(Position::Perpetual);
+        respond(response_tx, result);
+    }
+
+    /// 修改仓位杠杆，可以在持仓期间调用。
+    ///
+    /// 已有仓位时按新杠杆重新计算初始保证金：全仓调整账户占用的保证金，逐仓调整 `isolated_margin`（保留额外追加的部分），
+    /// 差额从可用余额中划转。若调整后仓位权益低于维持保证金则拒绝修改。
+    async fn set_leverage(&mut self, instrument: &Instrument, side: Side, leverage: f64) -> Result<PositionConfig, ExchangeError>
+    {
+        if !(1.0..=self.config.global_leverage_rate).contains(&leverage) {
+            return Err(ExchangeError::InvalidLeverage(format!("Requested leverage {} is outside of the allowed range [1, {}]", leverage, self.config.global_leverage_rate)));
+        }
+
+        let position = self.get_perpetual_position(instrument, side).await;
+        let mut config = match &position {
+            | Some(position) => position.pos_config.clone(),
+            | None => self.perpetual_config_or_default(instrument, side).await,
+        };
+        config.leverage = leverage;
+
+        if let Some(mut position) = position {
+            let liquidation_threshold = self.config.liquidation_threshold;
+            let available = self.get_balance(instrument.margin_token())?.available;
+            let mark_price = self.mark_price(instrument).await.unwrap_or(position.meta.current_symbol_price);
+
+            let old_initial_margin = position.initial_margin();
+            position.pos_config.leverage = leverage;
+            let margin_delta = position.initial_margin() - old_initial_margin;
+            if margin_delta > available {
+                return Err(ExchangeError::InsufficientBalance(instrument.margin_token().clone()));
+            }
+
+            // 调整后仓位可动用的保证金
+            let margin = match position.pos_config.pos_margin_mode {
+                | PositionMarginMode::Cross => position.initial_margin() + available - margin_delta,
+                | PositionMarginMode::Isolated => position.isolated_margin.unwrap_or(0.0) + margin_delta,
+            };
+            if margin + position.unrealised_pnl_at(mark_price) < position.maintenance_margin(liquidation_threshold) {
+                return Err(ExchangeError::InsufficientMargin(format!("leverage {} would put the position below maintenance margin", leverage)));
+            }
+
+            let balance = self.apply_balance_delta(instrument.margin_token(), BalanceDelta { total: 0.0, available: -margin_delta });
+            match position.pos_config.pos_margin_mode {
+                | PositionMarginMode::Cross => {
+                    self.account_margin.fetch_add(margin_delta, Ordering::SeqCst);
+                }
+                | PositionMarginMode::Isolated => position.isolated_margin = Some(margin),
+            }
+            position.update_liquidation_price(position.compute_liquidation_price(margin, liquidation_threshold));
+            self.store_perpetual_position(position.clone()).await;
+            self.send_margin_update_events(&position, balance);
+        }
+
+        self.store_perpetual_config(instrument, side, config.clone()).await;
+        Ok(PositionConfig::Perpetual(config))
+    }
+
+    async fn set_leverage_and_respond(&mut self, instrument: &Instrument, side: Side, leverage: f64, response_tx: Sender<Result<PositionConfig, ExchangeError>>)
+    {
+        let result = self.set_leverage(instrument, side, leverage).await;
+        respond(response_tx, result);
+    }
+
+    /// 切换仓位的保证金模式，可以在持仓期间调用，但该金融工具仍有挂单时不允许切换。
+    ///
+    /// 全仓切换为逐仓时，仓位的初始保证金转为 `isolated_margin`；逐仓切换为全仓时，`isolated_margin` 中超出初始保证金的部分退回可用余额。
+    async fn set_margin_mode(&mut self, instrument: &Instrument, side: Side, margin_mode: PositionMarginMode) -> Result<PositionConfig, ExchangeError>
+    {
+        let position = self.get_perpetual_position(instrument, side).await;
+        let mut config = match &position {
+            | Some(position) => position.pos_config.clone(),
+            | None => self.perpetual_config_or_default(instrument, side).await,
+        };
+        if config.pos_margin_mode == margin_mode {
+            return Ok(PositionConfig::Perpetual(config));
+        }
+
+        let open_orders = self.account_open_book.read().await.get_ins_orders_mut(instrument).map(|orders| orders.num_orders()).unwrap_or(0);
+        if open_orders > 0 {
+            return Err(ExchangeError::MarginModeSwitchForbidden(format!("{} has {} open orders", instrument, open_orders)));
+        }
+        config.pos_margin_mode = margin_mode.clone();
+
+        if let Some(mut position) = position {
+            let liquidation_threshold = self.config.liquidation_threshold;
+            let initial_margin = position.initial_margin();
+            let available = self.get_balance(instrument.margin_token())?.available;
+
+            let (margin, available_delta) = match margin_mode {
+                | PositionMarginMode::Isolated => {
+                    // 逐仓只能动用自身保证金，切换后不能立即低于维持保证金
+                    let mark_price = self.mark_price(instrument).await.unwrap_or(position.meta.current_symbol_price);
+                    if initial_margin + position.unrealised_pnl_at(mark_price) < position.maintenance_margin(liquidation_threshold) {
+                        return Err(ExchangeError::InsufficientMargin(format!("isolated margin {} cannot cover the maintenance margin", initial_margin)));
+                    }
+                    self.account_margin.fetch_sub(initial_margin, Ordering::SeqCst);
+                    position.isolated_margin = Some(initial_margin);
+                    (initial_margin, 0.0)
+                }
+                | PositionMarginMode::Cross => {
+                    let released = position.isolated_margin.take().unwrap_or(0.0) - initial_margin;
+                    if -released > available {
+                        return Err(ExchangeError::InsufficientBalance(instrument.margin_token().clone()));
+                    }
+                    self.account_margin.fetch_add(initial_margin, Ordering::SeqCst);
+                    (initial_margin + available + released, released)
+                }
+            };
+
+            let balance = self.apply_balance_delta(instrument.margin_token(), BalanceDelta { total: 0.0, available: available_delta });
+            position.pos_config.pos_margin_mode = margin_mode;
+            position.update_liquidation_price(position.compute_liquidation_price(margin, liquidation_threshold));
+            self.store_perpetual_position(position.clone()).await;
+            self.send_margin_update_events(&position, balance);
+        }
+
+        self.store_perpetual_config(instrument, side, config.clone()).await;
+        Ok(PositionConfig::Perpetual(config))
+    }
+
+    async fn set_margin_mode_and_respond(&mut self, instrument: &Instrument, side: Side, margin_mode: PositionMarginMode, response_tx: Sender<Result<PositionConfig, ExchangeError>>)
+    {
+        let result = self.set_margin_mode(instrument, side, margin_mode).await;
+        respond(response_tx, result);
+    }
+
+    async fn get_perpetual_position(&self, instrument: &Instrument, side: Side) -> Option<PerpetualPosition>
+    {
+        match side {
+            | Side::Buy => self.positions.perpetual_pos_long.read().await.get(instrument).cloned(),
+            | Side::Sell => self.positions.perpetual_pos_short.read().await.get(instrument).cloned(),
+        }
+    }
+
+    /// 按仓位自身的方向写回仓位列表
+    async fn store_perpetual_position(&self, position: PerpetualPosition)
+    {
+        let instrument = position.meta.instrument.clone();
+        match position.meta.side {
+            | Side::Buy => self.positions.perpetual_pos_long.write().await.insert(instrument, position),
+            | Side::Sell => self.positions.perpetual_pos_short.write().await.insert(instrument, position),
+        };
+    }
+
+    /// 取预设的仓位配置，没有预设时使用账户的全局配置
+    async fn perpetual_config_or_default(&self, instrument: &Instrument, side: Side) -> PerpetualPositionConfig
+    {
+        let config = match side {
+            | Side::Buy => self.positions.perpetual_pos_long_config.read().await.get(instrument).cloned(),
+            | Side::Sell => self.positions.perpetual_pos_short_config.read().await.get(instrument).cloned(),
+        };
+        config.unwrap_or_else(|| PerpetualPositionConfig { pos_margin_mode: self.config.global_position_margin_mode.clone(),
+                                                           leverage: self.config.global_leverage_rate,
+                                                           position_direction_mode: self.config.global_position_direction_mode.clone() })
+    }
+
+    async fn store_perpetual_config(&self, instrument: &Instrument, side: Side, config: PerpetualPositionConfig)
+    {
+        match side {
+            | Side::Buy => self.positions.perpetual_pos_long_config.write().await.insert(instrument.clone(), config),
+            | Side::Sell => self.positions.perpetual_pos_short_config.write().await.insert(instrument.clone(), config),
+        };
+    }
+
+    /// 标记价格取单层订单簿中的最新成交价，尚无行情时返回 `None`。
+    /// 行情数据中的交割合约不带到期时间，找不到该合约自身的订单簿时使用同一标的不带到期时间的订单簿。
+    async fn mark_price(&self, instrument: &Instrument) -> Option<f64>
+    {
+        let order_books = self.single_level_order_book.lock().await;
+        order_books.get(instrument)
+                   .or_else(|| order_books.get(&instrument.market_instrument()))
+                   .map(|order_book| order_book.latest_price)
+                   .filter(|price| *price > 0.0)
+    }
+
+    /// 保证金发生划转后，向客户端发送余额与仓位事件
+    fn send_margin_update_events(&self, position: &PerpetualPosition, balance: Balance)
+    {
+        let exchange_timestamp = self.exchange_timestamp.load(Ordering::SeqCst);
+        for kind in [AccountEventKind::Balance(TokenBalance::new(position.meta.instrument.margin_token().clone(), balance)),
+                     AccountEventKind::Position(Position::Perpetual(position.clone()))]
+        {
+            if let Err(err) = self.account_event_tx.send(AccountEvent { exchange_timestamp,
+                                                                        exchange: Exchange::Hourglass,
+                                                                        kind })
+            {
+                warn!("Client offline - Failed to send AccountEvent: {:?}", err);
             }
         }
     }
@@ -1125,8 +1702,8 @@ mod tests
 {
     use super::*;
     use crate::{
-        common::{order::identification::OrderId, token::Token, trade::ClientTradeId},
-        test_utils::create_test_account,
+        common::{balance::Balance, order::identification::OrderId, token::Token, trade::ClientTradeId},
+        test_utils::{create_test_account, create_test_order_open},
         Exchange,
     };
     // #[tokio::test]
@@ -1164,11 +1741,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 16999.0,
                                   size: 1.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         // 插入预先配置的多头仓位 PerpetualPositionConfig
         let instrument = trade.instrument.clone();
@@ -1204,11 +1785,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Sell,
                                   price: 100.0,
                                   size: 5.0,
-                                  fees: 0.05 };
+                                  fees: 0.05,
+                                  position_side: PositionSide::Both };
 
         // 使用与 `trade` 相同的 `instrument` 进行插入配置
         let instrument = trade.instrument.clone();
@@ -1244,11 +1829,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         // 插入多头仓位配置
         let instrument = trade.instrument.clone();
@@ -1268,11 +1857,15 @@ mod tests
                                              cid: None,
                                              instrument: Instrument { base: Token("BTC".to_string()),
                                                                       quote: Token("USDT".to_string()),
-                                                                      kind: InstrumentKind::Perpetual },
+                                                                      kind: InstrumentKind::Perpetual,
+                                                                      inverse: false,
+                                                                      expiry: None,
+                                                                      option_spec: None },
                                              side: Side::Buy,
                                              price: 100.0,
                                              size: 5.0,
-                                             fees: 0.05 };
+                                             fees: 0.05,
+                                             position_side: PositionSide::Both };
 
         // 更新现有仓位
         account.update_position_from_client_trade(additional_trade.clone()).await.unwrap();
@@ -1295,11 +1888,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         // 插入多头仓位配置
         let instrument = trade.instrument.clone();
@@ -1319,11 +1916,15 @@ mod tests
                                              cid: None,
                                              instrument: Instrument { base: Token("BTC".to_string()),
                                                                       quote: Token("USDT".to_string()),
-                                                                      kind: InstrumentKind::Perpetual },
+                                                                      kind: InstrumentKind::Perpetual,
+                                                                      inverse: false,
+                                                                      expiry: None,
+                                                                      option_spec: None },
                                              side: Side::Buy,
                                              price: 100.0,
                                              size: 5.0,
-                                             fees: 0.05 };
+                                             fees: 0.05,
+                                             position_side: PositionSide::Both };
 
         // 更新现有仓位
         account.update_position_from_client_trade(additional_trade.clone()).await.unwrap();
@@ -1346,11 +1947,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         // 插入多头仓位配置
         let instrument = trade.instrument.clone();
@@ -1370,11 +1975,15 @@ mod tests
                                              cid: None,
                                              instrument: Instrument { base: Token("BTC".to_string()),
                                                                       quote: Token("USDT".to_string()),
-                                                                      kind: InstrumentKind::Perpetual },
+                                                                      kind: InstrumentKind::Perpetual,
+                                                                      inverse: false,
+                                                                      expiry: None,
+                                                                      option_spec: None },
                                              side: Side::Buy,
                                              price: 100.0,
                                              size: 5.0,
-                                             fees: 0.05 };
+                                             fees: 0.05,
+                                             position_side: PositionSide::Both };
 
         // 更新现有仓位
         account.update_position_from_client_trade(additional_trade.clone()).await.unwrap();
@@ -1397,11 +2006,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         // 插入多头仓位配置
         let instrument = trade.instrument.clone();
@@ -1421,11 +2034,15 @@ mod tests
                                              cid: None,
                                              instrument: Instrument { base: Token("BTC".to_string()),
                                                                       quote: Token("USDT".to_string()),
-                                                                      kind: InstrumentKind::Perpetual },
+                                                                      kind: InstrumentKind::Perpetual,
+                                                                      inverse: false,
+                                                                      expiry: None,
+                                                                      option_spec: None },
                                              side: Side::Buy,
                                              price: 100.0,
                                              size: 5.0,
-                                             fees: 0.05 };
+                                             fees: 0.05,
+                                             position_side: PositionSide::Both };
 
         // 更新现有仓位
         account.update_position_from_client_trade(additional_trade.clone()).await.unwrap();
@@ -1448,11 +2065,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         // 插入多头仓位配置
         let instrument = trade.instrument.clone();
@@ -1473,11 +2094,15 @@ mod tests
                                           cid: None,
                                           instrument: Instrument { base: Token("BTC".to_string()),
                                                                    quote: Token("USDT".to_string()),
-                                                                   kind: InstrumentKind::Perpetual },
+                                                                   kind: InstrumentKind::Perpetual,
+                                                                   inverse: false,
+                                                                   expiry: None,
+                                                                   option_spec: None },
                                           side: Side::Sell,
                                           price: 100.0,
                                           size: 5.0,
-                                          fees: 0.05 };
+                                          fees: 0.05,
+                                          position_side: PositionSide::Both };
 
         account.update_position_from_client_trade(closing_trade.clone()).await.unwrap();
         // // 检查仓位是否部分平仓
@@ -1498,11 +2123,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Sell,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         // 插入多头仓位配置
         let instrument = trade.instrument.clone();
@@ -1521,11 +2150,15 @@ mod tests
                                           cid: None,
                                           instrument: Instrument { base: Token("BTC".to_string()),
                                                                    quote: Token("USDT".to_string()),
-                                                                   kind: InstrumentKind::Perpetual },
+                                                                   kind: InstrumentKind::Perpetual,
+                                                                   inverse: false,
+                                                                   expiry: None,
+                                                                   option_spec: None },
                                           side: Side::Buy,
                                           price: 100.0,
                                           size: 5.0,
-                                          fees: 0.05 };
+                                          fees: 0.05,
+                                          position_side: PositionSide::Both };
 
         account.update_position_from_client_trade(closing_trade.clone()).await.unwrap();
         // // 检查仓位是否部分平仓
@@ -1546,11 +2179,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         // 插入多头仓位配置
         let instrument = trade.instrument.clone();
@@ -1570,11 +2207,15 @@ mod tests
                                           cid: None,
                                           instrument: Instrument { base: Token("BTC".to_string()),
                                                                    quote: Token("USDT".to_string()),
-                                                                   kind: InstrumentKind::Perpetual },
+                                                                   kind: InstrumentKind::Perpetual,
+                                                                   inverse: false,
+                                                                   expiry: None,
+                                                                   option_spec: None },
                                           side: Side::Sell,
                                           price: 100.0,
                                           size: 5.0,
-                                          fees: 0.05 };
+                                          fees: 0.05,
+                                          position_side: PositionSide::Both };
 
         account.update_position_from_client_trade(closing_trade.clone()).await.unwrap();
         // 检查仓位是否部分平仓
@@ -1595,11 +2236,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         // 插入多头仓位配置
         let instrument = trade.instrument.clone();
@@ -1617,11 +2262,15 @@ mod tests
                                           cid: None,
                                           instrument: Instrument { base: Token("BTC".to_string()),
                                                                    quote: Token("USDT".to_string()),
-                                                                   kind: InstrumentKind::Perpetual },
+                                                                   kind: InstrumentKind::Perpetual,
+                                                                   inverse: false,
+                                                                   expiry: None,
+                                                                   option_spec: None },
                                           side: Side::Sell,
                                           price: 100.0,
                                           size: 10.0,
-                                          fees: 0.1 };
+                                          fees: 0.1,
+                                          position_side: PositionSide::Both };
 
         account.update_position_from_client_trade(closing_trade.clone()).await.unwrap();
 
@@ -1642,11 +2291,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         // 插入多头仓位配置
         let instrument = trade.instrument.clone();
@@ -1664,11 +2317,15 @@ mod tests
                                           cid: None,
                                           instrument: Instrument { base: Token("BTC".to_string()),
                                                                    quote: Token("USDT".to_string()),
-                                                                   kind: InstrumentKind::Perpetual },
+                                                                   kind: InstrumentKind::Perpetual,
+                                                                   inverse: false,
+                                                                   expiry: None,
+                                                                   option_spec: None },
                                           side: Side::Sell,
                                           price: 100.0,
                                           size: 15.0, // 卖出 15.0 超过当前的多头仓位
-                                          fees: 0.15 };
+                                          fees: 0.15,
+                                          position_side: PositionSide::Both };
 
         account.update_position_from_client_trade(reverse_trade.clone()).await.unwrap();
 
@@ -1696,11 +2353,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         // 插入多头仓位配置
         let instrument = trade.instrument.clone();
@@ -1718,11 +2379,15 @@ mod tests
                                           cid: None,
                                           instrument: Instrument { base: Token("BTC".to_string()),
                                                                    quote: Token("USDT".to_string()),
-                                                                   kind: InstrumentKind::Perpetual },
+                                                                   kind: InstrumentKind::Perpetual,
+                                                                   inverse: false,
+                                                                   expiry: None,
+                                                                   option_spec: None },
                                           side: Side::Sell,
                                           price: 100.0,
                                           size: 15.0, // 卖出 15.0 超过当前的多头仓位
-                                          fees: 0.15 };
+                                          fees: 0.15,
+                                          position_side: PositionSide::Both };
 
         let _ = account.update_position_from_client_trade(reverse_trade.clone()).await;
 
@@ -1750,11 +2415,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         // 插入多头仓位配置
         let instrument = trade.instrument.clone();
@@ -1772,11 +2441,15 @@ mod tests
                                           cid: None,
                                           instrument: Instrument { base: Token("BTC".to_string()),
                                                                    quote: Token("USDT".to_string()),
-                                                                   kind: InstrumentKind::Perpetual },
+                                                                   kind: InstrumentKind::Perpetual,
+                                                                   inverse: false,
+                                                                   expiry: None,
+                                                                   option_spec: None },
                                           side: Side::Sell,
                                           price: 100.0,
                                           size: 15.0, // 卖出 15.0 超过当前的多头仓位
-                                          fees: 0.15 };
+                                          fees: 0.15,
+                                          position_side: PositionSide::Both };
 
         let result = account.update_position_from_client_trade(reverse_trade.clone()).await;
         assert!(matches!(result, Err(ExchangeError::ConfigInheritanceNotAllowed)), "Unexpected error: {:?}", result);
@@ -1794,11 +2467,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("RRR".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Spot /* Spot Position is either not developed or not supported. */ },
+                                                           kind: InstrumentKind::Spot, // Spot Position is either not developed or not supported.
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Sell,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         // 执行管理仓位逻辑，应该返回错误
         let result = account.update_position_from_client_trade(trade.clone()).await;
@@ -1817,11 +2494,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         let instrument = trade.instrument.clone();
         let preconfig = PerpetualPositionConfig { pos_margin_mode: PositionMarginMode::Cross,
@@ -1848,11 +2529,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         let instrument = trade.instrument.clone();
         let preconfig = PerpetualPositionConfig { pos_margin_mode: PositionMarginMode::Isolated,
@@ -1879,11 +2564,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         let instrument = trade.instrument.clone();
         let preconfig = PerpetualPositionConfig { pos_margin_mode: PositionMarginMode::Isolated,
@@ -1925,11 +2614,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         let instrument = trade.instrument.clone();
         let preconfig = PerpetualPositionConfig { pos_margin_mode: PositionMarginMode::Isolated,
@@ -1948,11 +2641,15 @@ mod tests
                                           cid: None,
                                           instrument: Instrument { base: Token("BTC".to_string()),
                                                                    quote: Token("USDT".to_string()),
-                                                                   kind: InstrumentKind::Perpetual },
+                                                                   kind: InstrumentKind::Perpetual,
+                                                                   inverse: false,
+                                                                   expiry: None,
+                                                                   option_spec: None },
                                           side: Side::Sell,
                                           price: 100.0,
                                           size: 5.0,
-                                          fees: 0.05 };
+                                          fees: 0.05,
+                                          position_side: PositionSide::Both };
 
         account.update_position_from_client_trade(closing_trade.clone()).await.unwrap();
 
@@ -1976,11 +2673,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Sell,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         let instrument = trade.instrument.clone();
         let preconfig = PerpetualPositionConfig { pos_margin_mode: PositionMarginMode::Isolated,
@@ -2000,7 +2701,8 @@ mod tests
                                           side: Side::Buy,
                                           price: 100.0,
                                           size: 5.0,
-                                          fees: 0.05 };
+                                          fees: 0.05,
+                                          position_side: PositionSide::Both };
 
         account.update_position_from_client_trade(closing_trade.clone()).await.unwrap();
 
@@ -2023,11 +2725,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Sell,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         let instrument = trade.instrument.clone();
         let preconfig = PerpetualPositionConfig { pos_margin_mode: PositionMarginMode::Isolated,
@@ -2047,7 +2753,8 @@ mod tests
                                           side: Side::Buy,
                                           price: 100.0,
                                           size: 10.0,
-                                          fees: 0.1 };
+                                          fees: 0.1,
+                                          position_side: PositionSide::Both };
 
         account.update_position_from_client_trade(closing_trade.clone()).await.unwrap();
 
@@ -2068,11 +2775,15 @@ mod tests
                                   cid: None,
                                   instrument: Instrument { base: Token("BTC".to_string()),
                                                            quote: Token("USDT".to_string()),
-                                                           kind: InstrumentKind::Perpetual },
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
                                   side: Side::Buy,
                                   price: 100.0,
                                   size: 10.0,
-                                  fees: 0.1 };
+                                  fees: 0.1,
+                                  position_side: PositionSide::Both };
 
         let instrument = trade.instrument.clone();
         let preconfig = PerpetualPositionConfig { pos_margin_mode: PositionMarginMode::Cross,
@@ -2088,6 +2799,9 @@ mod tests
         let liquidation_price = pos.liquidation_price;
         info!("current liquidation_price is {:?}", liquidation_price);
 
+        // 全仓模式会动用可用余额抵御亏损，这里假设可用余额已被其他仓位占满
+        account.balances.insert(Token::from("USDT"), Balance::new(10_000.0, 0.0));
+
         // 市场触发爆仓
         let liquidation_trade = MarketTrade { timestamp: 1690000100,
                                               price: 5.0, // 低于清算价格
@@ -2102,4 +2816,477 @@ mod tests
         let positions = account.positions.perpetual_pos_long.read().await;
         assert!(!positions.contains_key(&trade.instrument));
     }
+
+    fn liquidation_test_trade(margin_mode: PositionMarginMode) -> (ClientTrade, PerpetualPositionConfig)
+    {
+        let trade = ClientTrade { exchange: Exchange::Hourglass,
+                                  timestamp: 1690000000,
+                                  trade_id: ClientTradeId(5),
+                                  order_id: Some(OrderId(5)),
+                                  cid: None,
+                                  instrument: Instrument { base: Token("BTC".to_string()),
+                                                           quote: Token("USDT".to_string()),
+                                                           kind: InstrumentKind::Perpetual,
+                                                           inverse: false,
+                                                           expiry: None,
+                                                           option_spec: None },
+                                  side: Side::Buy,
+                                  price: 100.0,
+                                  size: 10.0,
+                                  fees: 0.0,
+                                  position_side: PositionSide::Both };
+        let preconfig = PerpetualPositionConfig { pos_margin_mode: margin_mode,
+                                                  leverage: 10.0,
+                                                  position_direction_mode: PositionDirectionMode::Net };
+        (trade, preconfig)
+    }
+
+    #[tokio::test]
+    async fn test_partial_liquidation_in_isolated_mode()
+    {
+        let mut account = create_test_account().await;
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+        account.account_event_tx = event_tx;
+
+        let (trade, preconfig) = liquidation_test_trade(PositionMarginMode::Isolated);
+        account.positions.perpetual_pos_long_config.write().await.insert(trade.instrument.clone(), preconfig);
+        let pos = account.create_perpetual_position(trade.clone(), PositionHandling::OpenBrandNewPosition).await.unwrap();
+        assert_eq!(pos.liquidation_price, 91.0);
+
+        // 价格略低于强平价格，只需要部分强平
+        let market_trade = MarketTrade { timestamp: 1690000100,
+                                         price: 90.5,
+                                         exchange: "binance-futures".to_string(),
+                                         symbol: "BTCUSDT".to_string(),
+                                         amount: 10.0,
+                                         side: "Sell".to_string() };
+        account.check_and_handle_liquidation(&market_trade).await.unwrap();
+
+        let position = account.positions.perpetual_pos_long.read().await.get(&trade.instrument).cloned().unwrap();
+        assert!(position.meta.current_size > 0.0 && position.meta.current_size < 10.0);
+        let closed_size = 10.0 - position.meta.current_size;
+        let fee = closed_size * 90.5 * 0.005;
+        assert!((position.isolated_margin.unwrap() - (100.0 - 9.5 * closed_size - fee)).abs() < 1e-9);
+        assert!((position.meta.current_fees_total - fee).abs() < 1e-9);
+        // 强平后仓位恰好回到维持保证金，强平价格被重新计算为当前价格
+        assert!((position.liquidation_price - 90.5).abs() < 1e-9);
+
+        let usdt = account.get_balance(&Token::from("USDT")).unwrap();
+        assert!((usdt.total - (10_000.0 - 9.5 * closed_size - fee)).abs() < 1e-9);
+        assert_eq!(usdt.available, 10_000.0);
+        drop(usdt);
+
+        let mut liquidations = vec![];
+        while let Ok(event) = event_rx.try_recv() {
+            if let AccountEventKind::Liquidation(liquidation) = event.kind {
+                liquidations.push(liquidation);
+            }
+        }
+        assert_eq!(liquidations.len(), 1);
+        assert_eq!(liquidations[0].side, Side::Buy);
+        assert_eq!(liquidations[0].margin_mode, PositionMarginMode::Isolated);
+        assert!((liquidations[0].closed_size - closed_size).abs() < 1e-9);
+        assert!((liquidations[0].remaining_size - position.meta.current_size).abs() < 1e-9);
+        assert!((liquidations[0].fee - fee).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_partial_liquidation_in_cross_mode()
+    {
+        let mut account = create_test_account().await;
+        let (trade, preconfig) = liquidation_test_trade(PositionMarginMode::Cross);
+        account.positions.perpetual_pos_long_config.write().await.insert(trade.instrument.clone(), preconfig);
+        account.create_perpetual_position(trade.clone(), PositionHandling::OpenBrandNewPosition).await.unwrap();
+        account.balances.insert(Token::from("USDT"), Balance::new(10_000.0, 0.0));
+
+        let market_trade = MarketTrade { timestamp: 1690000100,
+                                         price: 90.5,
+                                         exchange: "binance-futures".to_string(),
+                                         symbol: "BTCUSDT".to_string(),
+                                         amount: 10.0,
+                                         side: "Sell".to_string() };
+        account.check_and_handle_liquidation(&market_trade).await.unwrap();
+
+        let position = account.positions.perpetual_pos_long.read().await.get(&trade.instrument).cloned().unwrap();
+        assert!(position.meta.current_size > 0.0 && position.meta.current_size < 10.0);
+        let closed_size = 10.0 - position.meta.current_size;
+        let fee = closed_size * 90.5 * 0.005;
+
+        // 释放的保证金扣除亏损和强平费后退回可用余额
+        let usdt = account.get_balance(&Token::from("USDT")).unwrap();
+        assert!((usdt.total - (10_000.0 - 9.5 * closed_size - fee)).abs() < 1e-9);
+        assert!((usdt.available - (10.0 * closed_size - 9.5 * closed_size - fee)).abs() < 1e-9);
+        drop(usdt);
+        assert!((account.account_margin.load(Ordering::SeqCst) - position.initial_margin()).abs() < 1e-9);
+        assert!((position.liquidation_price - 90.5).abs() < 1e-9);
+
+        // 价格继续下跌，仓位再次被部分强平
+        let market_trade = MarketTrade { price: 90.0, ..market_trade };
+        account.check_and_handle_liquidation(&market_trade).await.unwrap();
+        let remaining = account.positions.perpetual_pos_long.read().await.get(&trade.instrument).cloned().unwrap();
+        assert!(remaining.meta.current_size < position.meta.current_size);
+    }
+
+    #[tokio::test]
+    async fn test_liquidation_price_refreshed_when_cross_margin_sufficient()
+    {
+        let mut account = create_test_account().await;
+        let (trade, preconfig) = liquidation_test_trade(PositionMarginMode::Cross);
+        account.positions.perpetual_pos_long_config.write().await.insert(trade.instrument.clone(), preconfig);
+        account.create_perpetual_position(trade.clone(), PositionHandling::OpenBrandNewPosition).await.unwrap();
+
+        // 可用余额足以覆盖亏损，不应强平
+        let market_trade = MarketTrade { timestamp: 1690000100,
+                                         price: 90.5,
+                                         exchange: "binance-futures".to_string(),
+                                         symbol: "BTCUSDT".to_string(),
+                                         amount: 10.0,
+                                         side: "Sell".to_string() };
+        account.check_and_handle_liquidation(&market_trade).await.unwrap();
+
+        let position = account.positions.perpetual_pos_long.read().await.get(&trade.instrument).cloned().unwrap();
+        assert_eq!(position.meta.current_size, 10.0);
+        assert!(position.liquidation_price < 90.5);
+    }
+
+    #[tokio::test]
+    async fn test_liquidation_fee_flows_into_risk_reserve()
+    {
+        let mut account = create_test_account().await;
+        let (trade, preconfig) = liquidation_test_trade(PositionMarginMode::Isolated);
+        account.positions.perpetual_pos_long_config.write().await.insert(trade.instrument.clone(), preconfig);
+        account.create_perpetual_position(trade.clone(), PositionHandling::OpenBrandNewPosition).await.unwrap();
+
+        let market_trade = MarketTrade { timestamp: 1690000100,
+                                         price: 90.5,
+                                         exchange: "binance-futures".to_string(),
+                                         symbol: "BTCUSDT".to_string(),
+                                         amount: 10.0,
+                                         side: "Sell".to_string() };
+        account.check_and_handle_liquidation(&market_trade).await.unwrap();
+
+        let position = account.positions.perpetual_pos_long.read().await.get(&trade.instrument).cloned().unwrap();
+        let fee = (10.0 - position.meta.current_size) * 90.5 * 0.005;
+        assert!((account.risk_reserve.lock().await.total_reserve - fee * 0.5).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_bankrupt_liquidation_drawn_from_risk_reserve()
+    {
+        let mut account = create_test_account().await;
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+        account.account_event_tx = event_tx;
+        account.risk_reserve.lock().await.contribute(100.0);
+
+        let (trade, preconfig) = liquidation_test_trade(PositionMarginMode::Isolated);
+        account.positions.perpetual_pos_long_config.write().await.insert(trade.instrument.clone(), preconfig);
+        account.create_perpetual_position(trade.clone(), PositionHandling::OpenBrandNewPosition).await.unwrap();
+
+        // 亏损 150，超出逐仓保证金 100，穿仓缺口 50 由风险准备金弥补
+        let market_trade = MarketTrade { timestamp: 1690000100,
+                                         price: 85.0,
+                                         exchange: "binance-futures".to_string(),
+                                         symbol: "BTCUSDT".to_string(),
+                                         amount: 10.0,
+                                         side: "Sell".to_string() };
+        account.check_and_handle_liquidation(&market_trade).await.unwrap();
+
+        assert!(!account.positions.perpetual_pos_long.read().await.contains_key(&trade.instrument));
+        assert!((account.risk_reserve.lock().await.total_reserve - 50.0).abs() < 1e-9);
+        assert!((account.get_balance(&Token::from("USDT")).unwrap().total - 9_900.0).abs() < 1e-9);
+
+        let mut auto_deleveraged = false;
+        while let Ok(event) = event_rx.try_recv() {
+            if let AccountEventKind::AutoDeleveraged(_) = event.kind {
+                auto_deleveraged = true;
+            }
+        }
+        assert!(!auto_deleveraged);
+    }
+
+    #[tokio::test]
+    async fn test_no_auto_deleverage_in_net_mode()
+    {
+        let mut account = create_test_account().await;
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+        account.account_event_tx = event_tx;
+        account.risk_reserve.lock().await.contribute(20.0);
+
+        let (trade, preconfig) = liquidation_test_trade(PositionMarginMode::Isolated);
+        account.positions.perpetual_pos_long_config.write().await.insert(trade.instrument.clone(), preconfig);
+        account.create_perpetual_position(trade.clone(), PositionHandling::OpenBrandNewPosition).await.unwrap();
+
+        // 单向持仓模式下穿仓缺口 50 只能由风险准备金弥补 20，不触发自动减仓
+        let market_trade = MarketTrade { timestamp: 1690000100,
+                                         price: 85.0,
+                                         exchange: "binance-futures".to_string(),
+                                         symbol: "BTCUSDT".to_string(),
+                                         amount: 10.0,
+                                         side: "Sell".to_string() };
+        account.check_and_handle_liquidation(&market_trade).await.unwrap();
+
+        assert!(!account.positions.perpetual_pos_long.read().await.contains_key(&trade.instrument));
+        assert!(account.risk_reserve.lock().await.is_exhausted());
+        while let Ok(event) = event_rx.try_recv() {
+            assert!(!matches!(event.kind, AccountEventKind::AutoDeleveraged(_)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auto_deleverage_when_risk_reserve_exhausted()
+    {
+        let mut account = create_test_account().await;
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+        account.account_event_tx = event_tx;
+        account.risk_reserve.lock().await.contribute(20.0);
+
+        let (long_trade, mut preconfig) = liquidation_test_trade(PositionMarginMode::Isolated);
+        preconfig.position_direction_mode = PositionDirectionMode::LongShort;
+        let instrument = long_trade.instrument.clone();
+        account.positions.perpetual_pos_long_config.write().await.insert(instrument.clone(), preconfig.clone());
+        account.positions.perpetual_pos_short_config.write().await.insert(instrument.clone(), preconfig);
+        account.create_perpetual_position(long_trade.clone(), PositionHandling::OpenBrandNewPosition).await.unwrap();
+
+        let short_trade = ClientTrade { side: Side::Sell,
+                                        size: 5.0,
+                                        trade_id: ClientTradeId(6),
+                                        ..long_trade.clone() };
+        account.create_perpetual_position(short_trade, PositionHandling::OpenBrandNewPosition).await.unwrap();
+
+        // 多头穿仓 50，风险准备金只有 20，剩余 30 由盈利 75 的空头分摊
+        let market_trade = MarketTrade { timestamp: 1690000100,
+                                         price: 85.0,
+                                         exchange: "binance-futures".to_string(),
+                                         symbol: "BTCUSDT".to_string(),
+                                         amount: 10.0,
+                                         side: "Sell".to_string() };
+        account.check_and_handle_liquidation(&market_trade).await.unwrap();
+
+        assert!(!account.positions.perpetual_pos_long.read().await.contains_key(&instrument));
+        assert!(!account.positions.perpetual_pos_short.read().await.contains_key(&instrument));
+        assert!(account.risk_reserve.lock().await.is_exhausted());
+        // 多头亏损 100，空头利润 75 扣除分摊的 30
+        assert!((account.get_balance(&Token::from("USDT")).unwrap().total - (10_000.0 - 100.0 + 45.0)).abs() < 1e-9);
+
+        let mut deleverages = vec![];
+        while let Ok(event) = event_rx.try_recv() {
+            if let AccountEventKind::AutoDeleveraged(deleverage) = event.kind {
+                deleverages.push(deleverage);
+            }
+        }
+        assert_eq!(deleverages.len(), 1);
+        assert_eq!(deleverages[0].side, Side::Sell);
+        assert_eq!(deleverages[0].closed_size, 5.0);
+        assert!((deleverages[0].socialised_loss - 30.0).abs() < 1e-9);
+        assert!((deleverages[0].realised_pnl - 45.0).abs() < 1e-9);
+        assert!((deleverages[0].ranking_score - 15.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_adjust_isolated_margin()
+    {
+        let mut account = create_test_account().await;
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+        account.account_event_tx = event_tx;
+
+        let (trade, preconfig) = liquidation_test_trade(PositionMarginMode::Isolated);
+        let instrument = trade.instrument.clone();
+        account.positions.perpetual_pos_long_config.write().await.insert(instrument.clone(), preconfig);
+        account.create_perpetual_position(trade, PositionHandling::OpenBrandNewPosition).await.unwrap();
+
+        // 追加 50 保证金，强平价格随之下移
+        let position = account.adjust_isolated_margin(&instrument, Side::Buy, 50.0).await.unwrap();
+        assert_eq!(position.isolated_margin, Some(150.0));
+        assert!((position.liquidation_price - 86.0).abs() < 1e-9);
+        assert_eq!(account.get_balance(&Token::from("USDT")).unwrap().available, 9_950.0);
+
+        // 提取多余的保证金
+        let position = account.adjust_isolated_margin(&instrument, Side::Buy, -30.0).await.unwrap();
+        assert_eq!(position.isolated_margin, Some(120.0));
+        assert_eq!(account.get_balance(&Token::from("USDT")).unwrap().available, 9_980.0);
+
+        // 提取后低于初始保证金 100，应被拒绝
+        let result = account.adjust_isolated_margin(&instrument, Side::Buy, -30.0).await;
+        assert!(matches!(result, Err(ExchangeError::InsufficientMargin(_))));
+        assert_eq!(account.positions.perpetual_pos_long.read().await.get(&instrument).unwrap().isolated_margin, Some(120.0));
+
+        let mut kinds = vec![];
+        while let Ok(event) = event_rx.try_recv() {
+            kinds.push(event.kind);
+        }
+        assert_eq!(kinds.len(), 4);
+        assert!(matches!(kinds[0], AccountEventKind::Balance(_)));
+        assert!(matches!(kinds[1], AccountEventKind::Position(Position::Perpetual(_))));
+    }
+
+    #[tokio::test]
+    async fn test_adjust_isolated_margin_rejects_cross_position()
+    {
+        let mut account = create_test_account().await;
+        let (trade, preconfig) = liquidation_test_trade(PositionMarginMode::Cross);
+        let instrument = trade.instrument.clone();
+        account.positions.perpetual_pos_long_config.write().await.insert(instrument.clone(), preconfig);
+        account.create_perpetual_position(trade, PositionHandling::OpenBrandNewPosition).await.unwrap();
+
+        assert!(account.adjust_isolated_margin(&instrument, Side::Buy, 50.0).await.is_err());
+        assert!(matches!(account.adjust_isolated_margin(&instrument, Side::Sell, 50.0).await,
+                         Err(ExchangeError::AttemptToUpdateNonExistingPosition)));
+    }
+
+    #[tokio::test]
+    async fn test_set_leverage_on_open_isolated_position()
+    {
+        let mut account = create_test_account().await;
+        account.config.global_leverage_rate = 20.0;
+        let (trade, preconfig) = liquidation_test_trade(PositionMarginMode::Isolated);
+        let instrument = trade.instrument.clone();
+        account.positions.perpetual_pos_long_config.write().await.insert(instrument.clone(), preconfig);
+        account.create_perpetual_position(trade, PositionHandling::OpenBrandNewPosition).await.unwrap();
+
+        assert!(matches!(account.set_leverage(&instrument, Side::Buy, 50.0).await, Err(ExchangeError::InvalidLeverage(_))));
+
+        // 价格跌到 92，未实现亏损 80，提高到 20 倍后保证金只剩 50，低于维持保证金
+        account.positions.perpetual_pos_long.write().await.get_mut(&instrument).unwrap().meta.current_symbol_price = 92.0;
+        let result = account.set_leverage(&instrument, Side::Buy, 20.0).await;
+        assert!(matches!(result, Err(ExchangeError::InsufficientMargin(_))));
+        assert_eq!(account.positions.perpetual_pos_long.read().await.get(&instrument).unwrap().pos_config.leverage(), 10.0);
+
+        // 降低到 5 倍，需要额外冻结 100 的保证金
+        let config = account.set_leverage(&instrument, Side::Buy, 5.0).await.unwrap();
+        assert!(matches!(config, PositionConfig::Perpetual(ref config) if config.leverage() == 5.0));
+        let position = account.positions.perpetual_pos_long.read().await.get(&instrument).cloned().unwrap();
+        assert_eq!(position.isolated_margin, Some(200.0));
+        assert!((position.liquidation_price - 82.0).abs() < 1e-9);
+        assert_eq!(account.get_balance(&Token::from("USDT")).unwrap().available, 9_900.0);
+        assert_eq!(account.positions.perpetual_pos_long_config.read().await.get(&instrument).unwrap().leverage(), 5.0);
+    }
+
+    #[tokio::test]
+    async fn test_set_leverage_on_open_cross_position()
+    {
+        let mut account = create_test_account().await;
+        account.config.global_leverage_rate = 20.0;
+        let (trade, preconfig) = liquidation_test_trade(PositionMarginMode::Cross);
+        let instrument = trade.instrument.clone();
+        account.positions.perpetual_pos_long_config.write().await.insert(instrument.clone(), preconfig);
+        account.create_perpetual_position(trade, PositionHandling::OpenBrandNewPosition).await.unwrap();
+        let account_margin = account.account_margin.load(Ordering::SeqCst);
+
+        // 提高到 20 倍，释放 50 的保证金
+        account.set_leverage(&instrument, Side::Buy, 20.0).await.unwrap();
+        assert_eq!(account.get_balance(&Token::from("USDT")).unwrap().available, 10_050.0);
+        assert_eq!(account.account_margin.load(Ordering::SeqCst), account_margin - 50.0);
+        let position = account.positions.perpetual_pos_long.read().await.get(&instrument).cloned().unwrap();
+        assert_eq!(position.initial_margin(), 50.0);
+        assert_eq!(position.isolated_margin, None);
+    }
+
+    #[tokio::test]
+    async fn test_set_margin_mode_on_open_position()
+    {
+        let mut account = create_test_account().await;
+        let (trade, preconfig) = liquidation_test_trade(PositionMarginMode::Cross);
+        let instrument = trade.instrument.clone();
+        account.positions.perpetual_pos_long_config.write().await.insert(instrument.clone(), preconfig);
+        account.create_perpetual_position(trade, PositionHandling::OpenBrandNewPosition).await.unwrap();
+        let account_margin = account.account_margin.load(Ordering::SeqCst);
+
+        // 全仓切换为逐仓，初始保证金 100 转为仓位的逐仓保证金
+        account.set_margin_mode(&instrument, Side::Buy, PositionMarginMode::Isolated).await.unwrap();
+        let position = account.positions.perpetual_pos_long.read().await.get(&instrument).cloned().unwrap();
+        assert_eq!(position.pos_config.margin_mode(), &PositionMarginMode::Isolated);
+        assert_eq!(position.isolated_margin, Some(100.0));
+        assert!((position.liquidation_price - 91.0).abs() < 1e-9);
+        assert_eq!(account.account_margin.load(Ordering::SeqCst), account_margin - 100.0);
+
+        // 追加的逐仓保证金在切回全仓时退回可用余额
+        account.adjust_isolated_margin(&instrument, Side::Buy, 50.0).await.unwrap();
+        account.set_margin_mode(&instrument, Side::Buy, PositionMarginMode::Cross).await.unwrap();
+        let position = account.positions.perpetual_pos_long.read().await.get(&instrument).cloned().unwrap();
+        assert_eq!(position.pos_config.margin_mode(), &PositionMarginMode::Cross);
+        assert_eq!(position.isolated_margin, None);
+        assert_eq!(account.get_balance(&Token::from("USDT")).unwrap().available, 10_000.0);
+        assert_eq!(account.account_margin.load(Ordering::SeqCst), account_margin);
+        assert_eq!(account.positions.perpetual_pos_long_config.read().await.get(&instrument).unwrap().margin_mode(), &PositionMarginMode::Cross);
+    }
+
+    #[tokio::test]
+    async fn test_set_margin_mode_rejected_with_open_orders()
+    {
+        let mut account = create_test_account().await;
+        let instrument = Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual));
+        account.positions.perpetual_pos_long_config.write().await.insert(instrument.clone(),
+                                                                          PerpetualPositionConfig { pos_margin_mode: PositionMarginMode::Isolated,
+                                                                                                    leverage: 1.0,
+                                                                                                    position_direction_mode: PositionDirectionMode::Net });
+        account.account_open_book.write().await.get_ins_orders_mut(&instrument).unwrap().add_order_open(create_test_order_open(Side::Buy, 100.0, 1.0));
+
+        let result = account.set_margin_mode(&instrument, Side::Buy, PositionMarginMode::Cross).await;
+        assert!(matches!(result, Err(ExchangeError::MarginModeSwitchForbidden(_))));
+        assert_eq!(account.positions.perpetual_pos_long_config.read().await.get(&instrument).unwrap().margin_mode(), &PositionMarginMode::Isolated);
+
+        // 保证金模式不变时直接返回当前配置
+        assert!(account.set_margin_mode(&instrument, Side::Buy, PositionMarginMode::Isolated).await.is_ok());
+    }
+
+    async fn perpetual_position_sizes(account: &HourglassAccount, instrument: &Instrument) -> (Option<f64>, Option<f64>)
+    {
+        let long_size = account.positions.perpetual_pos_long.read().await.get(instrument).map(|position| position.meta.current_size);
+        let short_size = account.positions.perpetual_pos_short.read().await.get(instrument).map(|position| position.meta.current_size);
+        (long_size, short_size)
+    }
+
+    #[tokio::test]
+    async fn test_position_side_across_margin_and_direction_modes()
+    {
+        for margin_mode in [PositionMarginMode::Cross, PositionMarginMode::Isolated] {
+            for direction_mode in [PositionDirectionMode::Net, PositionDirectionMode::LongShort] {
+                let mut account = create_test_account().await;
+                let (trade, mut preconfig) = liquidation_test_trade(margin_mode.clone());
+                preconfig.position_direction_mode = direction_mode.clone();
+                let instrument = trade.instrument.clone();
+                account.positions.perpetual_pos_long_config.write().await.insert(instrument.clone(), preconfig.clone());
+                account.positions.perpetual_pos_short_config.write().await.insert(instrument.clone(), preconfig);
+
+                let (long_side, short_side) = match direction_mode {
+                    | PositionDirectionMode::Net => (PositionSide::Both, PositionSide::Both),
+                    | PositionDirectionMode::LongShort => (PositionSide::Long, PositionSide::Short),
+                };
+                let fill = |side: Side, position_side: PositionSide, size: f64| ClientTrade { side,
+                                                                                            position_side,
+                                                                                            size,
+                                                                                            ..trade.clone() };
+
+                // 买入 10 开多，再卖出 4
+                account.update_position_from_client_trade(fill(Side::Buy, long_side, 10.0)).await.unwrap();
+                account.update_position_from_client_trade(fill(Side::Sell, short_side, 4.0)).await.unwrap();
+
+                match direction_mode {
+                    | PositionDirectionMode::Net => {
+                        // 单向持仓：卖出减少多头仓位
+                        assert_eq!(perpetual_position_sizes(&account, &instrument).await, (Some(6.0), None));
+                    }
+                    | PositionDirectionMode::LongShort => {
+                        // 双向持仓：卖出开空，多头仓位不受影响
+                        assert_eq!(perpetual_position_sizes(&account, &instrument).await, (Some(10.0), Some(4.0)));
+
+                        // 卖出平多只减少多头仓位
+                        account.update_position_from_client_trade(fill(Side::Sell, PositionSide::Long, 4.0)).await.unwrap();
+                        assert_eq!(perpetual_position_sizes(&account, &instrument).await, (Some(6.0), Some(4.0)));
+
+                        // 平空数量超过空头仓位时不会反向开多
+                        let result = account.update_position_from_client_trade(fill(Side::Buy, PositionSide::Short, 5.0)).await;
+                        assert!(matches!(result, Err(ExchangeError::InvalidTradeSize)));
+
+                        account.update_position_from_client_trade(fill(Side::Buy, PositionSide::Short, 4.0)).await.unwrap();
+                        assert_eq!(perpetual_position_sizes(&account, &instrument).await, (Some(6.0), None));
+                    }
+                }
+
+                let long_position = account.positions.perpetual_pos_long.read().await.get(&instrument).cloned().unwrap();
+                assert_eq!(long_position.pos_config.margin_mode(), &margin_mode);
+                assert_eq!(long_position.position_side(), long_side);
+            }
+        }
+    }
 }
@@ -0,0 +1,482 @@
+use crate::{
+    common::{
+        account_positions::PositionDirectionMode,
+        instrument::{kind::InstrumentKind, Instrument},
+        order::{states::request_open::RequestOpen, Order},
+        Side,
+    },
+    error::ExchangeError,
+    hourglass::account::{
+        account_handlers::{balance_handler::BalanceHandler, position_handler::PositionHandler},
+        HourglassAccount,
+    },
+};
+use async_trait::async_trait;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::atomic::Ordering,
+};
+
+/// 按交易所时间统计最近一秒内请求数的滑动窗口。
+#[derive(Clone, Debug, Default)]
+pub struct OrderRateLimiter
+{
+    request_timestamps: VecDeque<i64>, // 窗口内每个请求的交易所时间戳（毫秒）
+}
+
+impl OrderRateLimiter
+{
+    const WINDOW_MS: i64 = 1_000;
+
+    /// 在 `timestamp` 时刻登记一次请求。窗口内的请求数已达到 `max_requests` 时返回 `false`，且不登记该请求。
+    pub fn try_acquire(&mut self, timestamp: i64, max_requests: usize) -> bool
+    {
+        while let Some(&earliest) = self.request_timestamps.front() {
+            if timestamp - earliest < Self::WINDOW_MS {
+                break;
+            }
+            self.request_timestamps.pop_front();
+        }
+
+        if self.request_timestamps.len() >= max_requests {
+            return false;
+        }
+        self.request_timestamps.push_back(timestamp);
+        true
+    }
+}
+
+/// 单个金融工具上的潜在敞口，包含已有仓位与全部挂单，均以 [`Instrument::margin_token`] 计的名义价值计。
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InstrumentExposure
+{
+    pub long_notional: f64,  // 多头仓位与买单的名义价值
+    pub short_notional: f64, // 空头仓位与卖单的名义价值
+}
+
+impl InstrumentExposure
+{
+    pub fn add(&mut self, side: Side, notional: f64)
+    {
+        match side {
+            | Side::Buy => self.long_notional += notional,
+            | Side::Sell => self.short_notional += notional,
+        }
+    }
+
+    pub fn notional(&self, side: Side) -> f64
+    {
+        match side {
+            | Side::Buy => self.long_notional,
+            | Side::Sell => self.short_notional,
+        }
+    }
+
+    /// 单向持仓模式下多空相互抵消，只保留较大一侧的差额。
+    pub fn netted(&self) -> Self
+    {
+        let net = self.long_notional - self.short_notional;
+        Self { long_notional: net.max(0.0),
+               short_notional: (-net).max(0.0) }
+    }
+}
+
+#[async_trait]
+pub trait RiskHandler
+{
+    /// 汇总每个金融工具的潜在敞口：仓位按标记价格计，挂单按剩余数量与挂单价格计。
+    async fn compute_potential_exposures(&self) -> HashMap<Instrument, InstrumentExposure>;
+
+    /// 下单前按 [`PositionLimits`](crate::hourglass::account::account_config::PositionLimits) 检查订单，
+    /// 假设新订单与现有挂单全部成交。减仓单与平仓单不会增加敞口，不做检查。
+    /// 账户级敞口只汇总与订单同一保证金币种的金融工具，按各金融工具的持仓方向模式，单向持仓的多空先相互抵消。
+    async fn check_position_limits(&self, order: &Order<RequestOpen>) -> Result<(), ExchangeError>;
+
+    /// 按 [`OrderRiskLimits::max_orders_per_second`](crate::hourglass::account::account_config::OrderRiskLimits) 登记一次下单或撤单请求，
+    /// 超过频率限制时返回 [`ExchangeError::ApiLimitReached`]。
+    fn check_order_rate(&mut self) -> Result<(), ExchangeError>;
+
+    /// 下单前的风控网关：依次检查请求频率、单个金融工具的挂单数、订单名义价值，以及订单价格相对标记价格的偏离（胖手指）。
+    async fn check_order_risk(&mut self, order: &Order<RequestOpen>) -> Result<(), ExchangeError>;
+}
+
+#[async_trait]
+impl RiskHandler for HourglassAccount
+{
+    async fn compute_potential_exposures(&self) -> HashMap<Instrument, InstrumentExposure>
+    {
+        // 标记价格取单层订单簿中的最新成交价，尚无行情时退回仓位记录的最新价格
+        let mark_prices: HashMap<Instrument, f64> = self.single_level_order_book
+                                                        .lock()
+                                                        .await
+                                                        .iter()
+                                                        .filter(|(_, order_book)| order_book.latest_price > 0.0)
+                                                        .map(|(instrument, order_book)| (instrument.clone(), order_book.latest_price))
+                                                        .collect();
+
+        let mut exposures: HashMap<Instrument, InstrumentExposure> = HashMap::new();
+        for (positions, side) in [(&self.positions.perpetual_pos_long, Side::Buy), (&self.positions.perpetual_pos_short, Side::Sell)] {
+            for (instrument, position) in positions.read().await.iter() {
+                let mark_price = mark_prices.get(instrument).copied().unwrap_or(position.meta.current_symbol_price);
+                exposures.entry(instrument.clone()).or_default().add(side, instrument.notional(mark_price, position.meta.current_size));
+            }
+        }
+        for (positions, side) in [(&self.positions.futures_pos_long, Side::Buy), (&self.positions.futures_pos_short, Side::Sell)] {
+            for (instrument, position) in positions.read().await.iter() {
+                let mark_price = mark_prices.get(instrument).or_else(|| mark_prices.get(&instrument.market_instrument())).copied().unwrap_or(position.meta.current_symbol_price);
+                exposures.entry(instrument.clone()).or_default().add(side, instrument.notional(mark_price, position.meta.current_size));
+            }
+        }
+
+        for order in self.account_open_book.read().await.fetch_all() {
+            if order.instrument.kind == InstrumentKind::Spot {
+                continue;
+            }
+            exposures.entry(order.instrument.clone())
+                     .or_default()
+                     .add(order.side, order.instrument.notional(order.state.price, order.state.remaining_quantity()));
+        }
+
+        exposures
+    }
+
+    async fn check_position_limits(&self, order: &Order<RequestOpen>) -> Result<(), ExchangeError>
+    {
+        let limits = &self.config.position_limits;
+        if order.instrument.kind == InstrumentKind::Spot || order.state.reduce_only || order.state.position_side.is_closing(order.side) {
+            return Ok(());
+        }
+
+        let margin_token = order.instrument.margin_token();
+        let exposures: HashMap<Instrument, InstrumentExposure> = self.compute_potential_exposures()
+                                                                     .await
+                                                                     .into_iter()
+                                                                     .filter(|(instrument, _)| instrument.margin_token() == margin_token)
+                                                                     .collect();
+        // 单向持仓模式的金融工具多空互相抵消，持仓方向模式按金融工具分别确定
+        let mut net_instruments = HashSet::new();
+        for instrument in exposures.keys().chain([&order.instrument]) {
+            if self.resolve_position_direction_mode(instrument).await == PositionDirectionMode::Net {
+                net_instruments.insert(instrument.clone());
+            }
+        }
+        let settle = |instrument: &Instrument, exposure: InstrumentExposure| if net_instruments.contains(instrument) { exposure.netted() } else { exposure };
+
+        let current = exposures.get(&order.instrument).copied().unwrap_or_default();
+        let mut after = current;
+        after.add(order.side, order.instrument.notional(order.state.price, order.state.size));
+        let (current, after) = (settle(&order.instrument, current), settle(&order.instrument, after));
+
+        // 单个金融工具的仓位限额
+        if let Some(limit) = limits.max_position_notional_for(&order.instrument) {
+            let notional = after.notional(order.side);
+            if notional > limit {
+                return Err(ExchangeError::PositionLimitExceeded(format!("{} {:?} notional {} exceeds limit {}", order.instrument, order.side, notional, limit)));
+            }
+        }
+
+        let (long_notional, short_notional) = exposures.iter().fold((0.0, 0.0), |(long, short), (instrument, exposure)| {
+                                                                    let exposure = settle(instrument, *exposure);
+                                                                    (long + exposure.long_notional, short + exposure.short_notional)
+                                                                });
+        let long_after = long_notional - current.long_notional + after.long_notional;
+        let short_after = short_notional - current.short_notional + after.short_notional;
+        let gross_exposure = long_after + short_after;
+
+        if let Some(limit) = limits.max_gross_exposure {
+            if gross_exposure > limit {
+                return Err(ExchangeError::PositionLimitExceeded(format!("gross exposure {} exceeds limit {}", gross_exposure, limit)));
+            }
+        }
+
+        // 只拒绝会扩大净敞口的订单
+        if let Some(limit) = limits.max_net_exposure {
+            let net_exposure = (long_after - short_after).abs();
+            if net_exposure > limit && net_exposure > (long_notional - short_notional).abs() {
+                return Err(ExchangeError::PositionLimitExceeded(format!("net exposure {} exceeds limit {}", net_exposure, limit)));
+            }
+        }
+
+        if let Some(limit) = limits.max_leveraged_exposure {
+            let equity = self.compute_account_summary(margin_token).await?.equity;
+            let leveraged_exposure = if equity > 0.0 { gross_exposure / equity } else { f64::INFINITY };
+            if leveraged_exposure > limit {
+                return Err(ExchangeError::PositionLimitExceeded(format!("leveraged exposure {} exceeds limit {}", leveraged_exposure, limit)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_order_rate(&mut self) -> Result<(), ExchangeError>
+    {
+        if let Some(max_orders_per_second) = self.config.order_risk_limits.max_orders_per_second {
+            let exchange_timestamp = self.exchange_timestamp.load(Ordering::SeqCst);
+            if !self.order_rate_limiter.try_acquire(exchange_timestamp, max_orders_per_second) {
+                return Err(ExchangeError::ApiLimitReached);
+            }
+        }
+        Ok(())
+    }
+
+    async fn check_order_risk(&mut self, order: &Order<RequestOpen>) -> Result<(), ExchangeError>
+    {
+        self.check_order_rate()?;
+        let limits = self.config.order_risk_limits.clone();
+
+        if let Some(max_open_orders) = limits.max_open_orders_per_instrument {
+            let open_orders = self.account_open_book.read().await.get_ins_orders_mut(&order.instrument).map(|orders| orders.num_orders()).unwrap_or(0);
+            if open_orders >= max_open_orders {
+                return Err(ExchangeError::OrderRejected(format!("{} already has {} open orders, limit is {}", order.instrument, open_orders, max_open_orders)));
+            }
+        }
+
+        if let Some(max_notional) = limits.max_order_notional {
+            let notional = order.instrument.notional(order.state.price, order.state.size);
+            if notional > max_notional {
+                return Err(ExchangeError::OrderRejected(format!("Order notional {} exceeds limit {}", notional, max_notional)));
+            }
+        }
+
+        // 尚无行情时无法判断价格是否偏离
+        if let (Some(max_deviation), Some(mark_price)) = (limits.max_mark_price_deviation, self.mark_price(&order.instrument).await) {
+            let deviation = (order.state.price - mark_price).abs() / mark_price;
+            if deviation > max_deviation {
+                return Err(ExchangeError::OrderRejected(format!("Order price {} deviates {:.4} from mark price {}, limit is {}", order.state.price, deviation, mark_price, max_deviation)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{
+        common::{
+            account_positions::{perpetual::PerpetualPositionConfig, PositionMarginMode, PositionSide},
+            order::{identification::OrderId, states::request_cancel::RequestCancel},
+            token::Token,
+        },
+        hourglass::account::account_config::{OrderRiskLimits, PositionLimits},
+        test_utils::{create_test_account, create_test_order_open, create_test_perpetual_position, create_test_request_open},
+    };
+    use tokio::sync::oneshot;
+
+    fn eth_perpetual_request(side: Side, price: f64, size: f64) -> Order<RequestOpen>
+    {
+        let mut request = create_test_request_open("ETH", "USDT");
+        request.instrument.kind = InstrumentKind::Perpetual;
+        request.side = side;
+        request.state.price = price;
+        request.state.size = size;
+        request
+    }
+
+    #[tokio::test]
+    async fn position_limit_should_include_resting_orders()
+    {
+        let mut account = create_test_account().await;
+        account.config.position_limits = PositionLimits { max_position_notional: Some(1_000.0),
+                                                          ..Default::default() };
+        let instrument = Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual));
+        account.account_open_book.write().await.get_ins_orders_mut(&instrument).unwrap().add_order_open(create_test_order_open(Side::Buy, 100.0, 5.0));
+
+        let result = account.check_position_limits(&eth_perpetual_request(Side::Buy, 100.0, 6.0)).await;
+        assert!(matches!(result, Err(ExchangeError::PositionLimitExceeded(_))));
+        assert!(account.check_position_limits(&eth_perpetual_request(Side::Buy, 100.0, 5.0)).await.is_ok());
+        assert!(account.check_position_limits(&eth_perpetual_request(Side::Sell, 100.0, 8.0)).await.is_ok());
+
+        // 按交易对设置的限额优先
+        account.config.position_limits.instrument_max_position_notional.insert("ETHUSDT".to_string(), 2_000.0);
+        assert!(account.check_position_limits(&eth_perpetual_request(Side::Buy, 100.0, 6.0)).await.is_ok());
+
+        // 平仓单不会增加敞口
+        let mut closing = eth_perpetual_request(Side::Buy, 100.0, 50.0);
+        closing.state.reduce_only = true;
+        assert!(account.check_position_limits(&closing).await.is_ok());
+        closing.state.reduce_only = false;
+        closing.state.position_side = PositionSide::Short;
+        assert!(account.check_position_limits(&closing).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn account_exposure_limits_should_use_mark_price()
+    {
+        let mut account = create_test_account().await;
+        let instrument = Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual));
+        account.single_level_order_book.lock().await.get_mut(&instrument).unwrap().latest_price = 100.0;
+        let mut position = create_test_perpetual_position(instrument.clone());
+        position.meta.current_size = 10.0;
+        position.meta.current_avg_price = 100.0;
+        account.positions.perpetual_pos_long.write().await.insert(instrument.clone(), position);
+
+        account.config.position_limits = PositionLimits { max_gross_exposure: Some(2_000.0),
+                                                          max_net_exposure: Some(1_500.0),
+                                                          ..Default::default() };
+        // 多头 1000 + 600，净敞口 1600 超限；卖出 600 则净敞口缩小
+        assert!(matches!(account.check_position_limits(&eth_perpetual_request(Side::Buy, 100.0, 6.0)).await, Err(ExchangeError::PositionLimitExceeded(_))));
+        assert!(account.check_position_limits(&eth_perpetual_request(Side::Sell, 100.0, 6.0)).await.is_ok());
+        // 单向持仓模式下卖出 11 与多头 10 抵消，只剩空头 1，总敞口 100
+        assert!(account.check_position_limits(&eth_perpetual_request(Side::Sell, 100.0, 11.0)).await.is_ok());
+        assert!(matches!(account.check_position_limits(&eth_perpetual_request(Side::Buy, 100.0, 11.0)).await, Err(ExchangeError::PositionLimitExceeded(_))));
+
+        // 双向持仓模式下多空各自计入，总敞口 1000 + 1100 超限
+        account.config.global_position_direction_mode = PositionDirectionMode::LongShort;
+        assert!(matches!(account.check_position_limits(&eth_perpetual_request(Side::Sell, 100.0, 11.0)).await, Err(ExchangeError::PositionLimitExceeded(_))));
+        account.config.global_position_direction_mode = PositionDirectionMode::Net;
+
+        // 账户权益 10000，实际杠杆不能超过 0.2 倍
+        account.config.position_limits = PositionLimits { max_leveraged_exposure: Some(0.2),
+                                                          ..Default::default() };
+        assert_eq!(account.get_balance(&Token::from("USDT")).unwrap().total, 10_000.0);
+        assert!(matches!(account.check_position_limits(&eth_perpetual_request(Side::Buy, 100.0, 15.0)).await, Err(ExchangeError::PositionLimitExceeded(_))));
+        assert!(account.check_position_limits(&eth_perpetual_request(Side::Buy, 100.0, 5.0)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn exposure_netting_should_follow_each_instrument_direction_mode()
+    {
+        let mut account = create_test_account().await;
+        let instrument = Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual));
+        let mut position = create_test_perpetual_position(instrument.clone());
+        position.meta.current_size = 10.0;
+        position.meta.current_symbol_price = 100.0;
+        account.positions.perpetual_pos_long.write().await.insert(instrument.clone(), position);
+        account.config.position_limits = PositionLimits { max_gross_exposure: Some(2_000.0),
+                                                          ..Default::default() };
+
+        // 账户为单向持仓，但该合约预设为双向持仓，多空不抵消：1000 + 1100 超限
+        let hedge_config = PerpetualPositionConfig { pos_margin_mode: PositionMarginMode::Cross,
+                                                     leverage: 1.0,
+                                                     position_direction_mode: PositionDirectionMode::LongShort };
+        account.positions.perpetual_pos_long_config.write().await.insert(instrument.clone(), hedge_config);
+        assert!(matches!(account.check_position_limits(&eth_perpetual_request(Side::Sell, 100.0, 11.0)).await, Err(ExchangeError::PositionLimitExceeded(_))));
+
+        // 账户为双向持仓，但该合约预设为单向持仓，卖出 11 与多头 10 抵消
+        account.config.global_position_direction_mode = PositionDirectionMode::LongShort;
+        let net_config = PerpetualPositionConfig { pos_margin_mode: PositionMarginMode::Cross,
+                                                   leverage: 1.0,
+                                                   position_direction_mode: PositionDirectionMode::Net };
+        account.positions.perpetual_pos_long_config.write().await.insert(instrument, net_config);
+        assert!(account.check_position_limits(&eth_perpetual_request(Side::Sell, 100.0, 11.0)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn inverse_exposures_should_be_counted_in_margin_token()
+    {
+        let mut account = create_test_account().await;
+        let linear = Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual));
+        let mut linear_position = create_test_perpetual_position(linear.clone());
+        linear_position.meta.current_size = 10.0;
+        linear_position.meta.current_symbol_price = 100.0;
+        account.positions.perpetual_pos_long.write().await.insert(linear, linear_position);
+
+        // 币本位仓位 1000 张、标记价格 100，敞口为 10 ETH
+        let inverse = Instrument::inverse_perpetual("ETH", "USDT");
+        let mut inverse_position = create_test_perpetual_position(inverse.clone());
+        inverse_position.meta.current_size = 1_000.0;
+        inverse_position.meta.current_symbol_price = 100.0;
+        account.positions.perpetual_pos_long.write().await.insert(inverse.clone(), inverse_position);
+        assert_eq!(account.compute_potential_exposures().await[&inverse].long_notional, 10.0);
+
+        // 总敞口只累计同一保证金币种的合约，USDT 本位的 1000 不计入
+        account.config.position_limits = PositionLimits { max_gross_exposure: Some(15.0),
+                                                          ..Default::default() };
+        let mut request = eth_perpetual_request(Side::Buy, 100.0, 400.0);
+        request.instrument = inverse;
+        assert!(account.check_position_limits(&request).await.is_ok());
+        request.state.size = 600.0;
+        assert!(matches!(account.check_position_limits(&request).await, Err(ExchangeError::PositionLimitExceeded(_))));
+    }
+
+    #[tokio::test]
+    async fn atomic_open_should_reject_orders_over_position_limit()
+    {
+        let mut account = create_test_account().await;
+        account.config.position_limits = PositionLimits { max_position_notional: Some(100.0),
+                                                          ..Default::default() };
+
+        let result = account.atomic_open(eth_perpetual_request(Side::Buy, 100.0, 2.0)).await;
+        assert!(matches!(result, Err(ExchangeError::PositionLimitExceeded(_))));
+        assert_eq!(account.account_open_book.read().await.fetch_all().len(), 0);
+    }
+
+    #[test]
+    fn order_rate_limiter_should_slide_with_exchange_time()
+    {
+        let mut limiter = OrderRateLimiter::default();
+        assert!(limiter.try_acquire(1_000, 2));
+        assert!(limiter.try_acquire(1_500, 2));
+        assert!(!limiter.try_acquire(1_999, 2));
+        // 被拒绝的请求不占用窗口
+        assert!(limiter.try_acquire(2_000, 2));
+        assert!(!limiter.try_acquire(2_499, 2));
+        assert!(limiter.try_acquire(2_500, 2));
+    }
+
+    #[tokio::test]
+    async fn order_risk_should_reject_excess_open_orders_notional_and_fat_finger()
+    {
+        let mut account = create_test_account().await;
+        let instrument = Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual));
+        account.config.order_risk_limits = OrderRiskLimits { max_open_orders_per_instrument: Some(1),
+                                                             ..Default::default() };
+        assert!(account.check_order_risk(&eth_perpetual_request(Side::Buy, 100.0, 1.0)).await.is_ok());
+        account.account_open_book.write().await.get_ins_orders_mut(&instrument).unwrap().add_order_open(create_test_order_open(Side::Buy, 100.0, 1.0));
+        assert!(matches!(account.check_order_risk(&eth_perpetual_request(Side::Sell, 100.0, 1.0)).await, Err(ExchangeError::OrderRejected(_))));
+
+        account.config.order_risk_limits = OrderRiskLimits { max_order_notional: Some(500.0),
+                                                             ..Default::default() };
+        assert!(account.check_order_risk(&eth_perpetual_request(Side::Buy, 100.0, 5.0)).await.is_ok());
+        assert!(matches!(account.check_order_risk(&eth_perpetual_request(Side::Buy, 100.0, 6.0)).await, Err(ExchangeError::OrderRejected(_))));
+
+        // 尚无标记价格时不做胖手指检查
+        account.config.order_risk_limits = OrderRiskLimits { max_mark_price_deviation: Some(0.1),
+                                                             ..Default::default() };
+        assert!(account.check_order_risk(&eth_perpetual_request(Side::Buy, 1_000.0, 1.0)).await.is_ok());
+        account.single_level_order_book.lock().await.get_mut(&instrument).unwrap().latest_price = 100.0;
+        assert!(account.check_order_risk(&eth_perpetual_request(Side::Buy, 110.0, 1.0)).await.is_ok());
+        assert!(matches!(account.check_order_risk(&eth_perpetual_request(Side::Buy, 111.0, 1.0)).await, Err(ExchangeError::OrderRejected(_))));
+        assert!(matches!(account.check_order_risk(&eth_perpetual_request(Side::Sell, 89.0, 1.0)).await, Err(ExchangeError::OrderRejected(_))));
+    }
+
+    #[tokio::test]
+    async fn order_rate_limit_should_be_reported_per_order()
+    {
+        let mut account = create_test_account().await;
+        account.config.order_risk_limits = OrderRiskLimits { max_orders_per_second: Some(2),
+                                                             ..Default::default() };
+        account.exchange_timestamp.store(1_000, Ordering::SeqCst);
+
+        let requests = vec![eth_perpetual_request(Side::Buy, 100.0, 1.0); 3];
+        let (tx, rx) = oneshot::channel();
+        account.open_orders(requests, tx).await.unwrap();
+        let results = rx.await.unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(!matches!(results[0], Err(ExchangeError::ApiLimitReached)));
+        assert!(!matches!(results[1], Err(ExchangeError::ApiLimitReached)));
+        assert!(matches!(results[2], Err(ExchangeError::ApiLimitReached)));
+
+        // 撤单与下单共用同一个频率窗口
+        let request = eth_perpetual_request(Side::Buy, 100.0, 1.0);
+        let cancel = Order { instruction: request.instruction,
+                             exchange: request.exchange,
+                             instrument: request.instrument,
+                             timestamp: request.timestamp,
+                             cid: request.cid,
+                             side: request.side,
+                             state: RequestCancel { id: Some(OrderId(1)) } };
+        let (tx, rx) = oneshot::channel();
+        account.cancel_orders(vec![cancel.clone()], tx).await;
+        assert!(matches!(rx.await.unwrap()[0], Err(ExchangeError::ApiLimitReached)));
+
+        // 窗口滑过后恢复
+        account.exchange_timestamp.store(2_000, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        account.cancel_orders(vec![cancel], tx).await;
+        assert!(!matches!(rx.await.unwrap()[0], Err(ExchangeError::ApiLimitReached)));
+    }
+}
@@ -3,40 +3,65 @@ use crate::{
         event::{AccountEvent, AccountEventKind},
         instrument::{kind::InstrumentKind, Instrument},
         order::OrderRole,
-        token::Token,
         trade::ClientTrade,
         Side,
     },
     error::ExchangeError,
     hourglass::{
         account::{
-            account_config::{FeesQuerier, HourglassMode},
-            account_handlers::{balance_handler::BalanceHandler, position_handler::PositionHandler},
+            account_config::{CommissionLevel, CommissionRates, FeesQuerier, HourglassMode},
+            respond,
+            account_handlers::{
+                balance_handler::BalanceHandler, future_handler::FutureHandler, leveraged_token_handler::LeveragedTokenHandler, margin_handler::MarginHandler, option_handler::OptionHandler,
+                position_handler::PositionHandler,
+            },
             HourglassAccount,
         },
         clickhouse_api::datatype::{
             clickhouse_trade_data::MarketTrade,
             single_level_order_book::{OrderBookUpdater, SingleLevelOrderBook},
         },
+        l2_order_book::{L2ApplyOutcome, L2BookMessage},
     },
     Exchange,
 };
 use async_trait::async_trait;
-use std::{
-    sync::atomic::Ordering,
-    time::{SystemTime, UNIX_EPOCH},
-};
+use std::sync::atomic::Ordering;
+use tokio::sync::oneshot::Sender;
 use tracing::warn;
 
 #[async_trait]
 pub trait TradeHandler
 {
-    async fn create_or_update_single_level_orderbook_from_market_trade(&mut self, trade: &MarketTrade);
+    async fn create_or_update_single_level_orderbook_from_market_trade(&mut self, trade: &MarketTrade) -> Result<(), ExchangeError>;
     async fn handle_trade_data(&mut self, trade: &MarketTrade) -> Result<(), ExchangeError>;
 
+    /// 把一条 L2 订单簿消息应用到对应金融工具的全深度订单簿，订单簿已同步时用其最优报价更新 `SingleLevelOrderBook`，
+    /// 并按 [`Self::match_orders_against_book`] 撮合与订单簿交叉的挂单。
+    /// 发现序号缺口时订单簿被清空，等待下一次快照重新同步，期间买卖价退回按成交方向推断。
+    async fn handle_book_update(&mut self, message: &L2BookMessage) -> Result<L2ApplyOutcome, ExchangeError>;
+
     async fn match_orders(&mut self, market_trade: &MarketTrade) -> Result<Vec<ClientTrade>, ExchangeError>;
 
-    async fn fees_percent(&self, instrument_kind: &InstrumentKind, role: OrderRole) -> Result<f64, ExchangeError>;
+    /// 用已同步的 L2 订单簿撮合挂单：买单价格不低于最优卖价、卖单价格不高于最优买价时，按对手方各档的挂单量依次成交，
+    /// 成交价为挂单价格，被消耗的挂单量从重建的订单簿中扣除。未与订单簿交叉的挂单仍由 [`Self::match_orders`] 按逐笔成交撮合。
+    /// 订单簿未同步时不做任何处理。
+    async fn match_orders_against_book(&mut self, instrument: &Instrument, timestamp: i64) -> Result<Vec<ClientTrade>, ExchangeError>;
+
+    /// 行情中的交割合约不带到期时间时，返回最近到期且有挂单的交割合约，其余金融工具原样返回。
+    async fn resolve_order_instrument(&self, instrument: Instrument) -> Instrument;
+
+    async fn fees_percent(&self, instrument: &Instrument, role: OrderRole) -> Result<f64, ExchangeError>;
+
+    /// 按截至当前交易所时间的滚动 30 天成交额确定账户当前的手续费等级。
+    fn current_commission_level(&self) -> CommissionLevel;
+
+    /// 在运行中设置或移除（`rates` 为 `None`）某个金融工具的手续费率，返回之前的设置。
+    /// 用于在回测中途模拟交易所的手续费活动，新费率从下一笔撮合开始生效。
+    fn set_instrument_fees(&mut self, instrument: &Instrument, rates: Option<CommissionRates>) -> Result<Option<CommissionRates>, ExchangeError>;
+
+    async fn set_instrument_fees_and_respond(&mut self, instrument: &Instrument, rates: Option<CommissionRates>, response_tx: Sender<Result<Option<CommissionRates>, ExchangeError>>);
+
     /// 处理客户端交易列表并更新账户余额及交易事件。
     ///
     /// 该方法接收多个 `ClientTrade` 实例，并依次处理每笔交易：
@@ -59,6 +84,9 @@ pub trait TradeHandler
     /// * 当 `client_trades` 为空时，该方法不会执行任何操作。
     async fn process_trade(&mut self, trade: ClientTrade) -> Result<(), ExchangeError>;
 
+    /// 处理强平与自动减仓产生的成交，与 [`Self::process_trade`] 相同，但不计入手续费等级考核的成交额。
+    async fn process_forced_trade(&mut self, trade: ClientTrade) -> Result<(), ExchangeError>;
+
     async fn process_trades(&mut self, client_trades: Vec<ClientTrade>);
     fn update_exchange_ts(&self, timestamp: i64);
 }
@@ -72,7 +100,7 @@ impl TradeHandler for HourglassAccount
     /// - `trade`: 引用 `MarketTrade` 类型的交易信息，用于从中提取 `instrument` 并更新对应的订单簿。
     ///
     /// # 实现步骤
-    /// 1. 从 `trade` 中解析出 `instrument`，如果解析失败，程序会 panic（`unwrap`）。
+    /// 1. 通过 [`SymbolRegistry`](crate::common::instrument::symbol_registry::SymbolRegistry) 解析出 `instrument`，未登记的符号返回错误。
     /// 2. 获取 `single_level_order_book` 的互斥锁以安全地访问共享资源。
     /// 3. 使用 `instrument` 作为键，查找对应的单级别订单簿，如果没有则创建一个新的订单簿。
     /// 4. 使用 `trade` 更新该 `instrument` 对应的订单簿。
@@ -80,14 +108,51 @@ impl TradeHandler for HourglassAccount
     /// # 备注
     /// - `SingleLevelOrderBook::from(trade)` 是一个基于 `trade` 初始化订单簿的工厂方法。
     /// - 该函数异步锁定了 `single_level_order_book`，并且通过 `.await` 实现对共享数据的安全访问。
-    async fn create_or_update_single_level_orderbook_from_market_trade(&mut self, trade: &MarketTrade)
+    async fn create_or_update_single_level_orderbook_from_market_trade(&mut self, trade: &MarketTrade) -> Result<(), ExchangeError>
     {
-        let instrument = trade.parse_instrument().unwrap();
+        let instrument = trade.resolve_instrument(&self.symbol_registry)?;
+        // 买卖价已由同步的 L2 订单簿给出时，成交只更新最新价格
+        let quoted = self.l2_books.is_synced(&instrument);
         let mut orderbook = self.single_level_order_book.lock().await;
 
-        orderbook.entry(instrument)
-                 .or_insert_with(|| SingleLevelOrderBook::from(trade)) // 传递引用 &trade
-                 .update_from_trade(&trade);
+        let book = orderbook.entry(instrument).or_insert_with(|| SingleLevelOrderBook::from(trade)); // 传递引用 &trade
+        if quoted {
+            book.latest_price = trade.price;
+        }
+        else {
+            book.update_from_trade(trade);
+        }
+        Ok(())
+    }
+
+    async fn handle_book_update(&mut self, message: &L2BookMessage) -> Result<L2ApplyOutcome, ExchangeError>
+    {
+        let instrument = self.symbol_registry.resolve(&message.exchange, &message.symbol)?.clone();
+        self.update_exchange_ts(message.timestamp);
+
+        let outcome = self.l2_books.apply(&instrument, message);
+        if let L2ApplyOutcome::Gap { expected, received } = outcome {
+            warn!("Order book gap for {}: expected sequence {}, received {}. Awaiting a new snapshot.", instrument, expected, received);
+        }
+
+        // 先撮合与订单簿交叉的挂单，最优报价随后反映被消耗的挂单量
+        self.match_orders_against_book(&instrument, message.timestamp).await?;
+
+        let quotes = self.l2_books
+                         .book(&instrument)
+                         .filter(|book| book.is_synced())
+                         .and_then(|book| book.best_bid().zip(book.best_ask()));
+        if let Some(((best_bid, _), (best_ask, _))) = quotes {
+            self.single_level_order_book
+                .lock()
+                .await
+                .entry(instrument)
+                .or_insert(SingleLevelOrderBook { latest_bid: best_bid,
+                                                  latest_ask: best_ask,
+                                                  latest_price: 0.0 })
+                .update_from_quotes(best_bid, best_ask);
+        }
+        Ok(outcome)
     }
 
     /// 处理交易数据的方法
@@ -95,8 +160,15 @@ impl TradeHandler for HourglassAccount
     {
         // 更新时间戳
         self.update_exchange_ts(trade.timestamp);
+        // 到期的交割合约与期权先撤单并交割，之后的行情不再与其挂单撮合
+        self.settle_expired_futures().await?;
+        self.settle_expired_options().await?;
         // 更新单层OrderBook，注意 这个做法仅仅适用于回测。
-        self.create_or_update_single_level_orderbook_from_market_trade(trade).await;
+        self.create_or_update_single_level_orderbook_from_market_trade(trade).await?;
+        // 按最新的标的价格更新杠杆代币的净值，必要时再平衡
+        self.update_leveraged_tokens().await?;
+        // 现货杠杆借款计息并检查风险率
+        self.check_margin_level().await?;
         // 用交易所记录的用户的挂单去匹配 market_rade 以实现模拟的目的
         self.check_and_handle_liquidation(trade).await?;
         self.match_orders(&trade).await?;
@@ -118,26 +190,24 @@ impl TradeHandler for HourglassAccount
     ///
     /// # 逻辑
     ///
-    /// 1. 从市场交易事件中解析出基础货币和报价货币，并确定金融工具种类。
+    /// 1. 通过符号表把市场交易事件的 `(exchange, symbol)` 解析为金融工具。
     /// 2. 查找与该金融工具相关的挂单（`InstrumentOrders`）。
     /// 3. 根据市场事件的方向（买或卖）尝试匹配相应的挂单（买单匹配卖单，卖单匹配买单）。
     /// 4. 使用订单的 `OrderRole` 来计算手续费，并生成交易记录。
     /// 5. 处理并返回生成的交易记录。
     ///
     /// # 注意
-    /// 该函数假设市场交易事件的符号格式为 `base_quote`，并从中解析出基础货币和报价货币。
+    /// 未在符号表中登记的符号返回 [`ExchangeError::UnknownSymbol`]。
     /// 如果找不到与市场事件相关的挂单，函数会记录警告并返回一个空的交易向量。
     async fn match_orders(&mut self, market_trade: &MarketTrade) -> Result<Vec<ClientTrade>, ExchangeError>
     {
         // println!("[match_orders]: market_trade: {:?}", market_trade);
         let mut trades = Vec::new();
 
-        // 从市场交易事件的符号中解析基础货币和报价货币，并确定金融工具种类
-        let base = Token::from(market_trade.parse_base().ok_or_else(|| ExchangeError::Hourglass("Unknown base.".to_string()))?);
-        let quote = Token::from(market_trade.parse_quote().ok_or_else(|| ExchangeError::Hourglass("Unknown quote.".to_string()))?);
-        let kind = market_trade.parse_kind();
-        // println!("[match_orders]: kind is {}", kind);
-        let instrument = Instrument { base, quote, kind };
+        // 通过符号表解析金融工具
+        let instrument = market_trade.resolve_instrument(&self.symbol_registry)?;
+        // 行情中的交割合约不带到期时间时，与最近到期且尚未到期的交割合约挂单撮合
+        let instrument = self.resolve_order_instrument(instrument).await;
         // println!("[match_orders]: instrument is {}", instrument);
 
         // 查找与指定金融工具相关的挂单
@@ -151,7 +221,7 @@ impl TradeHandler for HourglassAccount
                         if let Some(best_bid) = instrument_orders.bids.last() {
                             let order_role = best_bid.state.order_role;
                             // println!("[match_orders]: order_role: {:?}", order_role);
-                            let fees_percent = self.fees_percent(&kind, order_role).await.map_err(|_| ExchangeError::Hourglass("Missing fees.".to_string()))?;
+                            let fees_percent = self.fees_percent(&instrument, order_role).await.map_err(|_| ExchangeError::Hourglass("Missing fees.".to_string()))?;
 
                             // 使用计算出的手续费比例匹配买单
                             trades.append(&mut instrument_orders.match_bids(market_trade, fees_percent, &self.client_trade_counter));
@@ -162,7 +232,7 @@ impl TradeHandler for HourglassAccount
                         if let Some(best_ask) = instrument_orders.asks.last() {
                             let order_role = best_ask.state.order_role;
                             // println!("[match_orders]: order_role: {:?}", order_role);
-                            let fees_percent = self.fees_percent(&kind, order_role).await.map_err(|_| ExchangeError::Hourglass("Missing fees.".to_string()))?;
+                            let fees_percent = self.fees_percent(&instrument, order_role).await.map_err(|_| ExchangeError::Hourglass("Missing fees.".to_string()))?;
 
                             // 使用计算出的手续费比例匹配卖单
                             trades.append(&mut instrument_orders.match_asks(market_trade, fees_percent, &self.client_trade_counter));
@@ -182,11 +252,64 @@ impl TradeHandler for HourglassAccount
         Ok(trades)
     }
 
+    async fn match_orders_against_book(&mut self, instrument: &Instrument, timestamp: i64) -> Result<Vec<ClientTrade>, ExchangeError>
+    {
+        let mut trades = Vec::new();
+        let Some(book) = self.l2_books.book(instrument).filter(|book| book.is_synced())
+        else {
+            return Ok(trades);
+        };
+        let order_instrument = self.resolve_order_instrument(instrument.clone()).await;
+
+        if let Ok(mut instrument_orders) = self.account_open_book.read().await.get_ins_orders_mut(&order_instrument) {
+            // 卖盘压到买单价格及以下时，买单按卖盘各档的挂单量成交
+            if let Some(best_bid) = instrument_orders.bids.last() {
+                let levels = book.crossing_levels(Side::Buy, best_bid.state.price);
+                if !levels.is_empty() {
+                    let fees_percent = self.fees_percent(&order_instrument, best_bid.state.order_role).await.map_err(|_| ExchangeError::Hourglass("Missing fees.".to_string()))?;
+                    trades.append(&mut instrument_orders.match_bids_against_levels(timestamp, &levels, fees_percent, &self.client_trade_counter));
+                }
+            }
+            // 买盘抬到卖单价格及以上时，卖单按买盘各档的挂单量成交
+            if let Some(best_ask) = instrument_orders.asks.last() {
+                let levels = book.crossing_levels(Side::Sell, best_ask.state.price);
+                if !levels.is_empty() {
+                    let fees_percent = self.fees_percent(&order_instrument, best_ask.state.order_role).await.map_err(|_| ExchangeError::Hourglass("Missing fees.".to_string()))?;
+                    trades.append(&mut instrument_orders.match_asks_against_levels(timestamp, &levels, fees_percent, &self.client_trade_counter));
+                }
+            }
+        }
+
+        if let Some(book) = self.l2_books.book_mut(instrument) {
+            for side in [Side::Buy, Side::Sell] {
+                book.take_liquidity(side, trades.iter().filter(|trade| trade.side == side).map(|trade| trade.size).sum());
+            }
+        }
+        self.process_trades(trades.clone()).await;
+        Ok(trades)
+    }
+
+    async fn resolve_order_instrument(&self, instrument: Instrument) -> Instrument
+    {
+        match instrument.kind {
+            | InstrumentKind::Future if instrument.expiry.is_none() => self.account_open_book
+                                                                           .read()
+                                                                           .await
+                                                                           .instrument_orders_map
+                                                                           .iter()
+                                                                           .map(|entry| entry.key().clone())
+                                                                           .filter(|dated| dated.undated() == instrument && dated.expiry.is_some())
+                                                                           .min_by_key(|dated| dated.expiry)
+                                                                           .unwrap_or(instrument),
+            | _ => instrument,
+        }
+    }
+
     /// 根据金融工具类型和订单角色返回相应的手续费百分比。 NOTE 需要扩展并支持现货和期货。
     ///
     /// # 参数
     ///
-    /// * `kind` - 表示金融工具的种类，如 `Spot` 或 `Perpetual`。
+    /// * `instrument` - 金融工具，单独为其设置的手续费率优先于按种类（如 `Spot` 或 `Perpetual`）配置的费率。
     /// * `role` - 表示订单的角色，如 `Maker` 或 `Taker`。
     ///
     /// # 返回值
@@ -199,19 +322,43 @@ impl TradeHandler for HourglassAccount
     ///
     /// * 目前只支持 `Spot` 和 `Perpetual` 类型的金融工具。
     /// * 如果传入的 `InstrumentKind` 不受支持，函数会记录一个警告并返回 `None`。
-    async fn fees_percent(&self, instrument_kind: &InstrumentKind, role: OrderRole) -> Result<f64, ExchangeError>
+    async fn fees_percent(&self, instrument: &Instrument, role: OrderRole) -> Result<f64, ExchangeError>
     {
+        let level = self.current_commission_level();
         // Access the account's config field
         match role {
             | OrderRole::Maker => {
                 // Fetch the maker fee rate using AccountConfig's method
-                self.config.get_maker_fee_rate(instrument_kind)
+                self.config.get_maker_fee_rate(instrument, level)
             }
             | OrderRole::Taker => {
                 // Fetch the taker fee rate using AccountConfig's method
-                self.config.get_taker_fee_rate(instrument_kind)
+                self.config.get_taker_fee_rate(instrument, level)
+            }
+        }
+    }
+
+    fn current_commission_level(&self) -> CommissionLevel
+    {
+        let volume = self.trading_volume.volume(self.exchange_timestamp.load(Ordering::SeqCst));
+        self.config.commission_level_for_volume(volume)
+    }
+
+    fn set_instrument_fees(&mut self, instrument: &Instrument, rates: Option<CommissionRates>) -> Result<Option<CommissionRates>, ExchangeError>
+    {
+        if let Some(rates) = &rates {
+            // 只有 maker 费率允许为负数（返佣）
+            if !rates.maker_fees.is_finite() || !rates.taker_fees.is_finite() || rates.taker_fees < 0.0 {
+                return Err(ExchangeError::Hourglass(format!("Invalid commission rates for {}: {:?}", instrument, rates)));
             }
         }
+        Ok(self.config.set_instrument_commission_rates(instrument, rates))
+    }
+
+    async fn set_instrument_fees_and_respond(&mut self, instrument: &Instrument, rates: Option<CommissionRates>, response_tx: Sender<Result<Option<CommissionRates>, ExchangeError>>)
+    {
+        let result = self.set_instrument_fees(instrument, rates);
+        respond(response_tx, result);
     }
 
     /// 处理客户端交易列表并更新账户余额及交易事件。
@@ -235,6 +382,16 @@ impl TradeHandler for HourglassAccount
     ///
     /// * 当 `client_trades` 为空时，该方法不会执行任何操作。
     async fn process_trade(&mut self, trade: ClientTrade) -> Result<(), ExchangeError>
+    {
+        let (timestamp, volume) = (trade.timestamp, trade.instrument.quote_notional(trade.price, trade.size));
+        self.process_forced_trade(trade).await?;
+
+        // 计入滚动成交额，用于调整手续费等级
+        self.trading_volume.record(timestamp, volume);
+        Ok(())
+    }
+
+    async fn process_forced_trade(&mut self, trade: ClientTrade) -> Result<(), ExchangeError>
     {
         let exchange_timestamp = self.exchange_timestamp.load(Ordering::SeqCst);
 
@@ -247,6 +404,9 @@ impl TradeHandler for HourglassAccount
             }
         };
 
+        // 按比例将手续费划入风险准备金
+        self.risk_reserve.lock().await.contribute(trade.fees * self.config.risk_reserve_fee_share);
+
         // 发送交易事件
         if let Err(err) = self.account_event_tx.send(AccountEvent { exchange_timestamp,
                                                                     exchange: Exchange::Hourglass,
@@ -260,6 +420,22 @@ impl TradeHandler for HourglassAccount
             warn!("Client offline - Failed to send AccountEvent::Balance: {:?}", err);
         }
 
+        // 现货成交收到借入的币种时自动还款
+        if trade.instrument.kind == InstrumentKind::Spot {
+            let received = match trade.side {
+                | Side::Buy => &trade.instrument.base,
+                | Side::Sell => &trade.instrument.quote,
+            };
+            if let Some(token_balance) = self.auto_repay(received).await {
+                if let Err(err) = self.account_event_tx.send(AccountEvent { exchange_timestamp,
+                                                                            exchange: Exchange::Hourglass,
+                                                                            kind: AccountEventKind::Balance(token_balance) })
+                {
+                    warn!("Client offline - Failed to send AccountEvent::Balance: {:?}", err);
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -279,7 +455,7 @@ impl TradeHandler for HourglassAccount
     {
         let adjusted_timestamp = match self.config.execution_mode {
             | HourglassMode::Backtest => timestamp,                                                            // 在回测模式下使用传入的时间戳
-            | HourglassMode::Online => SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64, // 在实时模式下使用当前时间
+            | HourglassMode::Online => timestamp.max(self.exchange_timestamp.load(Ordering::SeqCst)),          // 实时行情可能乱序到达，交易所时间不回退
         };
         self.exchange_timestamp.store(adjusted_timestamp, Ordering::SeqCst);
     }
@@ -290,15 +466,25 @@ mod tests
 {
     use super::*;
     use crate::{
-        common::order::{
-            identification::{client_order_id::ClientOrderId, OrderId},
-            order_instructions::OrderInstruction,
-            states::{open::Open, request_cancel::RequestCancel, request_open::RequestOpen},
-            Order,
+        common::{
+            account_positions::PositionSide,
+            order::{
+                identification::{client_order_id::ClientOrderId, OrderId},
+                order_instructions::OrderInstruction,
+                states::{open::Open, request_cancel::RequestCancel, request_open::RequestOpen},
+                Order,
+            },
+            token::Token,
+            trade::ClientTradeId,
+        },
+        hourglass::account::{
+            account_config::{CommissionRates, FeeTier},
+            account_handlers::trade_handler::TradeHandler,
+            account_volume::THIRTY_DAYS_MS,
         },
-        hourglass::account::account_handlers::trade_handler::TradeHandler,
         test_utils::create_test_account,
     };
+    use std::collections::HashMap;
 
     #[tokio::test]
     async fn test_fail_to_cancel_limit_order_due_to_invalid_order_id()
@@ -345,13 +531,14 @@ mod tests
                                  side: Side::Sell,
                                  state: RequestOpen { reduce_only: false,
                                                       price: 16406.0,
-                                                      size: 2.0 } };
+                                                      size: 2.0,
+                                                      position_side: PositionSide::Both } };
 
         // 将订单添加到账户
         let result = account.atomic_open(open_order.clone()).await;
         assert_eq!(result.is_ok(), false);
         let market_event = MarketTrade { exchange: "binance-futures".to_string(),
-                                         symbol: "ETH_USDT".to_string(),
+                                         symbol: "ETHUSDT".to_string(),
                                          timestamp: 1625247600000,
                                          price: 16605.0,
                                          side: Side::Buy.to_string(),
@@ -398,6 +585,7 @@ mod tests
                 reduce_only: false,
                 price: 16406.0,
                 size: 2.0,
+                position_side: PositionSide::Both,
             },
         };
 
@@ -454,7 +642,8 @@ mod tests
                                                price: 100.0,
                                                size: 2.0,
                                                filled_quantity: 0.0,
-                                               order_role: OrderRole::Maker } };
+                                               order_role: OrderRole::Maker,
+                                               position_side: PositionSide::Both } };
         account.account_open_book.write().await.get_ins_orders_mut(&instrument).unwrap().add_order_open(open_order.clone());
 
         // 匹配一个完全匹配的市场事件
@@ -493,7 +682,8 @@ mod tests
                                          side: Side::Buy,
                                          state: RequestOpen { price: 16499.0,
                                                               size: 5.0,
-                                                              reduce_only: false } };
+                                                              reduce_only: false,
+                                                              position_side: PositionSide::Both } };
 
         let result = account.atomic_open(open_order_request).await;
 
@@ -521,4 +711,185 @@ mod tests
         // 验证时间戳是否已更新
         assert_eq!(account.get_exchange_ts().unwrap(), 1625247600000);
     }
+
+    #[tokio::test]
+    async fn test_commission_level_follows_rolling_trading_volume()
+    {
+        let mut account = create_test_account().await;
+        let instrument = Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual));
+        account.config.fee_schedule = vec![FeeTier { level: CommissionLevel::Lv2,
+                                                     min_volume: 1_000.0,
+                                                     fees_book: HashMap::from([(InstrumentKind::Perpetual, CommissionRates { maker_fees: -0.0001, taker_fees: 0.0005 })]) },];
+        assert_eq!(account.current_commission_level(), CommissionLevel::Lv1);
+        assert_eq!(account.fees_percent(&instrument, OrderRole::Maker).await.unwrap(), 0.001);
+
+        let trade = ClientTrade { exchange: Exchange::Hourglass,
+                                  timestamp: 1_000,
+                                  trade_id: ClientTradeId(1),
+                                  order_id: None,
+                                  cid: None,
+                                  instrument: instrument.clone(),
+                                  side: Side::Buy,
+                                  price: 100.0,
+                                  size: 10.0,
+                                  fees: 0.0,
+                                  position_side: PositionSide::Both };
+        account.update_exchange_ts(1_000);
+        account.process_trade(trade.clone()).await.unwrap();
+        assert_eq!(account.current_commission_level(), CommissionLevel::Lv2);
+        assert_eq!(account.fees_percent(&instrument, OrderRole::Maker).await.unwrap(), -0.0001);
+        assert_eq!(account.fees_percent(&instrument, OrderRole::Taker).await.unwrap(), 0.0005);
+
+        // maker 返佣增加余额
+        let balance_before = account.get_balance(&Token::from("USDT")).unwrap().total;
+        account.process_trade(ClientTrade { fees: -0.1, ..trade }).await.unwrap();
+        assert_eq!(account.get_balance(&Token::from("USDT")).unwrap().total, balance_before + 0.1);
+
+        // 30 天后成交额滑出窗口，降回配置的保底等级
+        account.update_exchange_ts(1_000 + THIRTY_DAYS_MS);
+        assert_eq!(account.current_commission_level(), CommissionLevel::Lv1);
+        assert_eq!(account.fees_percent(&instrument, OrderRole::Maker).await.unwrap(), 0.001);
+    }
+
+    #[tokio::test]
+    async fn test_trading_volume_counts_quote_notional_and_skips_forced_trades()
+    {
+        let mut account = create_test_account().await;
+        let instrument = Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual));
+        account.config.fee_schedule = vec![FeeTier { level: CommissionLevel::Lv2,
+                                                     min_volume: 2_000.0,
+                                                     fees_book: HashMap::new() },];
+        account.update_exchange_ts(1_000);
+        let trade = ClientTrade { exchange: Exchange::Hourglass,
+                                  timestamp: 1_000,
+                                  trade_id: ClientTradeId(1),
+                                  order_id: None,
+                                  cid: None,
+                                  instrument: instrument.clone(),
+                                  side: Side::Buy,
+                                  price: 100.0,
+                                  size: 10.0,
+                                  fees: 0.0,
+                                  position_side: PositionSide::Both };
+
+        // 强平成交不计入成交额
+        account.process_forced_trade(trade.clone()).await.unwrap();
+        assert_eq!(account.trading_volume.volume(1_000), 0.0);
+
+        // 币本位合约 1000 张（每张 1 USDT）按 1000 USDT 计
+        account.process_trade(ClientTrade { instrument: Instrument::inverse_perpetual("ETH", "USDT"),
+                                            size: 1_000.0,
+                                            ..trade.clone() })
+               .await
+               .unwrap();
+        assert_eq!(account.trading_volume.volume(1_000), 1_000.0);
+        assert_eq!(account.current_commission_level(), CommissionLevel::Lv1);
+
+        account.process_trade(trade).await.unwrap();
+        assert_eq!(account.current_commission_level(), CommissionLevel::Lv2);
+    }
+
+    #[tokio::test]
+    async fn test_instrument_fees_override_kind_rates_at_runtime()
+    {
+        let mut account = create_test_account().await;
+        let eth = Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual));
+        let btc = Instrument::from(("BTC", "USDT", InstrumentKind::Perpetual));
+        let promo = CommissionRates { maker_fees: 0.0, taker_fees: 0.0 };
+
+        assert_eq!(account.set_instrument_fees(&eth, Some(promo.clone())).unwrap(), None);
+        assert_eq!(account.fees_percent(&eth, OrderRole::Taker).await.unwrap(), 0.0);
+        // 其他永续合约仍按种类收费
+        assert_eq!(account.fees_percent(&btc, OrderRole::Taker).await.unwrap(), 0.002);
+
+        // 单独设置的费率优先于手续费等级费率表
+        account.config.fee_schedule = vec![FeeTier { level: CommissionLevel::Lv1,
+                                                     min_volume: 0.0,
+                                                     fees_book: HashMap::from([(InstrumentKind::Perpetual, CommissionRates { maker_fees: 0.0002, taker_fees: 0.0005 })]) },];
+        assert_eq!(account.fees_percent(&eth, OrderRole::Maker).await.unwrap(), 0.0);
+        assert_eq!(account.fees_percent(&btc, OrderRole::Maker).await.unwrap(), 0.0002);
+
+        let invalid = CommissionRates { maker_fees: 0.0, taker_fees: -0.001 };
+        assert!(matches!(account.set_instrument_fees(&eth, Some(invalid)), Err(ExchangeError::Hourglass(_))));
+
+        // 移除后恢复按种类收费
+        assert_eq!(account.set_instrument_fees(&eth, None).unwrap(), Some(promo));
+        assert_eq!(account.fees_percent(&eth, OrderRole::Taker).await.unwrap(), 0.0005);
+    }
+
+    #[tokio::test]
+    async fn test_synced_l2_book_should_fill_crossed_orders_against_depth()
+    {
+        let mut account = create_test_account().await;
+        let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
+        account.account_event_tx = event_tx;
+        let instrument = Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual));
+
+        let resting_order = |side: Side, price: f64, size: f64, id: u64| Order { instruction: OrderInstruction::Limit,
+                                                                                 exchange: Exchange::Hourglass,
+                                                                                 instrument: instrument.clone(),
+                                                                                 timestamp: 1_000,
+                                                                                 cid: Some(ClientOrderId(format!("book-{}", id))),
+                                                                                 side,
+                                                                                 state: Open { id: OrderId(id),
+                                                                                               price,
+                                                                                               size,
+                                                                                               filled_quantity: 0.0,
+                                                                                               order_role: OrderRole::Maker,
+                                                                                               position_side: PositionSide::Both } };
+        {
+            let open_book = account.account_open_book.read().await;
+            let mut orders = open_book.get_ins_orders_mut(&instrument).unwrap();
+            orders.add_order_open(resting_order(Side::Buy, 100.0, 2.0, 1));
+            orders.add_order_open(resting_order(Side::Buy, 99.0, 2.0, 2));
+            orders.add_order_open(resting_order(Side::Sell, 110.0, 1.0, 3));
+            orders.add_order_open(resting_order(Side::Sell, 105.0, 1.0, 4));
+        }
+
+        let book = |timestamp: i64, is_snapshot: bool, bids: Vec<(f64, f64)>, asks: Vec<(f64, f64)>| L2BookMessage { exchange: "binance-futures".to_string(),
+                                                                                                                     symbol: "ETHUSDT".to_string(),
+                                                                                                                     timestamp,
+                                                                                                                     is_snapshot,
+                                                                                                                     first_sequence: None,
+                                                                                                                     sequence: None,
+                                                                                                                     prev_sequence: None,
+                                                                                                                     bids,
+                                                                                                                     asks };
+
+        // 订单簿同步之前的增量消息不参与撮合
+        let outcome = account.handle_book_update(&book(2_000, false, vec![], vec![(99.5, 1.0)])).await.unwrap();
+        assert_eq!(outcome, L2ApplyOutcome::AwaitingSnapshot);
+        assert_eq!(account.account_open_book.read().await.fetch_all().len(), 4);
+
+        // 快照的卖盘压到 100 的买单之下，买单按 99.5 与 100 两档共 1.5 的挂单量以挂单价格成交，99 的买单不受影响
+        account.handle_book_update(&book(3_000, true, vec![(98.0, 5.0)], vec![(99.5, 1.0), (100.0, 0.5), (101.0, 4.0)])).await.unwrap();
+        let mut fills = vec![];
+        while let Ok(event) = event_rx.try_recv() {
+            if let AccountEventKind::Trade(trade) = event.kind {
+                fills.push((trade.side, trade.price, trade.size));
+            }
+        }
+        assert_eq!(fills, vec![(Side::Buy, 100.0, 1.0), (Side::Buy, 100.0, 0.5)]);
+        {
+            let open_book = account.account_open_book.read().await;
+            let orders = open_book.get_ins_orders_mut(&instrument).unwrap();
+            assert_eq!(orders.bids.iter().map(|order| (order.state.price, order.state.filled_quantity)).collect::<Vec<_>>(), vec![(99.0, 0.0), (100.0, 1.5)]);
+        }
+        // 被消耗的两档从订单簿中扣除，最优卖价随之变为 101
+        assert_eq!(account.l2_books.book(&instrument).unwrap().best_ask(), Some((101.0, 4.0)));
+        assert_eq!(account.single_level_order_book.lock().await.get(&instrument).unwrap().latest_ask, 101.0);
+
+        // 买盘抬到 106，只有 105 的卖单成交，110 的卖单仍在挂单
+        account.handle_book_update(&book(4_000, false, vec![(106.0, 3.0)], vec![])).await.unwrap();
+        let mut fills = vec![];
+        while let Ok(event) = event_rx.try_recv() {
+            if let AccountEventKind::Trade(trade) = event.kind {
+                fills.push((trade.side, trade.price, trade.size));
+            }
+        }
+        assert_eq!(fills, vec![(Side::Sell, 105.0, 1.0)]);
+        let open_book = account.account_open_book.read().await;
+        let orders = open_book.get_ins_orders_mut(&instrument).unwrap();
+        assert_eq!(orders.asks.iter().map(|order| order.state.price).collect::<Vec<_>>(), vec![110.0]);
+    }
 }
@@ -0,0 +1,71 @@
+use crate::common::token::Token;
+use std::collections::HashMap;
+
+/// 一小时的毫秒数，现货杠杆借款按整小时计息。
+pub const ONE_HOUR_MS: i64 = 60 * 60 * 1000;
+
+/// 现货杠杆借款的计息与风险状态。
+///
+/// 借款余额本身记录在 [`Balance::borrowed`](crate::common::balance::Balance::borrowed) 中，这里只记录计息进度、
+/// 每个币种累计计提的利息，以及是否已经发出过追加保证金提醒。
+#[derive(Clone, Debug, Default)]
+pub struct MarginLoanBook
+{
+    last_interest_ts: Option<i64>,          // 上次计息的交易所时间
+    accrued_interest: HashMap<Token, f64>,  // 每个币种累计计提的利息
+    pub margin_call_active: bool,           // 风险率是否处于追加保证金提醒线之下
+}
+
+impl MarginLoanBook
+{
+    /// 返回从上次计息至 `now` 经过的整小时数，并把计息进度推进到最后一个整小时。
+    /// 第一次调用只记录起始时间。
+    pub fn hours_due(&mut self, now: i64) -> i64
+    {
+        match self.last_interest_ts {
+            | None => {
+                self.last_interest_ts = Some(now);
+                0
+            }
+            | Some(last) => {
+                let hours = (now - last).max(0) / ONE_HOUR_MS;
+                self.last_interest_ts = Some(last + hours * ONE_HOUR_MS);
+                hours
+            }
+        }
+    }
+
+    /// 登记某个币种计提的利息
+    pub fn record_interest(&mut self, token: &Token, interest: f64)
+    {
+        *self.accrued_interest.entry(token.clone()).or_insert(0.0) += interest;
+    }
+
+    /// 返回某个币种累计计提的利息
+    pub fn accrued_interest(&self, token: &Token) -> f64
+    {
+        self.accrued_interest.get(token).copied().unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn margin_loan_book_should_count_whole_hours()
+    {
+        let mut book = MarginLoanBook::default();
+        assert_eq!(book.hours_due(1_000), 0);
+        assert_eq!(book.hours_due(1_000 + ONE_HOUR_MS - 1), 0);
+        assert_eq!(book.hours_due(1_000 + 2 * ONE_HOUR_MS + 500), 2);
+        // 不足一小时的部分留到下一次
+        assert_eq!(book.hours_due(1_000 + 3 * ONE_HOUR_MS), 1);
+
+        book.record_interest(&Token::from("USDT"), 1.5);
+        book.record_interest(&Token::from("USDT"), 0.5);
+        assert_eq!(book.accrued_interest(&Token::from("USDT")), 2.0);
+        assert_eq!(book.accrued_interest(&Token::from("BTC")), 0.0);
+    }
+}
@@ -135,7 +135,8 @@ impl AccountOrders
                               price: request.state.price,
                               size: request.state.size,
                               filled_quantity: 0.0,
-                              order_role: role } }
+                              order_role: role,
+                              position_side: request.state.position_side } }
     }
 
     /// 增加请求计数器的值。
@@ -498,7 +499,8 @@ impl LatencySimulator for AccountOrders
                 side: order.side,
                 state: RequestOpen { reduce_only: order.state.reduce_only,
                                      price: order.state.price,
-                                     size: order.state.size } }
+                                     size: order.state.size,
+                                     position_side: order.state.position_side } }
     }
 
     /// 更新账户的延迟值。
@@ -521,6 +523,7 @@ mod tests
     use super::*;
     use crate::{
         common::{
+            account_positions::PositionSide,
             instrument::{kind::InstrumentKind, Instrument},
             order::identification,
         },
@@ -659,7 +662,8 @@ mod tests
                             side: Side::Buy,
                             state: RequestOpen { reduce_only: false,
                                                  price: 35000.0,
-                                                 size: 0.1 } };
+                                                 size: 0.1,
+                                                 position_side: PositionSide::Both } };
 
         let simulated_order = account_orders.process_backtest_requestopen_with_a_simulated_latency(order).await;
         assert!(simulated_order.timestamp >= 1625232523000 + 10); // Assuming latency is at least 10
@@ -681,7 +685,8 @@ mod tests
                             side: Side::Buy,
                             state: RequestOpen { reduce_only: false,
                                                  price: 35000.0,
-                                                 size: 0.1 } };
+                                                 size: 0.1,
+                                                 position_side: PositionSide::Both } };
 
         // 构建模拟的订单簿
         let order_book = SingleLevelOrderBook { latest_bid: 34900.0,
@@ -709,7 +714,8 @@ mod tests
                             side: Side::Buy,
                             state: RequestOpen { reduce_only: false,
                                                  price: 35000.0, // 买单价格
-                                                 size: 0.1 } };
+                                                 size: 0.1,
+                                                 position_side: PositionSide::Both } };
 
         // 成功场景：Post-Only 买单，挂单价格低于市场价格，成为 Maker
         let result = account_orders.determine_post_only_order_role(&order, 35001.0);
@@ -735,7 +741,8 @@ mod tests
                             side: Side::Buy,
                             state: RequestOpen { reduce_only: false,
                                                  price: 35000.0,
-                                                 size: 0.1 } };
+                                                 size: 0.1,
+                                                 position_side: PositionSide::Both } };
 
         let open_order = account_orders.build_order_open(order, OrderRole::Maker).await;
 
@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+
+/// 30 天的毫秒数，即手续费等级考核的默认窗口。
+pub const THIRTY_DAYS_MS: i64 = 30 * 24 * 60 * 60 * 1000;
+
+/// 按交易所时间统计滚动窗口内的成交额，用于按成交量自动调整手续费等级。
+///
+/// 成交额以 quote 计（见 [`Instrument::quote_notional`](crate::common::instrument::Instrument::quote_notional)），强平与自动减仓的成交不计入。
+#[derive(Clone, Debug)]
+pub struct TradingVolumeTracker
+{
+    window_ms: i64,               // 统计窗口长度（毫秒）
+    trades: VecDeque<(i64, f64)>, // 窗口内每笔成交的时间戳与成交额
+    total_volume: f64,            // 窗口内的成交额合计
+}
+
+impl Default for TradingVolumeTracker
+{
+    fn default() -> Self
+    {
+        Self::new(THIRTY_DAYS_MS)
+    }
+}
+
+impl TradingVolumeTracker
+{
+    pub fn new(window_ms: i64) -> Self
+    {
+        Self { window_ms,
+               trades: VecDeque::new(),
+               total_volume: 0.0 }
+    }
+
+    /// 登记一笔成交，同时移除已经滑出窗口的记录。
+    pub fn record(&mut self, timestamp: i64, notional: f64)
+    {
+        self.trades.push_back((timestamp, notional));
+        self.total_volume += notional;
+
+        while let Some(&(earliest, volume)) = self.trades.front() {
+            if timestamp - earliest < self.window_ms {
+                break;
+            }
+            self.total_volume -= volume;
+            self.trades.pop_front();
+        }
+    }
+
+    /// 返回截至 `now` 的滚动窗口成交额。
+    pub fn volume(&self, now: i64) -> f64
+    {
+        let expired: f64 = self.trades.iter().take_while(|(timestamp, _)| now - timestamp >= self.window_ms).map(|(_, volume)| volume).sum();
+        (self.total_volume - expired).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn trading_volume_should_roll_off_after_window()
+    {
+        let mut tracker = TradingVolumeTracker::new(1_000);
+        tracker.record(0, 100.0);
+        tracker.record(500, 50.0);
+        assert_eq!(tracker.volume(500), 150.0);
+        assert_eq!(tracker.volume(999), 150.0);
+        assert_eq!(tracker.volume(1_000), 50.0);
+        assert_eq!(tracker.volume(1_500), 0.0);
+
+        tracker.record(1_200, 30.0);
+        assert_eq!(tracker.volume(1_200), 80.0);
+    }
+}
@@ -1,9 +1,9 @@
 use crate::{
     common::{
-        account_positions::{exited_positions::AccountExitedPositions, AccountPositions, PositionDirectionMode},
+        account_positions::{exited_positions::AccountExitedPositions, leveraged_token::LeveragedTokenNav, AccountPositions, PositionDirectionMode, PositionSide},
         balance::{Balance, BalanceDelta, TokenBalance},
         event::{AccountEvent, AccountEventKind},
-        instrument::Instrument,
+        instrument::{kind::InstrumentKind, symbol_registry::SymbolRegistry, Instrument},
         order::{
             identification::{client_order_id::ClientOrderId, machine_id::generate_machine_id},
             order_instructions::OrderInstruction,
@@ -17,12 +17,22 @@ use crate::{
     hourglass::{
         account::{
             account_config::{ConfigLoader, FeesQuerier, HourglassMode},
-            account_handlers::{balance_handler::BalanceHandler, position_handler::PositionHandler, trade_handler::TradeHandler},
+            account_handlers::{
+                balance_handler::BalanceHandler,
+                leveraged_token_handler::LeveragedTokenHandler,
+                position_handler::PositionHandler,
+                risk_handler::{OrderRateLimiter, RiskHandler},
+                trade_handler::TradeHandler,
+            },
             account_orders::{LatencySimulator, OrderRoleClassifier},
+            account_margin_loan::MarginLoanBook,
+            account_volume::TradingVolumeTracker,
         },
         clickhouse_api::datatype::single_level_order_book::{OrderBookUpdater, SingleLevelOrderBook},
+        l2_order_book::L2BookBuilder,
+        risk_reserve::RiskReserve,
     },
-    hourglass_log::info,
+    hourglass_log::{info, warn},
     Exchange,
 };
 use account_config::AccountConfig;
@@ -48,8 +58,10 @@ use uuid::Uuid;
 pub mod account_config;
 pub mod account_handlers;
 pub mod account_latency;
+pub mod account_margin_loan;
 pub mod account_market_feed;
 pub mod account_orders;
+pub mod account_volume;
 
 #[derive(Debug)]
 pub struct HourglassAccount
@@ -70,6 +82,13 @@ pub struct HourglassAccount
     pub positions: AccountPositions,                                                    // 帐户持仓
     pub exited_positions: AccountExitedPositions,                                       // pub vault: Vault,
     pub account_margin: Arc<AtomicF64>,
+    pub risk_reserve: Arc<Mutex<RiskReserve>>,                        // 风险准备金池
+    pub order_rate_limiter: OrderRateLimiter,                         // 按交易所时间统计下单与撤单频率
+    pub trading_volume: TradingVolumeTracker,                         // 滚动 30 天成交额，决定当前手续费等级
+    pub leveraged_token_navs: HashMap<Instrument, LeveragedTokenNav>, // 杠杆代币的净值状态
+    pub margin_loans: MarginLoanBook,                                 // 现货杠杆借款的计息与风险状态
+    pub symbol_registry: SymbolRegistry,                              // 行情符号到金融工具的映射
+    pub l2_books: L2BookBuilder,                                      // 由 L2 快照与增量更新重建的全深度订单簿
 }
 
 // 手动实现 Clone trait
@@ -88,7 +107,14 @@ impl Clone for HourglassAccount
                            balances: self.balances.clone(),
                            positions: self.positions.clone(),
                            exited_positions: self.exited_positions.clone(),
-                           account_margin: self.account_margin.clone() }
+                           account_margin: self.account_margin.clone(),
+                           risk_reserve: Arc::clone(&self.risk_reserve),
+                           order_rate_limiter: self.order_rate_limiter.clone(),
+                           trading_volume: self.trading_volume.clone(),
+                           leveraged_token_navs: self.leveraged_token_navs.clone(),
+                           margin_loans: self.margin_loans.clone(),
+                           symbol_registry: self.symbol_registry.clone(),
+                           l2_books: self.l2_books.clone() }
     }
 }
 #[derive(Debug)]
@@ -100,6 +126,7 @@ pub struct AccountBuilder
     balances: Option<DashMap<Token, Balance>>,
     positions: Option<AccountPositions>,
     closed_positions: Option<AccountExitedPositions>,
+    symbol_registry: Option<SymbolRegistry>,
 }
 
 impl Default for AccountBuilder
@@ -119,7 +146,8 @@ impl AccountBuilder
                          orders: None,
                          balances: None,
                          positions: None,
-                         closed_positions: None }
+                         closed_positions: None,
+                         symbol_registry: None }
     }
 
     pub fn account_event_tx(mut self, value: UnboundedSender<AccountEvent>) -> Self
@@ -152,6 +180,12 @@ impl AccountBuilder
         self
     }
 
+    pub fn symbol_registry(mut self, value: SymbolRegistry) -> Self
+    {
+        self.symbol_registry = Some(value);
+        self
+    }
+
     pub fn build(self) -> Result<HourglassAccount, String>
     {
         Ok(HourglassAccount { current_session: Uuid::new_v4(),
@@ -165,7 +199,14 @@ impl AccountBuilder
                               positions: self.positions.ok_or("positions are required")?,
                               single_level_order_book: Arc::new(Mutex::new(HashMap::new())),
                               exited_positions: self.closed_positions.ok_or("closed_positions sink are required")?,
-                              account_margin: Arc::new(0.0.into()) })
+                              account_margin: Arc::new(0.0.into()),
+                              risk_reserve: Arc::new(Mutex::new(RiskReserve::default())),
+                              order_rate_limiter: OrderRateLimiter::default(),
+                              trading_volume: TradingVolumeTracker::default(),
+                              leveraged_token_navs: HashMap::new(),
+                              margin_loans: MarginLoanBook::default(),
+                              symbol_registry: self.symbol_registry.ok_or("symbol_registry is required")?,
+                              l2_books: L2BookBuilder::default() })
     }
 }
 
@@ -189,7 +230,8 @@ impl HourglassAccount
             self.balances.entry(token.clone()).or_insert_with(|| Balance { time: Utc::now(),
                                                                            // current_price: Some(1.0), // 假设初始价格为 1.0，具体根据实际情况调整
                                                                            total: 0.0,
-                                                                           available: 0.0 });
+                                                                           available: 0.0,
+                                                                           borrowed: 0.0 });
         }
         Ok(())
     }
@@ -212,7 +254,8 @@ impl HourglassAccount
                                                                 Balance { time: Utc::now(),
                                                                           // current_price: Some(1.0), // 假设稳定币价格为1.0
                                                                           total: 0.0,
-                                                                          available: 0.0 }
+                                                                          available: 0.0,
+                                                                          borrowed: 0.0 }
                                                             });
 
         balance.total += amount;
@@ -268,6 +311,7 @@ impl HourglassAccount
 
     /// NOTE : BETA功能，待测试。
     /// 为账户充值 `b本位` 稳定币（BTC）。
+    /// 充值的 BTC 可作为 [`Instrument::inverse_perpetual`] 等币本位合约的保证金。
     ///
     /// # 参数
     /// * `amount` - 充值的数额。
@@ -327,6 +371,7 @@ impl HourglassAccount
     ///
     /// 对于每个开仓请求，该函数根据配置的 `PositionDirectionMode` 来判断是否允许方向冲突。如果是 `NetMode`，则会检查订单方向与当前持仓的方向是否冲突。
     /// 如果订单标记为 `reduce only`，则不会进行方向冲突检查，但仍需判断订单方向与现有持仓方向是否一致。如果 `reduce only` 订单的方向与现有持仓方向相同，将拒绝该订单。
+    /// 如果是 `LongShort` 模式，订单必须通过 `position_side` 指定作用的仓位，平仓单的数量不能超过对应仓位的大小。
     ///
     /// # 参数
     ///
@@ -338,6 +383,7 @@ impl HourglassAccount
     /// 1. 首先检查订单的 `reduce only` 状态：
     ///    - 如果是 `reduce only`，则跳过方向冲突检查，但如果订单方向与当前持仓方向相同，则拒绝该订单。
     /// 2. 如果是 `NetMode` 且订单不是 `reduce only`，则调用 `check_position_direction_conflict` 检查当前持仓方向是否与订单冲突。
+    ///    如果是 `LongShort` 模式，则调用 `check_position_side` 校验订单作用的仓位。
     /// 3. 计算订单的当前价格，并尝试原子性开仓操作。
     /// 4. 将每个订单的处理结果发送到 `response_tx`。
     ///
@@ -349,16 +395,21 @@ impl HourglassAccount
     {
         let mut open_results = Vec::new();
 
-        // 获取当前的 position_direction_mode 并提前判断是否需要进行方向冲突检查
-        let is_netmode = self.config.global_position_direction_mode == PositionDirectionMode::Net;
-
         for request in open_requests {
-            // 如果是 NetMode，检查方向冲突
-            if is_netmode {
-                if let Err(err) = self.check_direction_conflict(&request).await {
-                    open_results.push(Err(err));
-                    continue; // 跳过这个订单
-                }
+            // 风控网关，未通过的订单在结果中单独返回错误
+            if let Err(err) = self.check_order_risk(&request).await {
+                open_results.push(Err(err));
+                continue;
+            }
+
+            // 按该金融工具的持仓方向模式检查方向冲突
+            let check_result = match self.resolve_position_direction_mode(&request.instrument).await {
+                | PositionDirectionMode::Net => self.check_direction_conflict(&request).await,
+                | PositionDirectionMode::LongShort => self.check_position_side(&request).await,
+            };
+            if let Err(err) = check_result {
+                open_results.push(Err(err));
+                continue; // 跳过这个订单
             }
 
             // 处理订单请求，根据模式（回测或实时）选择处理方式
@@ -380,9 +431,24 @@ impl HourglassAccount
         Ok(())
     }
 
+    /// 返回金融工具的持仓方向模式：优先使用该金融工具预设的永续合约仓位配置，没有预设时使用账户的全局设置。
+    async fn resolve_position_direction_mode(&self, instrument: &Instrument) -> PositionDirectionMode
+    {
+        for configs in [&self.positions.perpetual_pos_long_config, &self.positions.perpetual_pos_short_config] {
+            if let Some(config) = configs.read().await.get(instrument) {
+                return config.position_direction_mode().clone();
+            }
+        }
+        self.config.global_position_direction_mode.clone()
+    }
+
     // 辅助函数，用于检查仓位方向冲突
     async fn check_direction_conflict(&self, request: &Order<RequestOpen>) -> Result<(), ExchangeError>
     {
+        if request.state.position_side != PositionSide::Both {
+            return Err(ExchangeError::InvalidRequestOpen(format!("position_side {:?} is not allowed in Net mode", request.state.position_side)));
+        }
+
         if request.state.reduce_only {
             // 获取当前仓位
             let (long_pos, short_pos) = self.get_position_both_ways(&request.instrument).await?;
@@ -409,6 +475,34 @@ impl HourglassAccount
         Ok(())
     }
 
+    /// 双向持仓模式下校验订单作用的仓位：开仓单不能是 `reduce_only`，平仓单必须有对应的仓位，且数量不能超过仓位大小。
+    async fn check_position_side(&self, request: &Order<RequestOpen>) -> Result<(), ExchangeError>
+    {
+        let position_side = request.state.position_side;
+        if position_side == PositionSide::Both {
+            return Err(ExchangeError::InvalidRequestOpen("position_side must be Long or Short in LongShort mode".into()));
+        }
+
+        if !position_side.is_closing(request.side) {
+            return match request.state.reduce_only {
+                | true => Err(ExchangeError::InvalidDirection),
+                | false => Ok(()),
+            };
+        }
+
+        let (long_pos, short_pos) = self.get_position_both_ways(&request.instrument).await?;
+        let position = match position_side.leg(request.side) {
+            | Side::Buy => long_pos,
+            | Side::Sell => short_pos,
+        };
+        let position_size = position.map(|position| position.current_size()).ok_or(ExchangeError::InvalidDirection)?;
+        if request.state.size > position_size {
+            return Err(ExchangeError::InvalidRequestOpen(format!("Closing size {} exceeds {:?} position size {}", request.state.size, position_side, position_size)));
+        }
+
+        Ok(())
+    }
+
     // #[allow(dead_code)]
     // // 辅助函数，用于获取当前市场价格 // NOTE 要处理不同的InstrumentKind,现在是不对的
     // async fn get_current_price(&self, order: &Order<RequestOpen>) -> Result<f64, ExchangeError>
@@ -440,12 +534,27 @@ impl HourglassAccount
 
         info!("[attempt_atomic_open] : Successfully validated order instruction");
 
+        // 已到期的交割合约不再接受新订单
+        if order.instrument.is_expired(self.exchange_timestamp.load(Ordering::SeqCst)) {
+            return Err(ExchangeError::OrderRejected(format!("{} has expired", order.instrument)));
+        }
+
+        // 假设订单与现有挂单全部成交，检查潜在仓位是否超过仓位限额
+        self.check_position_limits(&order).await?;
+
+        // 杠杆代币没有订单簿，按当前净值立即成交
+        if order.instrument.kind == InstrumentKind::CryptoLeveragedToken {
+            return Ok(self.execute_leveraged_token_order(order).await?.0);
+        }
+
         // 将锁的作用域限制在这个块内， 通过和订单簿比较价格来判断是潜在的 Taker 还是 Maker。
         let order_role = {
             let mut order_books_lock = self.single_level_order_book.lock().await;
             info!("[attempt_atomic_open] order_books_lock: {:?}", order_books_lock);
             info!("instrument is {:#?}", order.instrument);
-            let order_book = order_books_lock.get_mut(&order.instrument).unwrap(); // 引用的生命周期延长
+            // 行情中的交割合约不带到期时间，找不到该合约自身的订单簿时使用不带到期时间的订单簿
+            let book_key = if order_books_lock.contains_key(&order.instrument) { order.instrument.clone() } else { order.instrument.market_instrument() };
+            let order_book = order_books_lock.get_mut(&book_key).unwrap(); // 引用的生命周期延长
             let orders_guard = self.account_open_book.read().await;
             // 将订单簿传递给 determine_maker_taker
             orders_guard.determine_maker_taker(&order, order_book)?
@@ -454,7 +563,12 @@ impl HourglassAccount
         // 锁已经在此处释放，后续操作可以安全地借用 `self` NOTE 此处计算required_available_balance要分离出maker的处理规则
         let (token, required_balance) = self.required_available_balance(&order, order_role).await?;
         info!("[attempt_atomic_open] required balance is quoted in {}: {}", token, required_balance);
-        self.has_sufficient_available_balance(token, required_balance)?;
+        if self.uses_cross_margin(&order).await {
+            self.has_sufficient_available_margin(token, required_balance).await?;
+        }
+        else {
+            self.has_sufficient_available_balance(token, required_balance)?;
+        }
 
         let open_order = {
             let mut orders_guard = self.account_open_book.write().await;
@@ -560,7 +674,11 @@ impl HourglassAccount
         let mut results = Vec::with_capacity(cancel_requests.len());
 
         for request in cancel_requests {
-            let result = self.atomic_cancel(request).await;
+            // 撤单同样计入请求频率
+            let result = match self.check_order_rate() {
+                | Ok(()) => self.atomic_cancel(request).await,
+                | Err(err) => Err(err),
+            };
             results.push(result);
         }
 
@@ -700,6 +818,43 @@ impl HourglassAccount
         }
     }
 
+    /// 撤销已到期的交割合约与期权上的全部挂单，释放挂单时预留的余额。撤单失败时只记录警告。
+    pub async fn cancel_expired_orders(&mut self) -> Vec<Order<Cancelled>>
+    {
+        let exchange_timestamp = self.exchange_timestamp.load(Ordering::SeqCst);
+        self.cancel_orders_where(|instrument| instrument.is_expired(exchange_timestamp)).await
+    }
+
+    /// 撤销 `predicate` 选中的金融工具上的全部挂单，返回成功撤销的订单。
+    pub async fn cancel_orders_where(&mut self, predicate: impl Fn(&Instrument) -> bool + Send) -> Vec<Order<Cancelled>>
+    {
+        let exchange_timestamp = self.exchange_timestamp.load(Ordering::SeqCst);
+        let selected_orders: Vec<Order<Open>> = self.account_open_book
+                                                    .read()
+                                                    .await
+                                                    .instrument_orders_map
+                                                    .iter()
+                                                    .filter(|entry| predicate(entry.key()))
+                                                    .flat_map(|entry| entry.value().bids.iter().chain(entry.value().asks.iter()).cloned().collect::<Vec<_>>())
+                                                    .collect();
+
+        let mut cancelled_orders = Vec::with_capacity(selected_orders.len());
+        for order in selected_orders {
+            let request = Order { state: RequestCancel { id: Some(order.state.id) },
+                                  instrument: order.instrument,
+                                  side: order.side,
+                                  instruction: order.instruction,
+                                  cid: order.cid,
+                                  exchange: Exchange::Hourglass,
+                                  timestamp: exchange_timestamp };
+            match self.atomic_cancel(request).await {
+                | Ok(cancelled) => cancelled_orders.push(cancelled),
+                | Err(err) => warn!("Failed to cancel order: {:?}", err),
+            }
+        }
+        cancelled_orders
+    }
+
     /// [PART 3] - [Miscellaneous]
 
     pub(crate) fn get_exchange_ts(&self) -> Result<i64, ExchangeError>
@@ -759,7 +914,7 @@ mod tests
             instrument::kind::InstrumentKind,
             order::{identification::OrderId, states::request_open::RequestOpen},
         },
-        test_utils::create_test_account,
+        test_utils::{create_test_account, create_test_perpetual_position, create_test_request_open},
     };
 
     #[tokio::test]
@@ -769,13 +924,17 @@ mod tests
                             exchange: Exchange::Hourglass,
                             instrument: Instrument { base: Token::from("BTC"),
                                                      quote: Token::from("USD"),
-                                                     kind: InstrumentKind::Spot },
+                                                     kind: InstrumentKind::Spot,
+                                                     inverse: false,
+                                                     expiry: None,
+                                                     option_spec: None },
                             timestamp: 1625247600000,
                             cid: Some(ClientOrderId("validCID123".into())),
                             side: Side::Buy,
                             state: RequestOpen { price: 50000.0,
                                                  size: 1.0,
-                                                 reduce_only: false } };
+                                                 reduce_only: false,
+                                                 position_side: PositionSide::Both } };
 
         assert!(HourglassAccount::validate_order_request_open(&order).is_ok());
 
@@ -791,7 +950,10 @@ mod tests
                                    exchange: Exchange::Hourglass,
                                    instrument: Instrument { base: Token::from("BTC"),
                                                             quote: Token::from("USD"),
-                                                            kind: InstrumentKind::Spot },
+                                                            kind: InstrumentKind::Spot,
+                                                            inverse: false,
+                                                            expiry: None,
+                                                            option_spec: None },
                                    timestamp: 1625247600000,
                                    cid: Some(ClientOrderId("validCID123".into())),
                                    side: Side::Buy,
@@ -898,4 +1060,51 @@ mod tests
         assert_eq!(usdt_balance.total, 10_000.0);
         assert_eq!(btc_balance.total, usdt_amount / btc_price);
     }
+
+    #[tokio::test]
+    async fn test_check_position_side_in_long_short_mode()
+    {
+        let mut account = create_test_account().await;
+        account.config.global_position_direction_mode = PositionDirectionMode::LongShort;
+
+        let mut request = create_test_request_open("ETH", "USDT");
+        request.instrument.kind = InstrumentKind::Perpetual;
+        assert_eq!(account.resolve_position_direction_mode(&request.instrument).await, PositionDirectionMode::LongShort);
+
+        // 双向持仓模式下必须指定仓位
+        assert!(matches!(account.check_position_side(&request).await, Err(ExchangeError::InvalidRequestOpen(_))));
+
+        // 买入开多
+        request.state.position_side = PositionSide::Long;
+        assert!(account.check_position_side(&request).await.is_ok());
+
+        // 没有多头仓位时不能卖出平多
+        request.side = Side::Sell;
+        assert!(matches!(account.check_position_side(&request).await, Err(ExchangeError::InvalidDirection)));
+
+        // 平仓数量不能超过多头仓位大小
+        account.positions.perpetual_pos_long.write().await.insert(request.instrument.clone(), create_test_perpetual_position(request.instrument.clone()));
+        request.state.size = 2.0;
+        assert!(matches!(account.check_position_side(&request).await, Err(ExchangeError::InvalidRequestOpen(_))));
+        request.state.size = 1.0;
+        assert!(account.check_position_side(&request).await.is_ok());
+
+        // 卖出开空不受多头仓位影响，但开仓单不能是 reduce_only
+        request.state.position_side = PositionSide::Short;
+        request.state.size = 5.0;
+        assert!(account.check_position_side(&request).await.is_ok());
+        request.state.reduce_only = true;
+        assert!(matches!(account.check_position_side(&request).await, Err(ExchangeError::InvalidDirection)));
+    }
+
+    #[tokio::test]
+    async fn test_net_mode_rejects_long_short_position_side()
+    {
+        let account = create_test_account().await;
+        let mut request = create_test_request_open("ETH", "USDT");
+        request.instrument.kind = InstrumentKind::Perpetual;
+        request.state.position_side = PositionSide::Long;
+
+        assert!(matches!(account.check_direction_conflict(&request).await, Err(ExchangeError::InvalidRequestOpen(_))));
+    }
 }
@@ -0,0 +1,21 @@
+use crate::hourglass::clickhouse_api::queries_operations::Row;
+use serde::{Deserialize, Serialize};
+
+/// Tardis `derivative_ticker` 文件中的一行：衍生品的资金费率、持仓量与各类价格。
+///
+/// 交易所未提供的字段在文件中为空，对应 `None`。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Row)]
+pub struct DerivativeTicker
+{
+    pub exchange: String,
+    pub symbol: String,
+    pub timestamp: i64,                 // 交易所时间戳（毫秒，读取时由 Tardis 的微秒换算）
+    pub local_timestamp: i64,           // 接收时间戳（毫秒，读取时由 Tardis 的微秒换算）
+    pub funding_timestamp: Option<i64>, // 下一次资金费结算时间（毫秒）
+    pub funding_rate: Option<f64>,
+    pub predicted_funding_rate: Option<f64>,
+    pub open_interest: Option<f64>,
+    pub last_price: Option<f64>,
+    pub index_price: Option<f64>,
+    pub mark_price: Option<f64>,
+}
@@ -0,0 +1,19 @@
+use crate::hourglass::clickhouse_api::queries_operations::Row;
+use serde::{Deserialize, Serialize};
+
+/// Tardis `incremental_book_L2` 文件中的一行：某个价格层级的最新挂单量。
+///
+/// `amount` 为该价格层级更新后的总量而不是变化量，为 0 表示移除该层级。
+/// `is_snapshot` 为真的连续多行组成一次完整的订单簿快照。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Row)]
+pub struct IncrementalBookL2
+{
+    pub exchange: String,
+    pub symbol: String,
+    pub timestamp: i64,       // 交易所时间戳（毫秒，读取时由 Tardis 的微秒换算）
+    pub local_timestamp: i64, // 接收时间戳（毫秒，读取时由 Tardis 的微秒换算）
+    pub is_snapshot: bool,
+    pub side: String, // `bid` 或 `ask`
+    pub price: f64,
+    pub amount: f64,
+}
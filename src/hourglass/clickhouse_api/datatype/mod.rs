@@ -1,3 +1,6 @@
 pub mod clickhouse_trade_data;
+pub mod derivative_ticker;
+pub mod incremental_book_l2;
 pub mod order_book_25;
 pub mod single_level_order_book;
+pub mod tardis_trade;
@@ -17,6 +17,9 @@ pub struct SingleLevelOrderBook
 pub trait OrderBookUpdater
 {
     fn update_from_trade(&mut self, market_trade: &MarketTrade);
+
+    /// 用 L2 订单簿的真实最优报价更新买卖价，不再根据成交方向推断。
+    fn update_from_quotes(&mut self, best_bid: f64, best_ask: f64);
 }
 
 impl OrderBookUpdater for SingleLevelOrderBook
@@ -50,6 +53,16 @@ impl OrderBookUpdater for SingleLevelOrderBook
         // 始终更新最新的交易价格
         self.latest_price = market_trade.price;
     }
+
+    fn update_from_quotes(&mut self, best_bid: f64, best_ask: f64)
+    {
+        self.latest_bid = best_bid;
+        self.latest_ask = best_ask;
+        // 还没有成交时以中间价作为最新价格
+        if self.latest_price == 0.0 {
+            self.latest_price = (best_bid + best_ask) / 2.0;
+        }
+    }
 }
 
 impl From<&MarketTrade> for SingleLevelOrderBook
@@ -0,0 +1,30 @@
+use crate::hourglass::clickhouse_api::{datatype::clickhouse_trade_data::MarketTrade, queries_operations::Row};
+use serde::{Deserialize, Serialize};
+
+/// Tardis `trades` 文件中的一行，保留成交 `id` 以便合并表按 `(timestamp, id)` 去重。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Row)]
+pub struct TardisTrade
+{
+    pub exchange: String,
+    pub symbol: String,
+    pub timestamp: i64,       // 交易所时间戳（毫秒，读取时由 Tardis 的微秒换算）
+    pub local_timestamp: i64, // 接收时间戳（毫秒，读取时由 Tardis 的微秒换算）
+    pub id: String,
+    pub side: String,
+    pub price: f64,
+    pub amount: f64,
+}
+
+/// `TardisTrade` 的时间戳在读取文件时已换算为毫秒，与 `MarketTrade` 一致，直接沿用。
+impl From<TardisTrade> for MarketTrade
+{
+    fn from(trade: TardisTrade) -> Self
+    {
+        MarketTrade { exchange: trade.exchange,
+                      symbol: trade.symbol,
+                      side: trade.side,
+                      price: trade.price,
+                      timestamp: trade.timestamp,
+                      amount: trade.amount }
+    }
+}
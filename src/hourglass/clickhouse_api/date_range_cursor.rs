@@ -0,0 +1,158 @@
+use std::collections::{HashSet, VecDeque};
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use clickhouse::query::RowCursor;
+
+use crate::{
+    common::datafeed::replay::ReplaySource,
+    error::ExchangeError,
+    hourglass::{
+        clickhouse_api::{datatype::clickhouse_trade_data::MarketTrade, queries_operations::ClickHouseClient},
+        utils::chrono_operations::dates_between,
+    },
+    hourglass_log::{info, warn},
+};
+
+/// 日期范围内缺少当日合并表时的处理方式。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MissingDatePolicy
+{
+    /// 记录并跳过缺失的日期，继续回放其余日期。
+    Skip,
+    /// 只要有日期缺失就拒绝创建回放游标。
+    Fail,
+}
+
+/// 按日期顺序依次回放连续多天的合并成交表（`*_trades_union_YYYY_MM_DD`）。
+///
+/// 当天的游标读完后自动打开下一天的游标，对交易所事件循环来说就是一条连续的数据流。
+pub struct DateRangeCursor
+{
+    client: ClickHouseClient,
+    exchange: String,
+    instrument: String,
+    pending_dates: VecDeque<String>,                   // 尚未打开游标的日期
+    current: Option<(String, RowCursor<MarketTrade>)>, // 当前正在回放的日期与游标
+    missing_dates: Vec<String>,                        // 缺少合并表而被跳过的日期
+}
+
+impl DateRangeCursor
+{
+    /// 查询数据库中已有的合并表，创建 `start_date` 到 `end_date`（含两端）的回放游标。
+    pub async fn new(client: ClickHouseClient, exchange: &str, instrument: &str, start_date: NaiveDate, end_date: NaiveDate, policy: MissingDatePolicy) -> Result<Self, ExchangeError>
+    {
+        let database = client.construct_database_name(exchange, instrument, "trades");
+        let union_tables: HashSet<String> = client.get_union_table_names(&database).await.into_iter().collect();
+        Self::with_union_tables(client, exchange, instrument, start_date, end_date, &union_tables, policy)
+    }
+
+    /// 根据已知的合并表名单划分可回放的日期与缺失的日期。
+    ///
+    /// 起始日期晚于结束日期，或者跳过缺失日期后没有任何可回放的日期时返回 [`ExchangeError::InvalidDates`]。
+    fn with_union_tables(client: ClickHouseClient,
+                         exchange: &str,
+                         instrument: &str,
+                         start_date: NaiveDate,
+                         end_date: NaiveDate,
+                         union_tables: &HashSet<String>,
+                         policy: MissingDatePolicy)
+                         -> Result<Self, ExchangeError>
+    {
+        if start_date > end_date {
+            return Err(ExchangeError::InvalidDates(format!("Start date {} is after end date {}", start_date, end_date)));
+        }
+
+        let (pending_dates, missing_dates): (Vec<String>, Vec<String>) =
+            dates_between(start_date, end_date).into_iter()
+                                               .partition(|date| union_tables.contains(&client.construct_union_table_name(exchange, instrument, "trades", date)));
+
+        if !missing_dates.is_empty() {
+            match policy {
+                | MissingDatePolicy::Fail => {
+                    return Err(ExchangeError::InvalidDates(format!("Missing union tables for {} {} on: {}", exchange, instrument, missing_dates.join(", "))));
+                }
+                | MissingDatePolicy::Skip => warn!("Skipping {} dates without union tables for {} {}: {:?}", missing_dates.len(), exchange, instrument, missing_dates),
+            }
+        }
+        if pending_dates.is_empty() {
+            return Err(ExchangeError::InvalidDates(format!("No union tables for {} {} between {} and {}", exchange, instrument, start_date, end_date)));
+        }
+
+        Ok(Self { client,
+                  exchange: exchange.to_string(),
+                  instrument: instrument.to_string(),
+                  pending_dates: pending_dates.into(),
+                  current: None,
+                  missing_dates })
+    }
+
+    /// 缺少合并表而被跳过的日期。
+    pub fn missing_dates(&self) -> &[String]
+    {
+        &self.missing_dates
+    }
+
+    /// 尚未回放完的日期，包括当前正在回放的日期。
+    pub fn remaining_dates(&self) -> Vec<String>
+    {
+        self.current.iter().map(|(date, _)| date.clone()).chain(self.pending_dates.iter().cloned()).collect()
+    }
+}
+
+#[async_trait]
+impl ReplaySource<MarketTrade> for DateRangeCursor
+{
+    async fn next_event(&mut self) -> Result<Option<MarketTrade>, ExchangeError>
+    {
+        loop {
+            if self.current.is_none() {
+                let Some(date) = self.pending_dates.pop_front()
+                else {
+                    return Ok(None);
+                };
+                let cursor = self.client
+                                 .cursor_unioned_public_trades(&self.exchange, &self.instrument, &date)
+                                 .await
+                                 .map_err(|error| ExchangeError::MarketDataError(error.to_string()))?;
+                info!("Replaying {} {} trades on {}", self.exchange, self.instrument, date);
+                self.current = Some((date, cursor));
+            }
+
+            if let Some((_, cursor)) = self.current.as_mut() {
+                match cursor.next_event().await? {
+                    | Some(trade) => return Ok(Some(trade)),
+                    // 当天的数据读完，接着打开下一天的游标
+                    | None => self.current = None,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn date_range_cursor_should_report_or_reject_missing_dates()
+    {
+        let start_date = NaiveDate::from_ymd_opt(2024, 5, 4).unwrap();
+        let end_date = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        let union_tables: HashSet<String> = ["binance_futures_trades_union_2024_05_04", "binance_futures_trades_union_2024_05_06"].into_iter().map(String::from).collect();
+
+        let cursor = DateRangeCursor::with_union_tables(ClickHouseClient::new(), "binance", "futures", start_date, end_date, &union_tables, MissingDatePolicy::Skip).unwrap();
+        assert_eq!(cursor.missing_dates(), ["2024_05_05"]);
+        assert_eq!(cursor.remaining_dates(), vec!["2024_05_04", "2024_05_06"]);
+
+        let result = DateRangeCursor::with_union_tables(ClickHouseClient::new(), "binance", "futures", start_date, end_date, &union_tables, MissingDatePolicy::Fail);
+        assert_eq!(result.err(), Some(ExchangeError::InvalidDates("Missing union tables for binance futures on: 2024_05_05".to_string())));
+
+        // 日期范围颠倒或没有任何可回放的日期
+        let result = DateRangeCursor::with_union_tables(ClickHouseClient::new(), "binance", "futures", end_date, start_date, &union_tables, MissingDatePolicy::Skip);
+        assert_eq!(result.err(), Some(ExchangeError::InvalidDates("Start date 2024-05-06 is after end date 2024-05-04".to_string())));
+        let result = DateRangeCursor::with_union_tables(ClickHouseClient::new(), "binance", "futures", start_date, end_date, &HashSet::new(), MissingDatePolicy::Skip);
+        assert_eq!(result.err(), Some(ExchangeError::InvalidDates("No union tables for binance futures between 2024-05-04 and 2024-05-06".to_string())));
+    }
+}
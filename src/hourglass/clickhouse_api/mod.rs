@@ -1,3 +1,5 @@
 pub mod datatype;
+pub mod date_range_cursor;
 pub mod queries_operations;
 pub mod query_builder;
+pub mod tardis_import;
@@ -0,0 +1,217 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    common::datafeed::file_source::{FileFormat, FileSource, FileSourceConfig},
+    error::ExchangeError,
+    hourglass::clickhouse_api::{
+        datatype::{derivative_ticker::DerivativeTicker, incremental_book_l2::IncrementalBookL2, order_book_25::OrderBook25, tardis_trade::TardisTrade},
+        queries_operations::{ClickHouseClient, Row},
+    },
+    hourglass_log::info,
+};
+
+/// `book_snapshot_25` 文件中的档位数量。
+const BOOK_SNAPSHOT_DEPTH: usize = 25;
+
+/// Tardis 的时间戳为微秒，引擎内部统一使用毫秒。
+const MICROS_PER_MILLI: i64 = 1_000;
+
+/// tardis.dev 导出的 CSV 数据类型，名称与 Tardis 的数据类型一致，同时作为数据库名称中的 `channel`。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TardisDataType
+{
+    Trades,            // 逐笔成交，对应 `TardisTrade` / `MarketTrade`
+    IncrementalBookL2, // 增量 L2 订单簿，对应 `IncrementalBookL2`
+    BookSnapshot25,    // 25 档订单簿快照，对应 `OrderBook25`
+    DerivativeTicker,  // 衍生品行情，对应 `DerivativeTicker`
+}
+
+impl TardisDataType
+{
+    pub fn channel(&self) -> &'static str
+    {
+        match self {
+            | TardisDataType::Trades => "trades",
+            | TardisDataType::IncrementalBookL2 => "incremental_book_L2",
+            | TardisDataType::BookSnapshot25 => "book_snapshot_25",
+            | TardisDataType::DerivativeTicker => "derivative_ticker",
+        }
+    }
+
+    /// Tardis 列名到行类型字段名的映射，例如 `asks[0].price` -> `asks_0_price`。
+    fn column_mapping(&self) -> HashMap<String, String>
+    {
+        let mut columns = HashMap::new();
+        if *self == TardisDataType::BookSnapshot25 {
+            for level in 0..BOOK_SNAPSHOT_DEPTH {
+                for side in ["asks", "bids"] {
+                    for field in ["price", "amount"] {
+                        columns.insert(format!("{side}[{level}].{field}"), format!("{side}_{level}_{field}"));
+                    }
+                }
+            }
+        }
+        columns
+    }
+
+    /// Tardis 文件中以微秒记录的时间戳字段。
+    fn timestamp_fields(&self) -> &'static [&'static str]
+    {
+        match self {
+            | TardisDataType::DerivativeTicker => &["timestamp", "local_timestamp", "funding_timestamp"],
+            | _ => &["timestamp", "local_timestamp"],
+        }
+    }
+
+    /// 导入时使用的建表语句，表结构与对应的行类型一致。
+    pub fn create_table_query(&self, database: &str, table: &str) -> String
+    {
+        let columns = match self {
+            | TardisDataType::Trades => "id String, side String, price Float64, amount Float64".to_string(),
+            | TardisDataType::IncrementalBookL2 => "is_snapshot Bool, side String, price Float64, amount Float64".to_string(),
+            | TardisDataType::BookSnapshot25 => (0..BOOK_SNAPSHOT_DEPTH).map(|level| format!("asks_{level}_price Float64, asks_{level}_amount Float64, bids_{level}_price Float64, bids_{level}_amount Float64"))
+                                                                        .collect::<Vec<_>>()
+                                                                        .join(", "),
+            | TardisDataType::DerivativeTicker => ["funding_timestamp Nullable(Int64)",
+                                                   "funding_rate Nullable(Float64)",
+                                                   "predicted_funding_rate Nullable(Float64)",
+                                                   "open_interest Nullable(Float64)",
+                                                   "last_price Nullable(Float64)",
+                                                   "index_price Nullable(Float64)",
+                                                   "mark_price Nullable(Float64)"].join(", "),
+        };
+        let engine = match self {
+            | TardisDataType::Trades => "ReplacingMergeTree() ORDER BY (timestamp, id)",
+            // 同一时间戳可能有多条增量更新，不能去重
+            | TardisDataType::IncrementalBookL2 => "MergeTree() ORDER BY timestamp",
+            | TardisDataType::BookSnapshot25 | TardisDataType::DerivativeTicker => "ReplacingMergeTree() ORDER BY timestamp",
+        };
+        format!("CREATE TABLE IF NOT EXISTS {database}.{table} (exchange String, symbol String, timestamp Int64, local_timestamp Int64, {columns}) ENGINE = {engine}")
+    }
+}
+
+/// 打开一个 Tardis CSV 文件（可以是 `.csv.gz`），逐行解析为行类型 `T`。
+///
+/// Tardis 的微秒时间戳在读取时换算为引擎使用的毫秒，之后的行类型、ClickHouse 表与回放都使用毫秒。
+/// `T` 需要与 `data_type` 对应；`trades` 文件也可以直接读为 `MarketTrade`，多余的列会被忽略，
+/// 因此 `DataSource::File(open_tardis_file(path, TardisDataType::Trades)?)` 可以直接回放 Tardis 成交数据。
+pub fn open_tardis_file<T>(path: impl Into<PathBuf>, data_type: TardisDataType) -> Result<FileSource<T>, ExchangeError>
+    where T: DeserializeOwned
+{
+    let config = data_type.timestamp_fields()
+                          .iter()
+                          .fold(FileSourceConfig::new(path), |config, field| config.divide(*field, MICROS_PER_MILLI));
+    FileSource::open(FileSourceConfig { format: Some(FileFormat::Csv),
+                                        columns: data_type.column_mapping(),
+                                        ..config })
+}
+
+impl ClickHouseClient
+{
+    /// 把一个 Tardis CSV 文件批量写入 `{exchange}_{instrument}_{channel}` 库中按 [`ClickHouseClient::construct_table_name`] 命名的表，
+    /// 数据库与表不存在时自动创建，返回写入的行数。
+    #[allow(clippy::too_many_arguments)]
+    pub async fn import_tardis_file(&self, path: impl Into<PathBuf>, data_type: TardisDataType, exchange: &str, instrument: &str, date: &str, base: &str, quote: &str) -> Result<usize, ExchangeError>
+    {
+        let database = self.construct_database_name(exchange, instrument, data_type.channel());
+        let table = self.construct_table_name(exchange, instrument, data_type.channel(), date, base, quote);
+
+        self.create_database_if_not_exists(&database).await.map_err(|error| ExchangeError::MarketDataError(error.to_string()))?;
+        self.client
+            .read()
+            .await
+            .query(&data_type.create_table_query(&database, &table))
+            .execute()
+            .await
+            .map_err(|error| ExchangeError::MarketDataError(error.to_string()))?;
+
+        let path = path.into();
+        let rows = match data_type {
+            | TardisDataType::Trades => self.insert_rows::<TardisTrade>(&database, &table, open_tardis_file(path, data_type)?).await?,
+            | TardisDataType::IncrementalBookL2 => self.insert_rows::<IncrementalBookL2>(&database, &table, open_tardis_file(path, data_type)?).await?,
+            | TardisDataType::BookSnapshot25 => self.insert_rows::<OrderBook25>(&database, &table, open_tardis_file(path, data_type)?).await?,
+            | TardisDataType::DerivativeTicker => self.insert_rows::<DerivativeTicker>(&database, &table, open_tardis_file(path, data_type)?).await?,
+        };
+        info!("Imported {} rows into {}.{}", rows, database, table);
+        Ok(rows)
+    }
+
+    async fn insert_rows<T>(&self, database: &str, table: &str, mut source: FileSource<T>) -> Result<usize, ExchangeError>
+        where T: Row + Serialize + DeserializeOwned
+    {
+        let client = self.client.read().await;
+        let mut insert = client.insert::<T>(&format!("{}.{}", database, table)).map_err(|error| ExchangeError::MarketDataError(error.to_string()))?;
+        let mut rows = 0;
+        while let Some(row) = source.next_record()? {
+            insert.write(&row).await.map_err(|error| ExchangeError::MarketDataError(error.to_string()))?;
+            rows += 1;
+        }
+        insert.end().await.map_err(|error| ExchangeError::MarketDataError(error.to_string()))?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::hourglass::{clickhouse_api::datatype::clickhouse_trade_data::MarketTrade, l2_order_book::L2BookMessage};
+
+    #[test]
+    fn tardis_files_should_parse_into_row_types()
+    {
+        let dir = tempfile::tempdir().unwrap();
+
+        let trades = dir.path().join("binance-futures_trades_2024-05-05_BTCUSDT.csv");
+        std::fs::write(&trades,
+                       "exchange,symbol,timestamp,local_timestamp,id,side,price,amount\n\
+                        binance-futures,BTCUSDT,1714867200123000,1714867200125000,4919584931,sell,63950.1,0.003\n").unwrap();
+        let trade: TardisTrade = open_tardis_file(&trades, TardisDataType::Trades).unwrap().next_record().unwrap().unwrap();
+        assert_eq!((trade.id.as_str(), trade.timestamp, trade.local_timestamp), ("4919584931", 1714867200123, 1714867200125));
+        // 直接读为 MarketTrade 与经由 TardisTrade 转换得到相同的毫秒时间戳
+        let market_trade: MarketTrade = open_tardis_file(&trades, TardisDataType::Trades).unwrap().next_record().unwrap().unwrap();
+        assert_eq!(market_trade.timestamp, 1714867200123);
+        assert_eq!(market_trade, MarketTrade::from(trade));
+        assert_eq!((market_trade.side.as_str(), market_trade.price, market_trade.amount), ("sell", 63950.1, 0.003));
+
+        let book = dir.path().join("incremental_book_L2.csv");
+        std::fs::write(&book,
+                       "exchange,symbol,timestamp,local_timestamp,is_snapshot,side,price,amount\n\
+                        binance-futures,BTCUSDT,1714867200000000,1714867200001000,true,bid,63950.0,1.5\n\
+                        binance-futures,BTCUSDT,1714867200100000,1714867200101000,false,ask,63951.0,0\n").unwrap();
+        let mut source = open_tardis_file::<IncrementalBookL2>(&book, TardisDataType::IncrementalBookL2).unwrap();
+        let snapshot = source.next_record().unwrap().unwrap();
+        assert_eq!((snapshot.is_snapshot, snapshot.timestamp), (true, 1714867200000));
+        let update = source.next_record().unwrap().unwrap();
+        assert_eq!((update.is_snapshot, update.side.as_str(), update.amount), (false, "ask", 0.0));
+        let message = L2BookMessage::try_from(update.clone()).unwrap();
+        assert_eq!((message.timestamp, message.bids.len(), message.asks), (1714867200100, 0, vec![(63951.0, 0.0)]));
+        let unknown_side = IncrementalBookL2 { side: "mid".to_string(),
+                                               ..update };
+        assert!(matches!(L2BookMessage::try_from(unknown_side), Err(ExchangeError::MarketDataError(_))));
+
+        let snapshot = dir.path().join("book_snapshot_25.csv");
+        let mut header = Vec::new();
+        let mut levels = Vec::new();
+        for level in 0..BOOK_SNAPSHOT_DEPTH {
+            header.extend([format!("asks[{level}].price"), format!("asks[{level}].amount"), format!("bids[{level}].price"), format!("bids[{level}].amount")]);
+            levels.extend([100.0 + level as f64, 1.0, 99.0 - level as f64, 2.0].map(|value| value.to_string()));
+        }
+        std::fs::write(&snapshot,
+                       format!("exchange,symbol,timestamp,local_timestamp,{}\nbinance-futures,BTCUSDT,1714867200000000,1714867200001000,{}\n", header.join(","), levels.join(","))).unwrap();
+        let snapshot: OrderBook25 = open_tardis_file(&snapshot, TardisDataType::BookSnapshot25).unwrap().next_record().unwrap().unwrap();
+        assert_eq!((snapshot.asks_0_price, snapshot.bids_0_price, snapshot.asks_24_price, snapshot.bids_24_amount), (100.0, 99.0, 124.0, 2.0));
+
+        let ticker = dir.path().join("derivative_ticker.csv");
+        std::fs::write(&ticker,
+                       "exchange,symbol,timestamp,local_timestamp,funding_timestamp,funding_rate,predicted_funding_rate,open_interest,last_price,index_price,mark_price\n\
+                        binance-futures,BTCUSDT,1714867200000000,1714867200001000,1714896000000000,0.0001,,72000.5,63950.1,63948.2,63949.9\n").unwrap();
+        let ticker: DerivativeTicker = open_tardis_file(&ticker, TardisDataType::DerivativeTicker).unwrap().next_record().unwrap().unwrap();
+        assert_eq!((ticker.funding_timestamp, ticker.funding_rate, ticker.predicted_funding_rate, ticker.mark_price), (Some(1714896000000), Some(0.0001), None, Some(63949.9)));
+
+        assert_eq!(TardisDataType::BookSnapshot25.create_table_query("db", "t").matches("Float64").count(), BOOK_SNAPSHOT_DEPTH * 4);
+    }
+}
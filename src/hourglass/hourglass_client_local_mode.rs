@@ -7,16 +7,21 @@ use HourglassClientEvent::{CancelOrders, CancelOrdersAll, FetchOrdersOpen, Fetch
 
 use crate::{
     common::{
-        account_positions::{AccountPositions, Position, PositionConfig},
+        account_positions::{AccountPositions, Position, PositionConfig, PositionMarginMode},
+        account_summary::AccountSummary,
         balance::TokenBalance,
+        datafeed::bar::Bar,
         instrument::Instrument,
+        option_pricing::AccountGreeks,
         order::{
             states::{cancelled::Cancelled, open::Open, request_cancel::RequestCancel},
             Order,
         },
         token::Token,
+        trade::ClientTrade,
+        Side,
     },
-    hourglass::{clickhouse_api::datatype::clickhouse_trade_data::MarketTrade, config_request::ConfigurationRequest},
+    hourglass::{account::account_config::CommissionRates, clickhouse_api::datatype::clickhouse_trade_data::MarketTrade, config_request::ConfigurationRequest},
     network::login::{LoginRequest, LogoutRequest, RegisterRequest},
     AccountEvent, ClientExecution, Exchange, ExchangeError, RequestOpen,
 };
@@ -26,6 +31,7 @@ pub struct HourglassClient
 {
     pub client_event_tx: UnboundedSender<HourglassClientEvent>,
     pub market_event_rx: UnboundedReceiver<MarketTrade>,
+    pub bar_event_rx: Option<UnboundedReceiver<Bar>>, // 交易所聚合或回放完成的 K 线
     // pub account_event_rx: UnboundedReceiver<AccountEvent>,
 }
 
@@ -50,6 +56,16 @@ pub enum HourglassClientEvent
     FetchLongPosition(Instrument, Sender<Result<Option<Position>, ExchangeError>>),
     FetchShortPosition(Instrument, Sender<Result<Option<Position>, ExchangeError>>),
     FetchAllPositions(Sender<Result<AccountPositions, ExchangeError>>),
+    FetchAccountSummary(Token, Sender<Result<AccountSummary, ExchangeError>>),
+    FetchAccountGreeks(Token, Sender<Result<AccountGreeks, ExchangeError>>),
+    AdjustIsolatedMargin(Instrument, Side, f64, Sender<Result<Position, ExchangeError>>),
+    SetLeverage(Instrument, Side, f64, Sender<Result<PositionConfig, ExchangeError>>),
+    SetMarginMode(Instrument, Side, PositionMarginMode, Sender<Result<PositionConfig, ExchangeError>>),
+    SetInstrumentFees(Instrument, Option<CommissionRates>, Sender<Result<Option<CommissionRates>, ExchangeError>>),
+    SubscribeLeveragedToken(Instrument, f64, Sender<Result<ClientTrade, ExchangeError>>),
+    RedeemLeveragedToken(Instrument, f64, Sender<Result<ClientTrade, ExchangeError>>),
+    Borrow(Token, f64, Sender<Result<TokenBalance, ExchangeError>>),
+    Repay(Token, f64, Sender<Result<TokenBalance, ExchangeError>>),
     OpenOrders(RequestOpenOrders),
     CancelOrders(RequestCancelOrders),
     CancelOrdersAll(Sender<Result<Vec<Order<Cancelled>>, ExchangeError>>),
@@ -74,7 +90,8 @@ impl ClientExecution for HourglassClient
 
         // 使用 request_tx 和 market_event_rx 初始化 HourglassClient
         Self { client_event_tx: request_tx,
-               market_event_rx }
+               market_event_rx,
+               bar_event_rx: None }
     }
 
     async fn fetch_orders_open(&self) -> Result<Vec<Order<Open>>, ExchangeError>
@@ -129,6 +146,106 @@ impl ClientExecution for HourglassClient
         response_rx.await.expect("[HourglassClient] : Failed to receive FetchShortPosition response")
     }
 
+    //  FetchAccountSummary 的实现
+    async fn fetch_account_summary(&self, token: Token) -> Result<AccountSummary, ExchangeError>
+    {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.client_event_tx
+            .send(HourglassClientEvent::FetchAccountSummary(token, response_tx))
+            .expect("[HourglassClient] : Failed to send FetchAccountSummary request");
+        response_rx.await.expect("[HourglassClient] : Failed to receive FetchAccountSummary response")
+    }
+
+    //  FetchAccountGreeks 的实现
+    async fn fetch_account_greeks(&self, token: Token) -> Result<AccountGreeks, ExchangeError>
+    {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.client_event_tx
+            .send(HourglassClientEvent::FetchAccountGreeks(token, response_tx))
+            .expect("[HourglassClient] : Failed to send FetchAccountGreeks request");
+        response_rx.await.expect("[HourglassClient] : Failed to receive FetchAccountGreeks response")
+    }
+
+    //  AdjustIsolatedMargin 的实现，delta 为正时追加保证金，为负时提取保证金
+    async fn adjust_isolated_margin(&self, instrument: Instrument, side: Side, delta: f64) -> Result<Position, ExchangeError>
+    {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.client_event_tx
+            .send(HourglassClientEvent::AdjustIsolatedMargin(instrument, side, delta, response_tx))
+            .expect("[HourglassClient] : Failed to send AdjustIsolatedMargin request");
+        response_rx.await.expect("[HourglassClient] : Failed to receive AdjustIsolatedMargin response")
+    }
+
+    //  SetLeverage 的实现，已有仓位时会按新杠杆重新分配保证金
+    async fn set_leverage(&self, instrument: Instrument, side: Side, leverage: f64) -> Result<PositionConfig, ExchangeError>
+    {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.client_event_tx
+            .send(HourglassClientEvent::SetLeverage(instrument, side, leverage, response_tx))
+            .expect("[HourglassClient] : Failed to send SetLeverage request");
+        response_rx.await.expect("[HourglassClient] : Failed to receive SetLeverage response")
+    }
+
+    //  SetMarginMode 的实现，金融工具仍有挂单时会被拒绝
+    async fn set_margin_mode(&self, instrument: Instrument, side: Side, margin_mode: PositionMarginMode) -> Result<PositionConfig, ExchangeError>
+    {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.client_event_tx
+            .send(HourglassClientEvent::SetMarginMode(instrument, side, margin_mode, response_tx))
+            .expect("[HourglassClient] : Failed to send SetMarginMode request");
+        response_rx.await.expect("[HourglassClient] : Failed to receive SetMarginMode response")
+    }
+
+    //  SetInstrumentFees 的实现，`rates` 为 `None` 时恢复按种类配置的费率
+    async fn set_instrument_fees(&self, instrument: Instrument, rates: Option<CommissionRates>) -> Result<Option<CommissionRates>, ExchangeError>
+    {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.client_event_tx
+            .send(HourglassClientEvent::SetInstrumentFees(instrument, rates, response_tx))
+            .expect("[HourglassClient] : Failed to send SetInstrumentFees request");
+        response_rx.await.expect("[HourglassClient] : Failed to receive SetInstrumentFees response")
+    }
+
+    //  SubscribeLeveragedToken 的实现，按当前净值申购杠杆代币
+    async fn subscribe_leveraged_token(&self, instrument: Instrument, size: f64) -> Result<ClientTrade, ExchangeError>
+    {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.client_event_tx
+            .send(HourglassClientEvent::SubscribeLeveragedToken(instrument, size, response_tx))
+            .expect("[HourglassClient] : Failed to send SubscribeLeveragedToken request");
+        response_rx.await.expect("[HourglassClient] : Failed to receive SubscribeLeveragedToken response")
+    }
+
+    //  RedeemLeveragedToken 的实现，按当前净值赎回杠杆代币
+    async fn redeem_leveraged_token(&self, instrument: Instrument, size: f64) -> Result<ClientTrade, ExchangeError>
+    {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.client_event_tx
+            .send(HourglassClientEvent::RedeemLeveragedToken(instrument, size, response_tx))
+            .expect("[HourglassClient] : Failed to send RedeemLeveragedToken request");
+        response_rx.await.expect("[HourglassClient] : Failed to receive RedeemLeveragedToken response")
+    }
+
+    //  Borrow 的实现，现货杠杆借币
+    async fn borrow(&self, token: Token, amount: f64) -> Result<TokenBalance, ExchangeError>
+    {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.client_event_tx
+            .send(HourglassClientEvent::Borrow(token, amount, response_tx))
+            .expect("[HourglassClient] : Failed to send Borrow request");
+        response_rx.await.expect("[HourglassClient] : Failed to receive Borrow response")
+    }
+
+    //  Repay 的实现，归还现货杠杆借款
+    async fn repay(&self, token: Token, amount: f64) -> Result<TokenBalance, ExchangeError>
+    {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.client_event_tx
+            .send(HourglassClientEvent::Repay(token, amount, response_tx))
+            .expect("[HourglassClient] : Failed to send Repay request");
+        response_rx.await.expect("[HourglassClient] : Failed to receive Repay response")
+    }
+
     async fn open_orders(&self, open_requests: Vec<Order<RequestOpen>>) -> Vec<Result<Order<Open>, ExchangeError>>
     {
         let (response_tx, response_rx) = oneshot::channel();
@@ -200,6 +317,12 @@ impl HourglassClient
         }
         None // Return None if there are no events
     }
+
+    /// 接收下一根完成的 K 线，未订阅 K 线或通道关闭时返回 `None`。
+    pub async fn listen_for_bars(&mut self) -> Option<Bar>
+    {
+        self.bar_event_rx.as_mut()?.recv().await
+    }
 }
 
 #[cfg(test)]
@@ -216,7 +339,8 @@ mod tests
         let (_market_tx, market_rx) = mpsc::unbounded_channel();
 
         let client = HourglassClient { client_event_tx: request_tx.clone(),
-                                       market_event_rx: market_rx };
+                                       market_event_rx: market_rx,
+                                       bar_event_rx: None };
 
         // 启动一个异步任务来调用客户端的 fetch_orders_open 方法
         let client_task = tokio::spawn(async move {
@@ -256,7 +380,8 @@ mod tests
 
         // 初始化 HourglassClient
         let client = HourglassClient { client_event_tx: request_tx.clone(),
-                                       market_event_rx: market_rx };
+                                       market_event_rx: market_rx,
+                                       bar_event_rx: None };
 
         // 启动一个异步任务来调用客户端的 cancel_orders_all 方法
         let client_task = tokio::spawn(async move {
@@ -289,4 +414,30 @@ mod tests
         // 确保客户端任务完成
         client_task.await.expect("Client task should complete successfully");
     }
+
+    #[tokio::test]
+    async fn test_fetch_account_summary()
+    {
+        let (request_tx, mut request_rx) = mpsc::unbounded_channel();
+        let (_market_tx, market_rx) = mpsc::unbounded_channel();
+
+        let client = HourglassClient { client_event_tx: request_tx.clone(),
+                                       market_event_rx: market_rx,
+                                       bar_event_rx: None };
+
+        let client_task = tokio::spawn(async move {
+            let summary = client.fetch_account_summary(Token::from("USDT")).await.expect("fetch_account_summary failed");
+            assert_eq!(summary.equity, 1_000.0);
+        });
+
+        // 模拟交易所返回一个没有仓位的账户概览
+        if let Some(HourglassClientEvent::FetchAccountSummary(token, tx)) = request_rx.recv().await {
+            let _ = tx.send(Ok(AccountSummary::new(token, 1_000.0, 1_000.0, 0.0, 0.0, 0.0, 0.0)));
+        }
+        else {
+            panic!("Received unexpected event type");
+        }
+
+        client_task.await.expect("Client task should complete successfully");
+    }
 }
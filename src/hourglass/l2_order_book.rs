@@ -0,0 +1,330 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{instrument::Instrument, Side},
+    error::ExchangeError,
+    hourglass::clickhouse_api::datatype::incremental_book_l2::IncrementalBookL2,
+};
+
+/// 一条 L2 订单簿消息：完整快照的一部分或一批增量更新。
+///
+/// 每个价格层级给出更新后的挂单量，挂单量为 0 表示移除该层级。序号字段按交易所提供的情况填写，
+/// 都为空时（例如 Tardis 的 `incremental_book_L2`）只能依赖快照来重新同步。
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct L2BookMessage
+{
+    pub exchange: String,
+    pub symbol: String,
+    pub timestamp: i64,
+    pub is_snapshot: bool,
+    pub first_sequence: Option<u64>, // 本条消息的第一个更新序号
+    pub sequence: Option<u64>,       // 本条消息的最后一个更新序号
+    pub prev_sequence: Option<u64>,  // 上一条消息的最后一个更新序号，用于检测缺口
+    pub bids: Levels,                // (价格, 更新后的挂单量)
+    pub asks: Levels,                // (价格, 更新后的挂单量)
+}
+
+/// Tardis 的 `incremental_book_L2` 每行只更新一个价格层级，时间戳在读取时已换算为毫秒。
+impl TryFrom<IncrementalBookL2> for L2BookMessage
+{
+    type Error = ExchangeError;
+
+    fn try_from(row: IncrementalBookL2) -> Result<Self, Self::Error>
+    {
+        let level = vec![(row.price, row.amount)];
+        let (bids, asks) = match row.side.to_lowercase().as_str() {
+            | "bid" | "buy" => (level, Vec::new()),
+            | "ask" | "sell" => (Vec::new(), level),
+            | side => return Err(ExchangeError::MarketDataError(format!("Unknown order book side {} for {} at {}", side, row.symbol, row.timestamp))),
+        };
+        Ok(L2BookMessage { exchange: row.exchange,
+                           symbol: row.symbol,
+                           timestamp: row.timestamp,
+                           is_snapshot: row.is_snapshot,
+                           first_sequence: None,
+                           sequence: None,
+                           prev_sequence: None,
+                           bids,
+                           asks })
+    }
+}
+
+/// 订单簿一侧的价格层级，每个元素为 (价格, 挂单量)。
+pub type Levels = Vec<(f64, f64)>;
+
+/// 应用一条 L2 消息的结果。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum L2ApplyOutcome
+{
+    Applied,                              // 已应用到订单簿
+    AwaitingSnapshot,                     // 订单簿尚未同步，增量消息被丢弃，等待下一次快照
+    Stale,                                // 序号不晚于订单簿当前序号，已包含在订单簿中
+    Gap { expected: u64, received: u64 }, // 序号不连续，订单簿被清空并等待下一次快照重新同步
+}
+
+/// 价格作为有序映射的键。
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PriceKey(f64);
+
+impl Eq for PriceKey {}
+
+impl PartialOrd for PriceKey
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering>
+    {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PriceKey
+{
+    fn cmp(&self, other: &Self) -> Ordering
+    {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// 单个金融工具的全深度 L2 订单簿，由快照加增量更新重建。
+///
+/// 快照消息可以分多条连续到达，第一条快照消息清空订单簿；之后的增量消息按序号检查连续性，
+/// 发现缺口时清空订单簿并丢弃增量消息，直到下一次快照重新同步。
+#[derive(Clone, Debug, Default)]
+pub struct L2OrderBook
+{
+    bids: BTreeMap<PriceKey, f64>,
+    asks: BTreeMap<PriceKey, f64>,
+    last_sequence: Option<u64>, // 已应用的最后一个更新序号
+    last_timestamp: i64,        // 最后一条已应用消息的时间戳
+    synced: bool,               // 是否已由快照同步
+    in_snapshot: bool,          // 上一条消息是否为快照，用于合并连续的快照消息
+    bridging: bool,             // 快照后的第一条增量消息可以跨越快照的序号
+}
+
+impl L2OrderBook
+{
+    pub fn apply(&mut self, message: &L2BookMessage) -> L2ApplyOutcome
+    {
+        if message.is_snapshot {
+            if !self.in_snapshot {
+                self.bids.clear();
+                self.asks.clear();
+            }
+            self.apply_levels(message);
+            self.last_sequence = message.sequence;
+            self.synced = true;
+            self.in_snapshot = true;
+            self.bridging = true;
+            return L2ApplyOutcome::Applied;
+        }
+        self.in_snapshot = false;
+
+        if !self.synced {
+            return L2ApplyOutcome::AwaitingSnapshot;
+        }
+        if let (Some(last), Some(sequence)) = (self.last_sequence, message.sequence) {
+            if sequence <= last {
+                return L2ApplyOutcome::Stale;
+            }
+        }
+        if let Some((expected, received)) = self.find_gap(message) {
+            self.reset();
+            return L2ApplyOutcome::Gap { expected, received };
+        }
+
+        self.apply_levels(message);
+        self.last_sequence = message.sequence.or(self.last_sequence);
+        self.bridging = false;
+        L2ApplyOutcome::Applied
+    }
+
+    /// 检查增量消息与订单簿当前序号是否连续，不连续时返回 (期望的序号, 收到的序号)。
+    fn find_gap(&self, message: &L2BookMessage) -> Option<(u64, u64)>
+    {
+        let last = self.last_sequence?;
+        if self.bridging {
+            // 快照后的第一条增量消息只要求覆盖快照的下一个序号
+            return message.first_sequence.filter(|first| *first > last + 1).map(|first| (last + 1, first));
+        }
+        match (message.prev_sequence, message.first_sequence) {
+            | (Some(prev), _) if prev != last => Some((last, prev)),
+            | (None, Some(first)) if first != last + 1 => Some((last + 1, first)),
+            | _ => None,
+        }
+    }
+
+    fn apply_levels(&mut self, message: &L2BookMessage)
+    {
+        for (levels, book) in [(&message.bids, &mut self.bids), (&message.asks, &mut self.asks)] {
+            for &(price, amount) in levels {
+                if amount > 0.0 {
+                    book.insert(PriceKey(price), amount);
+                }
+                else {
+                    book.remove(&PriceKey(price));
+                }
+            }
+        }
+        self.last_timestamp = message.timestamp;
+    }
+
+    fn reset(&mut self)
+    {
+        *self = L2OrderBook { last_timestamp: self.last_timestamp,
+                              ..L2OrderBook::default() };
+    }
+
+    pub fn is_synced(&self) -> bool
+    {
+        self.synced
+    }
+
+    pub fn last_timestamp(&self) -> i64
+    {
+        self.last_timestamp
+    }
+
+    /// 最优买价与挂单量。
+    pub fn best_bid(&self) -> Option<(f64, f64)>
+    {
+        self.bids.last_key_value().map(|(price, amount)| (price.0, *amount))
+    }
+
+    /// 最优卖价与挂单量。
+    pub fn best_ask(&self) -> Option<(f64, f64)>
+    {
+        self.asks.first_key_value().map(|(price, amount)| (price.0, *amount))
+    }
+
+    /// 买卖两侧最优的 `levels` 档，均按从优到劣排列。
+    pub fn depth(&self, levels: usize) -> (Levels, Levels)
+    {
+        (self.bids.iter().rev().take(levels).map(|(price, amount)| (price.0, *amount)).collect(),
+         self.asks.iter().take(levels).map(|(price, amount)| (price.0, *amount)).collect())
+    }
+
+    /// 与 `side` 方向、价格为 `limit_price` 的订单交叉的对手方档位，按从优到劣排列：
+    /// 买单对应不高于该价格的卖盘档位，卖单对应不低于该价格的买盘档位。
+    pub fn crossing_levels(&self, side: Side, limit_price: f64) -> Levels
+    {
+        match side {
+            | Side::Buy => self.asks.range(..=PriceKey(limit_price)).map(|(price, amount)| (price.0, *amount)).collect(),
+            | Side::Sell => self.bids.range(PriceKey(limit_price)..).rev().map(|(price, amount)| (price.0, *amount)).collect(),
+        }
+    }
+
+    /// `side` 方向的订单与订单簿成交 `amount` 后，从对手方最优档开始扣除被消耗的挂单量，
+    /// 这些档位在交易所重新发布之前不会再次参与撮合。
+    pub fn take_liquidity(&mut self, side: Side, mut amount: f64)
+    {
+        let levels = match side {
+            | Side::Buy => &mut self.asks,
+            | Side::Sell => &mut self.bids,
+        };
+        while amount > 0.0 {
+            let best = match side {
+                | Side::Buy => levels.first_entry(),
+                | Side::Sell => levels.last_entry(),
+            };
+            let Some(mut level) = best
+            else {
+                break;
+            };
+            if *level.get() > amount {
+                *level.get_mut() -= amount;
+                break;
+            }
+            amount -= level.remove();
+        }
+    }
+}
+
+/// 回放过程中按金融工具维护的全深度订单簿。
+#[derive(Clone, Debug, Default)]
+pub struct L2BookBuilder
+{
+    books: HashMap<Instrument, L2OrderBook>,
+}
+
+impl L2BookBuilder
+{
+    pub fn apply(&mut self, instrument: &Instrument, message: &L2BookMessage) -> L2ApplyOutcome
+    {
+        self.books.entry(instrument.clone()).or_default().apply(message)
+    }
+
+    pub fn book(&self, instrument: &Instrument) -> Option<&L2OrderBook>
+    {
+        self.books.get(instrument)
+    }
+
+    pub fn book_mut(&mut self, instrument: &Instrument) -> Option<&mut L2OrderBook>
+    {
+        self.books.get_mut(instrument)
+    }
+
+    /// 该金融工具的买卖价是否来自已同步的订单簿。
+    pub fn is_synced(&self, instrument: &Instrument) -> bool
+    {
+        self.books.get(instrument).is_some_and(L2OrderBook::is_synced)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn message(is_snapshot: bool, first_sequence: u64, sequence: u64, prev_sequence: Option<u64>, bids: Vec<(f64, f64)>, asks: Vec<(f64, f64)>) -> L2BookMessage
+    {
+        L2BookMessage { exchange: "binance-futures".to_string(),
+                        symbol: "BTCUSDT".to_string(),
+                        timestamp: sequence as i64,
+                        is_snapshot,
+                        first_sequence: Some(first_sequence),
+                        sequence: Some(sequence),
+                        prev_sequence,
+                        bids,
+                        asks }
+    }
+
+    #[test]
+    fn l2_order_book_should_apply_diffs_detect_gaps_and_resync()
+    {
+        let mut book = L2OrderBook::default();
+
+        // 快照之前的增量消息无法应用
+        assert_eq!(book.apply(&message(false, 1, 2, None, vec![(99.0, 1.0)], vec![])), L2ApplyOutcome::AwaitingSnapshot);
+
+        assert_eq!(book.apply(&message(true, 10, 10, None, vec![(100.0, 1.0), (99.0, 2.0)], vec![(101.0, 1.5), (102.0, 3.0)])), L2ApplyOutcome::Applied);
+        assert_eq!((book.best_bid(), book.best_ask()), (Some((100.0, 1.0)), Some((101.0, 1.5))));
+
+        // 已包含在快照中的消息被忽略，第一条增量消息可以跨越快照序号
+        assert_eq!(book.apply(&message(false, 8, 10, Some(7), vec![(100.0, 0.0)], vec![])), L2ApplyOutcome::Stale);
+        assert_eq!(book.apply(&message(false, 9, 12, Some(8), vec![(100.5, 0.7)], vec![(101.0, 0.0)])), L2ApplyOutcome::Applied);
+        assert_eq!((book.best_bid(), book.best_ask()), (Some((100.5, 0.7)), Some((102.0, 3.0))));
+        assert_eq!(book.depth(5), (vec![(100.5, 0.7), (100.0, 1.0), (99.0, 2.0)], vec![(102.0, 3.0)]));
+        assert_eq!(book.crossing_levels(Side::Sell, 100.0), vec![(100.5, 0.7), (100.0, 1.0)]);
+        assert_eq!(book.crossing_levels(Side::Buy, 101.5), vec![]);
+        let mut consumed = book.clone();
+        consumed.take_liquidity(Side::Sell, 1.0);
+        assert_eq!(consumed.depth(5).0, vec![(100.0, 0.7), (99.0, 2.0)]);
+
+        // 上一条序号不连续：清空订单簿并等待快照
+        assert_eq!(book.apply(&message(false, 15, 16, Some(14), vec![], vec![(101.5, 1.0)])), L2ApplyOutcome::Gap { expected: 12, received: 14 });
+        assert!(!book.is_synced());
+        assert_eq!(book.best_bid(), None);
+        assert_eq!(book.apply(&message(false, 17, 18, Some(16), vec![], vec![])), L2ApplyOutcome::AwaitingSnapshot);
+
+        // 分两条到达的快照重新同步订单簿
+        assert_eq!(book.apply(&message(true, 20, 20, None, vec![(98.0, 1.0)], vec![])), L2ApplyOutcome::Applied);
+        assert_eq!(book.apply(&message(true, 20, 20, None, vec![], vec![(98.5, 2.0)])), L2ApplyOutcome::Applied);
+        assert_eq!((book.best_bid(), book.best_ask()), (Some((98.0, 1.0)), Some((98.5, 2.0))));
+        assert_eq!(book.apply(&message(false, 21, 21, Some(20), vec![(98.1, 1.0)], vec![])), L2ApplyOutcome::Applied);
+        assert_eq!(book.best_bid(), Some((98.1, 1.0)));
+    }
+}
@@ -1,12 +1,28 @@
 use crate::{
-    common::datafeed::market_event::MarketEvent,
+    common::{
+        datafeed::{
+            bar::{Bar, BarAggregator, BarReplay, BarSpec},
+            file_source::FileSource,
+            market_data::MarketData,
+            market_event::MarketEvent,
+            replay::{ReplayMerger, ReplaySource},
+            validation::{TradeValidator, ValidationConfig},
+        },
+        instrument::kind::InstrumentKind,
+    },
     error::ExchangeError,
     hourglass::{
-        account::account_handlers::{balance_handler::BalanceHandler, position_handler::PositionHandler, trade_handler::TradeHandler},
-        clickhouse_api::{datatype::clickhouse_trade_data::MarketTrade, queries_operations::ClickHouseClient},
+        account::{
+            account_config::HourglassMode,
+            account_handlers::{
+                balance_handler::BalanceHandler, leveraged_token_handler::LeveragedTokenHandler, margin_handler::MarginHandler, option_handler::OptionHandler, position_handler::PositionHandler,
+                trade_handler::TradeHandler,
+            },
+        },
+        clickhouse_api::{datatype::clickhouse_trade_data::MarketTrade, date_range_cursor::DateRangeCursor, queries_operations::ClickHouseClient},
         hourglass_client_local_mode::HourglassClientEvent,
     },
-    hourglass_log::warn,
+    hourglass_log::{info, warn},
     network::{event::NetworkEvent, is_port_in_use},
 };
 use account::HourglassAccount;
@@ -25,6 +41,7 @@ pub mod clickhouse_api;
 pub mod config_request;
 pub mod hourglass_client_local_mode;
 pub mod hourglass_orderbook;
+pub mod l2_order_book;
 pub mod open_orders_book;
 pub mod risk_reserve;
 pub mod utils;
@@ -34,6 +51,11 @@ pub enum DataSource
 {
     RealTime(UnboundedReceiver<MarketEvent<MarketTrade>>),
     Backtest(RowCursor<MarketTrade>),
+    Replay(ReplayMerger<MarketTrade>), // 多个数据源按时间戳归并回放
+    DateRange(DateRangeCursor),        // 连续多天的合并表依次回放
+    File(FileSource<MarketTrade>),     // 本地 CSV / JSON Lines 文件，不依赖 ClickHouse
+    Depth(ReplayMerger<MarketData>),   // 成交与 L2 订单簿消息按时间归并回放，订单簿消息在成交之间依次应用
+    Bars(BarReplay),                   // 只有 K 线数据时，按 K 线内的价格路径假设展开为合成成交回放
 }
 
 pub struct HourglassExchange
@@ -43,6 +65,9 @@ pub struct HourglassExchange
     pub market_event_tx: UnboundedSender<MarketTrade>,
     pub account: Arc<Mutex<HourglassAccount>>,
     pub data_source: DataSource,
+    pub bar_event_tx: Option<UnboundedSender<Bar>>,         // 完成的 K 线发送给客户端
+    pub bar_aggregator: Option<BarAggregator>,              // 从成交流聚合 K 线，K 线数据源下不使用
+    pub data_validator: Option<Arc<Mutex<TradeValidator>>>, // 数据路径中的行情质量校验，回放结束后可从中读取数据质量报告
     pub clickhouse_client: ClickHouseClient,
     pub active_sessions: Mutex<HashMap<String, Uuid>>, // 存储 session_token 和 username 的映射
 }
@@ -59,16 +84,51 @@ impl HourglassExchange
         Arc::clone(&self.account)
     }
 
+    /// 数据质量校验器，回放结束后通过 [`TradeValidator::report`] 读取本次回放的数据质量报告。
+    pub fn get_data_validator(&self) -> Option<Arc<Mutex<TradeValidator>>>
+    {
+        self.data_validator.clone()
+    }
+
+    /// 运行交易所事件循环。
+    ///
+    /// 回测数据源由客户端的 `LetItRoll` 逐条驱动；实时数据源由到达的行情驱动，`LetItRoll` 被忽略，
+    /// 交易所时间取自行情事件，直到实时通道关闭为止。
     pub async fn start(mut self)
     {
         let timeout = 1;
         let mut processed_count = 0; // 记录已处理的数据条目数
+        let realtime = matches!(self.data_source, DataSource::RealTime(_));
+        if realtime && self.account.lock().await.config.execution_mode != HourglassMode::Online {
+            warn!("Realtime data source is driven by incoming market events, but the account is not in HourglassMode::Online.");
+        }
 
         loop {
             tokio::select! {
+                    // 监听实时行情，由到达的数据驱动
+                    event = next_realtime_event(&mut self.data_source), if realtime => {
+                        match event {
+                            Some(event) => {
+                                if let Err(e) = self.process_realtime_event(event).await {
+                                    warn!("Realtime data source stopped: {:?}", e);
+                                    self.finish_data().await;
+                                    break;
+                                }
+                                processed_count += 1;
+                            }
+                            None => {
+                                self.finish_data().await;
+                                warn!("Realtime data source closed. Processed {} entries", processed_count);
+                                break; // 实时通道关闭后优雅退出循环
+                            }
+                        }
+                    },
                     // 监听客户端信号
                      Some(event) = self.client_event_rx.recv() => {
                 match event {
+                    HourglassClientEvent::LetItRoll if realtime => {
+                        // 实时数据源由到达的行情驱动，无需客户端推进
+                    },
                     HourglassClientEvent::LetItRoll => {
                         if let Some(row) = self.process_next_data().await {
                             let mut account = self.account.lock().await;
@@ -113,6 +173,36 @@ impl HourglassExchange
                                 self.account.lock().await.fetch_short_position_and_respond(&instrument, response_tx).await;
 
                             },
+                            HourglassClientEvent::FetchAccountSummary(token, response_tx) => {
+                                self.account.lock().await.fetch_account_summary_and_respond(&token, response_tx).await;
+                            },
+                            HourglassClientEvent::FetchAccountGreeks(token, response_tx) => {
+                                self.account.lock().await.fetch_account_greeks_and_respond(&token, response_tx).await;
+                            },
+                            HourglassClientEvent::AdjustIsolatedMargin(instrument, side, delta, response_tx) => {
+                                self.account.lock().await.adjust_isolated_margin_and_respond(&instrument, side, delta, response_tx).await;
+                            },
+                            HourglassClientEvent::SetLeverage(instrument, side, leverage, response_tx) => {
+                                self.account.lock().await.set_leverage_and_respond(&instrument, side, leverage, response_tx).await;
+                            },
+                            HourglassClientEvent::SetMarginMode(instrument, side, margin_mode, response_tx) => {
+                                self.account.lock().await.set_margin_mode_and_respond(&instrument, side, margin_mode, response_tx).await;
+                            },
+                            HourglassClientEvent::SetInstrumentFees(instrument, rates, response_tx) => {
+                                self.account.lock().await.set_instrument_fees_and_respond(&instrument, rates, response_tx).await;
+                            },
+                            HourglassClientEvent::SubscribeLeveragedToken(instrument, size, response_tx) => {
+                                self.account.lock().await.subscribe_leveraged_token_and_respond(&instrument, size, response_tx).await;
+                            },
+                            HourglassClientEvent::RedeemLeveragedToken(instrument, size, response_tx) => {
+                                self.account.lock().await.redeem_leveraged_token_and_respond(&instrument, size, response_tx).await;
+                            },
+                            HourglassClientEvent::Borrow(token, amount, response_tx) => {
+                                self.account.lock().await.borrow_and_respond(&token, amount, response_tx).await;
+                            },
+                            HourglassClientEvent::Repay(token, amount, response_tx) => {
+                                self.account.lock().await.repay_and_respond(&token, amount, response_tx).await;
+                            },
                             HourglassClientEvent::DepositTokens(deposit_request) => {
                                 self.account.lock().await.deposit_multiple_coins_and_respond(deposit_request.0, deposit_request.1).await;
                             },
@@ -133,8 +223,8 @@ impl HourglassExchange
                         }
                     }
                 }
-                    // 加入超时机制，防止一直挂起
-            _ = time::sleep(Duration::from_secs(timeout)) => {
+                    // 加入超时机制，防止一直挂起；实时数据源可能长时间没有行情，不设超时
+            _ = time::sleep(Duration::from_secs(timeout)), if !realtime => {
                 if processed_count > 0 {
                     println!("No more data available.");
                 } else {
@@ -146,26 +236,181 @@ impl HourglassExchange
         }
     }
 
+    /// 处理实时数据源推送的一条行情，交易所时间取自行情事件。
+    async fn process_realtime_event(&mut self, event: MarketEvent<MarketTrade>) -> Result<(), ExchangeError>
+    {
+        let mut trade = event.kind;
+        trade.timestamp = event.exchange_ts;
+        let Some(trade) = self.validate_trade(trade).await?
+        else {
+            return Ok(());
+        };
+        self.publish_trade(&trade);
+        if let Err(e) = self.account.lock().await.handle_trade_data(&trade).await {
+            warn!("Failed to handle realtime market trade: {:?}", e);
+        }
+        Ok(())
+    }
+
     /// 处理下一条数据
     async fn process_next_data(&mut self) -> Option<MarketTrade>
     {
+        let next = loop {
+            match self.read_next_trade().await {
+                | Ok(Some(row)) => match self.validate_trade(row).await {
+                    | Ok(Some(row)) => break Ok(Some(row)),
+                    // 被校验丢弃的数据直接跳过
+                    | Ok(None) => continue,
+                    | Err(e) => break Err(e),
+                },
+                | other => break other,
+            }
+        };
+
+        match next {
+            | Ok(Some(row)) => {
+                self.publish_trade(&row);
+                Some(row)
+            }
+            | Ok(None) => {
+                self.finish_data().await;
+                None
+            }
+            | Err(e) => {
+                // 读取失败或数据校验中止视为数据结束
+                warn!("Replay stopped: {:?}", e);
+                self.finish_data().await;
+                None
+            }
+        }
+    }
+
+    /// 从回放数据源读取下一笔成交，L2 数据源下其间的订单簿消息直接应用到账户的 L2 订单簿，K 线数据源下读取合成成交。
+    async fn read_next_trade(&mut self) -> Result<Option<MarketTrade>, ExchangeError>
+    {
+        if matches!(self.data_source, DataSource::Bars(_)) {
+            return self.read_next_bar_trade().await;
+        }
         match &mut self.data_source {
-            | DataSource::Backtest(cursor) => {
-                // 这里 cursor 需要是 mutable 的
-                if let Ok(Some(row)) = cursor.next().await {
-                    // 发送市场数据给客户端
-                    if let Err(e) = self.market_event_tx.send(row.clone()) {
-                        eprintln!("Failed to send market data to client: {:?}", e);
+            | DataSource::Backtest(cursor) => cursor.next_event().await,
+            | DataSource::Replay(merger) => merger.next_event().await,
+            | DataSource::DateRange(cursor) => cursor.next_event().await,
+            | DataSource::File(source) => source.next_record(),
+            | DataSource::Depth(merger) => loop {
+                match merger.next_event().await? {
+                    | Some(MarketData::Book(message)) => {
+                        if let Err(e) = self.account.lock().await.handle_book_update(&message).await {
+                            warn!("Failed to handle order book update: {:?}", e);
+                        }
                     }
-                    Some(row)
-                }
-                else {
-                    None
+                    | Some(MarketData::Trade(trade)) => break Ok(Some(trade)),
+                    | None => break Ok(None),
                 }
+            },
+            // K 线数据源由 `read_next_bar_trade` 回放；实时数据源由 `start` 直接消费，不经由 `LetItRoll` 拉取
+            | DataSource::Bars(_) | DataSource::RealTime(_) => Ok(None),
+        }
+    }
+
+    /// 按配置校验一笔成交，未配置校验时原样返回。
+    async fn validate_trade(&mut self, trade: MarketTrade) -> Result<Option<MarketTrade>, ExchangeError>
+    {
+        match &self.data_validator {
+            | Some(validator) => validator.lock().await.validate(trade),
+            | None => Ok(Some(trade)),
+        }
+    }
+
+    /// K 线数据源下读取下一笔合成成交，当前 K 线回放完毕时按账户持仓决定下一根 K 线的展开顺序。
+    async fn read_next_bar_trade(&mut self) -> Result<Option<MarketTrade>, ExchangeError>
+    {
+        let DataSource::Bars(replay) = &mut self.data_source
+        else {
+            return Ok(None);
+        };
+        if replay.is_bar_finished() {
+            let Some(bar) = replay.next_bar().await?
+            else {
+                return Ok(None);
+            };
+            let net_position = Self::net_position(&self.account, &bar).await;
+            let high_first = replay.fill().high_first(&bar, net_position);
+            replay.start_bar(bar, high_first);
+        }
+        Ok(replay.next_trade())
+    }
+
+    /// 取出由上一笔成交完成的 K 线：K 线数据源下为回放完毕的原始 K 线，否则由聚合器产生。
+    fn take_completed_bar(&mut self, trade: &MarketTrade) -> Option<Bar>
+    {
+        match &mut self.data_source {
+            | DataSource::Bars(replay) => replay.take_completed_bar(),
+            | _ => self.bar_aggregator.as_mut().and_then(|aggregator| aggregator.update(trade)),
+        }
+    }
+
+    /// K 线对应金融工具在账户中的净持仓（多头为正），无法解析或不支持持仓的金融工具视为无持仓。
+    async fn net_position(account: &Mutex<HourglassAccount>, bar: &Bar) -> f64
+    {
+        let account = account.lock().await;
+        let Ok(instrument) = account.symbol_registry.resolve(&bar.exchange, &bar.symbol)
+        else {
+            return 0.0;
+        };
+        match instrument.kind {
+            | InstrumentKind::Perpetual | InstrumentKind::Future | InstrumentKind::CryptoOption | InstrumentKind::CryptoLeveragedToken => {
+                let (long, short) = account.get_position_both_ways(instrument).await.unwrap_or_default();
+                long.map_or(0.0, |position| position.current_size()) - short.map_or(0.0, |position| position.current_size())
             }
-            | _ => {
-                println!("Unhandled data source type");
-                None
+            | _ => 0.0,
+        }
+    }
+
+    /// 把一笔成交发送给客户端，并把由此完成的 K 线一并发送。
+    fn publish_trade(&mut self, trade: &MarketTrade)
+    {
+        // 发送市场数据给客户端
+        if let Err(e) = self.market_event_tx.send(trade.clone()) {
+            eprintln!("Failed to send market data to client: {:?}", e);
+        }
+        if let Some(bar) = self.take_completed_bar(trade) {
+            self.send_bar(bar);
+        }
+    }
+
+    /// 数据结束时把尚未完成的 K 线发送给客户端，并输出数据质量报告。
+    async fn finish_data(&mut self)
+    {
+        let mut bars = self.bar_aggregator.as_mut().map(BarAggregator::flush).unwrap_or_default();
+        if let DataSource::Bars(replay) = &mut self.data_source {
+            bars.extend(replay.take_completed_bar());
+        }
+        for bar in bars {
+            self.send_bar(bar);
+        }
+        if let Some(validator) = &self.data_validator {
+            let validator = validator.lock().await;
+            let report = validator.report();
+            if report.is_clean() {
+                info!("Data quality: {} trades checked, no issues found", report.total);
+            }
+            else {
+                warn!("Data quality: {} trades checked, {} repaired, {} dropped, issues {:?}, {} gaps",
+                      report.total,
+                      report.repaired,
+                      report.dropped,
+                      report.issues,
+                      report.gaps.len());
+            }
+        }
+    }
+
+    /// 把完成的 K 线发送给订阅了 K 线的客户端。
+    fn send_bar(&self, bar: Bar)
+    {
+        if let Some(bar_event_tx) = &self.bar_event_tx {
+            if let Err(e) = bar_event_tx.send(bar) {
+                warn!("Client offline - Failed to send bar: {:?}", e);
             }
         }
     }
@@ -214,6 +459,15 @@ impl HourglassExchange
     }
 }
 
+/// 从实时数据源接收下一条行情，回测数据源永远挂起。
+async fn next_realtime_event(data_source: &mut DataSource) -> Option<MarketEvent<MarketTrade>>
+{
+    match data_source {
+        | DataSource::RealTime(rx) => rx.recv().await,
+        | DataSource::Backtest(_) | DataSource::Replay(_) | DataSource::DateRange(_) | DataSource::File(_) | DataSource::Depth(_) | DataSource::Bars(_) => std::future::pending().await,
+    }
+}
+
 impl Default for ExchangeBuilder
 {
     fn default() -> Self
@@ -222,7 +476,10 @@ impl Default for ExchangeBuilder
         Self { event_hourglass_rx: Some(rx),
                account: None,
                market_event_tx: None,
-               data_source: None }
+               data_source: None,
+               bar_event_tx: None,
+               bar_spec: None,
+               validation: None }
     }
 }
 pub struct ExchangeBuilder
@@ -231,6 +488,9 @@ pub struct ExchangeBuilder
     pub(crate) account: Option<Arc<Mutex<HourglassAccount>>>,
     pub(crate) market_event_tx: Option<UnboundedSender<MarketTrade>>,
     pub(crate) data_source: Option<DataSource>,
+    pub(crate) bar_event_tx: Option<UnboundedSender<Bar>>,
+    pub(crate) bar_spec: Option<BarSpec>,
+    pub(crate) validation: Option<ValidationConfig>,
 }
 
 impl ExchangeBuilder
@@ -240,7 +500,10 @@ impl ExchangeBuilder
         Self { event_hourglass_rx: None,
                account: None,
                market_event_tx: None,
-               data_source: None }
+               data_source: None,
+               bar_event_tx: None,
+               bar_spec: None,
+               validation: None }
     }
 
     pub fn event_hourglass_rx(self, value: UnboundedReceiver<HourglassClientEvent>) -> Self
@@ -264,13 +527,37 @@ impl ExchangeBuilder
         Self { account: Some(value), ..self }
     }
 
+    /// 把完成的 K 线发送到 `value`。
+    pub fn bar_event_tx(self, value: UnboundedSender<Bar>) -> Self
+    {
+        Self { bar_event_tx: Some(value), ..self }
+    }
+
+    /// 按 `value` 把成交流聚合为 K 线，需要同时设置 [`ExchangeBuilder::bar_event_tx`]。K 线数据源下直接发送原始 K 线，不再聚合。
+    pub fn bar_spec(self, value: BarSpec) -> Self
+    {
+        Self { bar_spec: Some(value), ..self }
+    }
+
+    /// 按 `value` 校验回放与实时数据路径中的成交。
+    pub fn validation(self, value: ValidationConfig) -> Self
+    {
+        Self { validation: Some(value), ..self }
+    }
+
     pub fn initiate(self) -> Result<HourglassExchange, ExchangeError>
     {
+        if let Some(spec) = &self.bar_spec {
+            spec.validate()?;
+        }
         Ok(HourglassExchange { client_event_rx: self.event_hourglass_rx.ok_or_else(|| ExchangeError::BuilderIncomplete("event_hourglass_rx".to_string()))?,
                                // market_event_tx: self.market_event_tx.ok_or_else(|| ExecutionError::BuilderIncomplete("market_event_tx".to_string()))?,
                                market_event_tx: self.market_event_tx.ok_or_else(|| ExchangeError::BuilderIncomplete("market_tx".to_string()))?,
                                account: self.account.ok_or_else(|| ExchangeError::BuilderIncomplete("account".to_string()))?,
                                data_source: self.data_source.ok_or_else(|| ExchangeError::BuilderIncomplete("data_source".to_string()))?,
+                               bar_event_tx: self.bar_event_tx,
+                               bar_aggregator: self.bar_spec.map(BarAggregator::new),
+                               data_validator: self.validation.map(|config| Arc::new(Mutex::new(TradeValidator::new(config)))),
                                clickhouse_client: ClickHouseClient::new(),
                                active_sessions: HashMap::new().into() })
     }
@@ -280,8 +567,28 @@ impl ExchangeBuilder
 mod tests
 {
     use super::*;
-    use crate::{hourglass::clickhouse_api::queries_operations::ClickHouseClient, test_utils::create_test_account};
-    use std::net::TcpListener;
+    use crate::{
+        common::{
+            account_positions::PositionSide,
+            datafeed::{
+                bar::IntrabarFill,
+                file_source::FileSourceConfig,
+                validation::{DataIssue, ValidationPolicy},
+            },
+            instrument::{kind::InstrumentKind, Instrument},
+            order::{
+                identification::{client_order_id::ClientOrderId, OrderId},
+                order_instructions::OrderInstruction,
+                states::open::Open,
+                Order, OrderRole,
+            },
+            Side,
+        },
+        hourglass::{clickhouse_api::queries_operations::ClickHouseClient, l2_order_book::L2BookMessage},
+        test_utils::{create_test_account, create_test_market_trade},
+        Exchange,
+    };
+    use std::{collections::VecDeque, net::TcpListener};
     use tokio::sync::mpsc;
 
     #[tokio::test]
@@ -328,17 +635,29 @@ mod tests
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn builder_should_reject_non_positive_bar_spec()
+    {
+        for spec in [BarSpec::Time(0), BarSpec::Tick(0), BarSpec::Volume(-1.0), BarSpec::Dollar(f64::NAN)] {
+            let (_tx, rx) = mpsc::unbounded_channel();
+            let (market_tx, _market_rx) = mpsc::unbounded_channel();
+            let result = ExchangeBuilder::new().event_hourglass_rx(rx)
+                                               .market_event_tx(market_tx)
+                                               .account(Arc::new(Mutex::new(create_test_account().await)))
+                                               .data_source(DataSource::Bars(BarReplay::new(VecDeque::new(), IntrabarFill::WorstCase)))
+                                               .bar_spec(spec)
+                                               .initiate();
+            assert!(matches!(result, Err(ExchangeError::ConfigParseError(_))));
+        }
+    }
+
     #[tokio::test]
     async fn run_online_should_return_if_port_is_in_use()
     {
         // 占用端口 3030
         let _listener = TcpListener::bind("127.0.0.1:3030").unwrap();
         let (market_tx, _market_rx) = mpsc::unbounded_channel();
-        let clickhouse_client = ClickHouseClient::new();
-        let exchange = "binance";
-        let instrument = "futures";
-        let date = "2024_05_05";
-        let cursor = clickhouse_client.cursor_unioned_public_trades(exchange, instrument, date).await.unwrap();
+        let trades = FileSource::open(FileSourceConfig::new("tests/util/sample_trades.jsonl")).unwrap();
 
         let (_tx, rx) = mpsc::unbounded_channel();
         let account = create_test_account().await;
@@ -346,13 +665,268 @@ mod tests
         let exchange = HourglassExchange { client_event_rx: rx,
                                            market_event_tx: market_tx,
                                            account,
-                                           data_source: DataSource::Backtest(cursor),
+                                           data_source: DataSource::File(trades),
+                                           bar_event_tx: None,
+                                           bar_aggregator: None,
+                                           data_validator: None,
                                            clickhouse_client: ClickHouseClient::new(),
                                            active_sessions: HashMap::new().into() };
         let address = "127.0.0.1:3030".parse().unwrap(); // Convert to a SocketAddr
         assert!(is_port_in_use(address));
         exchange.run_online().await;
     }
+
+    #[tokio::test]
+    async fn start_should_consume_realtime_data_source_with_event_time()
+    {
+        let mut account = create_test_account().await;
+        account.config.execution_mode = HourglassMode::Online;
+        let account = Arc::new(Mutex::new(account));
+
+        let (market_tx, mut market_rx) = mpsc::unbounded_channel();
+        let (client_tx, client_rx) = mpsc::unbounded_channel();
+        let (realtime_tx, realtime_rx) = mpsc::unbounded_channel();
+        let exchange = HourglassExchange::builder().event_hourglass_rx(client_rx)
+                                                   .account(account.clone())
+                                                   .market_event_tx(market_tx)
+                                                   .data_source(DataSource::RealTime(realtime_rx))
+                                                   .initiate()
+                                                   .unwrap();
+
+        let instrument = Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual));
+        for (exchange_ts, price) in [(1_700_000_000_000, 16400.0), (1_700_000_001_000, 16410.0)] {
+            let trade = MarketTrade { exchange: "binance-futures".to_string(),
+                                      symbol: "ETHUSDT".to_string(),
+                                      timestamp: 0,
+                                      price,
+                                      side: Side::Buy.to_string(),
+                                      amount: 1.0 };
+            realtime_tx.send(MarketEvent { exchange_ts,
+                                           received_ts: exchange_ts + 5,
+                                           exchange: Exchange::Hourglass,
+                                           instrument: instrument.clone(),
+                                           kind: trade })
+                       .unwrap();
+        }
+        // 实时模式下 LetItRoll 不会推进或结束事件循环
+        client_tx.send(HourglassClientEvent::LetItRoll).unwrap();
+        drop(realtime_tx);
+
+        // 实时通道关闭后事件循环退出，不依赖超时
+        exchange.start().await;
+
+        assert_eq!(market_rx.recv().await.unwrap().timestamp, 1_700_000_000_000);
+        assert_eq!(market_rx.recv().await.unwrap().timestamp, 1_700_000_001_000);
+        let account = account.lock().await;
+        assert_eq!(account.exchange_timestamp.load(std::sync::atomic::Ordering::SeqCst), 1_700_000_001_000);
+        assert_eq!(account.single_level_order_book.lock().await.get(&instrument).unwrap().latest_price, 16410.0);
+    }
+
+    #[tokio::test]
+    async fn start_should_replay_merged_sources_in_time_order()
+    {
+        let account = Arc::new(Mutex::new(create_test_account().await));
+        let trade = |symbol: &str, timestamp: i64| MarketTrade { exchange: "binance-futures".to_string(),
+                                                                 symbol: symbol.to_string(),
+                                                                 timestamp,
+                                                                 price: 16400.0,
+                                                                 side: Side::Buy.to_string(),
+                                                                 amount: 1.0 };
+        let merger = ReplayMerger::new().with_source(VecDeque::from(vec![trade("ETHUSDT", 20)]))
+                                        .with_source(VecDeque::from(vec![trade("BTCUSDT", 10), trade("BTCUSDT", 30)]));
+
+        let (market_tx, mut market_rx) = mpsc::unbounded_channel();
+        let (client_tx, client_rx) = mpsc::unbounded_channel();
+        let exchange = HourglassExchange::builder().event_hourglass_rx(client_rx)
+                                                   .account(account.clone())
+                                                   .market_event_tx(market_tx)
+                                                   .data_source(DataSource::Replay(merger))
+                                                   .initiate()
+                                                   .unwrap();
+        // 第四次 LetItRoll 时数据耗尽，事件循环退出
+        for _ in 0..4 {
+            client_tx.send(HourglassClientEvent::LetItRoll).unwrap();
+        }
+        exchange.start().await;
+
+        let mut replayed = Vec::new();
+        while let Ok(trade) = market_rx.try_recv() {
+            replayed.push((trade.symbol, trade.timestamp));
+        }
+        assert_eq!(replayed, vec![("BTCUSDT".to_string(), 10), ("ETHUSDT".to_string(), 20), ("BTCUSDT".to_string(), 30)]);
+        assert_eq!(account.lock().await.exchange_timestamp.load(std::sync::atomic::Ordering::SeqCst), 30);
+    }
+
+    fn book(timestamp: i64, is_snapshot: bool, bids: Vec<(f64, f64)>, asks: Vec<(f64, f64)>) -> L2BookMessage
+    {
+        L2BookMessage { exchange: "binance-futures".to_string(),
+                        symbol: "ETHUSDT".to_string(),
+                        timestamp,
+                        is_snapshot,
+                        first_sequence: None,
+                        sequence: None,
+                        prev_sequence: None,
+                        bids,
+                        asks }
+    }
+
+    #[tokio::test]
+    async fn start_should_quote_single_level_book_from_replayed_l2_updates()
+    {
+        let account = Arc::new(Mutex::new(create_test_account().await));
+        let books = VecDeque::from(vec![book(5, true, vec![(16400.0, 1.0), (16399.0, 2.0)], vec![(16402.0, 1.0)]), book(15, false, vec![(16400.0, 0.0)], vec![])]);
+        let trades = VecDeque::from(vec![MarketTrade { exchange: "binance-futures".to_string(),
+                                                       symbol: "ETHUSDT".to_string(),
+                                                       timestamp: 10,
+                                                       price: 16401.0,
+                                                       side: Side::Buy.to_string(),
+                                                       amount: 1.0 }]);
+        let merger = ReplayMerger::new().with_source_into(trades).with_source_into(books);
+
+        let (market_tx, _market_rx) = mpsc::unbounded_channel();
+        let (client_tx, client_rx) = mpsc::unbounded_channel();
+        let exchange = HourglassExchange::builder().event_hourglass_rx(client_rx)
+                                                   .account(account.clone())
+                                                   .market_event_tx(market_tx)
+                                                   .data_source(DataSource::Depth(merger))
+                                                   .initiate()
+                                                   .unwrap();
+        // 第一次推进应用快照并处理成交，第二次应用剩余的订单簿更新后数据耗尽
+        client_tx.send(HourglassClientEvent::LetItRoll).unwrap();
+        client_tx.send(HourglassClientEvent::LetItRoll).unwrap();
+        exchange.start().await;
+
+        let account = account.lock().await;
+        let instrument = Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual));
+        let quotes = account.single_level_order_book.lock().await.get(&instrument).map(|book| (book.latest_bid, book.latest_ask, book.latest_price));
+        // 买方成交不再改写买价，最优买价撤单后由下一档给出
+        assert_eq!(quotes, Some((16399.0, 16402.0, 16401.0)));
+        assert_eq!(account.l2_books.book(&instrument).unwrap().depth(5), (vec![(16399.0, 2.0)], vec![(16402.0, 1.0)]));
+    }
+
+    #[tokio::test]
+    async fn start_should_publish_bars_aggregated_from_replayed_trades()
+    {
+        let account = Arc::new(Mutex::new(create_test_account().await));
+        let trades = VecDeque::from(vec![create_test_market_trade("ETHUSDT", 1_000, 16400.0, "buy", 1.0),
+                                         create_test_market_trade("ETHUSDT", 30_000, 16410.0, "buy", 1.0),
+                                         create_test_market_trade("ETHUSDT", 61_000, 16405.0, "buy", 1.0)]);
+
+        let (market_tx, _market_rx) = mpsc::unbounded_channel();
+        let (bar_tx, mut bar_rx) = mpsc::unbounded_channel();
+        let (client_tx, client_rx) = mpsc::unbounded_channel();
+        let exchange = HourglassExchange::builder().event_hourglass_rx(client_rx)
+                                                   .account(account)
+                                                   .market_event_tx(market_tx)
+                                                   .bar_event_tx(bar_tx)
+                                                   .bar_spec(BarSpec::Time(60_000))
+                                                   .data_source(DataSource::Replay(ReplayMerger::new().with_source(trades)))
+                                                   .initiate()
+                                                   .unwrap();
+        for _ in 0..4 {
+            client_tx.send(HourglassClientEvent::LetItRoll).unwrap();
+        }
+        exchange.start().await;
+
+        // 第三笔成交进入下一分钟时完成第一根 K 线，数据结束时输出最后一根
+        let bars: Vec<(i64, f64, f64, f64)> = std::iter::from_fn(|| bar_rx.try_recv().ok()).map(|bar| (bar.open_time, bar.open, bar.high, bar.volume)).collect();
+        assert_eq!(bars, vec![(0, 16400.0, 16410.0, 2.0), (60_000, 16405.0, 16405.0, 1.0)]);
+    }
+
+    #[tokio::test]
+    async fn start_should_fill_resting_orders_from_bar_only_replay()
+    {
+        let account = Arc::new(Mutex::new(create_test_account().await));
+        let instrument = Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual));
+        let open_order = Order { instruction: OrderInstruction::Limit,
+                                 exchange: Exchange::Hourglass,
+                                 instrument: instrument.clone(),
+                                 timestamp: 0,
+                                 cid: Some(ClientOrderId("bar_fill".into())),
+                                 side: Side::Buy,
+                                 state: Open { id: OrderId::new(0, 0, 0),
+                                               price: 16390.0,
+                                               size: 0.1,
+                                               filled_quantity: 0.0,
+                                               order_role: OrderRole::Maker,
+                                               position_side: PositionSide::Both } };
+        account.lock().await.account_open_book.write().await.get_ins_orders_mut(&instrument).unwrap().add_order_open(open_order);
+
+        let bar = Bar { exchange: "binance-futures".to_string(),
+                        symbol: "ETHUSDT".to_string(),
+                        open_time: 0,
+                        close_time: 60_000,
+                        open: 16400.0,
+                        high: 16420.0,
+                        low: 16380.0,
+                        close: 16410.0,
+                        volume: 4.0,
+                        quote_volume: 0.0,
+                        trade_count: 0 };
+        let (market_tx, mut market_rx) = mpsc::unbounded_channel();
+        let (bar_tx, mut bar_rx) = mpsc::unbounded_channel();
+        let (client_tx, client_rx) = mpsc::unbounded_channel();
+        let exchange = HourglassExchange::builder().event_hourglass_rx(client_rx)
+                                                   .account(account.clone())
+                                                   .market_event_tx(market_tx)
+                                                   .bar_event_tx(bar_tx)
+                                                   .bar_spec(BarSpec::Tick(1))
+                                                   .validation(ValidationConfig::default())
+                                                   .data_source(DataSource::Bars(BarReplay::new(VecDeque::from(vec![bar.clone()]), IntrabarFill::OpenHighLowClose)))
+                                                   .initiate()
+                                                   .unwrap();
+        let validator = exchange.get_data_validator().unwrap();
+        // 四笔合成成交之后数据耗尽
+        for _ in 0..5 {
+            client_tx.send(HourglassClientEvent::LetItRoll).unwrap();
+        }
+        exchange.start().await;
+
+        let prices: Vec<f64> = std::iter::from_fn(|| market_rx.try_recv().ok()).map(|trade| trade.price).collect();
+        assert_eq!(prices, vec![16400.0, 16420.0, 16380.0, 16410.0]);
+        // 合成成交不再被聚合为新的 K 线，只发送原始 K 线
+        assert_eq!(std::iter::from_fn(|| bar_rx.try_recv().ok()).collect::<Vec<_>>(), vec![bar]);
+        // 合成成交同样经过数据校验
+        assert_eq!(validator.lock().await.report().total, 4);
+        // 最低价的卖方合成成交与买单撮合
+        assert!(account.lock().await.account_open_book.read().await.fetch_all().is_empty());
+    }
+
+    #[tokio::test]
+    async fn start_should_validate_replayed_trades_and_collect_a_quality_report()
+    {
+        let account = Arc::new(Mutex::new(create_test_account().await));
+        let trades = VecDeque::from(vec![create_test_market_trade("ETHUSDT", 10, 16400.0, "buy", 1.0),
+                                         create_test_market_trade("ETHUSDT", 10, 16400.0, "buy", 1.0),
+                                         create_test_market_trade("ETHUSDT", 20, 16405.0, "buy", 1.0),
+                                         create_test_market_trade("ETHUSDT", 15, 16410.0, "buy", 1.0),
+                                         create_test_market_trade("ETHUSDT", 30, -1.0, "buy", 1.0)]);
+
+        let (market_tx, mut market_rx) = mpsc::unbounded_channel();
+        let (client_tx, client_rx) = mpsc::unbounded_channel();
+        let exchange = HourglassExchange::builder().event_hourglass_rx(client_rx)
+                                                   .account(account)
+                                                   .market_event_tx(market_tx)
+                                                   .validation(ValidationConfig { invalid_price: ValidationPolicy::Drop,
+                                                                                  ..ValidationConfig::default() })
+                                                   .data_source(DataSource::Replay(ReplayMerger::new().with_source(trades)))
+                                                   .initiate()
+                                                   .unwrap();
+        let validator = exchange.get_data_validator().unwrap();
+        for _ in 0..4 {
+            client_tx.send(HourglassClientEvent::LetItRoll).unwrap();
+        }
+        exchange.start().await;
+
+        // 重复成交被跳过，乱序的时间戳被修复，最后一笔无效价格被丢弃后数据结束
+        let replayed: Vec<(i64, f64)> = std::iter::from_fn(|| market_rx.try_recv().ok()).map(|trade| (trade.timestamp, trade.price)).collect();
+        assert_eq!(replayed, vec![(10, 16400.0), (20, 16405.0), (20, 16410.0)]);
+        let validator = validator.lock().await;
+        let report = validator.report();
+        assert_eq!((report.total, report.passed, report.repaired, report.dropped), (5, 2, 1, 2));
+        assert_eq!(report.issues.keys().copied().collect::<Vec<_>>(), vec![DataIssue::OutOfOrder, DataIssue::Duplicate, DataIssue::InvalidPrice]);
+    }
+
     // Function to check if a port is in use
     fn is_port_in_use(address: std::net::SocketAddr) -> bool
     {
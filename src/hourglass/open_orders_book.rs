@@ -8,6 +8,7 @@ use crate::{
     },
     error::ExchangeError,
     hourglass::clickhouse_api::datatype::clickhouse_trade_data::MarketTrade,
+    hourglass_log::warn,
     Exchange,
 };
 use rayon::prelude::ParallelSliceMut;
@@ -48,7 +49,8 @@ pub fn calculate_fees(order: &Order<Open>, trade_quantity: f64, fees_percent: f6
 
         // 针对期权交易的费用计算
         | InstrumentKind::CryptoOption => {
-            let option_fees = OptionFees { trade_fee: fees_percent * trade_quantity /* 交易费率计算 */ };
+            let option_fees = OptionFees { trade_fee: fees_percent * trade_quantity, // 交易费率计算
+                                           exercise_fee: 0.0                        /* 行权费在到期行权时计算 */ };
             InstrumentFees::new(order.instrument.kind, Fees::Option(option_fees))
         }
 
@@ -69,9 +71,9 @@ impl OpenOrdersBook
                 self.bids.par_sort();
             }
             | Side::Sell => {
-                // 添加 Order<Open> 到卖单
+                // 添加 Order<Open> 到卖单，卖单按价格从高到低排列，最优卖单在末尾
                 self.asks.push(new_open_order);
-                self.asks.par_sort();
+                self.asks.par_sort_by(|a, b| b.cmp(a));
             }
         }
     }
@@ -96,34 +98,75 @@ impl OpenOrdersBook
                     }
                 }
             }
-            | _ => {
-                println!("Input MarketTrade is likely to have mistaken 'side' info.")
-            }
+            // 回放时可通过 `ValidationConfig` 在数据路径中校验或修复方向
+            | other => warn!("Ignoring MarketTrade with invalid side {:?}: {} {} at {}", other, market_event.exchange, market_event.symbol, market_event.timestamp),
         }
         None
     }
 
     pub fn match_bids(&mut self, market_trade: &MarketTrade, fees_percent: f64, counter: &AtomicI64) -> Vec<ClientTrade>
     {
-        let latest_trade_ts = market_trade.timestamp;
+        let mut trades = Vec::new();
+        self.fill_bids(market_trade.timestamp, market_trade.price, market_trade.amount, fees_percent, counter, &mut trades);
+        trades
+    }
 
-        // Track remaining liquidity for matching
-        let mut remaining_liquidity = market_trade.amount;
+    pub fn match_asks(&mut self, market_trade: &MarketTrade, fees_percent: f64, counter: &AtomicI64) -> Vec<ClientTrade>
+    {
+        let mut trades = Vec::new();
+        self.fill_asks(market_trade.timestamp, market_trade.price, market_trade.amount, fees_percent, counter, &mut trades);
+        trades
+    }
 
-        // Collect trades generated by matching outstanding bid orders
+    /// 用 L2 订单簿中与买单交叉的卖盘档位撮合买单，`levels` 按从优到劣排列。
+    ///
+    /// 每一档的挂单量依次被价格不低于该档的买单消耗，成交价为买单的挂单价格。
+    pub fn match_bids_against_levels(&mut self, timestamp: i64, levels: &[(f64, f64)], fees_percent: f64, counter: &AtomicI64) -> Vec<ClientTrade>
+    {
         let mut trades = Vec::new();
+        for &(price, amount) in levels {
+            // 这一档没有被消耗完，说明已没有与之交叉的买单
+            if self.fill_bids(timestamp, price, amount, fees_percent, counter, &mut trades) > 0.0 {
+                break;
+            }
+        }
+        trades
+    }
+
+    /// 用 L2 订单簿中与卖单交叉的买盘档位撮合卖单，`levels` 按从优到劣排列。
+    ///
+    /// 每一档的挂单量依次被价格不高于该档的卖单消耗，成交价为卖单的挂单价格。
+    pub fn match_asks_against_levels(&mut self, timestamp: i64, levels: &[(f64, f64)], fees_percent: f64, counter: &AtomicI64) -> Vec<ClientTrade>
+    {
+        let mut trades = Vec::new();
+        for &(price, amount) in levels {
+            // 这一档没有被消耗完，说明已没有与之交叉的卖单
+            if self.fill_asks(timestamp, price, amount, fees_percent, counter, &mut trades) > 0.0 {
+                break;
+            }
+        }
+        trades
+    }
+
+    /// 以 `price` 处的 `liquidity` 撮合价格不低于 `price` 的买单，返回未被消耗的流动性。
+    fn fill_bids(&mut self, latest_trade_ts: i64, price: f64, liquidity: f64, fees_percent: f64, counter: &AtomicI64, trades: &mut Vec<ClientTrade>) -> f64
+    {
+        // Track remaining liquidity for matching
+        let mut remaining_liquidity = liquidity;
+        // 挂单时间晚于行情时间的买单暂不参与撮合，结束后放回
+        let mut pending = Vec::new();
 
         while let Some(mut best_bid) = self.bids.pop() {
             let bid_timestamp = best_bid.timestamp;
 
             // 如果传入的market_trade.timestamp比bid_timestamp小，则跳过该bid，但不报错
             if latest_trade_ts < bid_timestamp {
-                self.bids.push(best_bid);
+                pending.push(best_bid);
                 continue;
             }
 
             // If the best bid price is below the market trade price or liquidity is exhausted, exit loop
-            if best_bid.state.price < market_trade.price || remaining_liquidity <= 0.0 {
+            if best_bid.state.price < price || remaining_liquidity <= 0.0 {
                 self.bids.push(best_bid);
                 break;
             }
@@ -148,6 +191,7 @@ impl OpenOrdersBook
             else {
                 // Partial fill
                 let trade_quantity = remaining_liquidity;
+                remaining_liquidity = 0.0;
                 best_bid.state.filled_quantity += trade_quantity;
                 trades.push(self.generate_client_trade_event(latest_trade_ts, &best_bid, trade_quantity, fees_percent, counter).unwrap());
                 self.bids.push(best_bid); // Put the partially filled order back into the queue
@@ -155,30 +199,32 @@ impl OpenOrdersBook
             }
         }
 
-        trades
+        if !pending.is_empty() {
+            self.bids.extend(pending);
+            self.bids.par_sort();
+        }
+        remaining_liquidity
     }
 
-    pub fn match_asks(&mut self, market_trade: &MarketTrade, fees_percent: f64, counter: &AtomicI64) -> Vec<ClientTrade>
+    /// 以 `price` 处的 `liquidity` 撮合价格不高于 `price` 的卖单，返回未被消耗的流动性。
+    fn fill_asks(&mut self, latest_trade_ts: i64, price: f64, liquidity: f64, fees_percent: f64, counter: &AtomicI64, trades: &mut Vec<ClientTrade>) -> f64
     {
-        let latest_trade_ts = market_trade.timestamp;
-
         // Track remaining liquidity for matching
-        let mut remaining_liquidity = market_trade.amount;
-
-        // Collect trades generated by matching outstanding sell orders
-        let mut trades = Vec::new();
+        let mut remaining_liquidity = liquidity;
+        // 挂单时间晚于行情时间的卖单暂不参与撮合，结束后放回
+        let mut pending = Vec::new();
 
         while let Some(mut best_ask) = self.asks.pop() {
             let ask_timestamp = best_ask.timestamp;
 
             // 略过 timestamp 比传入的 market_trade.timestamp 小的情况
             if latest_trade_ts < ask_timestamp {
-                self.asks.push(best_ask);
+                pending.push(best_ask);
                 continue;
             }
 
             // If the best ask price is higher than the market trade price or liquidity is exhausted, exit loop
-            if best_ask.state.price > market_trade.price || remaining_liquidity <= 0.0 {
+            if best_ask.state.price > price || remaining_liquidity <= 0.0 {
                 self.asks.push(best_ask);
                 break;
             }
@@ -203,6 +249,7 @@ impl OpenOrdersBook
             else {
                 // Partial fill
                 let trade_quantity = remaining_liquidity;
+                remaining_liquidity = 0.0;
                 best_ask.state.filled_quantity += trade_quantity;
                 trades.push(self.generate_client_trade_event(latest_trade_ts, &best_ask, trade_quantity, fees_percent, counter).unwrap());
                 self.asks.push(best_ask); // Put the partially filled order back into the queue
@@ -210,12 +257,16 @@ impl OpenOrdersBook
             }
         }
 
-        trades
+        if !pending.is_empty() {
+            self.asks.extend(pending);
+            self.asks.par_sort_by(|a, b| b.cmp(a));
+        }
+        remaining_liquidity
     }
 
     pub fn generate_client_trade_event(&self, timestamp: i64, order: &Order<Open>, trade_quantity: f64, fees_percent: f64, counter: &AtomicI64) -> Result<ClientTrade, ExchangeError>
     {
-        let fee = trade_quantity * order.state.price * fees_percent;
+        let fee = order.instrument.notional(order.state.price, trade_quantity) * fees_percent;
 
         // Fetch the current value from the AtomicI64
         let trade_id = counter.load(Ordering::SeqCst); // Get the current value as an `i64`
@@ -229,7 +280,8 @@ impl OpenOrdersBook
                          side: order.side,
                          price: order.state.price,
                          size: trade_quantity,
-                         fees: fee })
+                         fees: fee,
+                         position_side: order.state.position_side })
     }
 
     /// 计算所有未成交买单和卖单的总数。
@@ -1,28 +1,35 @@
-/// [本特性未实现] 风险准备金池结构体，用于管理市场中的风险准备金，并在爆仓等极端情况下提供资金支持。
+/// 风险准备金池结构体，用于管理市场中的风险准备金，并在爆仓等极端情况下提供资金支持。
 /// 该结构体维护一个全局的 `total_reserve` 变量，表示当前系统中可用于弥补亏损的风险准备金总量。
 ///
 /// 风险准备金池的设计目的是为交易系统提供一个安全网，在用户爆仓或市场波动较大的情况下，
 /// 可以优先从准备金池中提取资金弥补亏损，减少或避免亏损对用户的直接影响。
 ///
 /// # 风险准备金池工作机制:
-/// 1. 在每笔交易执行时，可以从手续费中按比例抽取一部分资金进入风险准备金池，以此积累风险准备金。
+/// 1. 在每笔交易执行时，按 `AccountConfig::risk_reserve_fee_share` 从手续费和强平费中抽取一部分资金进入风险准备金池。
 /// 2. 当市场出现较大波动，用户爆仓且其保证金不足以弥补亏损时，系统首先从风险准备金池中扣除相应的资金。
-/// 3. 如果准备金不足，系统可以从盈利用户中分摊剩余的亏损部分（如果启用了分摊机制）。
-struct RiskReserve
+/// 3. 如果准备金不足，系统会对盈利的反向仓位进行自动减仓（ADL），由其分摊剩余的亏损部分。
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RiskReserve
 {
     pub total_reserve: f64, // 风险准备金总量
 }
 
-#[allow(dead_code)]
 impl RiskReserve
 {
+    pub fn new(initial_reserve: f64) -> Self
+    {
+        Self { total_reserve: initial_reserve }
+    }
+
     // 预留部分资金进入风险准备金池
     pub fn contribute(&mut self, amount: f64)
     {
-        self.total_reserve += amount;
+        if amount > 0.0 {
+            self.total_reserve += amount;
+        }
     }
 
-    // 从准备金中扣除，用于弥补爆仓亏损
+    // 从准备金中扣除，用于弥补爆仓亏损，返回实际扣除的金额
     pub fn deduct(&mut self, amount: f64) -> f64
     {
         if self.total_reserve >= amount {
@@ -35,4 +42,26 @@ impl RiskReserve
             remaining
         }
     }
+
+    pub fn is_exhausted(&self) -> bool
+    {
+        self.total_reserve <= 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn risk_reserve_should_deduct_at_most_its_total()
+    {
+        let mut reserve = RiskReserve::new(10.0);
+        reserve.contribute(5.0);
+        reserve.contribute(-1.0);
+        assert_eq!(reserve.deduct(4.0), 4.0);
+        assert_eq!(reserve.deduct(20.0), 11.0);
+        assert!(reserve.is_exhausted());
+    }
 }
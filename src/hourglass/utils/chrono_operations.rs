@@ -1,4 +1,4 @@
-use chrono::{DateTime, Datelike, Local, TimeZone, Timelike, Utc};
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Timelike, Utc};
 use regex::Regex;
 use std::sync::LazyLock;
 
@@ -102,6 +102,15 @@ pub fn expand_date_str(input_str: &str) -> String
     }
 }
 
+/// 返回 `start_date` 到 `end_date`（含两端）的每一天，格式与表名中的日期一致: 2024_05_05
+pub fn dates_between(start_date: NaiveDate, end_date: NaiveDate) -> Vec<String>
+{
+    start_date.iter_days()
+              .take_while(|date| *date <= end_date)
+              .map(|date| date.format("%Y_%m_%d").to_string())
+              .collect()
+}
+
 /// TODO: parse date string to unix timestamp
 #[cfg(test)]
 mod tests
@@ -164,6 +173,15 @@ mod tests
         assert_eq!(local_datetime.minute(), minute);
     }
 
+    #[test]
+    fn test_dates_between()
+    {
+        let start_date = NaiveDate::from_ymd_opt(2024, 2, 28).unwrap();
+        let end_date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        assert_eq!(dates_between(start_date, end_date), vec!["2024_02_28", "2024_02_29", "2024_03_01"]);
+        assert!(dates_between(end_date, start_date).is_empty());
+    }
+
     #[test]
     fn test_extract_date_binance()
     {
@@ -1,4 +1,8 @@
-use crate::{error::ExchangeError, hourglass::account::account_config::AccountConfig};
+use crate::{
+    common::instrument::symbol_registry::{SymbolFile, SymbolRegistry},
+    error::ExchangeError,
+    hourglass::account::account_config::AccountConfig,
+};
 use std::{fs, path::Path};
 
 /// 读取配置文件，并返回`AccountConfig`结构体实例。
@@ -29,6 +33,23 @@ pub fn read_config_file() -> Result<AccountConfig, ExchangeError>
     Ok(config)
 }
 
+/// 读取符号文件（例如 `symbols.toml`），返回把行情符号映射为金融工具的 [`SymbolRegistry`]。
+///
+/// # 错误
+/// - `ExecutionError::ConfigMissing`: 如果符号文件不存在。
+/// - `ExecutionError::ConfigParseError`: 如果TOML解析失败，或同一符号被登记为不同的金融工具。
+pub fn read_symbol_file(path: impl AsRef<Path>) -> Result<SymbolRegistry, ExchangeError>
+{
+    let path = path.as_ref();
+    if !path.exists() {
+        return Err(ExchangeError::ConfigMissing);
+    }
+
+    let content = fs::read_to_string(path).map_err(ExchangeError::from)?;
+    let file: SymbolFile = toml::from_str(&content).map_err(ExchangeError::from)?;
+    SymbolRegistry::try_from(file.symbols)
+}
+
 // 将`std::io::Error`转换为自定义的`ExecutionError`
 impl From<std::io::Error> for ExchangeError
 {
@@ -52,8 +73,11 @@ mod tests
 {
     use super::*;
     use crate::{
-        common::{account_positions::PositionDirectionMode, instrument::kind::InstrumentKind},
-        hourglass::account::account_config::{CommissionLevel, CommissionRates, MarginMode},
+        common::{
+            account_positions::PositionDirectionMode,
+            instrument::{kind::InstrumentKind, Instrument},
+        },
+        hourglass::account::account_config::{CommissionLevel, CommissionRates, FeeTier, InstrumentCommissionRates, MarginMode, PositionLimits},
     };
     use std::{fs, io::Write};
     use tempfile::tempdir;
@@ -74,6 +98,8 @@ mod tests
     max_price_deviation = 0.05
     lazy_account_positions = false
     liquidation_threshold = 0.9
+    liquidation_fee_rate = 0.005
+    risk_reserve_fee_share = 0.5
 
 
 
@@ -117,6 +143,56 @@ mod tests
         assert_eq!(config.lazy_account_positions, false);
         assert_eq!(config.fees_book.get(&InstrumentKind::Spot).cloned(), Some(CommissionRates { maker_fees: 0.001, taker_fees: 0.002 }));
         assert_eq!(config.fees_book.get(&InstrumentKind::Perpetual).cloned(), Some(CommissionRates { maker_fees: 0.0005, taker_fees: 0.001 }));
+        assert_eq!(config.position_limits, PositionLimits::default());
+    }
+
+    /// 测试仓位限额配置的解析，未设置的项不做限制
+    #[test]
+    fn test_parse_position_limits()
+    {
+        let toml_content = r#"
+    max_position_notional = 1000000.0
+    max_net_exposure = 2000000.0
+    instrument_max_position_notional = { BTCUSDT = 2000000.0 }
+    "#;
+
+        let limits: PositionLimits = toml::from_str(toml_content).expect("Failed to parse position limits");
+        assert_eq!(limits.max_position_notional, Some(1_000_000.0));
+        assert_eq!(limits.max_net_exposure, Some(2_000_000.0));
+        assert_eq!(limits.max_gross_exposure, None);
+        assert_eq!(limits.max_position_notional_for(&Instrument::from(("BTC", "USDT", InstrumentKind::Perpetual))), Some(2_000_000.0));
+        assert_eq!(limits.max_position_notional_for(&Instrument::from(("ETH", "USDT", InstrumentKind::Perpetual))), Some(1_000_000.0));
+    }
+
+    /// 测试手续费等级费率表的解析，maker 费率可以为负数
+    #[test]
+    fn test_parse_fee_tier()
+    {
+        let toml_content = r#"
+    level = "Lv3"
+    min_volume = 50000000.0
+    fees_book = { perpetual = { maker_fees = -0.00005, taker_fees = 0.0004 } }
+    "#;
+
+        let tier: FeeTier = toml::from_str(toml_content).expect("Failed to parse fee tier");
+        assert_eq!(tier.level, CommissionLevel::Lv3);
+        assert_eq!(tier.min_volume, 50_000_000.0);
+        assert_eq!(tier.fees_book.get(&InstrumentKind::Perpetual).cloned(), Some(CommissionRates { maker_fees: -0.00005, taker_fees: 0.0004 }));
+    }
+
+    /// 测试按金融工具设置的手续费率的解析
+    #[test]
+    fn test_parse_instrument_commission_rates()
+    {
+        let toml_content = r#"
+    instrument = { base = "BTC", quote = "USDT", instrument_kind = "perpetual" }
+    maker_fees = 0.0
+    taker_fees = 0.0003
+    "#;
+
+        let entry: InstrumentCommissionRates = toml::from_str(toml_content).expect("Failed to parse instrument commission rates");
+        assert_eq!(entry.instrument, Instrument::from(("BTC", "USDT", InstrumentKind::Perpetual)));
+        assert_eq!(entry.rates, CommissionRates { maker_fees: 0.0, taker_fees: 0.0003 });
     }
 
     /// 测试配置文件缺失的情况
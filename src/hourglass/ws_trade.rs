@@ -3,13 +3,8 @@ use crate::common::datafeed::market_event::MarketEvent;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    common::{
-        instrument::{
-            kind::{InstrumentKind, InstrumentKind::Perpetual},
-            Instrument,
-        },
-        token::Token,
-    },
+    common::instrument::symbol_registry::SymbolRegistry,
+    error::ExchangeError,
     hourglass::clickhouse_api::datatype::clickhouse_trade_data::MarketTrade,
     Exchange,
 };
@@ -32,41 +27,39 @@ pub struct WsTrade
 // NOTE 这是按照Okex交易所API数据类型构建的 WebsocketTrade 数据结构，回测选用。
 impl MarketEvent<WsTrade>
 {
-    pub fn from_ws_trade(ws_trade: WsTrade, base: String, quote: String, instrument: InstrumentKind) -> Self
+    /// 通过符号表解析 `instId` 对应的金融工具，`exchange` 为行情数据来源的交易所名称。
+    pub fn from_ws_trade(ws_trade: WsTrade, exchange: &str, registry: &SymbolRegistry) -> Result<Self, ExchangeError>
     {
         let exchange_time = ws_trade.ts.parse::<i64>().unwrap_or(0);
         let received_time = ws_trade.ts.parse::<i64>().unwrap_or(0); // NOTE 注意这是不对的 应该加上一个标准化的随机延迟。
 
-        let instrument = Instrument { base: Token::from(base),
-                                      quote: Token::from(quote),
-                                      kind: instrument };
+        let instrument = registry.resolve(exchange, &ws_trade.instId)?.clone();
 
-        MarketEvent { exchange_ts: exchange_time,
-                      received_ts: received_time,
-                      exchange: Exchange::Hourglass,
+        Ok(MarketEvent { exchange_ts: exchange_time,
+                         received_ts: received_time,
+                         exchange: Exchange::Hourglass,
 
-                      instrument,
-                      kind: ws_trade }
+                         instrument,
+                         kind: ws_trade })
     }
 }
 
 // NOTE 这是按照Clickhouse中存储的数据类型构建的 WebsocketTrade 数据结构，回测选用。
 impl MarketEvent<MarketTrade>
 {
-    pub fn from_swap_trade_clickhouse(trade: MarketTrade, base: String, quote: String) -> Self
+    /// 通过符号表解析成交数据对应的金融工具，未登记的符号返回 [`ExchangeError::UnknownSymbol`]。
+    pub fn from_market_trade(trade: MarketTrade, registry: &SymbolRegistry) -> Result<Self, ExchangeError>
     {
         let exchange_time = trade.timestamp;
         let received_time = trade.timestamp; // NOTE 注意这是不对的 应该加上一个标准化的随机延迟。
 
-        let instrument = Instrument { base: Token::from(base),
-                                      quote: Token::from(quote),
-                                      kind: Perpetual };
+        let instrument = trade.resolve_instrument(registry)?;
 
-        MarketEvent { exchange_ts: exchange_time,
-                      received_ts: received_time,
-                      exchange: Exchange::Hourglass,
-                      instrument,
-                      kind: trade }
+        Ok(MarketEvent { exchange_ts: exchange_time,
+                         received_ts: received_time,
+                         exchange: Exchange::Hourglass,
+                         instrument,
+                         kind: trade })
     }
 }
 
@@ -83,22 +76,6 @@ impl From<MarketTrade> for WsTrade
     }
 }
 
-pub fn parse_base_and_quote(symbol: &str) -> (String, String)
-{
-    // 定义一个包含常见报价货币的数组
-    let quote_assets = ["USDT", "USTC", "USDC", "USD", "UST", "DAI", "FDUSD", "BTC", "ETH", "EURT"];
-    // 遍历所有已知的报价货币
-    for &quote in &quote_assets {
-        // 尝试去除报价货币的后缀
-        if let Some(base) = symbol.strip_suffix(quote) {
-            // 返回基础货币和报价货币
-            return (base.to_string(), quote.to_string());
-        }
-    }
-    // 如果没有匹配的报价货币，返回原始符号作为基础货币，报价货币为空字符串
-    (symbol.to_string(), String::new())
-}
-
 #[allow(dead_code)]
 impl WsTrade
 {
@@ -1,16 +1,21 @@
 use crate::{
     common::{
-        account_positions::{AccountPositions, Position},
+        account_positions::{AccountPositions, Position, PositionConfig, PositionMarginMode},
+        account_summary::AccountSummary,
         balance::TokenBalance,
         event::AccountEvent,
         instrument::Instrument,
+        option_pricing::AccountGreeks,
         order::{
             states::{cancelled::Cancelled, request_cancel::RequestCancel, request_open::RequestOpen},
             Order,
         },
         token::Token,
+        trade::ClientTrade,
+        Side,
     },
     error::ExchangeError,
+    hourglass::account::account_config::CommissionRates,
 };
 use async_trait::async_trait;
 use common::order::states::open::Open;
@@ -42,6 +47,16 @@ pub trait ClientExecution
     async fn fetch_all_positions(&self) -> Result<AccountPositions, ExchangeError>; // 补全 FetchLongPosition 的实现
     async fn fetch_long_position(&self, instrument: Instrument) -> Result<Option<Position>, ExchangeError>; // 补全 FetchShortPosition 的实现
     async fn fetch_short_position(&self, instrument: Instrument) -> Result<Option<Position>, ExchangeError>;
+    async fn fetch_account_summary(&self, token: Token) -> Result<AccountSummary, ExchangeError>;
+    async fn fetch_account_greeks(&self, token: Token) -> Result<AccountGreeks, ExchangeError>;
+    async fn adjust_isolated_margin(&self, instrument: Instrument, side: Side, delta: f64) -> Result<Position, ExchangeError>;
+    async fn set_leverage(&self, instrument: Instrument, side: Side, leverage: f64) -> Result<PositionConfig, ExchangeError>;
+    async fn set_margin_mode(&self, instrument: Instrument, side: Side, margin_mode: PositionMarginMode) -> Result<PositionConfig, ExchangeError>;
+    async fn set_instrument_fees(&self, instrument: Instrument, rates: Option<CommissionRates>) -> Result<Option<CommissionRates>, ExchangeError>;
+    async fn subscribe_leveraged_token(&self, instrument: Instrument, size: f64) -> Result<ClientTrade, ExchangeError>;
+    async fn redeem_leveraged_token(&self, instrument: Instrument, size: f64) -> Result<ClientTrade, ExchangeError>;
+    async fn borrow(&self, token: Token, amount: f64) -> Result<TokenBalance, ExchangeError>;
+    async fn repay(&self, token: Token, amount: f64) -> Result<TokenBalance, ExchangeError>;
     // async fn fetch_balance(&self) -> Result<TokenBalance, ExchangeError>; // TODO
     // async fn fetch_positions(&self) -> Result<AccountPositions, ExchangeError>;  // TODO
     async fn open_orders(&self, open_requests: Vec<Order<RequestOpen>>) -> Vec<Result<Order<Open>, ExchangeError>>;
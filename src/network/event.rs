@@ -24,6 +24,7 @@
 /// ///
 /// use hourglass::common::order::identification::client_order_id::ClientOrderId;
 /// use hourglass::common::order::states::request_open::RequestOpen;
+/// use hourglass::common::account_positions::PositionSide;
 ///
 /// fn create_open_orders_event() -> NetworkEvent
 /// {
@@ -39,7 +40,8 @@
 ///                               side: Side::Buy,                                                       // 买卖方向
 ///                               state: RequestOpen { reduce_only: false, // 非减仓订单
 ///                                                    price: 50000.0,     // 下单价格
-///                                                    size: 1.0           /* 下单数量 */ } }];
+///                                                    size: 1.0,          // 下单数量
+///                                                    position_side: PositionSide::Both /* 单向持仓 */ } }];
 ///
 ///     // 序列化 orders 为 JSON 字符串
 ///     let payload = serde_json::to_string(&orders).expect("Failed to serialize orders");
@@ -138,6 +140,7 @@ mod tests
     use super::*;
     use crate::{
         common::{
+            account_positions::PositionSide,
             instrument::{kind::InstrumentKind, Instrument},
             order::{identification::client_order_id::ClientOrderId, order_instructions::OrderInstruction, Order},
             Side,
@@ -163,7 +166,8 @@ mod tests
                                   side: Side::Buy,                                                       // 买卖方向
                                   state: RequestOpen { reduce_only: false, // 非减仓订单
                                                        price: 50000.0,     // 下单价格
-                                                       size: 1.0           /* 下单数量 */ } }];
+                                                       size: 1.0,          // 下单数量
+                                                       position_side: PositionSide::Both /* 单向持仓 */ } }];
 
         // 序列化 orders 为 JSON 字符串
         let payload = serde_json::to_string(&orders).expect("Failed to serialize orders");
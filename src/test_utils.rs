@@ -6,11 +6,12 @@ use crate::{
             perpetual::{PerpetualPosition, PerpetualPositionConfig},
             position_id::PositionId,
             position_meta::PositionMeta,
-            AccountPositions, PositionDirectionMode, PositionMarginMode,
+            AccountPositions, PositionDirectionMode, PositionMarginMode, PositionSide,
         },
         balance::Balance,
         instrument::{
             kind::{InstrumentKind, InstrumentKind::Perpetual},
+            symbol_registry::SymbolRegistry,
             Instrument,
         },
         order::{
@@ -20,16 +21,22 @@ use crate::{
             Order, OrderRole,
         },
         token::Token,
+        trade::{ClientTrade, ClientTradeId},
         Side,
     },
     hourglass::{
         account::{
-            account_config::{AccountConfig, CommissionLevel, CommissionRates, HourglassMode, MarginMode},
+            account_config::{AccountConfig, CommissionLevel, CommissionRates, HourglassMode, MarginMode, OptionConfig, OrderRiskLimits, PositionLimits, SpotMarginConfig},
+            account_handlers::risk_handler::OrderRateLimiter,
             account_latency::{AccountLatency, FluctuationMode},
+            account_margin_loan::MarginLoanBook,
             account_orders::AccountOrders,
+            account_volume::TradingVolumeTracker,
             HourglassAccount,
         },
-        clickhouse_api::datatype::single_level_order_book::SingleLevelOrderBook,
+        clickhouse_api::datatype::{clickhouse_trade_data::MarketTrade, single_level_order_book::SingleLevelOrderBook},
+        l2_order_book::L2BookBuilder,
+        risk_reserve::RiskReserve,
     },
     Exchange,
 };
@@ -43,12 +50,18 @@ use std::{
 use tokio::sync::{Mutex, RwLock};
 use uuid::Uuid;
 
+/// 测试用交割合约与期权的到期时间。
+pub const TEST_EXPIRY: i64 = 1_700_000_000_000;
+
 /// 创建一个测试用的 `Instrument` 实例。
 pub fn create_test_instrument(kind: InstrumentKind) -> Instrument
 {
     Instrument { base: Token::from("BTC"),
                  quote: Token::from("USDT"),
-                 kind }
+                 kind,
+                 inverse: false,
+                 expiry: None,
+                 option_spec: None }
 }
 
 /// 创建一个测试用的 `AccountConfig` 实例。
@@ -63,10 +76,19 @@ pub fn create_test_account_configuration() -> AccountConfig
                     funding_rate: 0.0,
                     global_leverage_rate: leverage_rate,
                     fees_book: HashMap::new(),
+                    fee_schedule: Vec::new(),
+                    instrument_fees_book: Vec::new(),
                     execution_mode: HourglassMode::Backtest,
                     max_price_deviation: 0.05,
                     lazy_account_positions: false,
-                    liquidation_threshold: 0.9 }
+                    liquidation_threshold: 0.9,
+                    liquidation_fee_rate: 0.005,
+                    risk_reserve_fee_share: 0.5,
+                    position_limits: PositionLimits::default(),
+                    order_risk_limits: OrderRiskLimits::default(),
+                    option_config: OptionConfig::default(),
+                    leveraged_tokens: Vec::new(),
+                    spot_margin: SpotMarginConfig::default() }
 }
 // 帮助函数，用于创建测试用的 AccountOrders 实例
 pub async fn create_test_account_orders() -> AccountOrders
@@ -83,7 +105,10 @@ pub fn create_test_order_open(side: Side, price: f64, size: f64) -> Order<Open>
             exchange: Exchange::Hourglass,        // 假设测试环境使用 Hourglass 交易所
             instrument: Instrument { base: Token::from("ETH"),        // 测试用基础货币
                                      quote: Token::from("USDT"),      // 测试用报价货币
-                                     kind: InstrumentKind::Perpetual  /* 测试用永续合约 */ },
+                                     kind: InstrumentKind::Perpetual, // 测试用永续合约
+                                     inverse: false,
+                                     expiry: None,
+                                     option_spec: None },
             timestamp: 1625247600000,                       // 假设的客户端时间戳
             cid: Some(ClientOrderId("validCID123".into())), // 假设的客户端订单ID
             side,
@@ -91,7 +116,8 @@ pub fn create_test_order_open(side: Side, price: f64, size: f64) -> Order<Open>
                           price,
                           size,
                           filled_quantity: 0.0,         // 初始填充数量为0
-                          order_role: OrderRole::Taker  /* 假设订单角色为 Taker */ } }
+                          order_role: OrderRole::Taker, // 假设订单角色为 Taker
+                          position_side: PositionSide::Both /* 单向持仓模式 */ } }
 }
 
 // 帮助函数，用于创建测试用的订单
@@ -107,13 +133,17 @@ pub fn create_test_request_open(base: &str, quote: &str) -> Order<RequestOpen>
             exchange: Exchange::Hourglass,
             instrument: Instrument { base: Token::from(base),
                                      quote: Token::from(quote),
-                                     kind: InstrumentKind::Spot },
+                                     kind: InstrumentKind::Spot,
+                                     inverse: false,
+                                     expiry: None,
+                                     option_spec: None },
             timestamp: 1625247600000,
             cid: Some(ClientOrderId(format!("CID{}", order_id.0 % 1_000_000))),
             side: Side::Buy,
             state: RequestOpen { price: 50000.0,
                                  size: 1.0,
-                                 reduce_only: false } }
+                                 reduce_only: false,
+                                 position_side: PositionSide::Both } }
 }
 
 pub async fn create_test_account() -> HourglassAccount
@@ -133,9 +163,18 @@ pub async fn create_test_account() -> HourglassAccount
                                              max_price_deviation: 0.05,
                                              global_leverage_rate: leverage_rate,
                                              fees_book: HashMap::new(),
+                                             fee_schedule: Vec::new(),
+                                             instrument_fees_book: Vec::new(),
                                              execution_mode: HourglassMode::Backtest,
                                              lazy_account_positions: false,
-                                             liquidation_threshold: 0.9 };
+                                             liquidation_threshold: 0.9,
+                                             liquidation_fee_rate: 0.005,
+                                             risk_reserve_fee_share: 0.5,
+                                             position_limits: PositionLimits::default(),
+                                             order_risk_limits: OrderRiskLimits::default(),
+                                             option_config: OptionConfig::default(),
+                                             leveraged_tokens: Vec::new(),
+                                             spot_margin: SpotMarginConfig::default() };
 
     account_config.fees_book.insert(Perpetual, commission_rates);
 
@@ -147,7 +186,10 @@ pub async fn create_test_account() -> HourglassAccount
     let mut single_level_order_books = HashMap::new();
     single_level_order_books.insert(Instrument { base: Token::new("ETH".to_string()),
                                                  quote: Token::new("USDT".to_string()),
-                                                 kind: Perpetual },
+                                                 kind: Perpetual,
+                                                 inverse: false,
+                                                 expiry: None,
+                                                 option_spec: None },
                                     SingleLevelOrderBook { latest_bid: 16305.0,
                                                            latest_ask: 16499.0,
                                                            latest_price: 0.0 });
@@ -168,7 +210,26 @@ pub async fn create_test_account() -> HourglassAccount
                                                                                                                                                                                    minimum: 0,
                                                                                                                                                                                    current_value: 0 }).await)),
                        single_level_order_book: Arc::new(Mutex::new(single_level_order_books)),
-                       account_margin: Arc::new(0.0.into()) }
+                       account_margin: Arc::new(0.0.into()),
+                       risk_reserve: Arc::new(Mutex::new(RiskReserve::default())),
+                       order_rate_limiter: OrderRateLimiter::default(),
+                       trading_volume: TradingVolumeTracker::default(),
+                       leveraged_token_navs: HashMap::new(),
+                       margin_loans: MarginLoanBook::default(),
+                       symbol_registry: create_test_symbol_registry(),
+                       l2_books: L2BookBuilder::default() }
+}
+
+/// 创建测试用的符号表，登记单元测试中行情数据用到的符号。
+pub fn create_test_symbol_registry() -> SymbolRegistry
+{
+    let mut registry = SymbolRegistry::default();
+    for (symbol, base) in [("BTCUSDT", "BTC"), ("ETHUSDT", "ETH"), ("1000PEPEUSDT", "1000PEPE")] {
+        registry.register("binance-futures", symbol, Instrument::new(base, "USDT", InstrumentKind::Perpetual)).unwrap();
+    }
+    // 未指定到期时间的交割合约，撮合时匹配最近到期的合约
+    registry.register("binance-coin-futures", "ETHUSDT", Instrument::new("ETH", "USDT", InstrumentKind::Future)).unwrap();
+    registry
 }
 
 /// 创建一个测试用的 `PerpetualPosition` 实例。
@@ -222,3 +283,30 @@ pub fn create_test_future_position_with_side(instrument: Instrument, side: Side)
                      isolated_margin: None,
                      funding_fee: 0.0 }
 }
+
+/// 创建一个测试用的 `MarketTrade` 实例，来自 `binance-futures`。
+pub fn create_test_market_trade(symbol: &str, timestamp: i64, price: f64, side: &str, amount: f64) -> MarketTrade
+{
+    MarketTrade { exchange: "binance-futures".to_string(),
+                  symbol: symbol.to_string(),
+                  timestamp,
+                  price,
+                  side: side.to_string(),
+                  amount }
+}
+
+/// 创建一个测试用的 `ClientTrade` 实例，成交时间在 [`TEST_EXPIRY`] 前一分钟。
+pub fn create_test_client_trade(instrument: &Instrument, side: Side, price: f64, size: f64, fees: f64) -> ClientTrade
+{
+    ClientTrade { exchange: Exchange::Hourglass,
+                  timestamp: TEST_EXPIRY - 60_000,
+                  trade_id: ClientTradeId(1),
+                  order_id: None,
+                  cid: None,
+                  instrument: instrument.clone(),
+                  side,
+                  price,
+                  size,
+                  fees,
+                  position_side: PositionSide::Both }
+}
@@ -53,7 +53,8 @@ async fn main()
 
     // 初始化 HourglassClient，用于与交易所进行交互
     let client = HourglassClient { client_event_tx: request_tx.clone(),
-                                   market_event_rx: market_rx };
+                                   market_event_rx: market_rx,
+                                   bar_event_rx: None };
     // // 1. 获取初始的未成交订单列表，检查当前没有未成交订单
     test_1_fetch_initial_orders_and_check_empty(&client).await;
     // // 2. 获取初始的余额信息，检查当前没有发生任何余额变化事件
@@ -10,8 +10,9 @@ use uuid::Uuid;
 
 use hourglass::{
     common::{
-        account_positions::{exited_positions::AccountExitedPositions, AccountPositions},
+        account_positions::{exited_positions::AccountExitedPositions, AccountPositions, PositionSide},
         balance::Balance,
+        datafeed::file_source::{FileSource, FileSourceConfig},
         event::AccountEvent,
         instrument::{kind::InstrumentKind, Instrument},
         order::{
@@ -25,15 +26,18 @@ use hourglass::{
     },
     hourglass::{
         account::{
+            account_handlers::risk_handler::OrderRateLimiter,
             account_latency::{AccountLatency, FluctuationMode},
+            account_margin_loan::MarginLoanBook,
             account_orders::AccountOrders,
+            account_volume::TradingVolumeTracker,
             HourglassAccount,
         },
-        clickhouse_api::{
-            datatype::{clickhouse_trade_data::MarketTrade, single_level_order_book::SingleLevelOrderBook},
-            queries_operations::ClickHouseClient,
-        },
+        clickhouse_api::datatype::{clickhouse_trade_data::MarketTrade, single_level_order_book::SingleLevelOrderBook},
         hourglass_client_local_mode::HourglassClientEvent,
+        l2_order_book::L2BookBuilder,
+        risk_reserve::RiskReserve,
+        utils::config_parser::read_symbol_file,
         DataSource, HourglassExchange,
     },
     test_utils::create_test_account_configuration,
@@ -71,7 +75,8 @@ pub async fn run_sample_exchange(event_account_tx: mpsc::UnboundedSender<Account
                                            price: 16499.0,
                                            size: 1.0,
                                            filled_quantity: 0.0,
-                                           order_role: OrderRole::Maker } };
+                                           order_role: OrderRole::Maker,
+                                           position_side: PositionSide::Both } };
 
     // Directly modify the orders within the RwLock
     {
@@ -85,7 +90,10 @@ pub async fn run_sample_exchange(event_account_tx: mpsc::UnboundedSender<Account
     let mut single_level_order_books = HashMap::new();
     single_level_order_books.insert(Instrument { base: Token::new("ETH".to_string()),
                                                  quote: Token::new("USDT".to_string()),
-                                                 kind: InstrumentKind::Perpetual },
+                                                 kind: InstrumentKind::Perpetual,
+                                                 inverse: false,
+                                                 expiry: None,
+                                                 option_spec: None },
                                     SingleLevelOrderBook { latest_bid: 16305.0,
                                                            latest_ask: 16499.0,
                                                            latest_price: 0.0 });
@@ -102,18 +110,22 @@ pub async fn run_sample_exchange(event_account_tx: mpsc::UnboundedSender<Account
                                                              positions,
                                                              exited_positions: closed_positions,
                                                              account_event_tx: event_account_tx,
-                                                             account_margin: Arc::new(Default::default()) }));
-    let clickhouse_client = ClickHouseClient::new();
-    let exchange = "binance";
-    let instrument = "futures";
-    let date = "2024_05_05";
-    let cursor = clickhouse_client.cursor_unioned_public_trades(exchange, instrument, date).await.unwrap();
+                                                             account_margin: Arc::new(Default::default()),
+                                                             risk_reserve: Arc::new(Mutex::new(RiskReserve::default())),
+                                                             order_rate_limiter: OrderRateLimiter::default(),
+                                                             trading_volume: TradingVolumeTracker::default(),
+                                                             leveraged_token_navs: HashMap::new(),
+                                                             margin_loans: MarginLoanBook::default(),
+                                                             symbol_registry: read_symbol_file("symbols.toml").expect("Failed to read symbols.toml"),
+                                                             l2_books: L2BookBuilder::default() }));
+    // Replay local sample trades so the test does not depend on a running ClickHouse
+    let trades = FileSource::open(FileSourceConfig::new("tests/util/sample_trades.jsonl")).expect("Failed to open sample trades");
 
     // Initialize and configure HourglassExchange
     let hourglass_exchange = HourglassExchange::builder().event_hourglass_rx(event_hourglass_rx)
                                                          .account(account_arc)
                                                          .market_event_tx(market_event_tx)
-                                                         .data_source(DataSource::Backtest(cursor))
+                                                         .data_source(DataSource::File(trades))
                                                          .initiate()
                                                          .expect("Failed to build HourglassExchange");
 
@@ -147,7 +159,8 @@ pub fn order_request_limit<I>(instrument: I, cid: ClientOrderId, side: Side, pri
             side,
             state: RequestOpen { reduce_only: false, // 假设创建的订单不是 reduce_only
                                  price,
-                                 size: quantity } }
+                                 size: quantity,
+                                 position_side: PositionSide::Both } }
 }
 
 /// 创建开放订单
@@ -164,7 +177,8 @@ pub fn open_order<I>(instrument: I, cid: ClientOrderId, id: OrderId, side: Side,
                           price,
                           size: quantity,
                           filled_quantity: filled,
-                          order_role: OrderRole::Maker } }
+                          order_role: OrderRole::Maker,
+                          position_side: PositionSide::Both } }
 }
 
 /// 创建订单取消请求